@@ -0,0 +1,65 @@
+//! Global allocator shared by benchmarks, which report heap usage of a single run next to
+//! criterion timings. It tracks every allocation of a bench binary including this module, so
+//! benchmarks which don't report heap usage should be kept in binaries that don't include it.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Global allocator counting heap allocations and keeping track of the peak heap usage.
+struct TrackingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for TrackingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            if new_size > layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: TrackingAlloc = TrackingAlloc;
+
+/// Runs `f` once and prints the number of heap allocations it made (also divided by `ops`)
+/// together with its peak heap usage.
+pub fn report_allocations<F: FnOnce()>(name: &str, ops: usize, f: F) {
+    let base_count = ALLOCATIONS.load(Ordering::Relaxed);
+    let base = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    f();
+    let count = ALLOCATIONS.load(Ordering::Relaxed) - base_count;
+    let peak = PEAK.load(Ordering::Relaxed) - base;
+    println!(
+        "{name}: {count} allocations, {:.2} per operation, peak heap: {peak} bytes",
+        count as f64 / ops as f64
+    );
+}
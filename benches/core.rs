@@ -0,0 +1,310 @@
+mod alloc;
+
+use alloc::report_allocations;
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tempfile::TempDir;
+use ysr::lib0::{Encoding, Value};
+use ysr::lmdb::EnvFlags;
+use ysr::{List, Map, MultiDoc, StateVector, Text, Unmounted};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// LMDB environment without fsync on commit. Documents are always stored in LMDB, as there's no
+/// in-memory store backend. When possible, the environment is placed in a RAM-backed file system
+/// to reduce the cost of disk I/O, but the measurements still include LMDB reads and writes.
+struct BenchEnv {
+    mdoc: MultiDoc,
+    _dir: TempDir,
+}
+
+impl BenchEnv {
+    fn new() -> Self {
+        Self::with_client(1)
+    }
+
+    fn with_client(client_id: u32) -> Self {
+        let dir = TempDir::new_in("/dev/shm")
+            .or_else(|_| TempDir::new())
+            .unwrap();
+        let env = ysr::lmdb::Env::builder()
+            .max_dbs(10)
+            .map_size(1024 * 1024 * 1024)
+            .flags(EnvFlags::NOSYNC)
+            .open(dir.path(), 0o600)
+            .unwrap();
+        let mdoc = MultiDoc::new(env, Some(client_id.into()));
+        BenchEnv { mdoc, _dir: dir }
+    }
+}
+
+fn text() -> Unmounted<Text> {
+    Unmounted::root("text")
+}
+
+fn map() -> Unmounted<Map> {
+    Unmounted::root("map")
+}
+
+fn list() -> Unmounted<List> {
+    Unmounted::root("list")
+}
+
+fn text_append(env: &BenchEnv, n: usize) {
+    let mut tx = env.mdoc.transact_mut("test").unwrap();
+    let mut txt = text().mount_mut(&mut tx).unwrap();
+    for _ in 0..n {
+        txt.push("a").unwrap();
+    }
+    tx.commit(None).unwrap();
+}
+
+fn text_random_insert(env: &BenchEnv, n: usize) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut tx = env.mdoc.transact_mut("test").unwrap();
+    let mut txt = text().mount_mut(&mut tx).unwrap();
+    for i in 0..n {
+        let index = rng.random_range(0..=i);
+        txt.insert(index, "a").unwrap();
+    }
+    tx.commit(None).unwrap();
+}
+
+fn map_insert(env: &BenchEnv, n: usize) {
+    let mut tx = env.mdoc.transact_mut("test").unwrap();
+    let mut map = map().mount_mut(&mut tx).unwrap();
+    for i in 0..n {
+        map.insert(format!("key-{i}"), i as f64).unwrap();
+    }
+    tx.commit(None).unwrap();
+}
+
+fn list_populate(env: &BenchEnv, n: usize) {
+    let mut tx = env.mdoc.transact_mut("test").unwrap();
+    list()
+        .mount_mut(&mut tx)
+        .unwrap()
+        .insert_range(0, (0..n).map(|i| i as f64))
+        .unwrap();
+    tx.commit(None).unwrap();
+}
+
+/// Builds a document with text, map and list content, which is used to encode updates from.
+fn mixed_document(n: usize) -> BenchEnv {
+    let env = BenchEnv::new();
+    text_random_insert(&env, n);
+    map_insert(&env, n);
+    list_populate(&env, n);
+    env
+}
+
+// ---------------------------------------------------------------------------
+// Benchmarks
+// ---------------------------------------------------------------------------
+
+/// Benchmark appending characters one by one at the end of a text.
+fn bench_text_append(c: &mut Criterion) {
+    const N: usize = 10_000;
+    let mut group = c.benchmark_group("core/text_append");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(N as u64));
+    report_allocations("core/text_append", N, || text_append(&BenchEnv::new(), N));
+
+    group.bench_function(BenchmarkId::from_parameter(N), |b| {
+        b.iter_batched(
+            BenchEnv::new,
+            |env| text_append(&env, N),
+            BatchSize::PerIteration,
+        );
+    });
+    group.finish();
+}
+
+/// Benchmark inserting characters one by one at random (but reproducible) positions of a text.
+fn bench_text_random_insert(c: &mut Criterion) {
+    const N: usize = 10_000;
+    let mut group = c.benchmark_group("core/text_random_insert");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(N as u64));
+    report_allocations("core/text_random_insert", N, || {
+        text_random_insert(&BenchEnv::new(), N)
+    });
+
+    group.bench_function(BenchmarkId::from_parameter(N), |b| {
+        b.iter_batched(
+            BenchEnv::new,
+            |env| text_random_insert(&env, N),
+            BatchSize::PerIteration,
+        );
+    });
+    group.finish();
+}
+
+/// Benchmark rendering a text as a string, when its blocks are stored in a different order than
+/// their logical order within the text.
+fn bench_text_to_string(c: &mut Criterion) {
+    const N: usize = 100_000;
+    let mut group = c.benchmark_group("core/text_to_string");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(N as u64));
+
+    // 10 characters long chunks inserted at random positions
+    let env = BenchEnv::new();
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut tx = env.mdoc.transact_mut("test").unwrap();
+    let mut txt = text().mount_mut(&mut tx).unwrap();
+    for i in 0..N / 10 {
+        let index = rng.random_range(0..=i * 10);
+        txt.insert(index, "abcdefghij").unwrap();
+    }
+    tx.commit(None).unwrap();
+    let render = || {
+        let tx = env.mdoc.transact("test").unwrap();
+        let str = text().mount(&tx).unwrap().to_string();
+        assert_eq!(str.len(), N);
+    };
+    report_allocations("core/text_to_string", N, render);
+
+    group.bench_function(BenchmarkId::from_parameter(N), |b| b.iter(render));
+    group.finish();
+}
+
+/// Benchmark inserting distinct keys into a map and reading random keys of a large map.
+fn bench_map(c: &mut Criterion) {
+    const N: usize = 100_000;
+    const READS: usize = 1_000;
+    let mut group = c.benchmark_group("core/map");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(N as u64));
+    report_allocations("core/map/insert", N, || map_insert(&BenchEnv::new(), N));
+
+    group.bench_function(BenchmarkId::new("insert", N), |b| {
+        b.iter_batched(
+            BenchEnv::new,
+            |env| map_insert(&env, N),
+            BatchSize::PerIteration,
+        );
+    });
+
+    let env = BenchEnv::new();
+    map_insert(&env, N);
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys: Vec<String> = (0..READS)
+        .map(|_| format!("key-{}", rng.random_range(0..N)))
+        .collect();
+    let tx = env.mdoc.transact("test").unwrap();
+    let map = map().mount(&tx).unwrap();
+    let get_all = || {
+        for key in keys.iter() {
+            let _: f64 = map.get(key).unwrap();
+        }
+    };
+    report_allocations("core/map/get", READS, get_all);
+
+    group.throughput(Throughput::Elements(READS as u64));
+    group.bench_function(BenchmarkId::new("get", N), |b| b.iter(get_all));
+    group.finish();
+}
+
+/// Benchmark iterating over all elements of a large list.
+fn bench_list_iter(c: &mut Criterion) {
+    const N: usize = 100_000;
+    let mut group = c.benchmark_group("core/list_iter");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(N as u64));
+
+    let env = BenchEnv::new();
+    list_populate(&env, N);
+    let tx = env.mdoc.transact("test").unwrap();
+    let list = list().mount(&tx).unwrap();
+    let iterate = || {
+        let count = list.iter::<Value>().map(Result::unwrap).count();
+        assert_eq!(count, N);
+    };
+    report_allocations("core/list_iter", N, iterate);
+
+    group.bench_function(BenchmarkId::from_parameter(N), |b| b.iter(iterate));
+    group.finish();
+}
+
+/// Benchmark encoding a full document state as an update, and applying that update to an empty
+/// document, using both encoding versions.
+fn bench_update(c: &mut Criterion) {
+    const N: usize = 10_000;
+    let mut group = c.benchmark_group("core/update");
+    group.sample_size(10);
+
+    let env = mixed_document(N);
+    let tx = env.mdoc.transact("test").unwrap();
+    for (name, version) in [("v1", Encoding::V1), ("v2", Encoding::V2)] {
+        let update = tx.diff_update(&StateVector::default(), version).unwrap();
+        group.throughput(Throughput::Bytes(update.len() as u64));
+        report_allocations(&format!("core/update/encode/{name}"), 1, || {
+            tx.diff_update(&StateVector::default(), version).unwrap();
+        });
+        group.bench_function(BenchmarkId::new("encode", name), |b| {
+            b.iter(|| tx.diff_update(&StateVector::default(), version).unwrap());
+        });
+
+        let apply = |env: BenchEnv| {
+            let mut tx = env.mdoc.transact_mut("test").unwrap();
+            tx.apply_update(&update, version).unwrap();
+            tx.commit(None).unwrap();
+        };
+        report_allocations(&format!("core/update/apply/{name}"), 1, || {
+            apply(BenchEnv::with_client(2))
+        });
+        group.bench_function(BenchmarkId::new("apply", name), |b| {
+            b.iter_batched(|| BenchEnv::with_client(2), apply, BatchSize::PerIteration);
+        });
+    }
+    group.finish();
+}
+
+/// Benchmark computing a state vector of a document edited by many clients.
+fn bench_state_vector(c: &mut Criterion) {
+    const CLIENTS: u32 = 1_000;
+    let mut group = c.benchmark_group("core/state_vector");
+    group.throughput(Throughput::Elements(CLIENTS as u64));
+
+    let env = BenchEnv::new();
+    for client in 1..=CLIENTS {
+        let peer = BenchEnv::with_client(client + 1);
+        let mut tx = peer.mdoc.transact_mut("test").unwrap();
+        map()
+            .mount_mut(&mut tx)
+            .unwrap()
+            .insert(format!("client-{client}"), client as f64)
+            .unwrap();
+        let update = tx.incremental_update(Encoding::V1).unwrap();
+        drop(tx);
+
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        tx.apply_update(&update, Encoding::V1).unwrap();
+        tx.commit(None).unwrap();
+    }
+
+    let tx = env.mdoc.transact("test").unwrap();
+    report_allocations("core/state_vector", 1, || {
+        tx.state_vector().unwrap();
+    });
+    group.bench_function(BenchmarkId::from_parameter(CLIENTS), |b| {
+        b.iter(|| tx.state_vector().unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_text_append,
+    bench_text_random_insert,
+    bench_text_to_string,
+    bench_map,
+    bench_list_iter,
+    bench_update,
+    bench_state_vector,
+);
+criterion_main!(benches);
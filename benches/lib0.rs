@@ -0,0 +1,49 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::collections::HashMap;
+use ysr::lib0;
+use ysr::lib0::Value;
+
+/// Builds a string-heavy lib0 value: an array of objects with a few text fields each.
+fn string_heavy_value(n: usize) -> Value {
+    let items = (0..n)
+        .map(|i| {
+            Value::Object(HashMap::from([
+                ("id".to_string(), Value::String(format!("item-{i}"))),
+                (
+                    "title".to_string(),
+                    Value::String(format!("The quick brown fox jumps over the lazy dog #{i}")),
+                ),
+                (
+                    "body".to_string(),
+                    Value::String("lorem ipsum dolor sit amet ".repeat(8)),
+                ),
+            ]))
+        })
+        .collect();
+    Value::Array(items)
+}
+
+/// Benchmark decoding a large, string-heavy lib0 atom buffer.
+fn bench_decode_strings(c: &mut Criterion) {
+    let value = string_heavy_value(10_000);
+    let buf = lib0::to_vec(&value).unwrap();
+    let strings: Vec<String> = (0..10_000)
+        .map(|i| format!("The quick brown fox jumps over the lazy dog #{i}"))
+        .collect();
+    let strings_buf = lib0::to_vec(&strings).unwrap();
+
+    let mut group = c.benchmark_group("lib0_decode");
+    group.bench_function("value", |b| {
+        b.iter(|| lib0::from_slice::<Value>(&buf).unwrap());
+    });
+    group.bench_function("vec_string", |b| {
+        b.iter(|| lib0::from_slice::<Vec<String>>(&strings_buf).unwrap());
+    });
+    group.bench_function("vec_str", |b| {
+        b.iter(|| lib0::from_slice::<Vec<&str>>(&strings_buf).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode_strings);
+criterion_main!(benches);
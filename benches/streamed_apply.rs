@@ -0,0 +1,97 @@
+mod alloc;
+
+use alloc::report_allocations;
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::fs::File;
+use std::io::BufReader;
+use tempfile::TempDir;
+use ysr::lib0::Encoding;
+use ysr::lmdb::EnvFlags;
+use ysr::{Map, MultiDoc, StateVector, Unmounted};
+
+// Heap usage is measured by a global allocator (see the `alloc` module), which is kept in
+// a separate bench binary, so that it doesn't affect timings of the other benchmarks.
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// LMDB environment without fsync on commit, backed by a temporary directory.
+/// The `TempDir` is kept alive to prevent cleanup until the struct is dropped.
+struct TestEnv {
+    mdoc: MultiDoc,
+    _dir: TempDir,
+}
+
+impl TestEnv {
+    fn nosync() -> Self {
+        let dir = TempDir::new().unwrap();
+        let env = ysr::lmdb::Env::builder()
+            .max_dbs(10)
+            .map_size(100 * 1024 * 1024)
+            .flags(EnvFlags::NOSYNC)
+            .open(dir.path(), 0o600)
+            .unwrap();
+        let mdoc = MultiDoc::new(env, Some(1.into()));
+        TestEnv { mdoc, _dir: dir }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Benchmarks
+// ---------------------------------------------------------------------------
+
+/// Benchmark applying a large update read from a file, either buffered in memory first or
+/// streamed directly from the file. Allocations and peak heap usage of both variants are printed.
+fn bench_streamed_apply(c: &mut Criterion) {
+    const ENTRIES: usize = 100_000;
+    let mut group = c.benchmark_group("streamed_apply");
+    group.sample_size(10);
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("update.bin");
+    {
+        let env = TestEnv::nosync();
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        for i in 0..ENTRIES {
+            m.insert(format!("key{i}"), i as f64).unwrap();
+        }
+        let update = tx
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        std::fs::write(&path, update).unwrap();
+    }
+    let update_len = std::fs::metadata(&path).unwrap().len();
+    println!("streamed_apply: update: {update_len} bytes");
+
+    let buffered = |env: &TestEnv| {
+        let data = std::fs::read(&path).unwrap();
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        tx.apply_update(&data, Encoding::V1).unwrap();
+        tx.commit(None).unwrap();
+    };
+    let streamed = |env: &TestEnv| {
+        let reader = BufReader::new(File::open(&path).unwrap());
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        tx.apply_update_from(reader, Encoding::V1).unwrap();
+        tx.commit(None).unwrap();
+    };
+    for (name, apply) in [
+        ("buffered", &buffered as &dyn Fn(&TestEnv)),
+        ("streamed", &streamed),
+    ] {
+        let env = TestEnv::nosync();
+        report_allocations(&format!("streamed_apply/{name}"), 1, || apply(&env));
+
+        group.bench_function(BenchmarkId::new("apply", name), |b| {
+            b.iter_batched(TestEnv::nosync, |env| apply(&env), BatchSize::PerIteration);
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_streamed_apply);
+criterion_main!(benches);
@@ -5,9 +5,9 @@ use std::fs::File;
 use std::io::{BufReader, Read};
 use std::time::Duration;
 use tempfile::TempDir;
-use ysr::lib0::Encoding;
+use ysr::lib0::{Encoding, Value};
 use ysr::lmdb::EnvFlags;
-use ysr::{MultiDoc, StateVector, Text, Unmounted};
+use ysr::{BlockHeader, List, Map, MultiDoc, StateVector, Text, Unmounted};
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -29,16 +29,41 @@ impl TestEnv {
     }
 
     fn with_flags(flags: EnvFlags) -> Self {
+        Self::with_map_size(flags, 100 * 1024 * 1024) // 100 MB – enough for all bench datasets
+    }
+
+    fn with_map_size(flags: EnvFlags, map_size: usize) -> Self {
         let dir = TempDir::new().unwrap();
         let env = ysr::lmdb::Env::builder()
             .max_dbs(10)
-            .map_size(100 * 1024 * 1024) // 100 MB – enough for all bench datasets
+            .map_size(map_size)
             .flags(flags)
             .open(dir.path(), 0o600)
             .unwrap();
         let mdoc = MultiDoc::new(env, Some(1.into()));
         TestEnv { mdoc, _dir: dir }
     }
+
+    fn with_max_block_content_len(self, max_len: usize) -> Self {
+        TestEnv {
+            mdoc: self.mdoc.with_max_block_content_len(max_len),
+            _dir: self._dir,
+        }
+    }
+
+    fn with_content_interning(self, enabled: bool) -> Self {
+        TestEnv {
+            mdoc: self.mdoc.with_content_interning(enabled),
+            _dir: self._dir,
+        }
+    }
+
+    /// Size of the LMDB data file in bytes.
+    fn file_size(&self) -> u64 {
+        std::fs::metadata(self._dir.path().join("data.mdb"))
+            .unwrap()
+            .len()
+    }
 }
 
 struct BinDataset {
@@ -269,6 +294,246 @@ fn bench_editing_trace(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark appending characters one by one at the end of a Text type.
+fn bench_text_push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("text_push");
+    group.sample_size(10);
+
+    for n in [10_000usize, 100_000] {
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter_batched(
+                TestEnv::nosync,
+                |env| {
+                    let mut tx = env.mdoc.transact_mut("test").unwrap();
+                    let txt: Unmounted<Text> = Unmounted::root("text");
+                    {
+                        let mut txt = txt.mount_mut(&mut tx).unwrap();
+                        for _ in 0..n {
+                            txt.push("a").unwrap();
+                        }
+                    }
+                    tx.commit(None).unwrap();
+                },
+                BatchSize::PerIteration,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+/// Benchmark inserting many distinct keys into a Map within a single transaction.
+fn bench_map_insert(c: &mut Criterion) {
+    const N: usize = 50_000;
+    let mut group = c.benchmark_group("map_insert");
+    group.sample_size(10);
+
+    group.bench_with_input(BenchmarkId::from_parameter(N), &N, |b, &n| {
+        b.iter_batched(
+            TestEnv::nosync,
+            |env| {
+                let mut tx = env.mdoc.transact_mut("test").unwrap();
+                let map: Unmounted<Map> = Unmounted::root("map");
+                {
+                    let mut map = map.mount_mut(&mut tx).unwrap();
+                    for i in 0..n {
+                        map.insert(format!("key-{i}"), i as u64).unwrap();
+                    }
+                }
+                tx.commit(None).unwrap();
+            },
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.finish();
+}
+
+/// Benchmark splitting a block produced by merging many consecutive inserts, with and without
+/// the limit on merged block content size. Split transactions are aborted, so every iteration
+/// splits the same block.
+fn bench_split_large_block(c: &mut Criterion) {
+    const N: usize = 100_000;
+    let mut group = c.benchmark_group("split_large_block");
+
+    for (name, max_len) in [
+        ("uncapped", usize::MAX),
+        ("capped", BlockHeader::DEFAULT_MAX_CONTENT_LEN),
+    ] {
+        let env = TestEnv::nosync().with_max_block_content_len(max_len);
+        let list: Unmounted<List> = Unmounted::root("list");
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        list.mount_mut(&mut tx)
+            .unwrap()
+            .insert_range(0, (0..N).map(|i| format!("value-{i:06}")))
+            .unwrap();
+        {
+            let mut txt = txt.mount_mut(&mut tx).unwrap();
+            for _ in 0..N / 10 {
+                txt.push("0123456789").unwrap();
+            }
+        }
+        tx.commit(None).unwrap();
+
+        group.bench_function(BenchmarkId::new("atom", name), |b| {
+            b.iter(|| {
+                let mut tx = env.mdoc.transact_mut("test").unwrap();
+                let mut list = list.mount_mut(&mut tx).unwrap();
+                list.insert(N / 2, "split").unwrap();
+            });
+        });
+        group.bench_function(BenchmarkId::new("string", name), |b| {
+            b.iter(|| {
+                let mut tx = env.mdoc.transact_mut("test").unwrap();
+                let mut txt = txt.mount_mut(&mut tx).unwrap();
+                txt.insert(N / 2, "split").unwrap();
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// Benchmark inserting the same large embed many times with and without content interning.
+/// Since criterion only measures time, the resulting store size is printed out separately.
+fn bench_repeated_embeds(c: &mut Criterion) {
+    const EMBEDS: usize = 100;
+    const EMBED_LEN: usize = 1024 * 1024;
+    const MAP_SIZE: usize = 1024 * 1024 * 1024;
+    let mut group = c.benchmark_group("repeated_embeds");
+    group.sample_size(10);
+
+    let image = Value::from("x".repeat(EMBED_LEN));
+    let insert_embeds = |env: &TestEnv| {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        {
+            let mut txt = txt.mount_mut(&mut tx).unwrap();
+            for i in 0..EMBEDS {
+                txt.insert_embed(i, image.clone()).unwrap();
+            }
+        }
+        tx.commit(None).unwrap();
+    };
+
+    for (name, interning) in [("verbatim", false), ("interned", true)] {
+        let env =
+            TestEnv::with_map_size(EnvFlags::NOSYNC, MAP_SIZE).with_content_interning(interning);
+        insert_embeds(&env);
+        let stats = env.mdoc.transact("test").unwrap().gc_stats().unwrap();
+        println!(
+            "repeated_embeds/{name}: content: {} bytes, data file: {} bytes",
+            stats.content_bytes,
+            env.file_size()
+        );
+
+        group.bench_function(BenchmarkId::new("insert", name), |b| {
+            b.iter_batched(
+                || {
+                    TestEnv::with_map_size(EnvFlags::NOSYNC, MAP_SIZE)
+                        .with_content_interning(interning)
+                },
+                |env| insert_embeds(&env),
+                BatchSize::PerIteration,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+/// Encodes changes of a transaction, used to compare keystroke encodings.
+type EncodeFn<'a> = &'a dyn Fn(&ysr::Transaction<'_>) -> Vec<u8>;
+
+/// Benchmark typing text one character per transaction, encoding every keystroke either as
+/// a regular incremental update or using the compact keystroke format. Average number of bytes
+/// per keystroke is printed separately.
+fn bench_keystroke_update(c: &mut Criterion) {
+    const CHARS: usize = 1_000;
+    let mut group = c.benchmark_group("keystroke_update");
+    group.sample_size(10);
+
+    let type_text = |env: &TestEnv, encode: EncodeFn<'_>| {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().push("a").unwrap();
+        tx.commit(None).unwrap();
+        let mut total = 0;
+        for i in 0..CHARS {
+            let mut tx = env.mdoc.transact_mut("test").unwrap();
+            let mut t = txt.mount_mut(&mut tx).unwrap();
+            // alternate between appending and inserting in the middle of the text
+            let index = if i % 2 == 0 { t.len() } else { t.len() / 2 };
+            t.insert(index, "b").unwrap();
+            total += encode(&tx).len();
+            tx.commit(None).unwrap();
+        }
+        total
+    };
+    let v1 = |tx: &ysr::Transaction<'_>| tx.incremental_update(Encoding::V1).unwrap();
+    let v2 = |tx: &ysr::Transaction<'_>| tx.incremental_update(Encoding::V2).unwrap();
+    let keystroke = |tx: &ysr::Transaction<'_>| tx.keystroke_update().unwrap().unwrap();
+    let variants: [(&str, EncodeFn<'_>); 3] = [("v1", &v1), ("v2", &v2), ("keystroke", &keystroke)];
+
+    for (name, encode) in variants {
+        let total = type_text(&TestEnv::nosync(), encode);
+        println!(
+            "keystroke_update/{name}: {:.2} bytes per keystroke",
+            total as f64 / CHARS as f64
+        );
+
+        group.bench_function(BenchmarkId::new("type", name), |b| {
+            b.iter_batched(
+                TestEnv::nosync,
+                |env| type_text(&env, encode),
+                BatchSize::PerIteration,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+/// Benchmark reading a text built purely from remote updates (one update per inserted character),
+/// before and after merging their blocks with `Transaction::optimize`.
+fn bench_optimize_applied(c: &mut Criterion) {
+    const CHARS: usize = 10_000;
+    let mut group = c.benchmark_group("optimize_applied");
+    group.sample_size(10);
+
+    let txt: Unmounted<Text> = Unmounted::root("text");
+    let updates: Vec<Vec<u8>> = {
+        let env = TestEnv::nosync();
+        (0..CHARS)
+            .map(|_| {
+                let mut tx = env.mdoc.transact_mut("test").unwrap();
+                txt.mount_mut(&mut tx).unwrap().push("a").unwrap();
+                let update = tx.incremental_update(Encoding::V1).unwrap();
+                tx.commit(None).unwrap();
+                update
+            })
+            .collect()
+    };
+
+    for optimize in [false, true] {
+        let name = if optimize { "optimized" } else { "fragmented" };
+        let env = TestEnv::nosync();
+        let mut tx = env.mdoc.transact_mut("test").unwrap();
+        for update in updates.iter() {
+            tx.apply_update(update, Encoding::V1).unwrap();
+        }
+        if optimize {
+            tx.optimize().unwrap();
+        }
+        group.bench_function(BenchmarkId::new("to_string", name), |b| {
+            b.iter(|| txt.mount(&tx).unwrap().to_string());
+        });
+    }
+
+    group.finish();
+}
+
 /// Same as `bench_apply_and_commit` but with `ENV_NOSYNC` — no fsync on commit.
 fn bench_apply_and_commit_nosync(c: &mut Criterion) {
     let datasets = load_bin_datasets();
@@ -355,5 +620,11 @@ criterion_group!(
     bench_diff_update,
     bench_editing_trace,
     bench_editing_trace_nosync,
+    bench_text_push,
+    bench_map_insert,
+    bench_split_large_block,
+    bench_repeated_embeds,
+    bench_optimize_applied,
+    bench_keystroke_update,
 );
 criterion_main!(benches);
@@ -1,11 +1,13 @@
 use crate::block_reader::BlockRange;
-use crate::content::{Content, ContentType, FormatAttribute, utf16_to_utf8};
+use crate::content::{Content, ContentType, FormatAttribute, split_utf16};
 use crate::integrate::IntegrationContext;
 use crate::lib0::{Encoder, WriteExt};
 use crate::node::{Named, Node, NodeID, NodeType};
 use crate::prelim::Prelim;
 use crate::store::Db;
 use crate::transaction::TxMutScope;
+use crate::types::ValueEncoding;
+use crate::types::map::MapResolver;
 use crate::{ClientID, Clock, Optional, Prepare, U32, lib0};
 use crate::{Error, Result};
 use bitflags::bitflags;
@@ -159,6 +161,9 @@ pub struct BlockHeader {
 impl BlockHeader {
     pub const SIZE: usize = size_of::<BlockHeader>();
     pub const INLINE_CONTENT_LEN: usize = 8;
+    /// Default max size (in bytes) of a block content, up to which consecutive blocks can be
+    /// merged together.
+    pub const DEFAULT_MAX_CONTENT_LEN: usize = 64 * 1024;
 
     pub fn empty() -> Self {
         BlockHeader {
@@ -282,6 +287,23 @@ impl BlockHeader {
         }
     }
 
+    /// Sets both left/right neighbors and left/right origins of the current block, keeping
+    /// corresponding flags consistent. This is how locally inserted blocks are initialized:
+    /// `left` should point to the last element of a left neighbor block, while `right` should point
+    /// to the first element of a right neighbor block.
+    pub fn set_neighbors(&mut self, left: Option<&ID>, right: Option<&ID>) {
+        self.set_left(left);
+        self.set_right(right);
+        match left {
+            Some(&id) => self.set_origin_left(id),
+            None => self.flags -= BlockFlags::ORIGIN_LEFT,
+        }
+        match right {
+            Some(&id) => self.set_origin_right(id),
+            None => self.flags -= BlockFlags::ORIGIN_RIGHT,
+        }
+    }
+
     pub fn origin_left(&self) -> Option<&ID> {
         if self.flags.contains(BlockFlags::ORIGIN_LEFT) {
             Some(&self.origin_left)
@@ -506,6 +528,14 @@ impl<'a> Block<'a> {
         self.header
     }
 
+    /// Returns raw bytes of the block header. This is a zero-copy view over the same memory
+    /// the block was mapped from, so it can be forwarded as is without re-serialization.
+    #[inline]
+    pub fn header_bytes(&self) -> &'a [u8] {
+        let header: &'a BlockHeader = self.header;
+        header.as_bytes()
+    }
+
     /// Returns the inline content of this block, if any, with the lifetime
     /// tied to the underlying `BlockHeader` reference (`'a`) rather than the
     /// local borrow of `self` (which is what you'd get going through `Deref`).
@@ -591,11 +621,16 @@ impl BlockMut {
                 // split inlined content - only applicable to strings
                 let source = unsafe { std::str::from_utf8_unchecked(bytes) };
                 let utf16_offset = offset.get() as usize;
-                if let Some(utf8_offset) = utf16_to_utf8(source, utf16_offset) {
-                    let bytes = &bytes[utf8_offset..];
-                    inline_content[..bytes.len()].copy_from_slice(bytes);
-                    inline_content_len = bytes.len() as u8;
-                    self.inline_content_len = utf8_offset as u8; // we don't need to truncate content itself
+                if let Some((left, right)) = split_utf16(source, utf16_offset) {
+                    inline_content[..right.len()].copy_from_slice(right.as_bytes());
+                    inline_content_len = right.len() as u8;
+                    if let Cow::Owned(left) = left {
+                        // a surrogate pair was split, left side ends with a replacement character
+                        self.inline_content[..left.len()].copy_from_slice(left.as_bytes());
+                        self.inline_content_len = left.len() as u8;
+                    } else {
+                        self.inline_content_len = left.len() as u8; // we don't need to truncate content itself
+                    }
                 }
             }
 
@@ -746,12 +781,21 @@ impl InsertBlockData {
     {
         let node: Node = (*parent.id()).into();
         let len = value.clock_len();
+        // empty inserts are expected to be filtered out by callers: a zero-length block
+        // wouldn't carry any content, but it would still advance the local clock
+        if len == 0 {
+            return Err(crate::Error::EmptyInsert);
+        }
         let id = tx.state.next_id(len);
         let mut block = {
-            let mut insert =
-                InsertBlockData::new(id, len, left, right, left, right, node, entry_key);
+            let mut insert = InsertBlockData::new(id, len, None, None, None, None, node, entry_key);
+            insert.block.set_neighbors(left, right);
 
-            match value.prepare()? {
+            let prepared = match tx.state.value_encodings.get(parent.id()) {
+                Some(ValueEncoding::Json) => value.prepare_json()?,
+                _ => value.prepare()?,
+            };
+            match prepared {
                 Prepare::Node(node_type) => {
                     insert.block.set_content_type(ContentType::Node);
                     insert.block.set_node_type(node_type);
@@ -800,6 +844,15 @@ impl InsertBlockData {
         self.entry = Some(Bytes::copy_from_slice(key.as_ref()));
     }
 
+    /// Sets neighbors and origins of this block based on the blocks it's going to be inserted
+    /// in between, the same way it's done by local map, list and text inserts.
+    pub fn with_neighbors(mut self, left: Option<&Block<'_>>, right: Option<&Block<'_>>) -> Self {
+        let left = left.map(|block| block.last_id());
+        let right = right.map(|block| *block.id());
+        self.block.set_neighbors(left.as_ref(), right.as_ref());
+        self
+    }
+
     /// Split this block data at `offset` (measured in clock units from the start).
     /// Self becomes the left part `[id.clock, id.clock+offset-1]`.
     /// Returns the right part `[id.clock+offset, end]`, or `None` if split is not possible.
@@ -811,16 +864,20 @@ impl InsertBlockData {
                 // Clock offset corresponds to UTF-16 code units.
                 if let Some(content) = self.content.pop() {
                     let utf16_offset = offset.get() as usize;
-                    let byte_offset = {
+                    let split = {
                         let s = unsafe { std::str::from_utf8_unchecked(content.bytes()) };
-                        utf16_to_utf8(s, utf16_offset)
+                        split_utf16(s, utf16_offset)
+                            .map(|(left, right)| (left.into_owned(), right.into_owned()))
                     };
-                    if let Some(byte_offset) = byte_offset {
-                        let right_data = content.bytes()[byte_offset..].to_vec();
-                        let left_data = content.bytes()[..byte_offset].to_vec();
-                        self.content
-                            .push(Content::new(ContentType::String, Cow::Owned(left_data)));
-                        smallvec![Content::new(ContentType::String, Cow::Owned(right_data))]
+                    if let Some((left, right)) = split {
+                        self.content.push(Content::new(
+                            ContentType::String,
+                            Cow::Owned(left.into_bytes()),
+                        ));
+                        smallvec![Content::new(
+                            ContentType::String,
+                            Cow::Owned(right.into_bytes())
+                        )]
                     } else {
                         self.content.push(content);
                         smallvec![]
@@ -848,25 +905,66 @@ impl InsertBlockData {
         })
     }
 
+    /// Returns the size (in bytes) of the content carried by this block.
+    pub fn content_len(&self) -> usize {
+        let inline_len = self.block.try_inline_data().map(<[u8]>::len).unwrap_or(0);
+        inline_len + self.content.iter().map(Content::len).sum::<usize>()
+    }
+
+    /// Checks if `other` block can be merged into the current one without exceeding
+    /// `max_content_len` bytes of content.
+    pub fn can_merge(&self, other: &Self, max_content_len: usize) -> bool {
+        self.block.can_merge(&other.as_block())
+            && self.content_len() + other.content_len() <= max_content_len
+    }
+
+    /// Merges `other` block into the current one. Content size limit is not checked here,
+    /// see [InsertBlockData::can_merge].
     pub fn merge(&mut self, other: Self) -> bool {
         if self.block.merge(other.block.as_block()) {
-            if !self.content.is_empty() {
-                self.content.extend(other.content);
-                if self.block.content_type() == ContentType::String {
-                    // compress the contents
-                    let mut buf = Vec::new();
-                    for content in self.content.drain(..) {
-                        buf.extend_from_slice(content.data.as_bytes());
-                    }
-                    self.content = smallvec![Content::new(ContentType::String, Cow::Owned(buf))];
-                }
-            }
+            self.append_content(other.content);
             true
         } else {
             false
         }
     }
 
+    /// Checks if `other` block is a direct continuation of this one, i.e. both of them could be
+    /// produced by splitting a single block. Unlike [InsertBlockData::can_merge], it doesn't
+    /// depend on block neighbors nor on the content size limit, so it can be used on blocks
+    /// which were never integrated.
+    pub(crate) fn can_squash(&self, other: &Self) -> bool {
+        let (a, b) = (&self.block, &other.block);
+        a.id().client == b.id().client
+            && a.id().clock + a.clock_len() == b.id().clock
+            && b.origin_left() == Some(&a.last_id())
+            && a.origin_right() == b.origin_right()
+            && a.content_type() == b.content_type()
+            && a.content_type().is_mergeable()
+    }
+
+    /// Appends `other` block to this one. The caller must ensure that
+    /// [InsertBlockData::can_squash] returned `true` beforehand.
+    pub(crate) fn squash(&mut self, other: Self) {
+        let len = self.block.clock_len() + other.block.clock_len();
+        self.block.set_clock_len(len);
+        self.append_content(other.content);
+    }
+
+    fn append_content(&mut self, content: SmallVec<[Content<'static>; 1]>) {
+        if !self.content.is_empty() {
+            self.content.extend(content);
+            if self.block.content_type() == ContentType::String {
+                // compress the contents
+                let mut buf = Vec::new();
+                for content in self.content.drain(..) {
+                    buf.extend_from_slice(content.data.as_bytes());
+                }
+                self.content = smallvec![Content::new(ContentType::String, Cow::Owned(buf))];
+            }
+        }
+    }
+
     pub fn as_block(&self) -> Block<'_> {
         Block {
             id: self.block.id,
@@ -895,6 +993,18 @@ impl InsertBlockData {
             self.block.set_origin_left(left);
         }
 
+        // neighbors resolved from the origins must belong to the same node as this block,
+        // otherwise integrating it would corrupt the list of another node
+        let parent_id = *self.block.header.parent();
+        let foreign_neighbor = context
+            .left
+            .iter()
+            .chain(context.right.iter())
+            .any(|block| *block.parent() != parent_id);
+        if foreign_neighbor {
+            return Err(Error::MalformedBlock(*self.id()));
+        }
+
         if context.detect_conflict(self) {
             context.resolve_conflict(self, &mut tx.cursor)?;
         }
@@ -948,7 +1058,8 @@ impl InsertBlockData {
                 }
             } else {
                 if context.parent.is_none() {
-                    context.parent = tx.cursor.seek(parent_id).optional()?.map(BlockMut::from);
+                    context.parent =
+                        IntegrationContext::fetch_parent(&tx.cursor, Node::from(parent_id))?;
                 }
                 if let Some(parent) = &mut context.parent {
                     // current block is new head of the list
@@ -975,7 +1086,7 @@ impl InsertBlockData {
             }
             let right = context.right.as_mut().unwrap();
             right.set_left(Some(self.id()));
-        } else if self.is_map_entry() {
+        } else if self.is_map_entry() && tx.config.map_resolver.is_none() {
             // set as current parent value if right === null and this is parentSub
             let map_entries = tx.cursor.db().map_entries();
             if let Some(entry_key) = self.entry_key() {
@@ -1022,14 +1133,27 @@ impl InsertBlockData {
             _ => { /* do nothing */ }
         }
 
-        let content_inlined = if self.content.len() == 1 {
-            self.block.set_inline_content(&self.content[0])
-        } else {
-            false
-        };
+        // inline data of node blocks is reserved for the ID of their first element
+        let content_inlined =
+            if self.content.len() == 1 && self.block.content_type() != ContentType::Node {
+                self.block.set_inline_content(&self.content[0])
+            } else {
+                false
+            };
         if !content_inlined {
             let contents = tx.cursor.db().contents();
-            contents.insert_range(*self.block.id(), self.content.as_ref())?;
+            match self.content.as_slice() {
+                [content]
+                    if tx.config.intern_content
+                        && matches!(
+                            content.content_type(),
+                            ContentType::Binary | ContentType::Embed
+                        ) =>
+                {
+                    contents.intern(*self.block.id(), content.bytes())?
+                }
+                _ => contents.insert_range(*self.block.id(), self.content.as_ref())?,
+            }
         }
         // For Node blocks, len represents node_len (number of children, initially 0).
         // clock_len() for Node always returns 1 hardcoded, so len is free for node_len.
@@ -1037,6 +1161,9 @@ impl InsertBlockData {
             self.block.set_clock_len(Clock::new(0));
         }
         tx.cursor.insert(self.as_block())?;
+        if !self.block.is_deleted() {
+            tx.notify_inserted(self.block.id(), &parent_id, &self.content)?;
+        }
 
         let parent_deleted = if let Some(parent_block) = context.parent.as_mut() {
             if self.entry_key().is_none() && self.block.is_countable() && !self.block.is_deleted() {
@@ -1054,10 +1181,19 @@ impl InsertBlockData {
             true // parent GCed?
         };
 
-        if parent_deleted || (self.block.key_hash().is_some() && self.block.right().is_some()) {
-            // if either parent is deleted or this block is not the last block in
-            // a map-like structure, delete it
-            tx.delete(&mut self.block, parent_deleted)?;
+        match tx.config.map_resolver {
+            Some(resolver) if !parent_deleted && self.is_map_entry() => {
+                self.resolve_map_value(tx, context, resolver)?;
+            }
+            _ => {
+                if parent_deleted
+                    || (self.block.key_hash().is_some() && self.block.right().is_some())
+                {
+                    // if either parent is deleted or this block is not the last block in
+                    // a map-like structure, delete it
+                    tx.delete(&mut self.block, parent_deleted)?;
+                }
+            }
         }
 
         if let Some(right) = context.right.as_mut() {
@@ -1074,6 +1210,127 @@ impl InsertBlockData {
         self.entry.is_some() || self.block.key_hash().is_some()
     }
 
+    /// Used instead of last-writer-wins rule, when a custom [MapResolver] was configured. Decides
+    /// which value of a map entry stays visible after this block has been integrated: either
+    /// this block or a currently visible value of the same entry. The other one gets deleted.
+    ///
+    /// There's at most one visible value in the entry's chain of blocks. Since it's no longer
+    /// guaranteed to be the last one, the chain is scanned to find it.
+    fn resolve_map_value(
+        &mut self,
+        tx: &mut TxMutScope<'_>,
+        context: &mut IntegrationContext,
+        resolver: MapResolver,
+    ) -> crate::Result<()> {
+        let parent_id = *self.block.parent();
+        let (current, current_on_right) = match self.find_visible_sibling(tx)? {
+            Some(found) => found,
+            None => {
+                if !self.block.is_deleted() {
+                    self.set_map_entry(tx, *self.id())?;
+                }
+                return Ok(());
+            }
+        };
+        let incoming_wins = if self.block.origin_left() == Some(&current.last_id()) {
+            // this block was created by peer aware of the current value: it's a plain overwrite
+            true
+        } else if self.block.is_deleted() {
+            false
+        } else {
+            let map_entries = tx.db.map_entries();
+            let key = match self.entry_key() {
+                Some(key) => key,
+                None => {
+                    let key_hash = *self.block.key_hash().unwrap();
+                    match map_entries.keys_for_hash(parent_id, key_hash).next()? {
+                        Some((key, _)) => key,
+                        None => return Err(Error::MalformedBlock(*self.id())),
+                    }
+                }
+            };
+            let contents = tx.db.contents();
+            let current_content = match current.try_inline_content() {
+                Some(content) => content,
+                None if current.content_type() == ContentType::Node => {
+                    Content::new(ContentType::Node, Cow::Borrowed(&[]))
+                }
+                None => {
+                    let data = contents.get(*current.id())?;
+                    Content::new(current.content_type(), Cow::Borrowed(data))
+                }
+            };
+            let incoming_content = match self.block.try_inline_content() {
+                Some(content) => content,
+                None => match self.content.first() {
+                    Some(content) => {
+                        Content::new(content.content_type(), Cow::Borrowed(content.bytes()))
+                    }
+                    None => Content::new(self.block.content_type(), Cow::Borrowed(&[])),
+                },
+            };
+            match resolver(&parent_id, key, &current_content, &incoming_content) {
+                std::cmp::Ordering::Greater => false,
+                std::cmp::Ordering::Less => true,
+                // fallback to the last-writer-wins: the rightmost value in the chain wins
+                std::cmp::Ordering::Equal => !current_on_right,
+            }
+        };
+
+        if incoming_wins {
+            let current_id = *current.id();
+            if let Some(left) = context.left.as_mut().filter(|b| b.id() == &current_id) {
+                tx.delete(left, false)?;
+            } else if let Some(right) = context.right.as_mut().filter(|b| b.id() == &current_id) {
+                tx.delete(right, false)?;
+            } else {
+                let mut block = current;
+                tx.delete(&mut block, false)?;
+            }
+            self.set_map_entry(tx, *self.id())
+        } else {
+            tx.delete(&mut self.block, false)?;
+            self.set_map_entry(tx, *current.id())
+        }
+    }
+
+    /// Scans the chain of blocks of the same map entry in search of the non-deleted block other
+    /// than this one. Returns it together with the flag telling if it was found on the right
+    /// side of this block.
+    fn find_visible_sibling<'tx>(
+        &self,
+        tx: &mut TxMutScope<'tx>,
+    ) -> crate::Result<Option<(BlockMut, bool)>> {
+        let mut next = self.block.left().copied();
+        while let Some(id) = next {
+            let block = tx.cursor.seek_containing(id)?;
+            if !block.is_deleted() {
+                return Ok(Some((block.into(), false)));
+            }
+            next = block.left().copied();
+        }
+        let mut next = self.block.right().copied();
+        while let Some(id) = next {
+            let block = tx.cursor.seek(id)?;
+            if !block.is_deleted() {
+                return Ok(Some((block.into(), true)));
+            }
+            next = block.right().copied();
+        }
+        Ok(None)
+    }
+
+    fn set_map_entry(&self, tx: &mut TxMutScope<'_>, id: ID) -> crate::Result<()> {
+        let parent_id = *self.block.parent();
+        let map_entries = tx.db.map_entries();
+        if let Some(entry_key) = self.entry_key() {
+            map_entries.insert(&parent_id, entry_key, &id)
+        } else {
+            let key_hash = *self.block.key_hash().unwrap();
+            map_entries.insert_first(parent_id, key_hash, &id)
+        }
+    }
+
     pub(crate) fn encode<E: Encoder>(&self, writer: &mut E) -> crate::Result<()> {
         let block = self.block.as_block();
         let origin_left = block.origin_left();
@@ -1111,7 +1368,7 @@ impl InsertBlockData {
             ContentType::Deleted => {
                 writer.write_len(block.clock_len())?;
             }
-            ContentType::Binary => {
+            ContentType::Binary | ContentType::Custom => {
                 let content = match data {
                     Some(data) => data,
                     None => &*self.content[0].data,
@@ -1145,7 +1402,16 @@ impl InsertBlockData {
                 writer.write_json(&fmt.value::<lib0::Value>()?)?;
             }
             ContentType::Node => {
-                writer.write_type_ref(*block.node_type().unwrap() as u8)?;
+                let node_type = *block.node_type().unwrap();
+                writer.write_type_ref(node_type as u8)?;
+                if node_type == NodeType::XmlElement {
+                    let tag = self
+                        .content
+                        .first()
+                        .ok_or_else(|| Error::InvalidMapping("xml element tag"))?;
+                    let tag = &*tag.data;
+                    writer.write_key(unsafe { std::str::from_utf8_unchecked(tag) })?;
+                }
             }
             ContentType::Atom | ContentType::Json => match data {
                 Some(data) => {
@@ -1246,6 +1512,9 @@ pub const CONTENT_TYPE_ATOM: u8 = 8;
 pub const CONTENT_TYPE_DOC: u8 = 9;
 pub const CONTENT_TYPE_SKIP: u8 = 10;
 pub const CONTENT_TYPE_MOVE: u8 = 11;
+/// Content type of user-defined, opaque content. It uses the last content ref value which fits
+/// into the block info bits, far from the ones reserved by Yjs.
+pub const CONTENT_TYPE_CUSTOM: u8 = 31;
 
 impl Debug for BlockHeader {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -1285,11 +1554,18 @@ impl Display for BlockHeader {
 
 #[cfg(test)]
 mod test {
-    use crate::block::{ID, InsertBlockData};
+    use crate::block::{BlockFlags, ID, InsertBlockData};
     use crate::content::{Content, ContentType};
+    use crate::lib0::{Encode, Encoding};
     use crate::node::{Node, NodeID};
-    use crate::{BlockHeader, BlockMut, ClientID, Clock};
+    use crate::store::Db;
+    use crate::test_util::multi_doc;
+    use crate::{
+        Block, BlockHeader, BlockMut, Carrier, ClientID, Clock, IDSet, Text, Unmounted, Update,
+    };
     use smallvec::smallvec;
+    use std::collections::{BTreeMap, VecDeque};
+    use zerocopy::IntoBytes;
 
     const CLIENT: ClientID = unsafe { ClientID::new_unchecked(123) };
     const PARENT: Node = Node::nested(ID::new(CLIENT, Clock::new(0)));
@@ -1299,6 +1575,26 @@ mod test {
         assert_eq!(size_of::<BlockHeader>(), 60);
     }
 
+    #[test]
+    fn block_header_bytes_zero_copy() {
+        let header = BlockHeader::new(
+            5.into(),
+            Some(&ID::new(CLIENT, 4.into())),
+            None,
+            None,
+            None,
+            PARENT.id(),
+            None,
+        );
+        let data = header.as_bytes().to_vec();
+        let block = Block::new(ID::new(CLIENT, 10.into()), &data).unwrap();
+
+        let bytes = block.header_bytes();
+        assert_eq!(bytes.as_ptr(), data.as_ptr());
+        assert_eq!(bytes.len(), data.len());
+        assert_eq!(bytes, header.as_bytes());
+    }
+
     #[test]
     fn id_serialize() {
         let id = ID::new(123.into(), 42.into());
@@ -1331,6 +1627,77 @@ mod test {
         assert_eq!(insert.entry_key(), Some("key"));
     }
 
+    #[test]
+    fn merge_content_limit() {
+        let text = "hello world, it's a longer text";
+        let mut left = block(1, 31, 0, 32, 0, 32, None, Content::string(text));
+        let right = left.split(10.into()).unwrap();
+        assert!(left.can_merge(&right, BlockHeader::DEFAULT_MAX_CONTENT_LEN));
+        assert!(!left.can_merge(&right, 20));
+
+        // merged updates keep blocks apart once their content would exceed the limit
+        let update = |insert: InsertBlockData| Update {
+            blocks: BTreeMap::from([(CLIENT, VecDeque::from([Carrier::Block(insert)]))]),
+            delete_set: IDSet::default(),
+        };
+        let merged = Update::merge_updates_with(update(left.clone()), update(right.clone()), 20);
+        assert_eq!(merged.blocks[&CLIENT].len(), 2);
+        let merged = Update::merge_updates(update(left), update(right));
+        assert_eq!(merged.blocks[&CLIENT].len(), 1);
+    }
+
+    #[test]
+    fn binary_content_roundtrip() {
+        let payload: Vec<u8> = (0..20).collect();
+        let text = "text following binary content";
+        for version in [Encoding::V1, Encoding::V2] {
+            let mut binary = InsertBlockData::new(
+                ID::new(CLIENT, 0.into()),
+                1.into(),
+                None,
+                None,
+                None,
+                None,
+                PARENT,
+                None,
+            );
+            binary.block.set_content_type(ContentType::Binary);
+            binary.content = smallvec![Content::new(ContentType::Binary, payload.clone().into())];
+            // following block checks that the binary content didn't misalign the decoder
+            let left = ID::new(CLIENT, 0.into());
+            let mut string = InsertBlockData::new(
+                ID::new(CLIENT, 1.into()),
+                (text.len() as u32).into(),
+                None,
+                None,
+                Some(&left),
+                None,
+                PARENT,
+                None,
+            );
+            string.block.set_content_type(ContentType::String);
+            string.content = smallvec![Content::string(text)];
+            let update = Update {
+                blocks: BTreeMap::from([(
+                    CLIENT,
+                    VecDeque::from([Carrier::Block(binary), Carrier::Block(string)]),
+                )]),
+                delete_set: IDSet::default(),
+            };
+
+            let decoded = Update::decode(&update.encode(version).unwrap(), version).unwrap();
+            let carriers = &decoded.blocks[&CLIENT];
+            assert_eq!(carriers.len(), 2);
+            let (Carrier::Block(binary), Carrier::Block(string)) = (&carriers[0], &carriers[1])
+            else {
+                panic!("expected two blocks, got: {carriers:?}");
+            };
+            assert_eq!(binary.block.content_type(), ContentType::Binary);
+            assert_eq!(binary.content()[0].bytes(), payload.as_slice());
+            assert_eq!(string.content()[0], Content::string(text));
+        }
+    }
+
     #[test]
     fn block_set_key_shorter() {
         let mut block = block(1, 3, 0, 4, 0, 4, Some("test"), Content::str(&"hello world"));
@@ -1486,6 +1853,54 @@ mod test {
         assert_eq!(block, expected);
     }
 
+    #[test]
+    fn with_neighbors_matches_local_insert() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "ac").unwrap();
+        t.insert(1, "b").unwrap();
+
+        let db = tx.db.get();
+        let blocks = db.blocks();
+        let left = blocks.get(ID::new(1.into(), 0.into())).unwrap();
+        let right = blocks.get(ID::new(1.into(), 1.into())).unwrap();
+        let inserted = blocks.get(ID::new(1.into(), 2.into())).unwrap();
+
+        let built = InsertBlockData::new(
+            *inserted.id(),
+            inserted.clock_len(),
+            None,
+            None,
+            None,
+            None,
+            Node::root_named("text"),
+            None,
+        )
+        .with_neighbors(Some(&left), Some(&right));
+
+        let neighbor_flags = BlockFlags::LEFT
+            | BlockFlags::RIGHT
+            | BlockFlags::ORIGIN_LEFT
+            | BlockFlags::ORIGIN_RIGHT;
+        assert_eq!(
+            built.block.flags & neighbor_flags,
+            inserted.flags & neighbor_flags
+        );
+        assert_eq!(built.block.left(), inserted.left());
+        assert_eq!(built.block.right(), inserted.right());
+        assert_eq!(built.block.origin_left(), inserted.origin_left());
+        assert_eq!(built.block.origin_right(), inserted.origin_right());
+        assert_eq!(built.block.parent(), inserted.parent());
+
+        // missing neighbors clear previously set flags
+        let built = built.with_neighbors(None, None);
+        assert!((built.block.flags & neighbor_flags).is_empty());
+        assert_eq!(built.block.origin_left(), None);
+        assert_eq!(built.block.origin_right(), None);
+    }
+
     fn block(
         id: u32,
         len: u32,
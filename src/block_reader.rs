@@ -6,7 +6,7 @@ use crate::id_set::IDSet;
 use crate::integrate::IntegrationContext;
 use crate::lib0::v1::DecoderV1;
 use crate::lib0::v2::DecoderV2;
-use crate::lib0::{Decode, Decoder, Encoder, Encoding, ReadExt};
+use crate::lib0::{Decode, Decoder, Encode, Encoder, Encoding, ReadExt, WriteExt};
 use crate::node::{Node, NodeID, NodeType};
 use crate::transaction::TxMutScope;
 use crate::{ClientID, Clock, U32};
@@ -15,6 +15,7 @@ use smallvec::SmallVec;
 use std::borrow::Cow;
 use std::collections::{BTreeMap, VecDeque};
 use std::fmt::{Display, Formatter};
+use std::io::Read;
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
 
 #[derive(Default)]
@@ -24,53 +25,55 @@ pub struct Update {
 }
 
 impl Update {
+    /// Decodes an update using provided lib0 `version`. Updates tagged with a document GUID
+    /// (see [tag_update]) are rejected with [crate::Error::TaggedUpdate], use
+    /// [Update::decode_tagged] to decode them.
     pub fn decode(bytes: &[u8], version: Encoding) -> crate::Result<Self> {
+        match Self::decode_tagged(bytes, version)? {
+            (None, update) => Ok(update),
+            (Some(guid), _) => Err(crate::Error::TaggedUpdate(guid)),
+        }
+    }
+
+    /// Decodes an update using provided lib0 `version`, together with a GUID of the document
+    /// it was tagged with (see [tag_update]), if any.
+    pub fn decode_tagged(bytes: &[u8], version: Encoding) -> crate::Result<(Option<String>, Self)> {
         match version {
-            Encoding::V1 => {
-                let mut decoder = DecoderV1::from_slice(bytes);
-                Self::decode_with(&mut decoder)
-            }
-            Encoding::V2 => {
-                let mut decoder = DecoderV2::from_slice(bytes)?;
-                Self::decode_with(&mut decoder)
-            }
+            Encoding::V1 => Self::decode_tagged_with(&mut DecoderV1::from_slice(bytes)),
+            Encoding::V2 => Self::decode_tagged_with(&mut DecoderV2::from_slice(bytes)?),
         }
     }
 
+    /// Decodes an update, rejecting it with [crate::Error::TaggedUpdate] if it was tagged with
+    /// a document GUID.
     pub fn decode_with<D: Decoder>(decoder: &mut D) -> crate::Result<Self> {
+        match Self::decode_tagged_with(decoder)? {
+            (None, update) => Ok(update),
+            (Some(guid), _) => Err(crate::Error::TaggedUpdate(guid)),
+        }
+    }
+
+    fn decode_tagged_with<D: Decoder>(decoder: &mut D) -> crate::Result<(Option<String>, Self)> {
+        let guid = decoder.read_guid_tag()?;
         // read blocks
         let blocks = Self::decode_blocks(decoder)?;
         // read delete set
         let delete_set = IDSet::decode_with(decoder)?;
-        Ok(Update { blocks, delete_set })
+        Ok((guid, Update { blocks, delete_set }))
+    }
+
+    /// Iterates over all decoded block carriers, ordered by client ID and then by clock.
+    pub fn carriers(&self) -> impl Iterator<Item = &Carrier> {
+        self.blocks.values().flatten()
     }
 
     fn decode_blocks<D: Decoder>(
         decoder: &mut D,
     ) -> crate::Result<BTreeMap<ClientID, VecDeque<Carrier>>> {
-        // read blocks
-        let clients_len: u32 = decoder.read_var()?;
-        let mut clients = BTreeMap::new();
-
-        for _ in 0..clients_len {
-            let blocks_len = decoder.read_var::<u32>()? as usize;
-
-            let client = decoder.read_client()?;
-            let mut clock: Clock = decoder.read_var()?;
-            let blocks = clients.entry(client).or_insert_with(VecDeque::new);
-            // Attempt to pre-allocate memory for the blocks. If the capacity overflows and
-            // allocation fails, return an error.
-            blocks.try_reserve(blocks_len)?;
-
-            for _ in 0..blocks_len {
-                let id = ID::new(client, clock);
-                if let Some(block) = Self::decode_block(id, decoder)? {
-                    // due to bug in the past it was possible for empty bugs to be generated
-                    // even though they had no effect on the document store
-                    clock += block.len();
-                    blocks.push_back(block);
-                }
-            }
+        let mut reader = CarrierReader::new(decoder)?;
+        let mut clients: BTreeMap<ClientID, VecDeque<Carrier>> = BTreeMap::new();
+        while let Some((client, carriers)) = reader.next_batch(usize::MAX)? {
+            clients.entry(client).or_default().extend(carriers);
         }
         Ok(clients)
     }
@@ -159,20 +162,17 @@ impl Update {
                 let len = copy_lib0(decoder, &mut result)?;
                 block.set_clock_len(len);
             }
-            ContentType::Binary => {
+            content_type @ (ContentType::Binary | ContentType::Custom) => {
                 block.set_clock_len(1.into());
-                let len = decoder.read_len()?.get() as usize;
-                let mut w = Vec::with_capacity(len);
-                unsafe { w.set_len(len) };
-                decoder.read_exact(&mut w)?;
+                // buffers are written together with their length, not into the length column
+                let mut w = Vec::new();
+                decoder.read_bytes(&mut w)?;
 
-                result.push(Content::new(ContentType::Binary, Cow::Owned(w)));
+                result.push(Content::new(content_type, Cow::Owned(w)));
             }
             ContentType::String => {
-                let byte_len = decoder.read_len()?.get() as usize;
-                let mut w = Vec::with_capacity(byte_len);
-                unsafe { w.set_len(byte_len) };
-                decoder.read_exact(&mut w)?;
+                let mut w = Vec::new();
+                decoder.read_string(&mut w)?;
                 let utf16_len = crate::content::utf8_to_utf16_len(&w);
                 block.set_clock_len(Clock::new(utf16_len));
 
@@ -198,6 +198,12 @@ impl Update {
                 let type_ref = decoder.read_type_ref()?;
                 let node_type = NodeType::try_from(type_ref)?;
                 block.set_node_type(node_type);
+                if node_type == NodeType::XmlElement {
+                    // XML elements carry their tag name
+                    let mut tag = Vec::new();
+                    decoder.read_key(&mut tag)?;
+                    result.push(Content::new(ContentType::Node, Cow::Owned(tag)));
+                }
             }
             ContentType::Doc => {
                 block.set_clock_len(1.into());
@@ -207,10 +213,44 @@ impl Update {
         Ok(result)
     }
 
+    /// Splits current update in two: the first one contains blocks and deletions of clients
+    /// accepted by a given `filter`, while the second one contains everything else.
+    pub(crate) fn partition<F>(self, filter: F) -> (Self, Self)
+    where
+        F: Fn(ClientID) -> bool,
+    {
+        let mut accepted = Update::default();
+        let mut rejected = Update::default();
+        for (client, carriers) in self.blocks {
+            if filter(client) {
+                accepted.blocks.insert(client, carriers);
+            } else {
+                rejected.blocks.insert(client, carriers);
+            }
+        }
+        for (&client, range) in self.delete_set.iter() {
+            if filter(client) {
+                accepted.delete_set.insert_range(client, range.clone());
+            } else {
+                rejected.delete_set.insert_range(client, range.clone());
+            }
+        }
+        (accepted, rejected)
+    }
+
     /// Merge two updates into one, deduplicating overlapping carriers.
-    pub fn merge_updates(mut a: Self, mut b: Self) -> Self {
-        let blocks =
-            Self::merge_blocks(std::mem::take(&mut a.blocks), std::mem::take(&mut b.blocks));
+    pub fn merge_updates(a: Self, b: Self) -> Self {
+        Self::merge_updates_with(a, b, BlockHeader::DEFAULT_MAX_CONTENT_LEN)
+    }
+
+    /// Merge two updates into one, deduplicating overlapping carriers. Adjacent blocks are
+    /// merged only as long as their combined content doesn't exceed `max_content_len` bytes.
+    pub fn merge_updates_with(mut a: Self, mut b: Self, max_content_len: usize) -> Self {
+        let blocks = Self::merge_blocks(
+            std::mem::take(&mut a.blocks),
+            std::mem::take(&mut b.blocks),
+            max_content_len,
+        );
 
         // merge delete sets
         a.delete_set.merge(b.delete_set);
@@ -225,6 +265,7 @@ impl Update {
     fn merge_blocks(
         a: BTreeMap<ClientID, VecDeque<Carrier>>,
         b: BTreeMap<ClientID, VecDeque<Carrier>>,
+        max_content_len: usize,
     ) -> BTreeMap<ClientID, VecDeque<Carrier>> {
         let mut blocks_a = a.into_iter().peekable();
         let mut blocks_b = b.into_iter().peekable();
@@ -245,7 +286,7 @@ impl Update {
                         // same client in both updates — merge carrier lists
                         let (client, carriers_a) = blocks_a.next().unwrap();
                         let (_, carriers_b) = blocks_b.next().unwrap();
-                        let merged = Self::merge_carriers(carriers_a, carriers_b);
+                        let merged = Self::merge_carriers(carriers_a, carriers_b, max_content_len);
                         if !merged.is_empty() {
                             blocks.insert(client, merged);
                         }
@@ -269,7 +310,11 @@ impl Update {
     /// Merge two sorted carrier sequences for the same client into one.
     /// Handles overlapping carriers by keeping the higher-priority variant
     /// and splitting/deduplicating as needed.
-    fn merge_carriers(mut a: VecDeque<Carrier>, mut b: VecDeque<Carrier>) -> VecDeque<Carrier> {
+    fn merge_carriers(
+        mut a: VecDeque<Carrier>,
+        mut b: VecDeque<Carrier>,
+        max_content_len: usize,
+    ) -> VecDeque<Carrier> {
         let mut result = VecDeque::with_capacity(a.len() + b.len());
 
         loop {
@@ -285,7 +330,7 @@ impl Update {
                 (None, Some(_)) => b.pop_front().unwrap(),
                 (None, None) => break,
             };
-            Self::push_carrier(&mut result, carrier);
+            Self::push_carrier(&mut result, carrier, max_content_len);
         }
 
         result
@@ -294,7 +339,7 @@ impl Update {
     /// Push a carrier into a sorted, non-overlapping result sequence.
     /// Handles overlaps with existing entries by splitting/deduplicating
     /// based on carrier priority, and tries to merge adjacent carriers.
-    fn push_carrier(result: &mut VecDeque<Carrier>, mut carrier: Carrier) {
+    fn push_carrier(result: &mut VecDeque<Carrier>, mut carrier: Carrier, max_content_len: usize) {
         let mut suffix: Option<Carrier> = None;
 
         loop {
@@ -308,7 +353,7 @@ impl Update {
 
             if curr_start > last_end {
                 // No overlap: try to merge with the back or just push
-                if result.back().unwrap().can_merge(&carrier) {
+                if result.back().unwrap().can_merge(&carrier, max_content_len) {
                     result.back_mut().unwrap().merge(carrier);
                 } else {
                     result.push_back(carrier);
@@ -331,7 +376,7 @@ impl Update {
                 let offset = last_end + 1 - curr_start;
                 if let Some(right) = carrier.split(offset) {
                     carrier = right;
-                    if result.back().unwrap().can_merge(&carrier) {
+                    if result.back().unwrap().can_merge(&carrier, max_content_len) {
                         result.back_mut().unwrap().merge(carrier);
                     } else {
                         result.push_back(carrier);
@@ -360,7 +405,7 @@ impl Update {
                     suffix = rem.split(skip).or(suffix);
                 }
                 // Prefix is adjacent to carrier — try merge or push
-                if result.back().unwrap().can_merge(&carrier) {
+                if result.back().unwrap().can_merge(&carrier, max_content_len) {
                     result.back_mut().unwrap().merge(carrier);
                 } else {
                     result.push_back(carrier);
@@ -378,7 +423,10 @@ impl Update {
         }
 
         if let Some(suffix) = suffix {
-            if result.back().is_some_and(|last| last.can_merge(&suffix)) {
+            if result
+                .back()
+                .is_some_and(|last| last.can_merge(&suffix, max_content_len))
+            {
                 result.back_mut().unwrap().merge(suffix);
             } else {
                 result.push_back(suffix);
@@ -387,6 +435,238 @@ impl Update {
     }
 }
 
+impl Update {
+    /// Encodes the block part of this update, without the trailing delete set.
+    pub(crate) fn encode_blocks<E: Encoder>(&self, encoder: &mut E) -> crate::Result<()> {
+        encoder.write_var(self.blocks.len())?;
+        for (&client_id, carriers) in self.blocks.iter() {
+            encoder.write_var(carriers.len())?;
+            encoder.write_client(client_id)?;
+            encoder.write_var(carriers[0].id().clock)?;
+
+            for carrier in carriers {
+                carrier.encode(encoder)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Update {
+    /// Normalizes this update, so that updates carrying the same changes are always encoded into
+    /// the same bytes, no matter how the blocks were split at the time they were produced:
+    /// consecutive blocks of the same client are squashed together whenever they could come
+    /// from a single block, and delete set ranges are sorted and merged.
+    ///
+    /// Clients are always encoded in ascending order of their IDs.
+    pub fn canonicalize(&mut self) {
+        for carriers in self.blocks.values_mut() {
+            let mut squashed: VecDeque<Carrier> = VecDeque::with_capacity(carriers.len());
+            for carrier in carriers.drain(..) {
+                match squashed.back_mut() {
+                    Some(last) if last.can_squash(&carrier) => match (last, carrier) {
+                        (Carrier::Block(a), Carrier::Block(b)) => a.squash(b),
+                        (last, carrier) => last.merge(carrier),
+                    },
+                    _ => squashed.push_back(carrier),
+                }
+            }
+            *carriers = squashed;
+        }
+        self.blocks.retain(|_, carriers| !carriers.is_empty());
+        self.delete_set.squash();
+    }
+}
+
+impl Encode for Update {
+    fn encode_with<E: Encoder>(&self, encoder: &mut E) -> crate::Result<()> {
+        self.encode_blocks(encoder)?;
+        self.delete_set.encode_with(encoder)
+    }
+}
+
+/// Lazily decodes block carriers of an update, without the need to keep all of them in memory
+/// at once. Carriers are returned in batches, each containing consecutive blocks of a single
+/// client, in the same order as they were encoded.
+pub(crate) struct CarrierReader<'a, D> {
+    decoder: &'a mut D,
+    /// Number of client sections that haven't been started yet.
+    clients_left: u32,
+    /// Client, clock of the next block and number of blocks left in a current client section.
+    section: Option<(ClientID, Clock, usize)>,
+}
+
+impl<'a, D: Decoder> CarrierReader<'a, D> {
+    pub fn new(decoder: &'a mut D) -> crate::Result<Self> {
+        let clients_left: u32 = decoder.read_var()?;
+        Ok(CarrierReader {
+            decoder,
+            clients_left,
+            section: None,
+        })
+    }
+
+    /// Decodes up to `max_len` next carriers of the same client. Returns `None` once all block
+    /// sections of the update have been read.
+    pub fn next_batch(
+        &mut self,
+        max_len: usize,
+    ) -> crate::Result<Option<(ClientID, VecDeque<Carrier>)>> {
+        let (client, mut clock, blocks_left) = loop {
+            match self.section.take() {
+                Some(section) if section.2 > 0 => break section,
+                _ if self.clients_left == 0 => return Ok(None),
+                _ => {
+                    self.clients_left -= 1;
+                    let blocks_len = self.decoder.read_var::<u32>()? as usize;
+                    let client = self.decoder.read_client()?;
+                    let clock: Clock = self.decoder.read_var()?;
+                    self.section = Some((client, clock, blocks_len));
+                }
+            }
+        };
+        let len = blocks_left.min(max_len);
+        let mut blocks = VecDeque::new();
+        // Attempt to pre-allocate memory for the blocks. If the capacity overflows and
+        // allocation fails, return an error.
+        blocks.try_reserve(len)?;
+
+        for _ in 0..len {
+            let id = ID::new(client, clock);
+            if let Some(block) = Update::decode_block(id, self.decoder)? {
+                // due to bug in the past it was possible for empty bugs to be generated
+                // even though they had no effect on the document store
+                clock += block.len();
+                blocks.push_back(block);
+            }
+        }
+        self.section = Some((client, clock, blocks_left - len));
+        Ok(Some((client, blocks)))
+    }
+
+    /// Decodes the delete set, which follows the block sections of the update. All remaining
+    /// carriers must have been read before.
+    pub fn finish(self) -> crate::Result<IDSet> {
+        debug_assert!(self.clients_left == 0 && self.section.is_none_or(|s| s.2 == 0));
+        IDSet::decode_with(self.decoder)
+    }
+}
+
+/// Merges a list of `updates` into a single update, without the need for any document store.
+/// Overlapping blocks are deduplicated, adjacent blocks are squashed together and delete sets
+/// are merged, so the result can be used as a compact, archival replacement of all of them.
+///
+/// All updates, as well as the returned one, use the same encoding `version`. Updates tagged
+/// with a document GUID (see [tag_update]) must all be tagged with the same one, otherwise
+/// [crate::Error::WrongDocument] is returned. The result is tagged with it as well.
+pub fn compact_updates<B: AsRef<[u8]>>(updates: &[B], version: Encoding) -> crate::Result<Vec<u8>> {
+    let mut compacted = Update::default();
+    let mut guid: Option<String> = None;
+    for update in updates {
+        let (tag, update) = Update::decode_tagged(update.as_ref(), version)?;
+        match (&guid, tag) {
+            (Some(expected), Some(actual)) if *expected != actual => {
+                return Err(crate::Error::WrongDocument {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+            (None, Some(actual)) => guid = Some(actual),
+            _ => {}
+        }
+        compacted = Update::merge_updates(compacted, update);
+    }
+    let encoded = compacted.encode(version)?;
+    Ok(match guid {
+        Some(guid) => tag_update(&guid, &encoded),
+        None => encoded,
+    })
+}
+
+/// Returns a canonical form of a given `update` (see [Update::canonicalize]). Updates carrying
+/// the same changes have byte-identical canonical forms, so they can be deduplicated i.e. by
+/// their content hash.
+///
+/// Both the `update` and the returned one use the same encoding `version`. If the `update` was
+/// tagged with a document GUID (see [tag_update]), the returned one is tagged with it as well.
+pub fn canonical_update(update: &[u8], version: Encoding) -> crate::Result<Vec<u8>> {
+    let (guid, mut update) = Update::decode_tagged(update, version)?;
+    update.canonicalize();
+    let encoded = update.encode(version)?;
+    Ok(match guid {
+        Some(guid) => tag_update(&guid, &encoded),
+        None => encoded,
+    })
+}
+
+/// Prefix of updates tagged with a document GUID. Updates produced by lib0 encoders never start
+/// with it: v1 updates would have to start with a non-canonical varint and v2 ones always start
+/// with a zero byte.
+pub(crate) const GUID_TAG: [u8; 2] = [0xFF, 0x00];
+
+/// Frames a given `update` (using any encoding) with a GUID of the document it was produced for,
+/// so that [crate::Transaction::apply_update] can reject it when it's applied to another
+/// document. Untagged updates are still accepted by every document.
+///
+/// Binary format:
+/// - {u8; 2} tag prefix `[0xFF, 0x00]`
+/// - {varint} length of GUID followed by its UTF-8 bytes
+/// - update itself
+pub fn tag_update(guid: &str, update: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(GUID_TAG.len() + 5 + guid.len() + update.len());
+    buf.extend_from_slice(&GUID_TAG);
+    buf.write_var(guid.len()).unwrap();
+    buf.extend_from_slice(guid.as_bytes());
+    buf.extend_from_slice(update);
+    buf
+}
+
+/// Splits an update framed with [tag_update] into its document GUID and the update itself.
+/// Updates which were not tagged are returned as they are, with no GUID.
+pub fn untag_update(update: &[u8]) -> crate::Result<(Option<&str>, &[u8])> {
+    let Some(tagged) = update.strip_prefix(&GUID_TAG) else {
+        return Ok((None, update));
+    };
+    let mut cursor = std::io::Cursor::new(tagged);
+    let len: usize = cursor.read_var()?;
+    let start = cursor.position() as usize;
+    let end = start.checked_add(len).ok_or(crate::Error::EndOfBuffer)?;
+    let guid = tagged.get(start..end).ok_or(crate::Error::EndOfBuffer)?;
+    let guid = std::str::from_utf8(guid).map_err(|_| crate::Error::InvalidMapping("guid"))?;
+    Ok((Some(guid), &tagged[end..]))
+}
+
+/// Reader over an update which GUID tag was read by [untag_reader].
+pub(crate) type Untagged<R> = std::io::Chain<std::io::Cursor<Vec<u8>>, R>;
+
+/// Reads an optional GUID tag (see [tag_update]) from the beginning of a `reader`. Returns the
+/// GUID together with a reader over the update itself, which replays the bytes read ahead when
+/// the update was not tagged.
+pub(crate) fn untag_reader<R: Read>(mut reader: R) -> crate::Result<(Option<String>, Untagged<R>)> {
+    let mut prefix = Vec::with_capacity(GUID_TAG.len());
+    (&mut reader)
+        .take(GUID_TAG.len() as u64)
+        .read_to_end(&mut prefix)?;
+    if prefix == GUID_TAG {
+        let guid = read_guid(&mut reader)?;
+        prefix.clear();
+        Ok((Some(guid), std::io::Cursor::new(prefix).chain(reader)))
+    } else {
+        Ok((None, std::io::Cursor::new(prefix).chain(reader)))
+    }
+}
+
+/// Reads a GUID of a tag (see [tag_update]) from a `reader` positioned right after [GUID_TAG].
+pub(crate) fn read_guid<R: Read>(reader: &mut R) -> crate::Result<String> {
+    let len: u64 = reader.read_var()?;
+    let mut guid = Vec::new();
+    reader.take(len).read_to_end(&mut guid)?;
+    if guid.len() as u64 != len {
+        return Err(crate::Error::EndOfBuffer);
+    }
+    String::from_utf8(guid).map_err(|_| crate::Error::InvalidMapping("guid"))
+}
+
 fn copy_lib0<D: Decoder>(
     decoder: &mut D,
     acc: &mut SmallVec<[Content<'static>; 1]>,
@@ -468,6 +748,23 @@ impl Carrier {
         matches!(self, Carrier::Skip(_))
     }
 
+    /// Returns a parent node of a carried block. It's `None` for GC and skip ranges, and for
+    /// blocks which parent must be inherited from their left/right neighbors during integration.
+    pub fn parent(&self) -> Option<&Node<'static>> {
+        match self {
+            Carrier::Block(block) => block.parent(),
+            _ => None,
+        }
+    }
+
+    /// Returns a content type of a carried block or `None` for GC and skip ranges.
+    pub fn content_type(&self) -> Option<ContentType> {
+        match self {
+            Carrier::Block(block) => Some(block.block.content_type()),
+            _ => None,
+        }
+    }
+
     #[inline(always)]
     pub fn integrate<'tx>(self, offset: Clock, tx: &mut TxMutScope<'tx>) -> crate::Result<()> {
         match self {
@@ -525,19 +822,29 @@ impl Carrier {
     }
 
     /// Check whether this carrier can be merged with the `other` carrier
-    /// that follows it (i.e. `other` is the right neighbor).
-    pub fn can_merge(&self, other: &Self) -> bool {
+    /// that follows it (i.e. `other` is the right neighbor), without exceeding
+    /// `max_content_len` bytes of block content.
+    pub fn can_merge(&self, other: &Self, max_content_len: usize) -> bool {
         match (self, other) {
             (Carrier::GC(a), Carrier::GC(b)) | (Carrier::Skip(a), Carrier::Skip(b)) => {
                 a.head().client == b.head().client && a.end() + 1 == b.head().clock
             }
-            (Carrier::Block(a), Carrier::Block(b)) => a.block.can_merge(&b.as_block()),
+            (Carrier::Block(a), Carrier::Block(b)) => a.can_merge(b, max_content_len),
             _ => false,
         }
     }
 
+    /// Checks whether this carrier and the `other` one that follows it could be produced by
+    /// splitting a single carrier, no matter the size of their content.
+    pub fn can_squash(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Carrier::Block(a), Carrier::Block(b)) => a.can_squash(b),
+            _ => self.can_merge(other, usize::MAX),
+        }
+    }
+
     /// Merge `other` into this carrier. The caller must ensure
-    /// `self.can_merge(&other)` returned `true` beforehand.
+    /// `self.can_merge(&other, max_content_len)` returned `true` beforehand.
     pub fn merge(&mut self, other: Self) {
         match (self, other) {
             (Carrier::GC(a), Carrier::GC(b)) | (Carrier::Skip(a), Carrier::Skip(b)) => {
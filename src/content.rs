@@ -1,7 +1,7 @@
 use crate::block::{
-    CONTENT_TYPE_ATOM, CONTENT_TYPE_BINARY, CONTENT_TYPE_DELETED, CONTENT_TYPE_DOC,
-    CONTENT_TYPE_EMBED, CONTENT_TYPE_FORMAT, CONTENT_TYPE_JSON, CONTENT_TYPE_NODE,
-    CONTENT_TYPE_STRING,
+    CONTENT_TYPE_ATOM, CONTENT_TYPE_BINARY, CONTENT_TYPE_CUSTOM, CONTENT_TYPE_DELETED,
+    CONTENT_TYPE_DOC, CONTENT_TYPE_EMBED, CONTENT_TYPE_FORMAT, CONTENT_TYPE_JSON,
+    CONTENT_TYPE_NODE, CONTENT_TYPE_STRING,
 };
 use crate::lib0::{Decoder, Value, WriteExt};
 use crate::node::{Named, Node, NodeID};
@@ -26,6 +26,13 @@ pub enum ContentType {
     Node = CONTENT_TYPE_NODE,
     Atom = CONTENT_TYPE_ATOM,
     Doc = CONTENT_TYPE_DOC,
+    /// User-defined content, opaque to this crate. Its data starts with a user type byte followed
+    /// by the payload. It's never split nor merged with its neighbors.
+    ///
+    /// Only this content ref is opaque. Any other unknown ref can't be skipped over, since its
+    /// encoding (and therefore its length in the update) is unknown, so it's rejected with
+    /// [crate::Error::UnsupportedContent].
+    Custom = CONTENT_TYPE_CUSTOM,
 }
 
 impl ContentType {
@@ -47,6 +54,7 @@ impl ContentType {
             ContentType::Node => true,
             ContentType::Deleted => false,
             ContentType::Format => false,
+            ContentType::Custom => true,
             //ContentType::Move => false,
         }
     }
@@ -74,6 +82,7 @@ impl ContentType {
             ContentType::Node => false,
             ContentType::Atom => true,
             ContentType::Doc => true,
+            ContentType::Custom => true,
         }
     }
 }
@@ -90,6 +99,7 @@ impl Display for ContentType {
             ContentType::Node => write!(f, "node"),
             ContentType::Atom => write!(f, "atom"),
             ContentType::Doc => write!(f, "doc"),
+            ContentType::Custom => write!(f, "custom"),
         }
     }
 }
@@ -108,6 +118,7 @@ impl TryFrom<u8> for ContentType {
             CONTENT_TYPE_NODE => Ok(ContentType::Node),
             CONTENT_TYPE_ATOM => Ok(ContentType::Atom),
             CONTENT_TYPE_DOC => Ok(ContentType::Doc),
+            CONTENT_TYPE_CUSTOM => Ok(ContentType::Custom),
             _ => Err(crate::Error::UnsupportedContent(value)),
         }
     }
@@ -205,6 +216,13 @@ impl<'a> Content<'a> {
         Self::new(ContentType::Doc, Cow::Borrowed(doc_id.as_bytes()))
     }
 
+    pub fn custom(type_byte: u8, payload: &[u8]) -> Content<'static> {
+        let mut data = Vec::with_capacity(payload.len() + 1);
+        data.push(type_byte);
+        data.extend_from_slice(payload);
+        Content::new(ContentType::Custom, Cow::Owned(data))
+    }
+
     pub fn as_json<T>(&self) -> crate::Result<T>
     where
         T: DeserializeOwned,
@@ -264,6 +282,17 @@ impl<'a> Content<'a> {
         Ok(node_id)
     }
 
+    /// Returns a user type byte and payload of a [ContentType::Custom] content.
+    pub fn as_custom(&self) -> crate::Result<(u8, &[u8])> {
+        if self.content_type != ContentType::Custom {
+            return Err(crate::Error::InvalidMapping("custom content"));
+        }
+        match self.data.split_first() {
+            Some((&type_byte, payload)) => Ok((type_byte, payload)),
+            None => Err(crate::Error::InvalidMapping("custom content")),
+        }
+    }
+
     pub fn as_doc(&self) -> crate::Result<&str> {
         if self.content_type != ContentType::Doc {
             return Err(crate::Error::InvalidMapping("document id"));
@@ -290,14 +319,52 @@ impl<'a> Content<'a> {
         }
 
         let str: &str = unsafe { std::str::from_utf8_unchecked(self.data.as_ref()) };
-        let offset = utf16_to_utf8(str, utf16_offset)?;
-        let (left, right) = self.data.split_at(offset);
-        let left: Content<'b> = Content::new(self.content_type, left.into());
-        let right: Content<'b> = Content::new(self.content_type, right.into());
+        let (left, right) = split_utf16(str, utf16_offset)?;
+        let left: Content<'b> = Content::new(self.content_type, cow_bytes(left));
+        let right: Content<'b> = Content::new(self.content_type, cow_bytes(right));
         Some((left, right))
     }
 }
 
+fn cow_bytes(str: Cow<'_, str>) -> Cow<'_, [u8]> {
+    match str {
+        Cow::Borrowed(str) => Cow::Borrowed(str.as_bytes()),
+        Cow::Owned(str) => Cow::Owned(str.into_bytes()),
+    }
+}
+
+/// Split `str` at a given UTF-16 code-unit offset. Returns `None` if the offset lies past the
+/// end of the string.
+///
+/// If the offset falls between the halves of a surrogate pair, the split character is replaced
+/// with U+FFFD on both sides (just like Yjs does). U+FFFD takes a single UTF-16 code unit, so
+/// both parts keep the lengths expected by block clocks, while remaining valid UTF-8.
+pub(crate) fn split_utf16(str: &str, utf16: usize) -> Option<(Cow<'_, str>, Cow<'_, str>)> {
+    let mut utf16_count = 0;
+    for (byte_offset, ch) in str.char_indices() {
+        if utf16_count == utf16 {
+            let (left, right) = str.split_at(byte_offset);
+            return Some((Cow::Borrowed(left), Cow::Borrowed(right)));
+        }
+        utf16_count += ch.len_utf16();
+        if utf16_count > utf16 {
+            let mut left = String::with_capacity(byte_offset + 3);
+            left.push_str(&str[..byte_offset]);
+            left.push(char::REPLACEMENT_CHARACTER);
+            let rest = &str[byte_offset + ch.len_utf8()..];
+            let mut right = String::with_capacity(rest.len() + 3);
+            right.push(char::REPLACEMENT_CHARACTER);
+            right.push_str(rest);
+            return Some((Cow::Owned(left), Cow::Owned(right)));
+        }
+    }
+    if utf16_count == utf16 {
+        Some((Cow::Borrowed(str), Cow::Borrowed("")))
+    } else {
+        None
+    }
+}
+
 /// Convert a UTF-16 code-unit offset within `str` into a UTF-8 byte offset.
 /// Returns `None` if the offset is not at a valid UTF-16 boundary (e.g. it would split a
 /// surrogate pair) or if it lies past the end of the string.
@@ -384,6 +451,10 @@ impl<'a> Display for Content<'a> {
                 let doc_id = std::str::from_utf8(&self.data).map_err(|_| std::fmt::Error)?;
                 write!(f, "{}", doc_id)
             }
+            ContentType::Custom => {
+                let (type_byte, payload) = self.as_custom().map_err(|_| std::fmt::Error)?;
+                write!(f, "custom({}, {} bytes)", type_byte, payload.len())
+            }
         }
     }
 }
@@ -5,12 +5,15 @@ use crate::store::Db;
 use crate::store::block_store::BlockStore;
 use crate::store::content_store::ContentStore;
 use crate::store::map_entries::MapEntries;
-use crate::{Block, Clock, Error, ID, Out, Unmounted, lib0};
+use crate::types::xml::{write_escaped, write_open_tag};
+use crate::{Block, Clock, Error, ID, Out, Unmounted, XmlElement, lib0};
+use serde::de::value::MapAccessDeserializer;
 use serde::de::{
     DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
 };
 use serde::{Deserialize, Deserializer};
 use serde_json::de::SliceRead;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::io::Cursor;
 
@@ -156,7 +159,8 @@ impl<'de> Deserializer<'de> for BlockDeserializer<'de> {
                     Ok(deserializer.deserialize_any(visitor)?)
                 }
             }
-            ContentType::Binary => {
+            ContentType::Binary | ContentType::Custom => {
+                // custom content is exposed as binary data prefixed with its user type byte
                 let bytes = read_block_data(&self.block, &self.content_store)?;
                 visitor.visit_bytes(bytes)
             }
@@ -648,8 +652,11 @@ impl<'de> Deserializer<'de> for NodeDeserializer<'de> {
                 let deserializer = TextNodeDeserializer::new(self.block, self.blocks, true);
                 deserializer.deserialize_string(visitor)
             }
-            NodeType::XmlFragment => todo!(),
-            NodeType::XmlElement => todo!(),
+            NodeType::XmlFragment | NodeType::XmlElement => {
+                let mut buf = String::new();
+                write_xml_node(&mut buf, self.block, self.blocks)?;
+                visitor.visit_string(buf)
+            }
         }
     }
 
@@ -901,6 +908,48 @@ fn read_block_data<'a, 'b>(
     }
 }
 
+/// Writes XML node as XML markup, the same way as [crate::XmlElementRef::to_xml] does.
+fn write_xml_node(buf: &mut String, node: Block<'_>, blocks: BlockStore<'_>) -> crate::Result<()> {
+    let db: Database<'_> = blocks.into();
+    let element = node.node_type() == Some(&NodeType::XmlElement);
+    let tag = if !element {
+        None
+    } else if node.id().is_root() {
+        Some(XmlElement::ROOT_TAG.to_owned())
+    } else {
+        let tag = db.contents().get(*node.id())?;
+        Some(String::from_utf8_lossy(tag).into_owned())
+    };
+    if let Some(tag) = &tag {
+        let attributes: BTreeMap<String, String> = BTreeMap::deserialize(
+            MapAccessDeserializer::new(MapNodeDeserializer::new(node, blocks)),
+        )?;
+        write_open_tag(buf, tag, &attributes);
+    }
+    let mut current = node.start().copied();
+    while let Some(id) = current {
+        let block = blocks.get(id)?;
+        current = block.right().copied();
+        if block.is_deleted() || !block.is_countable() {
+            continue;
+        }
+        match block.node_type() {
+            Some(NodeType::XmlElement) => write_xml_node(buf, block, blocks)?,
+            Some(NodeType::XmlText) => {
+                let text = TextNodeDeserializer::new(block, blocks, false).read_string()?;
+                write_escaped(buf, &text, false);
+            }
+            _ => {}
+        }
+    }
+    if let Some(tag) = &tag {
+        buf.push_str("</");
+        buf.push_str(tag);
+        buf.push('>');
+    }
+    Ok(())
+}
+
 struct ListNodeDeserializer<'de> {
     node: Block<'de>,
     blocks: BlockStore<'de>,
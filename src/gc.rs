@@ -6,6 +6,39 @@ use crate::store::Db;
 use crate::transaction::TxMutScope;
 use crate::{Block, BlockMut, ID, Optional};
 
+/// Diagnostic counters describing how much of the document's block store is occupied by
+/// tombstones. Used to decide when it's worth running [crate::Transaction::gc] and compacting
+/// the underlying LMDB file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GcStats {
+    /// Number of blocks that are not deleted.
+    pub live_blocks: usize,
+    /// Number of blocks that were deleted, but not garbage collected yet.
+    pub deleted_blocks: usize,
+    /// Number of blocks that were already garbage collected and only hold tombstone metadata.
+    pub gc_blocks: usize,
+    /// Total number of bytes occupied by block contents (both inlined and stored separately).
+    pub content_bytes: usize,
+}
+
+impl GcStats {
+    /// Total number of blocks.
+    pub fn total_blocks(&self) -> usize {
+        self.live_blocks + self.deleted_blocks + self.gc_blocks
+    }
+
+    /// Ratio of tombstoned (deleted or garbage collected) blocks to all blocks.
+    /// Returns `0.0` for empty document.
+    pub fn deleted_ratio(&self) -> f64 {
+        let total = self.total_blocks();
+        if total == 0 {
+            0.0
+        } else {
+            (self.deleted_blocks + self.gc_blocks) as f64 / total as f64
+        }
+    }
+}
+
 pub struct GarbageCollector<'tx> {
     tx: TxMutScope<'tx>,
 }
@@ -57,12 +90,17 @@ impl<'tx> GarbageCollector<'tx> {
     fn gc_block(&mut self, block: &Block<'tx>, parent_gc: bool) -> crate::Result<bool> {
         if block.is_deleted() {
             let len = block.clock_len();
+            // copy the header before any writes, which may invalidate the memory it points to
+            let copy = BlockMut::from(*block);
 
             if block.content_type() == ContentType::Node {
                 self.gc_node(block)?;
             }
 
-            if !block.flags().contains(BlockFlags::INLINE_CONTENT) {
+            // node blocks are inlined, but XML elements keep their tags in the content store
+            if block.content_type() == ContentType::Node
+                || !block.flags().contains(BlockFlags::INLINE_CONTENT)
+            {
                 let contents = self.tx.db.contents();
                 let range = BlockRange::new(*block.id(), len);
                 contents.delete_range(block.content_type(), &range)?;
@@ -73,7 +111,7 @@ impl<'tx> GarbageCollector<'tx> {
                 self.tx.cursor.remove(*block.id())?;
             } else {
                 // soft delete
-                let mut block = BlockMut::from(*block);
+                let mut block = copy;
                 block.clear_inline_content();
                 block.flags().remove(BlockFlags::COUNTABLE);
                 block.set_content_type(ContentType::Deleted);
@@ -122,7 +160,10 @@ mod test {
     use crate::store::Db;
     use crate::test_util::multi_doc;
     use crate::types::text::TextPrelim;
-    use crate::{BlockMut, ID, In, List, ListPrelim, Map, MapPrelim, Optional, Unmounted, lib0};
+    use crate::{
+        BlockMut, ID, In, List, ListPrelim, Map, MapPrelim, Optional, Text, Unmounted,
+        XmlElementPrelim, XmlFragment, lib0,
+    };
 
     const CLIENT: u32 = 1;
 
@@ -141,7 +182,11 @@ mod test {
     }
 
     fn content_exists(tx: &crate::Transaction<'_>, id: ID) -> bool {
-        tx.db.get().contents().get(id).optional().unwrap().is_some()
+        match tx.db.get().contents().get(id) {
+            Ok(_) => true,
+            Err(crate::Error::ContentNotFound(_)) => false,
+            Err(e) => panic!("failed to read content: {}", e),
+        }
     }
 
     fn count_map_entries(tx: &crate::Transaction<'_>, node_id: ID) -> usize {
@@ -198,6 +243,84 @@ mod test {
         tx.commit(None).unwrap();
     }
 
+    #[test]
+    fn gc_xml_element_tags() {
+        let xml: Unmounted<XmlFragment> = Unmounted::root("xml");
+        let (doc, _dir) = multi_doc(CLIENT);
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        xml.mount_mut(&mut tx)
+            .unwrap()
+            .push_back(
+                XmlElementPrelim::new("custom-element")
+                    .with_child(XmlElementPrelim::new("nested-element")),
+            )
+            .unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        assert!(content_exists(&tx, id(0)), "outer tag exists");
+        assert!(content_exists(&tx, id(1)), "nested tag exists");
+        let size_before = tx.doc_size().unwrap();
+
+        xml.mount_mut(&mut tx).unwrap().remove_range(0..1).unwrap();
+        let ds = tx.delete_set().cloned().unwrap_or_default();
+        tx.gc(&ds).unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = doc.transact("test").unwrap();
+        assert!(!content_exists(&tx, id(0)), "outer tag was collected");
+        assert!(!content_exists(&tx, id(1)), "nested tag was collected");
+        assert!(tx.doc_size().unwrap() < size_before);
+    }
+
+    #[test]
+    fn gc_stats_after_deletes() {
+        let text: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _dir) = multi_doc(CLIENT);
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        {
+            let mut txt = text.mount_mut(&mut tx).unwrap();
+            txt.insert(0, "0123456789").unwrap();
+        }
+        tx.commit(None).unwrap();
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        let stats = tx.gc_stats().unwrap();
+        assert_eq!(stats.deleted_blocks, 0);
+        assert_eq!(stats.gc_blocks, 0);
+        assert_eq!(stats.deleted_ratio(), 0.0);
+        assert!(stats.content_bytes > 0);
+
+        {
+            // remove every other character: "13579"
+            let mut txt = text.mount_mut(&mut tx).unwrap();
+            for i in 0..5 {
+                txt.remove_range(i..=i).unwrap();
+            }
+            assert_eq!(txt.to_string(), "13579");
+        }
+
+        let stats = tx.gc_stats().unwrap();
+        // text root node + 5 remaining characters
+        assert_eq!(stats.live_blocks, 6);
+        assert_eq!(stats.deleted_blocks, 5);
+        assert_eq!(stats.gc_blocks, 0);
+        assert_eq!(stats.deleted_ratio(), 5.0 / 11.0);
+
+        let ds = tx.delete_set().cloned().unwrap_or_default();
+        tx.gc(&ds).unwrap();
+
+        let gc_stats = tx.gc_stats().unwrap();
+        assert_eq!(gc_stats.live_blocks, 6);
+        assert_eq!(gc_stats.deleted_blocks, 0);
+        assert_eq!(gc_stats.gc_blocks, 5);
+        assert_eq!(gc_stats.deleted_ratio(), stats.deleted_ratio());
+        assert!(gc_stats.content_bytes < stats.content_bytes);
+        tx.commit(None).unwrap();
+    }
+
     #[test]
     fn gc_tombstones_map() {
         let map: Unmounted<Map> = Unmounted::root("map");
@@ -81,6 +81,22 @@ impl IDSet {
         self.0.get(client_id)
     }
 
+    /// Returns a new ID set with all ID ranges of the current set, which are not present
+    /// in the `other` one.
+    pub fn difference(&self, other: &IDSet) -> IDSet {
+        let mut result = IDSet::default();
+        for (client, range) in self.0.iter() {
+            let diff = match other.0.get(client) {
+                None => range.clone(),
+                Some(other) => range.difference(other),
+            };
+            if !diff.is_empty() {
+                result.0.insert(*client, diff);
+            }
+        }
+        result
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&ClientID, &IDRange)> {
         self.0.iter()
     }
@@ -227,6 +243,29 @@ impl IDRange {
         self.0.append(&mut other.0);
     }
 
+    /// Returns ranges of the current [IDRange] with all ranges of the `other` one cut out.
+    fn difference(&self, other: &IDRange) -> IDRange {
+        let mut other = other.clone();
+        other.squash();
+        let mut result = SmallVec::new();
+        for range in self.0.iter() {
+            let mut start = range.start;
+            for hole in other.0.iter() {
+                if hole.end <= start || hole.start >= range.end {
+                    continue;
+                }
+                if hole.start > start {
+                    result.push(start..hole.start);
+                }
+                start = hole.end;
+            }
+            if start < range.end {
+                result.push(start..range.end);
+            }
+        }
+        IDRange(result)
+    }
+
     fn encode_raw<E: Encoder>(&self, encoder: &mut E) -> crate::Result<()> {
         encoder.write_var(self.0.len() as u64)?;
         for range in self.iter() {
@@ -1,11 +1,12 @@
 use crate::lib0::Value;
-use crate::{ListPrelim, MapPrelim};
+use crate::{ListPrelim, MapPrelim, TextPrelim};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum In {
     Value(Value),
     List(ListPrelim),
     Map(MapPrelim),
+    Text(TextPrelim),
 }
 
 impl In {
@@ -16,6 +17,7 @@ impl In {
             In::Value(Value::Object(map)) => map.is_empty(),
             In::List(prelim) => prelim.is_empty(),
             In::Map(prelim) => prelim.is_empty(),
+            In::Text(prelim) => prelim.is_empty(),
             _ => false,
         }
     }
@@ -33,6 +35,12 @@ impl From<MapPrelim> for In {
     }
 }
 
+impl From<TextPrelim> for In {
+    fn from(value: TextPrelim) -> Self {
+        In::Text(value)
+    }
+}
+
 impl<T> From<T> for In
 where
     T: Into<Value>,
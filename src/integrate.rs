@@ -1,11 +1,23 @@
-use crate::Clock;
 use crate::block::{BlockMut, InsertBlockData};
-use crate::node::NodeType;
+use crate::node::{Node, NodeType};
 use crate::store::Db;
 use crate::store::block_store::{BlockCursor, SplitResult};
+use crate::{Clock, ID};
 use std::collections::HashSet;
 use std::ops::Deref;
 
+/// Breaks a tie between two concurrent blocks inserted at the same position, i.e. blocks sharing
+/// the same left origin. Returns `true` if a block identified by `a` must be placed to the left
+/// of a block identified by `b`.
+///
+/// Like in yjs, a block created by a lower client ID goes first. Blocks inserted by the same
+/// client are never concurrent, so comparing client IDs is enough to get a total order that all
+/// peers agree on, regardless of the order in which they received these blocks.
+#[inline]
+pub(crate) fn goes_left(a: &ID, b: &ID) -> bool {
+    a.client < b.client
+}
+
 pub(crate) struct IntegrationContext {
     pub left: Option<BlockMut>,
     pub right: Option<BlockMut>,
@@ -55,14 +67,11 @@ impl IntegrationContext {
             }
         }
         let parent = match target.parent() {
-            Some(node) => match cursor.get_or_insert_node(node.clone(), NodeType::Unknown) {
-                Ok(block) => Some(block),
-                Err(crate::Error::NotFound) => None,
-                Err(e) => return Err(e),
-            },
+            Some(node) => Self::fetch_parent(cursor, node.clone())?,
             None => {
-                let block = cursor.seek(*target.block.parent())?;
-                Some(block.into())
+                let parent = Node::from(*target.block.parent());
+                let block = Self::fetch_parent(cursor, parent)?.ok_or(crate::Error::NotFound)?;
+                Some(block)
             }
         };
         Ok(IntegrationContext {
@@ -73,6 +82,20 @@ impl IntegrationContext {
         })
     }
 
+    /// Returns a block of a given `parent` node. Root nodes are created on demand, since
+    /// a concurrently created root type may not have been initialized in the local store yet.
+    /// Returns `None` if a nested parent node could not be found.
+    pub fn fetch_parent(
+        cursor: &BlockCursor<'_>,
+        parent: Node<'_>,
+    ) -> crate::Result<Option<BlockMut>> {
+        match cursor.get_or_insert_node(parent, NodeType::Unknown) {
+            Ok(block) => Ok(Some(block)),
+            Err(crate::Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn detect_conflict(&self, _target: &InsertBlockData) -> bool {
         // original code: ((!target.left && (!target.right || target.right.left !== null)) || (target.left && target.left.right !== target.right))
         match (&self.left, &self.right) {
@@ -125,7 +148,7 @@ impl IntegrationContext {
             if target.block.origin_left() == item.origin_left() {
                 // case 1
                 let item_id = item.id();
-                if item_id.client < target.id().client {
+                if goes_left(item_id, target.id()) {
                     left = Some(*item_id);
                     conflicting_items.clear();
                 } else if target.block.origin_right() == item.origin_right() {
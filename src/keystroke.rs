@@ -0,0 +1,214 @@
+use crate::block::{CONTENT_TYPE_STRING, ID};
+use crate::id_set::IDSet;
+use crate::lib0::{Encode, Encoder, ReadExt, WriteExt};
+use crate::{ClientID, Clock};
+use std::io::{Cursor, Read};
+
+/// Compact representation of a single-character edit, meant to be sent in place of a regular
+/// update when changes are sent over a constrained channel on every keystroke.
+///
+/// Use [Transaction::keystroke_update](crate::Transaction::keystroke_update) to produce it and
+/// [Transaction::apply_keystroke](crate::Transaction::apply_keystroke) to integrate it. Keystroke
+/// can also be re-encoded into a regular update using [Encode].
+///
+/// Binary format:
+/// - {u8} flags (see `FLAG_*` constants)
+/// - {varint} client ID
+/// - {varint} clock of inserted character or start of deleted range
+/// - for insertions:
+///   - (optional) left origin: {varint} clock delta if origin has the same client,
+///     {varint} client ID and {varint} clock otherwise
+///   - (optional) right origin, encoded the same way as the left one
+///   - {utf8} inserted character
+/// - for deletions: {varint} length of deleted range
+///
+/// Clock delta of an origin is counted backwards from the character's own clock, so that
+/// the common case of appending to a previously typed character takes a single byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Keystroke {
+    /// A single character inserted next to at least one of its origins.
+    Insert {
+        id: ID,
+        chr: char,
+        origin_left: Option<ID>,
+        origin_right: Option<ID>,
+    },
+    /// A continuous range of elements deleted by a single client.
+    Delete { id: ID, len: Clock },
+}
+
+impl Keystroke {
+    const FLAG_DELETE: u8 = 0b0000_0001;
+    const FLAG_ORIGIN_LEFT: u8 = 0b0000_0010;
+    const FLAG_ORIGIN_RIGHT: u8 = 0b0000_0100;
+    const FLAG_LEFT_DELTA: u8 = 0b0000_1000;
+    const FLAG_RIGHT_DELTA: u8 = 0b0001_0000;
+
+    /// Serializes current keystroke using its compact binary format.
+    pub fn to_bytes(&self) -> crate::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(16);
+        match self {
+            Keystroke::Insert {
+                id,
+                chr,
+                origin_left,
+                origin_right,
+            } => {
+                let mut flags = 0;
+                if let Some(origin) = origin_left {
+                    flags |= Self::FLAG_ORIGIN_LEFT;
+                    if origin.client == id.client {
+                        flags |= Self::FLAG_LEFT_DELTA;
+                    }
+                }
+                if let Some(origin) = origin_right {
+                    flags |= Self::FLAG_ORIGIN_RIGHT;
+                    if origin.client == id.client {
+                        flags |= Self::FLAG_RIGHT_DELTA;
+                    }
+                }
+                buf.write_u8(flags)?;
+                buf.write_var(id.client)?;
+                buf.write_var(id.clock)?;
+                for origin in [origin_left, origin_right].into_iter().flatten() {
+                    Self::write_origin(&mut buf, id, origin)?;
+                }
+                let mut utf8 = [0u8; 4];
+                buf.extend_from_slice(chr.encode_utf8(&mut utf8).as_bytes());
+            }
+            Keystroke::Delete { id, len } => {
+                buf.write_u8(Self::FLAG_DELETE)?;
+                buf.write_var(id.client)?;
+                buf.write_var(id.clock)?;
+                buf.write_var(*len)?;
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Deserializes keystroke from its compact binary format.
+    pub fn from_bytes(data: &[u8]) -> crate::Result<Self> {
+        let mut reader = Cursor::new(data);
+        let flags = reader.read_u8()?;
+        let client: ClientID = reader.read_var()?;
+        let clock: Clock = reader.read_var()?;
+        let id = ID::new(client, clock);
+        if flags & Self::FLAG_DELETE != 0 {
+            let len: Clock = reader.read_var()?;
+            if len == Clock::new(0) {
+                return Err(crate::Error::InvalidMapping("Keystroke"));
+            }
+            return Ok(Keystroke::Delete { id, len });
+        }
+        let origin_left = if flags & Self::FLAG_ORIGIN_LEFT != 0 {
+            let delta = flags & Self::FLAG_LEFT_DELTA != 0;
+            Some(Self::read_origin(&mut reader, &id, delta)?)
+        } else {
+            None
+        };
+        let origin_right = if flags & Self::FLAG_ORIGIN_RIGHT != 0 {
+            let delta = flags & Self::FLAG_RIGHT_DELTA != 0;
+            Some(Self::read_origin(&mut reader, &id, delta)?)
+        } else {
+            None
+        };
+        if origin_left.is_none() && origin_right.is_none() {
+            return Err(crate::Error::InvalidMapping("Keystroke"));
+        }
+        let mut utf8 = [0u8; 4];
+        reader.read_exact(&mut utf8[..1])?;
+        let len = match utf8[0] {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        reader.read_exact(&mut utf8[1..len])?;
+        let chr = std::str::from_utf8(&utf8[..len])
+            .ok()
+            .and_then(|s| s.chars().next())
+            .ok_or(crate::Error::InvalidMapping("char"))?;
+        Ok(Keystroke::Insert {
+            id,
+            chr,
+            origin_left,
+            origin_right,
+        })
+    }
+
+    fn write_origin(buf: &mut Vec<u8>, id: &ID, origin: &ID) -> crate::Result<()> {
+        if origin.client == id.client {
+            // origins of the same client always precede the inserted character
+            let delta = id
+                .clock
+                .get()
+                .checked_sub(origin.clock.get())
+                .and_then(|delta| delta.checked_sub(1))
+                .ok_or(crate::Error::InvalidMapping("Keystroke"))?;
+            buf.write_var(delta)?;
+        } else {
+            buf.write_var(origin.client)?;
+            buf.write_var(origin.clock)?;
+        }
+        Ok(())
+    }
+
+    fn read_origin<R: Read>(reader: &mut R, id: &ID, delta: bool) -> crate::Result<ID> {
+        if delta {
+            let delta: u32 = reader.read_var()?;
+            let clock = id
+                .clock
+                .get()
+                .checked_sub(delta)
+                .and_then(|clock| clock.checked_sub(1))
+                .ok_or(crate::Error::InvalidMapping("Keystroke"))?;
+            Ok(ID::new(id.client, clock.into()))
+        } else {
+            let client: ClientID = reader.read_var()?;
+            let clock: Clock = reader.read_var()?;
+            Ok(ID::new(client, clock))
+        }
+    }
+}
+
+impl Encode for Keystroke {
+    /// Encodes current keystroke as a regular update.
+    fn encode_with<E: Encoder>(&self, encoder: &mut E) -> crate::Result<()> {
+        match self {
+            Keystroke::Insert {
+                id,
+                chr,
+                origin_left,
+                origin_right,
+            } => {
+                encoder.write_var(1u32)?; // number of clients
+                encoder.write_var(1u32)?; // number of blocks
+                encoder.write_client(id.client)?;
+                encoder.write_var(id.clock)?;
+                let mut info = CONTENT_TYPE_STRING;
+                if origin_left.is_some() {
+                    info |= 0b1000_0000;
+                }
+                if origin_right.is_some() {
+                    info |= 0b0100_0000;
+                }
+                encoder.write_info(info)?;
+                if let Some(origin) = origin_left {
+                    encoder.write_left_id(origin)?;
+                }
+                if let Some(origin) = origin_right {
+                    encoder.write_right_id(origin)?;
+                }
+                let mut utf8 = [0u8; 4];
+                encoder.write_string(chr.encode_utf8(&mut utf8))?;
+                IDSet::default().encode_with(encoder)
+            }
+            Keystroke::Delete { id, len } => {
+                encoder.write_var(0u32)?; // no blocks
+                let mut delete_set = IDSet::default();
+                delete_set.insert(*id, *len);
+                delete_set.encode_with(encoder)
+            }
+        }
+    }
+}
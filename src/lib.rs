@@ -6,10 +6,12 @@ mod gc;
 mod id_set;
 mod input;
 mod integrate;
+mod keystroke;
 pub mod lib0;
 pub mod lmdb;
 mod multi_doc;
 mod node;
+mod observer;
 mod output;
 mod prelim;
 mod state_vector;
@@ -21,21 +23,37 @@ mod types;
 mod update;
 
 pub use crate::block::{Block, BlockHeader, BlockMut, ID};
+pub use block_reader::{
+    Carrier, Update, canonical_update, compact_updates, tag_update, untag_update,
+};
+pub use content::{Content, ContentType};
+pub use gc::GcStats;
+pub use id_set::IDSet;
 pub use input::In;
+pub use keystroke::Keystroke;
 pub use lib0::Encoding;
-pub use multi_doc::MultiDoc;
+pub use multi_doc::{DocHandle, MultiDoc};
+pub use node::{Named, Node, NodeType};
+pub use observer::IntegrationObserver;
 pub use output::Out;
 pub use prelim::*;
 use serde::{Deserialize, Serialize};
 use smallvec::CollectionAllocErr;
-pub use state_vector::StateVector;
+pub use state_vector::{Snapshot, StateVector};
 use std::collections::TryReserveError;
-pub use transaction::{DbHandle, LazyState, Transaction};
-pub use types::dynamic::{Dyn, DynRef};
+pub use transaction::{
+    ClientBlocks, ClientLimit, CommitFlags, DbHandle, IntegrationLimits, LazyState,
+    TombstoneRetention, Transaction, TransactionSummary,
+};
+pub use types::dynamic::{Dyn, DynRef, UnmountedNode};
 pub use types::list::{List, ListPrelim, ListRef};
-pub use types::map::{Map, MapPrelim, MapRef};
-pub use types::text::{Text, TextRef};
-pub use types::{Mounted, Unmounted};
+pub use types::map::{ChunkedIter, KeyChange, Map, MapPrelim, MapRef, MapResolver};
+pub use types::text::{Text, TextPrelim, TextRef};
+pub use types::xml::{
+    Walk, XmlContainer, XmlElement, XmlElementPrelim, XmlElementRef, XmlEvent, XmlFragment,
+    XmlFragmentRef, XmlNode, XmlPrelim, XmlText, XmlTextPrelim, XmlTextRef,
+};
+pub use types::{Mounted, Unmounted, ValueEncoding};
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -76,14 +94,49 @@ pub enum Error {
     Custom(DynError),
     #[error("block not found: {0}")]
     BlockNotFound(ID),
+    #[error("content of block {0} not found")]
+    ContentNotFound(ID),
     #[error("Client ID must be between 1 and 2^32")]
     ClientIDOutOfRange,
     #[error("LMDB error: {0}")]
-    Lmdb(#[from] crate::lmdb::Error),
+    Lmdb(#[source] crate::lmdb::Error),
+    #[error("all LMDB reader slots are in use, too many read-only transactions are open")]
+    TooManyReaders,
     #[error("expected value couldn't be fit into containing data")]
     ValueTooLarge,
     #[error("hash collision detected on {0}")]
     HashCollision(crate::U32),
+    #[error("expected node of type {expected:?}, found {actual:?}")]
+    NodeTypeMismatch {
+        expected: NodeType,
+        actual: NodeType,
+    },
+    #[error("document exceeded the limit of {max_clients} clients: found {clients}")]
+    ClientLimitExceeded { max_clients: usize, clients: usize },
+    #[error("update integration exceeded the limit of {max} {limit}")]
+    IntegrationLimitExceeded { limit: &'static str, max: usize },
+    #[error("document size {size} bytes exceeds the quota of {quota} bytes")]
+    QuotaExceeded { quota: usize, size: usize },
+    #[error("block {0} already exists, its clock has been allocated more than once")]
+    DuplicateBlock(ID),
+    #[error("root type '{0}' already exists")]
+    RootAlreadyExists(String),
+    #[error("update was produced for document '{actual}', expected '{expected}'")]
+    WrongDocument { expected: String, actual: String },
+    #[error("cannot insert an empty value")]
+    EmptyInsert,
+    #[error("update is tagged with document '{0}', it must be decoded with Update::decode_tagged")]
+    TaggedUpdate(String),
+}
+
+impl From<crate::lmdb::Error> for Error {
+    fn from(e: crate::lmdb::Error) -> Self {
+        if e == crate::lmdb::Error::READERS_FULL {
+            Self::TooManyReaders
+        } else {
+            Self::Lmdb(e)
+        }
+    }
 }
 
 impl From<TryReserveError> for Error {
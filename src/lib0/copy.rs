@@ -35,8 +35,9 @@ fn copy_any<R: Read, W: Write>(
             *n += 8;
         }
         Tag::BigInt => {
-            let num: i64 = src.read_var()?;
-            *n += dst.write_var(num)?;
+            let num: i64 = src.read_i64()?;
+            dst.write_i64(num)?;
+            *n += 8;
         }
         Tag::String | Tag::ByteArray => copy_var_bytes(src, dst, n)?,
         Tag::Object => copy_object(src, dst, n)?,
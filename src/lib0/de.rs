@@ -3,22 +3,109 @@ use crate::lib0::{
     TAG_NULL, TAG_OBJECT, TAG_STRING, TAG_TRUE, TAG_UNDEFINED, Tag, Value,
 };
 use serde::de;
-use serde::de::{DeserializeSeed, Error, SeqAccess, Unexpected, Visitor};
-use smallvec::SmallVec;
+use serde::de::{DeserializeSeed, Error, IntoDeserializer, SeqAccess, Unexpected, Visitor};
 use std::io::Read;
 
-const DEFAULT_INLINE_STRING_SIZE: usize = 16;
+/// Input of the lib0 [Deserializer].
+pub trait Source<'de>: Read {
+    /// Reads a length-prefixed UTF-8 string. If a string cannot be borrowed directly from the
+    /// input, it's copied into a `scratch` buffer instead.
+    fn read_str<'s>(&'s mut self, scratch: &'s mut Vec<u8>) -> Result<Str<'de, 's>, super::Error>;
+}
+
+/// String returned by [Source::read_str].
+pub enum Str<'de, 's> {
+    /// String borrowed from the deserializer input.
+    Borrowed(&'de str),
+    /// String copied into a scratch buffer.
+    Copied(&'s str),
+}
+
+/// [Source] over any [Read] implementation. Strings are always copied.
+pub struct IoReader<R>(R);
+
+impl<R: Read> Read for IoReader<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.0.read_exact(buf)
+    }
+}
+
+impl<'de, R: Read> Source<'de> for IoReader<R> {
+    fn read_str<'s>(&'s mut self, scratch: &'s mut Vec<u8>) -> Result<Str<'de, 's>, super::Error> {
+        scratch.clear();
+        self.0.read_string(scratch)?;
+        Ok(Str::Copied(std::str::from_utf8(scratch)?))
+    }
+}
+
+/// [Source] over an in-memory slice. Strings are borrowed from the slice without copying.
+pub struct SliceReader<'de>(&'de [u8]);
+
+impl<'de> Read for SliceReader<'de> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.0.read_exact(buf)
+    }
+}
+
+impl<'de> Source<'de> for SliceReader<'de> {
+    fn read_str<'s>(&'s mut self, _: &'s mut Vec<u8>) -> Result<Str<'de, 's>, super::Error> {
+        let len: u64 = self.read_var()?;
+        if len > self.0.len() as u64 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        let (bytes, rest) = self.0.split_at(len as usize);
+        self.0 = rest;
+        Ok(Str::Borrowed(std::str::from_utf8(bytes)?))
+    }
+}
 
 pub struct Deserializer<R> {
     reader: R,
     peeked_tag: Option<u8>,
+    scratch: Vec<u8>,
 }
 
-impl<R: Read> Deserializer<R> {
+impl<R: Read> Deserializer<IoReader<R>> {
     pub fn new(reader: R) -> Self {
         Deserializer {
-            reader,
+            reader: IoReader(reader),
             peeked_tag: None,
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl<'de> Deserializer<SliceReader<'de>> {
+    /// Creates a deserializer over an in-memory slice, which can deserialize borrowed strings.
+    pub fn from_slice(buf: &'de [u8]) -> Self {
+        Deserializer {
+            reader: SliceReader(buf),
+            peeked_tag: None,
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl<'de, R: Source<'de>> Deserializer<R> {
+    fn visit_str<V>(&mut self, visitor: V) -> Result<V::Value, super::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.reader.read_str(&mut self.scratch)? {
+            Str::Borrowed(str) => visitor.visit_borrowed_str(str),
+            Str::Copied(str) => visitor.visit_str(str),
         }
     }
 
@@ -50,13 +137,9 @@ impl<R: Read> Deserializer<R> {
         }
     }
 
-    fn deserialize_any_tagged<'v, V>(
-        &mut self,
-        tag: Tag,
-        visitor: V,
-    ) -> Result<V::Value, super::Error>
+    fn deserialize_any_tagged<V>(&mut self, tag: Tag, visitor: V) -> Result<V::Value, super::Error>
     where
-        V: Visitor<'v>,
+        V: Visitor<'de>,
     {
         match tag {
             Tag::Undefined => visitor.visit_unit(),
@@ -79,12 +162,7 @@ impl<R: Read> Deserializer<R> {
             }
             Tag::True => visitor.visit_bool(true),
             Tag::False => visitor.visit_bool(false),
-            Tag::String => {
-                let mut buf: SmallVec<[u8; DEFAULT_INLINE_STRING_SIZE]> = SmallVec::new();
-                self.reader.read_string(&mut buf)?;
-                let str = std::str::from_utf8(&buf)?;
-                visitor.visit_str(str)
-            }
+            Tag::String => self.visit_str(visitor),
             Tag::Object => visitor.visit_map(Access::new(self)?),
             Tag::Array => visitor.visit_seq(Access::new(self)?),
             Tag::ByteArray => {
@@ -96,7 +174,7 @@ impl<R: Read> Deserializer<R> {
     }
 }
 
-impl<'de, R: Read> serde::Deserializer<'de> for &mut Deserializer<R> {
+impl<'de, R: Source<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
     type Error = super::Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -226,9 +304,10 @@ impl<'de, R: Read> serde::Deserializer<'de> for &mut Deserializer<R> {
         V: Visitor<'de>,
     {
         self.expect_tag(TAG_STRING)?;
-        let mut buf: SmallVec<[u8; 4]> = SmallVec::new();
-        self.reader.read_string(&mut buf)?;
-        let str = std::str::from_utf8(&buf)?;
+        let str = match self.reader.read_str(&mut self.scratch)? {
+            Str::Borrowed(str) => str,
+            Str::Copied(str) => str,
+        };
         match str.chars().next() {
             None => Err(super::Error::invalid_value(
                 Unexpected::Str(""),
@@ -243,10 +322,7 @@ impl<'de, R: Read> serde::Deserializer<'de> for &mut Deserializer<R> {
         V: Visitor<'de>,
     {
         self.expect_tag(TAG_STRING)?;
-        let mut buf: SmallVec<[u8; DEFAULT_INLINE_STRING_SIZE]> = SmallVec::new();
-        self.reader.read_string(&mut buf)?;
-        let str = std::str::from_utf8(&buf)?;
-        visitor.visit_str(str)
+        self.visit_str(visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -254,10 +330,7 @@ impl<'de, R: Read> serde::Deserializer<'de> for &mut Deserializer<R> {
         V: Visitor<'de>,
     {
         self.expect_tag(TAG_STRING)?;
-        let mut buf = String::new();
-        let writer = unsafe { buf.as_mut_vec() };
-        self.reader.read_string(writer)?;
-        visitor.visit_string(buf)
+        self.visit_str(visitor)
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -380,8 +453,17 @@ impl<'de, R: Read> serde::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        self.expect_tag(TAG_OBJECT)?;
-        visitor.visit_enum(Access::new(self)?)
+        match self.read_tag()? {
+            TAG_OBJECT => visitor.visit_enum(Access::new(self)?),
+            TAG_STRING => {
+                // unit variants can also be encoded as plain strings (eg. by JavaScript peers)
+                match self.reader.read_str(&mut self.scratch)? {
+                    Str::Borrowed(variant) => visitor.visit_enum(variant.into_deserializer()),
+                    Str::Copied(variant) => visitor.visit_enum(variant.into_deserializer()),
+                }
+            }
+            tag => Err(super::Error::UnknownTag(tag)),
+        }
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -404,14 +486,14 @@ struct Access<'a, R> {
     len: usize,
 }
 
-impl<'a, R: Read> Access<'a, R> {
+impl<'a, 'de, R: Source<'de>> Access<'a, R> {
     fn new(de: &'a mut Deserializer<R>) -> Result<Self, super::Error> {
         let len = de.reader.read_var()?;
         Ok(Access { de, len })
     }
 }
 
-impl<'a, 'de, R: Read> de::SeqAccess<'de> for Access<'a, R> {
+impl<'a, 'de, R: Source<'de>> de::SeqAccess<'de> for Access<'a, R> {
     type Error = super::Error;
 
     #[inline]
@@ -433,7 +515,7 @@ impl<'a, 'de, R: Read> de::SeqAccess<'de> for Access<'a, R> {
     }
 }
 
-impl<'a, 'de, R: Read> de::MapAccess<'de> for Access<'a, R> {
+impl<'a, 'de, R: Source<'de>> de::MapAccess<'de> for Access<'a, R> {
     type Error = super::Error;
 
     #[inline]
@@ -463,7 +545,7 @@ impl<'a, 'de, R: Read> de::MapAccess<'de> for Access<'a, R> {
     }
 }
 
-impl<'a, 'de, R: Read> de::EnumAccess<'de> for Access<'a, R> {
+impl<'a, 'de, R: Source<'de>> de::EnumAccess<'de> for Access<'a, R> {
     type Error = super::Error;
     type Variant = Self;
 
@@ -475,16 +557,22 @@ impl<'a, 'de, R: Read> de::EnumAccess<'de> for Access<'a, R> {
     }
 }
 
-impl<'a, 'de, R: Read> de::VariantAccess<'de> for Access<'a, R> {
+impl<'a, 'de, R: Source<'de>> de::VariantAccess<'de> for Access<'a, R> {
     type Error = super::Error;
 
     fn unit_variant(self) -> Result<(), Self::Error> {
-        self.de.expect_tag(TAG_ARRAY)?;
-        let mut access = Access::new(self.de)?;
-        while let Some(_) = access.next_element::<Value>()? {
-            // skip over all possible values for forward compatibility
+        match self.de.read_tag()? {
+            TAG_ARRAY => {
+                let mut access = Access::new(self.de)?;
+                while let Some(_) = access.next_element::<Value>()? {
+                    // skip over all possible values for forward compatibility
+                }
+                Ok(())
+            }
+            // `{ "variant": undefined }` and `{ "variant": null }` are also accepted
+            TAG_UNDEFINED | TAG_NULL => Ok(()),
+            tag => Err(super::Error::UnknownTag(tag)),
         }
-        Ok(())
     }
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
@@ -497,7 +585,7 @@ impl<'a, 'de, R: Read> de::VariantAccess<'de> for Access<'a, R> {
             None => {
                 return Err(super::Error::invalid_length(
                     0,
-                    &"newtype variant with >1 element",
+                    &"newtype variant with 1 element",
                 ));
             }
             Some(value) => value,
@@ -531,7 +619,7 @@ struct MapKey<'a, R> {
     de: &'a mut Deserializer<R>,
 }
 
-impl<'a, 'de, R: Read> de::Deserializer<'de> for MapKey<'a, R> {
+impl<'a, 'de, R: Source<'de>> de::Deserializer<'de> for MapKey<'a, R> {
     type Error = super::Error;
 
     #[inline]
@@ -643,10 +731,7 @@ impl<'a, 'de, R: Read> de::Deserializer<'de> for MapKey<'a, R> {
     where
         V: Visitor<'de>,
     {
-        let mut buf: SmallVec<[u8; DEFAULT_INLINE_STRING_SIZE]> = SmallVec::new();
-        self.de.reader.read_string(&mut buf)?;
-        let str = std::str::from_utf8(&buf)?;
-        visitor.visit_str(str)
+        self.de.visit_str(visitor)
     }
 
     #[inline]
@@ -654,10 +739,7 @@ impl<'a, 'de, R: Read> de::Deserializer<'de> for MapKey<'a, R> {
     where
         V: Visitor<'de>,
     {
-        let mut s = String::new();
-        let buf = unsafe { s.as_mut_vec() };
-        self.de.reader.read_string(buf)?;
-        visitor.visit_string(s)
+        self.de.visit_str(visitor)
     }
 
     #[inline]
@@ -1,5 +1,5 @@
-use serde::Serialize;
 use serde::de::{DeserializeOwned, Expected};
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::io::{Read, Write};
 use std::ops::Range;
@@ -86,14 +86,26 @@ where
     Ok(buf)
 }
 
-pub fn from_slice<T>(buf: &[u8]) -> Result<T, Error>
+/// Deserializes a value from an in-memory buffer. Unlike [from_reader], strings can be
+/// deserialized as borrowed from the `buf` without copying.
+pub fn from_slice<'de, T>(buf: &'de [u8]) -> Result<T, Error>
 where
-    T: DeserializeOwned,
+    T: Deserialize<'de>,
 {
-    let mut deserializer = de::Deserializer::new(buf);
+    let mut deserializer = de::Deserializer::from_slice(buf);
     T::deserialize(&mut deserializer)
 }
 
+/// Same as [to_vec], but uses a given [NonFinitePolicy] for NaN and infinite numbers.
+pub fn to_vec_with<T>(value: &T, non_finite: NonFinitePolicy) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut buf = Vec::new();
+    to_writer_with(&mut buf, value, non_finite)?;
+    Ok(buf)
+}
+
 pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
 where
     W: Write,
@@ -103,6 +115,16 @@ where
     value.serialize(&mut serializer)
 }
 
+/// Same as [to_writer], but uses a given [NonFinitePolicy] for NaN and infinite numbers.
+pub fn to_writer_with<W, T>(writer: W, value: &T, non_finite: NonFinitePolicy) -> Result<(), Error>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    let mut serializer = ser::Serializer::with_policy(writer, non_finite);
+    value.serialize(&mut serializer)
+}
+
 pub fn from_reader<R, T>(reader: R) -> Result<T, Error>
 where
     R: Read,
@@ -119,6 +141,24 @@ where
     T::deserialize(value)
 }
 
+/// Defines how NaN and infinite floating point numbers are serialized. These values cannot be
+/// represented in JSON, and storing them may corrupt the document state of JavaScript peers.
+///
+/// The policy only applies to values serialized with [to_vec_with] and [to_writer_with]. Values
+/// inserted into documents are always serialized with [NonFinitePolicy::Allow]. To keep them out
+/// of a document, serialize the value with [NonFinitePolicy::Reject] before inserting it.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Fail serialization with [Error::NonFiniteNumber].
+    Reject,
+    /// Serialize NaN and infinite numbers as `null`.
+    Null,
+    /// Serialize NaN and infinite numbers as they are. This is the default, used by [to_vec] and
+    /// [to_writer].
+    #[default]
+    Allow,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("I/O error: {0}")]
@@ -135,6 +175,8 @@ pub enum Error {
     Custom(String),
     #[error("invalid type: {0}")]
     InvalidType(ValueKind),
+    #[error("cannot serialize non-finite number: {0}")]
+    NonFiniteNumber(f64),
 }
 
 impl serde::ser::Error for Error {
@@ -366,6 +408,11 @@ impl Display for BufferReservationError {
 }
 
 pub trait Decoder: Read {
+    /// Reads a document GUID tag (see [crate::tag_update]), which may precede an encoded update.
+    /// It must be called before anything else is read from the update. Returns `None` if the
+    /// update was not tagged.
+    fn read_guid_tag(&mut self) -> crate::Result<Option<String>>;
+
     /// Reset the value of current delete set state.
     fn reset_ds_cur_val(&mut self);
 
@@ -496,6 +543,49 @@ pub trait ReadExt: Read + Sized {
         self.read_exact(&mut buf)?;
         Ok(u64::from_be_bytes(buf))
     }
+
+    /// Advance the reader by `len` bytes without reading them.
+    fn skip(&mut self, len: u64) -> std::io::Result<()> {
+        let skipped = std::io::copy(&mut self.by_ref().take(len), &mut std::io::sink())?;
+        if skipped == len {
+            Ok(())
+        } else {
+            Err(std::io::ErrorKind::UnexpectedEof.into())
+        }
+    }
+
+    /// Advance the reader past the next value encoded in lib0 any format without decoding it.
+    /// Arrays and objects are skipped together with all of their nested values.
+    fn skip_value(&mut self) -> Result<(), Error> {
+        let tag = Tag::try_from(self.read_u8()?)?;
+        match tag {
+            Tag::Undefined | Tag::Null | Tag::True | Tag::False => { /* no payload */ }
+            Tag::VarInt => {
+                let _: i64 = self.read_var()?;
+            }
+            Tag::Float32 => self.skip(4)?,
+            Tag::Float64 | Tag::BigInt => self.skip(8)?,
+            Tag::String | Tag::ByteArray => {
+                let len: u64 = self.read_var()?;
+                self.skip(len)?;
+            }
+            Tag::Object => {
+                let len: u64 = self.read_var()?;
+                for _ in 0..len {
+                    let key_len: u64 = self.read_var()?;
+                    self.skip(key_len)?;
+                    self.skip_value()?;
+                }
+            }
+            Tag::Array => {
+                let len: u64 = self.read_var()?;
+                for _ in 0..len {
+                    self.skip_value()?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<T: Read> ReadExt for T {}
@@ -1,6 +1,6 @@
 use crate::lib0::{
-    ExpectedString, TAG_ARRAY, TAG_BIGINT, TAG_BYTE_ARRAY, TAG_FALSE, TAG_FLOAT32, TAG_FLOAT64,
-    TAG_INTEGER, TAG_NULL, TAG_OBJECT, TAG_STRING, TAG_TRUE, TAG_UNDEFINED, WriteExt,
+    ExpectedString, NonFinitePolicy, TAG_ARRAY, TAG_BIGINT, TAG_BYTE_ARRAY, TAG_FALSE, TAG_FLOAT32,
+    TAG_FLOAT64, TAG_INTEGER, TAG_NULL, TAG_OBJECT, TAG_STRING, TAG_TRUE, TAG_UNDEFINED, WriteExt,
 };
 use serde::Serialize;
 use serde::de::{Error, Unexpected};
@@ -12,11 +12,33 @@ use std::io::Write;
 
 pub(super) struct Serializer<W> {
     writer: W,
+    non_finite: NonFinitePolicy,
 }
 
 impl<W: Write> Serializer<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self::with_policy(writer, NonFinitePolicy::default())
+    }
+
+    pub fn with_policy(writer: W, non_finite: NonFinitePolicy) -> Self {
+        Self { writer, non_finite }
+    }
+
+    /// Checks a non-finite number against the [NonFinitePolicy]. Returns `true` if the number
+    /// has already been written as `null`.
+    fn handle_non_finite(&mut self, v: f64) -> Result<bool, super::Error> {
+        if v.is_finite() {
+            return Ok(false);
+        }
+        match self.non_finite {
+            NonFinitePolicy::Reject => Err(super::Error::NonFiniteNumber(v)),
+            NonFinitePolicy::Null => {
+                // TYPE 126: NULL
+                self.writer.write_u8(TAG_NULL)?;
+                Ok(true)
+            }
+            NonFinitePolicy::Allow => Ok(false),
+        }
     }
 }
 
@@ -64,11 +86,11 @@ impl<'a, W: Write> serde::ser::Serializer for &'a mut Serializer<W> {
             // TYPE 125: INTEGER
             self.writer.write_u8(TAG_INTEGER)?;
             self.writer.write_var(v)?;
-        } else if ((v as f32) as i64) == v {
+        } else if is_exact_f32(v) {
             // TYPE 124: FLOAT32
             self.writer.write_u8(TAG_FLOAT32)?;
             self.writer.write_f32(v as f32)?;
-        } else if ((v as f64) as i64) == v {
+        } else if is_exact_f64(v) {
             // TYPE 123: FLOAT64
             self.writer.write_u8(TAG_FLOAT64)?;
             self.writer.write_f64(v as f64)?;
@@ -115,6 +137,9 @@ impl<'a, W: Write> serde::ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        if self.handle_non_finite(v as f64)? {
+            return Ok(());
+        }
         // TYPE 124: FLOAT32
         self.writer.write_u8(TAG_FLOAT32)?;
         self.writer.write_f32(v)?;
@@ -122,6 +147,9 @@ impl<'a, W: Write> serde::ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        if self.handle_non_finite(v)? {
+            return Ok(());
+        }
         if ((v as f32) as f64) == v {
             // TYPE 1234 FLOAT32
             self.writer.write_u8(TAG_FLOAT32)?;
@@ -295,6 +323,24 @@ impl<'a, W: Write> serde::ser::Serializer for &'a mut Serializer<W> {
     }
 }
 
+/// Float to integer casts are saturating, so an integer is considered exactly representable
+/// only if it's strictly within i64 bounds (2^63 itself can't be cast back to i64).
+const I64_FLOAT_BOUND: f64 = 9_223_372_036_854_775_808.0;
+
+/// Checks if `v` can be encoded as f32 without losing precision.
+#[inline]
+fn is_exact_f32(v: i64) -> bool {
+    let f = v as f32;
+    (f as f64) < I64_FLOAT_BOUND && f as i64 == v
+}
+
+/// Checks if `v` can be encoded as f64 without losing precision.
+#[inline]
+fn is_exact_f64(v: i64) -> bool {
+    let f = v as f64;
+    f < I64_FLOAT_BOUND && f as i64 == v
+}
+
 pub(super) struct SeqSerializer<'a, W> {
     ser: &'a mut Serializer<W>,
 }
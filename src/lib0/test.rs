@@ -74,6 +74,237 @@ fn serialize_value() {
     ])));
 }
 
+#[test]
+fn enum_variants_roundtrip() {
+    let values = vec![
+        AllVariants::Unit,
+        AllVariants::Newtype("hello".into()),
+        AllVariants::Tuple(1, Some(2.5)),
+        AllVariants::Struct { x: -3, y: None },
+    ];
+    for value in values.iter() {
+        roundtrip(value);
+
+        // roundtrip through intermediate lib0 Value
+        let buf = super::to_vec(value).unwrap();
+        let intermediate: Value = super::from_slice(&buf).unwrap();
+        let actual: AllVariants = super::from_value(intermediate).unwrap();
+        assert_eq!(&actual, value);
+    }
+    roundtrip(&values);
+}
+
+#[test]
+fn unit_variant_alternative_encodings() {
+    for value in [
+        crate::lib0!("Unit"),
+        crate::lib0!({ "Unit": null }),
+        crate::lib0!({ "Unit": [] }),
+        Value::Object(HashMap::from([("Unit".to_string(), Value::Undefined)])),
+    ] {
+        let buf = super::to_vec(&value).unwrap();
+        let actual: AllVariants = super::from_slice(&buf).unwrap();
+        assert_eq!(actual, AllVariants::Unit, "failed to decode {value}");
+        let actual: AllVariants = super::from_value(value).unwrap();
+        assert_eq!(actual, AllVariants::Unit);
+    }
+}
+
+#[test]
+fn deserialize_borrowed_str() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Borrowed<'a> {
+        name: &'a str,
+        tags: Vec<&'a str>,
+    }
+
+    let value = Borrowed {
+        name: "hello world",
+        tags: vec!["a", "bc", ""],
+    };
+    let buf = super::to_vec(&value).unwrap();
+    let actual: Borrowed = super::from_slice(&buf).unwrap();
+    assert_eq!(actual, value);
+    // strings point directly into the source buffer
+    let range = buf.as_ptr_range();
+    assert!(range.contains(&actual.name.as_ptr()));
+    assert!(range.contains(&actual.tags[1].as_ptr()));
+
+    // readers don't support borrowing, but owned strings are still fine
+    let owned: Value = super::from_reader(Cursor::new(&buf)).unwrap();
+    assert_eq!(
+        owned,
+        crate::lib0!({"name": "hello world", "tags": ["a", "bc", ""]})
+    );
+}
+
+#[test]
+fn skip_values() {
+    use super::ReadExt;
+    let values = [
+        crate::lib0!(null),
+        crate::lib0!(true),
+        crate::lib0!(-1234),
+        crate::lib0!(0.5),
+        crate::lib0!(0.1),
+        Value::Number(i64::MAX.into()),
+        crate::lib0!("hello"),
+        Value::Bytes(Bytes::copy_from_slice(b"deadbeef")),
+        crate::lib0!([1, "two", [3.5, {"four": 4}]]),
+        crate::lib0!({"a": {"b": [null, false]}, "c": "d"}),
+        crate::lib0!(false),
+    ];
+    let mut buf = Vec::new();
+    for value in values.iter() {
+        super::to_writer(&mut buf, value).unwrap();
+    }
+
+    for (n, expected) in values.iter().enumerate() {
+        let mut reader = Cursor::new(buf.as_slice());
+        for _ in 0..n {
+            reader.skip_value().unwrap();
+        }
+        let actual: Value = super::from_reader(&mut reader).unwrap();
+        assert_eq!(&actual, expected, "failed after skipping {n} values");
+    }
+
+    let mut reader = Cursor::new(&buf[..buf.len() - 2]);
+    for _ in 0..values.len() - 2 {
+        reader.skip_value().unwrap();
+    }
+    assert!(reader.skip_value().is_err());
+}
+
+#[test]
+fn serialize_i64_boundaries() {
+    const SAFE: i64 = (1 << 53) - 1;
+    let values = [
+        0,
+        -1,
+        i32::MIN as i64,
+        i32::MAX as i64,
+        SAFE,
+        -SAFE,
+        SAFE + 1,
+        SAFE + 2,
+        -SAFE - 1,
+        -SAFE - 2,
+        -(1 << 24) - 1,
+        (1 << 60) + 1,
+        -(1 << 60) - 1,
+        1 << 62,
+        -(1 << 62),
+        i64::MAX,
+        i64::MAX - 1,
+        i64::MIN,
+        i64::MIN + 1,
+    ];
+    for v in values {
+        let buf = super::to_vec(&v).unwrap();
+        let actual: i64 = super::from_slice(&buf).unwrap();
+        assert_eq!(actual, v, "failed to roundtrip {v} (tag: {})", buf[0]);
+    }
+
+    // i64::MAX as a float would be rounded up to 2^63, which is out of i64 bounds
+    let buf = super::to_vec(&i64::MAX).unwrap();
+    assert_eq!(buf[0], super::TAG_BIGINT);
+}
+
+#[test]
+fn copy_bigint() {
+    // BigInt payload is a fixed 8-byte big endian integer, not a var int
+    for v in [i64::MAX, i64::MAX - 1, (1 << 60) + 1] {
+        let buf = super::to_vec(&v).unwrap();
+        assert_eq!(buf[0], super::TAG_BIGINT);
+        roundtrip(&v);
+    }
+    roundtrip(&crate::lib0!([i64::MAX, "after", (1i64 << 60) + 1]));
+}
+
+#[test]
+fn serialize_non_finite_numbers() {
+    use super::{Error, NonFinitePolicy};
+    for v in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        // rejected on demand
+        let reject = NonFinitePolicy::Reject;
+        let err = super::to_vec_with(&v, reject).unwrap_err();
+        assert!(matches!(err, Error::NonFiniteNumber(_)), "{v}: {err}");
+        let err = super::to_vec_with(&(v as f32), reject).unwrap_err();
+        assert!(matches!(err, Error::NonFiniteNumber(_)), "{v}: {err}");
+        let err = super::to_vec_with(&Value::Number(v.into()), reject).unwrap_err();
+        assert!(matches!(err, Error::NonFiniteNumber(_)), "{v}: {err}");
+
+        // mapped to null
+        let buf = super::to_vec_with(&vec![v], NonFinitePolicy::Null).unwrap();
+        let value: Value = super::from_slice(&buf).unwrap();
+        assert_eq!(value, Value::Array(vec![Value::Null]));
+
+        // written as is by default
+        let buf = super::to_vec(&v).unwrap();
+        assert_eq!(buf, super::to_vec_with(&v, NonFinitePolicy::Allow).unwrap());
+        let actual: f64 = super::from_slice(&buf).unwrap();
+        if v.is_nan() {
+            assert!(actual.is_nan());
+        } else {
+            assert_eq!(actual, v);
+        }
+    }
+}
+
+#[test]
+fn merge_nested_objects() {
+    let mut value = crate::lib0!({
+        "name": "default",
+        "editor": {
+            "theme": "light",
+            "font": { "family": "mono", "size": 12 }
+        }
+    });
+    value.merge(crate::lib0!({
+        "editor": {
+            "font": { "size": 14 },
+            "wrap": true
+        }
+    }));
+    assert_eq!(
+        value,
+        crate::lib0!({
+            "name": "default",
+            "editor": {
+                "theme": "light",
+                "font": { "family": "mono", "size": 14 },
+                "wrap": true
+            }
+        })
+    );
+}
+
+#[test]
+fn merge_replaces_scalars_and_arrays() {
+    let mut value = crate::lib0!({
+        "count": 1,
+        "tags": ["a", "b"],
+        "nested": { "x": 1 }
+    });
+    value.merge(crate::lib0!({
+        "count": "many",
+        "tags": ["c"],
+        "nested": null
+    }));
+    assert_eq!(
+        value,
+        crate::lib0!({
+            "count": "many",
+            "tags": ["c"],
+            "nested": null
+        })
+    );
+
+    let mut value = crate::lib0!([1, 2, 3]);
+    value.merge(crate::lib0!({ "a": 1 }));
+    assert_eq!(value, crate::lib0!({ "a": 1 }));
+}
+
 #[test]
 fn serialize_deserialize() {
     let data = TestData {
@@ -175,6 +406,14 @@ enum ADT {
     C(Option<f32>),
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum AllVariants {
+    Unit,
+    Newtype(String),
+    Tuple(u32, Option<f64>),
+    Struct { x: i64, y: Option<bool> },
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 enum MultiFieldADT {
     A(u32, u32),
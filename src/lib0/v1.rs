@@ -115,21 +115,44 @@ impl<W: Write> Encoder for EncoderV1<W> {
 
 pub struct DecoderV1<R> {
     reader: R,
+    guid: crate::Result<Option<String>>,
 }
 
 impl<R: Read> DecoderV1<R> {
+    /// Creates a decoder reading from a given `reader`. Unlike [DecoderV1::from_slice], it doesn't
+    /// recognize GUID tags (see [crate::tag_update]), as that would require reading ahead.
     #[inline]
     pub fn new(reader: R) -> Self {
-        DecoderV1 { reader }
+        DecoderV1 {
+            reader,
+            guid: Ok(None),
+        }
+    }
+
+    /// Creates a decoder over an update which GUID tag has already been read from the `reader`.
+    pub(crate) fn tagged(reader: R, guid: Option<String>) -> Self {
+        DecoderV1 {
+            reader,
+            guid: Ok(guid),
+        }
     }
 }
 
 impl<'a> DecoderV1<Cursor<&'a [u8]>> {
+    /// Creates a decoder reading from a given `slice`. If it starts with a GUID tag
+    /// (see [crate::tag_update]), the tag is skipped and returned by [Decoder::read_guid_tag].
     pub fn from_slice<T>(slice: &'a T) -> Self
     where
         T: AsRef<[u8]> + ?Sized,
     {
-        DecoderV1::new(Cursor::new(slice.as_ref()))
+        match crate::untag_update(slice.as_ref()) {
+            Ok((guid, update)) => DecoderV1::tagged(Cursor::new(update), guid.map(str::to_owned)),
+            // malformed tag is reported by `read_guid_tag`
+            Err(e) => DecoderV1 {
+                reader: Cursor::new(&[]),
+                guid: Err(e),
+            },
+        }
     }
 }
 
@@ -156,6 +179,11 @@ impl<R: Read> Read for DecoderV1<R> {
 }
 
 impl<R: Read> Decoder for DecoderV1<R> {
+    #[inline]
+    fn read_guid_tag(&mut self) -> crate::Result<Option<String>> {
+        std::mem::replace(&mut self.guid, Ok(None))
+    }
+
     #[inline]
     fn reset_ds_cur_val(&mut self) {}
 
@@ -1,3 +1,4 @@
+use crate::block_reader::{GUID_TAG, read_guid};
 use crate::lib0::varint::Signed;
 use crate::lib0::{Decoder, Encoder, ReadExt, WriteExt};
 use crate::{ClientID, Clock, ID};
@@ -78,7 +79,8 @@ impl<W: Write> Write for EncoderV2<W> {
         buf.write_bytes(type_ref)?;
         buf.write_bytes(len)?;
         buf.write_all(rest)?;
-        Ok(())
+        self.writer.write_all(&buf)?;
+        self.writer.flush()
     }
 
     fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
@@ -371,6 +373,7 @@ impl StringEncoder {
 /// Version 2 of lib0 decoder.
 pub struct DecoderV2<R> {
     reader: R,
+    guid: Option<String>,
     keys: Vec<String>,
     ds_curr_val: Clock,
     key_clock_decoder: IntDiffOptRleDecoder,
@@ -392,8 +395,16 @@ impl<'a> DecoderV2<Cursor<&'a [u8]>> {
 
 impl<R: Read> DecoderV2<R> {
     pub fn new(mut reader: R) -> crate::Result<Self> {
-        // read feature flag - currently unused
-        let _: u8 = reader.read_u8()?;
+        // read feature flag - currently unused. It's always zero, so it's never confused with
+        // a GUID tag (see [crate::tag_update]) preceding it
+        let mut guid = None;
+        if reader.read_u8()? == GUID_TAG[0] {
+            if reader.read_u8()? != GUID_TAG[1] {
+                return Err(crate::Error::InvalidMapping("guid"));
+            }
+            guid = Some(read_guid(&mut reader)?);
+            let _: u8 = reader.read_u8()?;
+        }
 
         let key_clock_buf = Self::read_buf(&mut reader)?;
         let client_buf = Self::read_buf(&mut reader)?;
@@ -406,6 +417,7 @@ impl<R: Read> DecoderV2<R> {
         let len_buf = Self::read_buf(&mut reader)?;
         Ok(DecoderV2 {
             reader,
+            guid,
             ds_curr_val: Clock::new(0),
             keys: Vec::new(),
             key_clock_decoder: IntDiffOptRleDecoder::new(Cursor::new(key_clock_buf)),
@@ -434,6 +446,10 @@ impl<R: Read> Read for DecoderV2<R> {
 }
 
 impl<R: Read> Decoder for DecoderV2<R> {
+    fn read_guid_tag(&mut self) -> crate::Result<Option<String>> {
+        Ok(self.guid.take())
+    }
+
     fn reset_ds_cur_val(&mut self) {
         self.ds_curr_val = Clock::new(0);
     }
@@ -1,6 +1,9 @@
 use bytes::Bytes;
 use serde::de::value::StringDeserializer;
-use serde::de::{DeserializeSeed, Error, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::de::{
+    DeserializeSeed, EnumAccess, Error, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
@@ -107,6 +110,25 @@ impl Value {
             _ => None,
         }
     }
+
+    /// Deep-merges `other` into the current value. If both values are objects, their entries
+    /// are merged recursively. In any other case (including arrays) the current value is
+    /// replaced by `other`.
+    pub fn merge(&mut self, other: Value) {
+        match (self, other) {
+            (Value::Object(target), Value::Object(source)) => {
+                for (key, value) in source {
+                    match target.get_mut(&key) {
+                        Some(existing) => existing.merge(value),
+                        None => {
+                            target.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (this, other) => *this = other,
+        }
+    }
 }
 
 impl Serialize for Value {
@@ -577,6 +599,10 @@ impl<'de> Deserializer<'de> for Value {
     {
         match self {
             Value::String(str) => visitor.visit_enum(str.into_deserializer()),
+            Value::Object(entries) if entries.len() == 1 => {
+                let (variant, value) = entries.into_iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer { variant, value })
+            }
             other => Err(super::Error::InvalidType(other.kind())),
         }
     }
@@ -682,6 +708,71 @@ impl<'de, I: Iterator<Item = (String, Value)> + ExactSizeIterator> MapAccess<'de
     }
 }
 
+/// Deserializer of enum variants encoded as `{ "variant": value }`, which is a format produced
+/// by lib0 serializer for all non-unit variants.
+struct EnumDeserializer {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = super::Error;
+    type Variant = Value;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(StringDeserializer::<super::Error>::new(self.variant))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Value {
+    type Error = super::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self {
+            Value::Array(_) | Value::Undefined | Value::Null => Ok(()),
+            other => Err(super::Error::InvalidType(other.kind())),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self {
+            Value::Array(values) => match values.into_iter().next() {
+                Some(value) => seed.deserialize(value),
+                None => Err(super::Error::invalid_length(
+                    0,
+                    &"newtype variant with 1 element",
+                )),
+            },
+            other => Err(super::Error::InvalidType(other.kind())),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Number {
     Int(i64),
@@ -6,6 +6,7 @@
 
 use bitflags::bitflags;
 use lmdb_master_sys::*;
+use std::cell::Cell;
 use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 use std::path::Path;
@@ -24,6 +25,7 @@ impl Error {
     pub const KEY_EXISTS: Self = Self(MDB_KEYEXIST);
     pub const MAP_FULL: Self = Self(MDB_MAP_FULL);
     pub const DBS_FULL: Self = Self(MDB_DBS_FULL);
+    pub const READERS_FULL: Self = Self(MDB_READERS_FULL);
 }
 
 impl std::fmt::Display for Error {
@@ -188,6 +190,14 @@ impl EnvBuilder {
         self
     }
 
+    /// Set the maximum number of threads/reader slots for the environment. This defines
+    /// the number of read-only transactions that can be open at the same time. LMDB uses 126
+    /// by default.
+    pub fn max_readers(self, n: u32) -> Self {
+        unsafe { mdb_env_set_maxreaders(self.env, n) };
+        self
+    }
+
     /// Set the memory map size in bytes.
     pub fn map_size(self, size: usize) -> Self {
         unsafe { mdb_env_set_mapsize(self.env, size) };
@@ -256,6 +266,20 @@ impl<'env> RwTxn<'env> {
         Database {
             txn: self.txn,
             dbi: dbi.0,
+            size_delta: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a [`Database`] view for the given database handle, which adds the change in total
+    /// size (in bytes) of keys and values written or deleted through it to a given `size_delta`.
+    ///
+    /// Tracking requires looking up the previous size of every overwritten or deleted entry.
+    pub fn bind_tracked<'a>(&'a self, dbi: &Dbi, size_delta: &'a Cell<isize>) -> Database<'a> {
+        Database {
+            txn: self.txn,
+            dbi: dbi.0,
+            size_delta: Some(size_delta),
             _marker: PhantomData,
         }
     }
@@ -297,6 +321,7 @@ impl Drop for RwTxn<'_> {
 pub struct Database<'txn> {
     txn: *mut MDB_txn,
     dbi: MDB_dbi,
+    size_delta: Option<&'txn Cell<isize>>,
     _marker: PhantomData<&'txn ()>,
 }
 
@@ -312,16 +337,41 @@ impl<'txn> Database<'txn> {
 
     /// Store a key-value pair (overwrites any existing value for the key).
     pub fn put(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        let old_len = self.tracked_len(key)?;
         let mut key_val = to_mdb_val(key);
         let mut data_val = to_mdb_val(value);
         let rc = unsafe { mdb_put(self.txn, self.dbi, &mut key_val, &mut data_val, 0) };
-        lmdb_result(rc)
+        lmdb_result(rc)?;
+        self.track(old_len, key.len() + value.len());
+        Ok(())
     }
 
     pub fn del(&self, key: &[u8]) -> Result<(), Error> {
+        let old_len = self.tracked_len(key)?;
         let mut key_val = to_mdb_val(key);
         let rc = unsafe { mdb_del(self.txn, self.dbi, &mut key_val, null_mut()) };
-        lmdb_result(rc)
+        lmdb_result(rc)?;
+        self.track(old_len, 0);
+        Ok(())
+    }
+
+    /// Returns the size of an entry stored under a given `key` (0 if there's none), but only
+    /// if this view tracks size changes.
+    fn tracked_len(&self, key: &[u8]) -> Result<usize, Error> {
+        if self.size_delta.is_none() {
+            return Ok(0);
+        }
+        match self.get(key) {
+            Ok(value) => Ok(key.len() + value.len()),
+            Err(Error::NOT_FOUND) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn track(&self, old_len: usize, new_len: usize) {
+        if let Some(size_delta) = self.size_delta {
+            size_delta.set(size_delta.get() + new_len as isize - old_len as isize);
+        }
     }
 
     /// Empty all contents of the database, but keep the database itself.
@@ -343,6 +393,7 @@ impl<'txn> Database<'txn> {
         lmdb_result(rc)?;
         Ok(Cursor {
             cursor,
+            size_delta: self.size_delta,
             _marker: PhantomData,
         })
     }
@@ -358,6 +409,7 @@ impl<'txn> Database<'txn> {
 /// outlive the transaction.
 pub struct Cursor<'txn> {
     cursor: *mut MDB_cursor,
+    size_delta: Option<&'txn Cell<isize>>,
     _marker: PhantomData<&'txn ()>,
 }
 
@@ -427,25 +479,66 @@ impl<'txn> Cursor<'txn> {
 
     /// Write a key-value pair via the cursor (`mdb_cursor_put`).
     pub fn put(&mut self, key: &[u8], value: &[u8], flags: u32) -> Result<(), Error> {
+        let old_len = if flags & MDB_CURRENT != 0 {
+            self.tracked_current_len()?
+        } else if self.size_delta.is_some() {
+            self.database().tracked_len(key)?
+        } else {
+            0
+        };
         let mut key_val = to_mdb_val(key);
         let mut data_val = to_mdb_val(value);
         let rc = unsafe { mdb_cursor_put(self.cursor, &mut key_val, &mut data_val, flags) };
-        lmdb_result(rc)
+        lmdb_result(rc)?;
+        self.track(old_len, key.len() + value.len());
+        Ok(())
     }
 
     /// Replace the value at the current cursor position (`MDB_CURRENT`).
     /// The caller must provide the key that matches the current position.
     pub fn put_current(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        let old_len = self.tracked_current_len()?;
         let mut key_val = to_mdb_val(key);
         let mut data_val = to_mdb_val(value);
         let rc = unsafe { mdb_cursor_put(self.cursor, &mut key_val, &mut data_val, MDB_CURRENT) };
-        lmdb_result(rc)
+        lmdb_result(rc)?;
+        self.track(old_len, key.len() + value.len());
+        Ok(())
     }
 
     /// Delete the entry at the current cursor position.
     pub fn del(&mut self) -> Result<(), Error> {
+        let old_len = self.tracked_current_len()?;
         let rc = unsafe { mdb_cursor_del(self.cursor, 0) };
-        lmdb_result(rc)
+        lmdb_result(rc)?;
+        self.track(old_len, 0);
+        Ok(())
+    }
+
+    fn track(&self, old_len: usize, new_len: usize) {
+        if let Some(size_delta) = self.size_delta {
+            size_delta.set(size_delta.get() + new_len as isize - old_len as isize);
+        }
+    }
+
+    /// Returns a database view this cursor was opened from.
+    fn database(&self) -> Database<'txn> {
+        Database {
+            txn: unsafe { mdb_cursor_txn(self.cursor) },
+            dbi: unsafe { mdb_cursor_dbi(self.cursor) },
+            size_delta: self.size_delta,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the size of an entry at the current cursor position, but only if the database
+    /// view this cursor was opened from tracks size changes.
+    fn tracked_current_len(&self) -> Result<usize, Error> {
+        if self.size_delta.is_none() {
+            return Ok(0);
+        }
+        let (key, value) = self.key_value()?;
+        Ok(key.len() + value.len())
     }
 }
 
@@ -1,7 +1,19 @@
-use crate::lmdb::Env;
-use crate::transaction::Origin;
-use crate::{ClientID, Transaction};
+use crate::lmdb::{Dbi, Env};
+use crate::store::text_index::FullTextIndex;
+use crate::transaction::{
+    ClientLimit, IntegrationLimits, Origin, TombstoneRetention, TransactionConfig,
+};
+use crate::{ClientID, IntegrationObserver, MapResolver, Snapshot, Transaction};
 use lmdb_master_sys::MDB_CREATE;
+use std::sync::Arc;
+
+/// Database handle of a single document opened with [MultiDoc::doc_handle], together with
+/// the settings of a [MultiDoc] it was opened by.
+#[derive(Clone)]
+pub struct DocHandle {
+    pub(crate) dbi: Dbi,
+    pub(crate) config: TransactionConfig,
+}
 
 /// [MultiDoc] is an entry point to the library. It allows to store multiple documents within
 /// the same database file. Individual documents can be accessed by opening transaction with their
@@ -9,6 +21,7 @@ use lmdb_master_sys::MDB_CREATE;
 pub struct MultiDoc {
     env: Env,
     client_id: Option<ClientID>,
+    config: TransactionConfig,
 }
 
 impl MultiDoc {
@@ -17,8 +30,107 @@ impl MultiDoc {
     /// If `client_id` was provided it will be used by all the documents within the scope of
     /// this multi-doc. Otherwise, it will be generated randomly once when the document is created,
     /// then persisted and reused in subsequent requests.
+    ///
+    /// The number of read-only transactions that can be open concurrently is limited by
+    /// the number of reader slots, configured with [crate::lmdb::EnvBuilder::max_readers].
     pub fn new(env: Env, client_id: Option<ClientID>) -> Self {
-        MultiDoc { env, client_id }
+        MultiDoc {
+            env,
+            client_id,
+            config: TransactionConfig::default(),
+        }
+    }
+
+    /// Sets a [ClientLimit] checked by every read-write transaction of this multi-doc on commit.
+    /// It's an operational guardrail against documents accumulating changes from an excessive
+    /// number of clients.
+    pub fn with_client_limit(mut self, limit: ClientLimit) -> Self {
+        self.config.client_limit = Some(limit);
+        self
+    }
+
+    /// Sets the max size (in bytes) of a block content, up to which consecutive blocks will be
+    /// merged together when a transaction is committed. This prevents creation of large blocks,
+    /// which are expensive to split. Defaults to [crate::BlockHeader::DEFAULT_MAX_CONTENT_LEN].
+    pub fn with_max_block_content_len(mut self, max_len: usize) -> Self {
+        self.config.max_block_content_len = max_len;
+        self
+    }
+
+    /// Enables content interning: identical [ContentType::Binary] and [ContentType::Embed]
+    /// payloads inserted by read-write transactions of this multi-doc are stored only once and
+    /// shared by all blocks referencing them. This is useful for documents with large, repeated
+    /// embeds (e.g. the same image inserted many times), at the cost of hashing every such payload.
+    ///
+    /// [ContentType::Binary]: crate::ContentType::Binary
+    /// [ContentType::Embed]: crate::ContentType::Embed
+    pub fn with_content_interning(mut self, enabled: bool) -> Self {
+        self.config.intern_content = enabled;
+        self
+    }
+
+    /// Sets [IntegrationLimits] of the update integration loop used by read-write transactions
+    /// of this multi-doc. This is a safeguard against maliciously crafted updates.
+    pub fn with_integration_limits(mut self, limits: IntegrationLimits) -> Self {
+        self.config.integration_limits = limits;
+        self
+    }
+
+    /// Sets a [MapResolver] used to decide which of the concurrently written map values stays
+    /// visible. By default, the last-writer-wins rule (based on client IDs) is used.
+    /// All peers of the same document must use the same resolver in order to converge.
+    pub fn with_map_resolver(mut self, resolver: MapResolver) -> Self {
+        self.config.map_resolver = Some(resolver);
+        self
+    }
+
+    /// Sets a [TombstoneRetention] policy, which protects recently deleted blocks from being
+    /// garbage collected by [Transaction::gc] before lagging peers could learn about deletions.
+    pub fn with_tombstone_retention(mut self, retention: TombstoneRetention) -> Self {
+        self.config.tombstone_retention = Some(retention);
+        self
+    }
+
+    /// Enables or disables block merging, which is on by default. When disabled, blocks inserted
+    /// locally or integrated from remote updates are never merged with their neighbors, so every
+    /// operation stays a distinct block. Document contents converge just the same, but the
+    /// document takes more space. This is meant for testing: merging coalesces blocks, which can
+    /// hide bugs depending on the order in which blocks are integrated.
+    pub fn with_block_merging(mut self, enabled: bool) -> Self {
+        self.config.block_merging = enabled;
+        self
+    }
+
+    /// Registers an [IntegrationObserver], which will be notified about blocks inserted and deleted
+    /// by read-write transactions of this multi-doc, both by local edits and remote updates.
+    pub fn with_observer(mut self, observer: Arc<dyn IntegrationObserver>) -> Self {
+        self.config.observers.push(observer);
+        self
+    }
+
+    /// Enables a full-text index of all text contents of documents of this multi-doc, which can be
+    /// queried with [Transaction::search]. Index is kept up to date by an [IntegrationObserver], so
+    /// it must be enabled before any text is inserted.
+    ///
+    /// Every insertion and deletion re-indexes the whole words around it, so words typed one
+    /// character at a time, or composed of blocks coming from different peers, are indexed as
+    /// they appear in the text.
+    pub fn with_full_text_index(self) -> Self {
+        self.with_observer(Arc::new(FullTextIndex))
+    }
+
+    /// Sets a quota (in bytes) on the size of every document of this multi-doc, as returned by
+    /// [MultiDoc::doc_size]. Read-write transactions which would leave the document over
+    /// the quota fail to commit with [crate::Error::QuotaExceeded] and are rolled back.
+    ///
+    /// Transactions track the size of entries they write and add it to the document size
+    /// persisted by previous commits, so that the document is scanned only when it's checked
+    /// for the first time, or after it was modified by a multi-doc without a quota. Keep in mind
+    /// that deleted content keeps occupying space until it's garbage collected with
+    /// [Transaction::gc].
+    pub fn with_doc_quota(mut self, max_bytes: usize) -> Self {
+        self.config.doc_quota = Some(max_bytes);
+        self
     }
 
     /// Returns the LMDB [Env] reference.
@@ -38,6 +150,9 @@ impl MultiDoc {
     /// reused by future writes. This means that keeping the transaction for prolonged amount of
     /// time can cause database file to grow in face of writes. The database file can be compacted
     /// into a new file via [Env::copy_to] method with `compact` flag on.
+    ///
+    /// Each open read-only transaction occupies one of the LMDB reader slots. When all of them
+    /// are in use, [crate::Error::TooManyReaders] is returned.
     pub fn transact(&self, doc_id: &str) -> crate::Result<Transaction<'_>> {
         let handle = self.env.create_db(doc_id, 0)?;
         let tx = self.env.begin_ro_txn()?;
@@ -49,6 +164,9 @@ impl MultiDoc {
     /// reserve at least 4 pages of extra space (1 db page, 2x transaction root pages and
     /// 1 leaf page for initial data), which means that **overhead of a document is at least 16KiB**.
     ///
+    /// All changes made within a transaction are visible to reads made through the same transaction
+    /// right away, even before it's committed. Other transactions can see them only after commit.
+    ///
     /// Only one read-write transaction for the same document can exist at the same time. It will
     /// not block read-only transactions from being created, however read-only transactions will
     /// hold on database pages from being released and reused by read-write transaction to apply
@@ -58,7 +176,7 @@ impl MultiDoc {
     pub fn transact_mut(&self, doc_id: &str) -> crate::Result<Transaction<'_>> {
         let handle = self.env.create_db(doc_id, MDB_CREATE)?;
         let tx = self.env.begin_rw_txn()?;
-        Transaction::read_write(tx, handle, self.client_id, None)
+        Transaction::read_write(tx, handle, self.client_id, None, self.config.clone())
     }
 
     /// Opens a new read-write transaction into the document with a given `doc_id` with a specific
@@ -81,7 +199,43 @@ impl MultiDoc {
         let origin = origin.into();
         let handle = self.env.create_db(doc_id, MDB_CREATE)?;
         let tx = self.env.begin_rw_txn()?;
-        Transaction::read_write(tx, handle, self.client_id, Some(origin))
+        Transaction::read_write(
+            tx,
+            handle,
+            self.client_id,
+            Some(origin),
+            self.config.clone(),
+        )
+    }
+
+    /// Opens an LMDB database handle of a document with a given `doc_id`, creating the document if
+    /// it doesn't exist. It can be used together with an externally created LMDB transaction to
+    /// construct a [Transaction] with [Transaction::from_raw], which uses the same settings as
+    /// transactions of this multi-doc. Since opening a database handle requires its own write
+    /// transaction, this must be done before the external transaction is started.
+    pub fn doc_handle(&self, doc_id: &str) -> crate::Result<DocHandle> {
+        Ok(DocHandle {
+            dbi: self.env.create_db(doc_id, MDB_CREATE)?,
+            config: self.config.clone(),
+        })
+    }
+
+    /// Returns all named snapshots saved for a document with a given `doc_id` via
+    /// [Transaction::save_snapshot], ordered by their names.
+    pub fn list_snapshots(&self, doc_id: &str) -> crate::Result<Vec<(String, Snapshot)>> {
+        let tx = self.transact(doc_id)?;
+        tx.snapshots()
+    }
+
+    /// Returns the total size (in bytes) of all keys and values stored for a document with a given
+    /// `doc_id`: its blocks, contents, map entries, state vector and metadata. This is the logical
+    /// size of the document data, it doesn't include the LMDB page overhead (see
+    /// [MultiDoc::transact_mut]).
+    ///
+    /// Computing the size requires scanning all entries of the document.
+    pub fn doc_size(&self, doc_id: &str) -> crate::Result<usize> {
+        let tx = self.transact(doc_id)?;
+        tx.doc_size()
     }
 
     /// Permanently removes a document from current database file, together with all of its contents.
@@ -107,12 +261,29 @@ impl From<Env> for MultiDoc {
 
 #[cfg(test)]
 mod test {
-    use crate::test_util::multi_doc;
-    use crate::transaction::{CommitFlags, TransactionSummary};
+    use crate::block_reader::BlockRange;
+    use crate::lib0::v1::{DecoderV1, EncoderV1};
+    use crate::lib0::{Encode, Encoder, WriteExt};
+    use crate::lmdb::Database;
+    use crate::node::NodeID;
+    use crate::store::Db;
+    use crate::test_util::{multi_doc, reopen, sync};
+    use crate::transaction::{
+        ClientLimit, CommitFlags, IntegrationLimits, TombstoneRetention, TransactionSummary,
+    };
+    use crate::types::text::Chunk;
+    use zerocopy::IntoBytes;
 
-    use crate::{Map, MultiDoc, StateVector, Text, TextRef, Unmounted, lib0};
+    use crate::{
+        ClientID, Clock, Content, ContentType, Error, ID, IntegrationObserver, Keystroke, List,
+        Map, MapPrelim, MultiDoc, Named, Node, NodeType, Optional, StateVector, Text, TextRef,
+        Transaction, Unmounted, Update, lib0,
+    };
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
 
     use crate::lib0::Encoding;
+    use lmdb_master_sys::MDB_CREATE;
     use uuid::Uuid;
 
     #[test]
@@ -202,6 +373,710 @@ mod test {
         assert_eq!(&*encoded, expected);
     }
 
+    #[test]
+    fn pending_update_size() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        assert_eq!(tx.pending_update_size().unwrap(), 0);
+
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "hello world").unwrap();
+        t.remove_range(2..7).unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("a", 1.0).unwrap();
+        m.insert("b", "value").unwrap();
+        m.insert("a", 2.0).unwrap();
+
+        let expected = tx.incremental_update(Encoding::V1).unwrap().len();
+        assert_eq!(tx.pending_update_size().unwrap(), expected);
+        tx.commit(None).unwrap();
+    }
+
+    #[test]
+    fn incremental_update_chunked() {
+        const MAX_BYTES: usize = 64;
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut t1).unwrap();
+        for c in "the quick brown fox jumps over the lazy dog".chars().rev() {
+            t.insert(0, c.to_string()).unwrap();
+        }
+        t.remove_range(4..10).unwrap();
+        let mut m = map.mount_mut(&mut t1).unwrap();
+        for i in 0..20 {
+            m.insert(format!("key-{i}"), i as f64).unwrap();
+        }
+
+        let chunks = t1.incremental_update_chunked(MAX_BYTES).unwrap();
+        assert!(chunks.len() > 1);
+        let mut t2 = d2.transact_mut("test").unwrap();
+        for chunk in chunks.iter() {
+            assert!(chunk.len() <= MAX_BYTES, "chunk too big: {}", chunk.len());
+            t2.apply_update(chunk, Encoding::V1).unwrap();
+        }
+
+        let txt1 = txt.mount(&t1).unwrap();
+        let txt2 = txt.mount(&t2).unwrap();
+        assert_eq!(txt2.to_string(), "the brown fox jumps over the lazy dog");
+        assert_eq!(txt1.to_string(), txt2.to_string());
+        let m1 = map.mount(&t1).unwrap();
+        let m2 = map.mount(&t2).unwrap();
+        assert_eq!(m1.to_value().unwrap(), m2.to_value().unwrap());
+        t1.commit(None).unwrap();
+        t2.commit(None).unwrap();
+    }
+
+    #[test]
+    fn incremental_update_chunked_large_delete_set() {
+        const MAX_BYTES: usize = 64;
+        let txt: Unmounted<Text> = Unmounted::root("text");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut t1).unwrap();
+        for _ in 0..200 {
+            t.push("ab").unwrap();
+        }
+        let update = t1
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        t1.commit(None).unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        t2.apply_update(&update, Encoding::V1).unwrap();
+
+        // delete every other character, producing 200 disjoint deleted ranges
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut t1).unwrap();
+        for i in 0..200 {
+            t.remove_range(i..i + 1).unwrap();
+        }
+        let chunks = t1.incremental_update_chunked(MAX_BYTES).unwrap();
+        assert!(chunks.len() > 1);
+        for chunk in chunks.iter() {
+            assert!(chunk.len() <= MAX_BYTES, "chunk too big: {}", chunk.len());
+            t2.apply_update(chunk, Encoding::V1).unwrap();
+        }
+        assert_eq!(txt.mount(&t2).unwrap().to_string(), "b".repeat(200));
+        assert_eq!(
+            txt.mount(&t1).unwrap().to_string(),
+            txt.mount(&t2).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn group_update_carriers_by_parent() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("a", 1.0).unwrap();
+        m.insert("b", "value").unwrap();
+        let update = tx.incremental_update(Encoding::V1).unwrap();
+        tx.commit(None).unwrap();
+
+        let update = Update::decode(&update, Encoding::V1).unwrap();
+        let mut groups: HashMap<String, Vec<(ID, Option<ContentType>)>> = HashMap::new();
+        for carrier in update.carriers() {
+            assert!(!carrier.is_skip());
+            let key = match carrier.parent() {
+                Some(Node::Root(Named::Name(name))) => name.to_string(),
+                other => panic!("unexpected parent: {:?}", other),
+            };
+            groups
+                .entry(key)
+                .or_default()
+                .push((*carrier.id(), carrier.content_type()));
+        }
+
+        assert_eq!(
+            groups["text"],
+            vec![(ID::new(1.into(), 0.into()), Some(ContentType::String))]
+        );
+        let map_carriers = &groups["map"];
+        assert_eq!(map_carriers.len(), 2);
+        assert!(
+            map_carriers
+                .iter()
+                .all(|(_, content_type)| *content_type == Some(ContentType::Atom))
+        );
+    }
+
+    #[test]
+    fn apply_update_filtered_by_client() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("a", 1.0).unwrap();
+        map.mount_mut(&mut t2).unwrap().insert("b", 2.0).unwrap();
+        sync([&mut t1, &mut t2]);
+        let update = t1
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+
+        let (d3, _dir3) = multi_doc(3);
+        let mut t3 = d3.transact_mut("test").unwrap();
+        t3.apply_update_filtered(&mut DecoderV1::from_slice(&update), |client| {
+            client == 1.into()
+        })
+        .unwrap();
+
+        let sv = t3.state_vector().unwrap();
+        assert_eq!(sv.get(&1.into()), Clock::new(1));
+        assert_eq!(sv.get(&2.into()), Clock::new(0));
+        let m = map.mount(&t3).unwrap();
+        assert_eq!(m.get::<_, f64>("a").unwrap(), 1.0);
+        assert!(m.get::<_, f64>("b").optional().unwrap().is_none());
+
+        // deferred blocks are picked up from pending storage by the next update, even if
+        // it's not related to them
+        let (d4, _dir4) = multi_doc(4);
+        let mut t4 = d4.transact_mut("test").unwrap();
+        map.mount_mut(&mut t4).unwrap().insert("c", 3.0).unwrap();
+        let unrelated = t4
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        t3.apply_update(&unrelated, Encoding::V1).unwrap();
+        let m = map.mount(&t3).unwrap();
+        assert_eq!(m.get::<_, f64>("b").unwrap(), 2.0);
+        assert_eq!(m.get::<_, f64>("c").unwrap(), 3.0);
+        assert!(t3.pending_update().unwrap().is_none());
+    }
+
+    #[test]
+    fn concurrent_root_creation() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let (d3, _dir3) = multi_doc(3);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().insert(0, "hello").unwrap();
+        txt.mount_mut(&mut t2).unwrap().insert(0, "world").unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("a", 1.0).unwrap();
+        map.mount_mut(&mut t2).unwrap().insert("a", 2.0).unwrap();
+        sync([&mut t1, &mut t2]);
+
+        // third peer has never seen any of the root types before
+        let mut t3 = d3.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2, &mut t3]);
+
+        let expected = txt.mount(&t1).unwrap().to_string();
+        assert_eq!(expected.len(), 10);
+        assert_eq!(txt.mount(&t2).unwrap().to_string(), expected);
+        assert_eq!(txt.mount(&t3).unwrap().to_string(), expected);
+
+        let expected: f64 = map.mount(&t1).unwrap().get("a").unwrap();
+        assert_eq!(
+            map.mount(&t2).unwrap().get::<_, f64>("a").unwrap(),
+            expected
+        );
+        assert_eq!(
+            map.mount(&t3).unwrap().get::<_, f64>("a").unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn concurrent_root_blocks_identical() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let root_block = |tx: &Transaction, name: &str| {
+            let node_id = Node::root_named(name).id();
+            let db = tx.db.get();
+            let block = db.blocks().get(node_id).unwrap();
+            block.header().as_bytes().to_vec()
+        };
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let (d3, _dir3) = multi_doc(3);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let mut t3 = d3.transact_mut("test").unwrap();
+        // peer 1 creates text root first, peer 2 creates map root first
+        txt.mount_mut(&mut t1).unwrap().insert(0, "hello").unwrap();
+        map.mount_mut(&mut t2).unwrap().insert("a", 2.0).unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("a", 1.0).unwrap();
+        txt.mount_mut(&mut t2).unwrap().insert(0, "world").unwrap();
+        // peer 3 only learns about root types from remote updates
+        sync([&mut t1, &mut t2, &mut t3]);
+        txt.mount_mut(&mut t3).unwrap();
+        map.mount_mut(&mut t3).unwrap();
+
+        for name in ["text", "map"] {
+            let expected = root_block(&t1, name);
+            assert_eq!(root_block(&t2, name), expected, "root '{name}' on peer 2");
+            assert_eq!(root_block(&t3, name), expected, "root '{name}' on peer 3");
+        }
+    }
+
+    #[test]
+    fn list_document_clients() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let (d3, _dir3) = multi_doc(3);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let mut t3 = d3.transact_mut("test").unwrap();
+        assert!(t1.clients().unwrap().is_empty());
+
+        txt.mount_mut(&mut t1).unwrap().insert(0, "a").unwrap();
+        txt.mount_mut(&mut t3).unwrap().insert(0, "c").unwrap();
+        sync([&mut t1, &mut t2, &mut t3]);
+
+        let expected = vec![ClientID::from(1), ClientID::from(3)];
+        assert_eq!(t2.clients().unwrap(), expected);
+        t2.commit(None).unwrap();
+
+        let t2 = d2.transact("test").unwrap();
+        assert_eq!(t2.clients().unwrap(), expected);
+    }
+
+    #[test]
+    fn client_limit_exceeded() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().insert(0, "a").unwrap();
+        txt.mount_mut(&mut t2).unwrap().insert(0, "b").unwrap();
+        sync([&mut t1, &mut t2]);
+        let update = t1
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+
+        // reaching the limit is fine
+        let (d3, _dir3) = multi_doc(3);
+        let d3 = d3.with_client_limit(ClientLimit::Report(2));
+        let mut summary = TransactionSummary::new(CommitFlags::NONE);
+        let mut t3 = d3.transact_mut("test").unwrap();
+        t3.apply_update(&update, Encoding::V1).unwrap();
+        t3.commit(Some(&mut summary)).unwrap();
+        assert_eq!(summary.client_limit_exceeded, None);
+
+        // crossing the limit is reported, but changes are committed
+        let mut t3 = d3.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t3).unwrap().insert(0, "c").unwrap();
+        t3.commit(Some(&mut summary)).unwrap();
+        assert_eq!(summary.client_limit_exceeded, Some(3));
+
+        // existing clients can keep editing the document
+        summary.clear();
+        let mut t3 = d3.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t3).unwrap().insert(0, "d").unwrap();
+        t3.commit(Some(&mut summary)).unwrap();
+        assert_eq!(summary.client_limit_exceeded, None);
+
+        // report is recorded even if commit didn't ask for a summary
+        let (d5, _dir5) = multi_doc(5);
+        let d5 = d5.with_client_limit(ClientLimit::Report(1));
+        let mut t5 = d5.transact_mut("test").unwrap();
+        t5.apply_update(&update, Encoding::V1).unwrap();
+        t5.commit(None).unwrap();
+        let t5 = d5.transact("test").unwrap();
+        assert_eq!(t5.client_limit_exceeded().unwrap(), Some(2));
+
+        let (d4, _dir4) = multi_doc(4);
+        let d4 = d4.with_client_limit(ClientLimit::Reject(1));
+        let mut t4 = d4.transact_mut("test").unwrap();
+        t4.apply_update(&update, Encoding::V1).unwrap();
+        let result = t4.commit(None);
+        assert!(matches!(
+            result,
+            Err(Error::ClientLimitExceeded {
+                max_clients: 1,
+                clients: 2
+            })
+        ));
+        let t4 = d4.transact("test").unwrap();
+        assert!(t4.clients().unwrap().is_empty());
+    }
+
+    #[test]
+    fn transaction_dirty_state() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        assert!(!tx.is_dirty());
+
+        // mounting and reading roots doesn't count as a change
+        assert_eq!(txt.mount_mut(&mut tx).unwrap().to_string(), "");
+        assert!(!map.mount_mut(&mut tx).unwrap().remove("key").unwrap());
+        assert!(!tx.is_dirty());
+        assert_eq!(tx.change_count(), 0);
+
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        assert!(tx.is_dirty());
+        assert_eq!(tx.change_count(), 5);
+        tx.commit(None).unwrap();
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        assert!(!tx.is_dirty());
+        txt.mount_mut(&mut tx).unwrap().remove_range(0..2).unwrap();
+        assert!(tx.is_dirty());
+        assert_eq!(tx.change_count(), 2);
+        tx.commit(None).unwrap();
+
+        let tx = doc.transact("test").unwrap();
+        assert!(!tx.is_dirty());
+        assert_eq!(tx.change_count(), 0);
+    }
+
+    #[test]
+    fn read_your_writes() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let list: Unmounted<List> = Unmounted::root("list");
+
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+
+        // every write is visible to the reads following it, both through the same mount
+        // and through newly mounted views
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("a", 1.0).unwrap();
+        assert_eq!(m.get::<_, f64>("a").unwrap(), 1.0);
+        m.insert("a", 2.0).unwrap();
+        assert_eq!(m.get::<_, f64>("a").unwrap(), 2.0);
+        let nested: Unmounted<Map> = m.insert("nested", MapPrelim::default()).unwrap();
+        assert_eq!(map.mount(&tx).unwrap().get::<_, f64>("a").unwrap(), 2.0);
+
+        nested
+            .mount_mut(&mut tx)
+            .unwrap()
+            .insert("b", true)
+            .unwrap();
+        let m = map.mount(&tx).unwrap();
+        assert_eq!(
+            m.to_value().unwrap(),
+            lib0!({"a": 2.0, "nested": {"b": true}})
+        );
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.remove("a").unwrap();
+        assert!(m.get::<_, f64>("a").optional().unwrap().is_none());
+
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.push_back(1.0).unwrap();
+        l.push_front(0.0).unwrap();
+        assert_eq!(l.get::<f64>(0).unwrap(), 0.0);
+        assert_eq!(list.mount(&tx).unwrap().get::<f64>(1).unwrap(), 1.0);
+
+        // appends use cached text tails
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.push("hello").unwrap();
+        assert_eq!(t.to_string(), "hello");
+        t.remove_range(4..5).unwrap();
+        t.push(" world").unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hell world");
+
+        // uncommitted writes are not visible to other transactions
+        drop(tx);
+        let tx = doc.transact_mut("test").unwrap();
+        assert!(!tx.root_exists("map", NodeType::Unknown).unwrap());
+    }
+
+    #[test]
+    fn missing_content_is_skipped() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let list: Unmounted<List> = Unmounted::root("list");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut text = txt.mount_mut(&mut tx).unwrap();
+        text.insert(0, "world wide web").unwrap(); // clocks 0..=13
+        text.insert(0, "beautiful and ").unwrap(); // clocks 14..=27
+        text.insert(0, "hello there, ").unwrap();
+        list.mount_mut(&mut tx)
+            .unwrap()
+            .insert_range(0, ["a".repeat(20), "b".repeat(20), "c".repeat(20)])
+            .unwrap(); // clocks 41..=43
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("x", "x".repeat(20)).unwrap(); // clock 44
+        m.insert("y", "y".repeat(20)).unwrap();
+
+        // simulate content lost e.g. due to partial GC
+        {
+            let db = tx.db.get();
+            let contents = db.contents();
+            for (id, content_type) in [
+                (ID::new(1.into(), 14.into()), ContentType::String),
+                (ID::new(1.into(), 42.into()), ContentType::Atom),
+                (ID::new(1.into(), 44.into()), ContentType::Atom),
+            ] {
+                let range = BlockRange::new(id, id.clock);
+                contents.delete_range(content_type, &range).unwrap();
+                assert!(
+                    matches!(contents.get(id), Err(Error::ContentNotFound(missing)) if missing == id)
+                );
+            }
+        }
+
+        let text = txt.mount(&tx).unwrap();
+        assert_eq!(text.to_string(), "hello there, world wide web");
+        let chunks: Vec<_> = text.chunks().map(Result::unwrap).collect();
+        assert_eq!(chunks, vec![Chunk::new("hello there, world wide web")]);
+        assert_eq!(
+            list.mount(&tx).unwrap().to_value().unwrap(),
+            lib0!(["a".repeat(20), "c".repeat(20)])
+        );
+        assert_eq!(
+            map.mount(&tx).unwrap().to_value().unwrap(),
+            lib0!({"y": "y".repeat(20)})
+        );
+    }
+
+    #[test]
+    fn content_hash_of_converged_docs() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let (d3, _dir3) = multi_doc(3);
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let mut t3 = d3.transact_mut("test").unwrap();
+        assert_eq!(t2.content_hash().unwrap(), t3.content_hash().unwrap());
+
+        // peer 2 receives peer 1 changes piece by piece, while peer 3 gets them all at once
+        for chunk in ["hel", "lo", " world"] {
+            let mut t1 = d1.transact_mut("test").unwrap();
+            txt.mount_mut(&mut t1).unwrap().push(chunk).unwrap();
+            sync([&mut t1, &mut t2]);
+            t1.commit(None).unwrap();
+        }
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().remove_range(2..4).unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("a", 1.0).unwrap();
+        sync([&mut t1, &mut t2, &mut t3]);
+
+        let hash = t1.content_hash().unwrap();
+        assert_eq!(txt.mount(&t3).unwrap().to_string(), "heo world");
+        assert_eq!(t2.content_hash().unwrap(), hash);
+        assert_eq!(t3.content_hash().unwrap(), hash);
+
+        // any change to the visible content changes the hash
+        txt.mount_mut(&mut t3).unwrap().push("!").unwrap();
+        assert_ne!(t3.content_hash().unwrap(), hash);
+    }
+
+    #[test]
+    fn full_update() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("hello").unwrap();
+        map.mount_mut(&mut t2).unwrap().insert("a", 1.0).unwrap();
+        sync([&mut t1, &mut t2]);
+        txt.mount_mut(&mut t2).unwrap().insert(5, " world").unwrap();
+        txt.mount_mut(&mut t2).unwrap().remove_range(0..1).unwrap();
+        map.mount_mut(&mut t2).unwrap().insert("a", 2.0).unwrap();
+
+        for version in [Encoding::V1, Encoding::V2] {
+            let update = t2.full_update(version).unwrap();
+            let (d3, _dir3) = multi_doc(3);
+            let mut t3 = d3.transact_mut("test").unwrap();
+            t3.apply_update(&update, version).unwrap();
+
+            assert!(t3.pending_update().unwrap().is_none());
+            assert_eq!(t3.state_vector().unwrap(), t2.state_vector().unwrap());
+            assert_eq!(t3.content_hash().unwrap(), t2.content_hash().unwrap());
+            assert_eq!(txt.mount(&t3).unwrap().to_string(), "ello world");
+        }
+    }
+
+    #[test]
+    fn diff_update_filtered() {
+        let meta: Unmounted<Map> = Unmounted::root("metadata");
+        let content: Unmounted<Text> = Unmounted::root("content");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        content.mount_mut(&mut t1).unwrap().push("hello").unwrap();
+        let mut m = meta.mount_mut(&mut t1).unwrap();
+        m.insert("title", "greeting").unwrap();
+        let nested: Unmounted<Map> = m.insert("nested", MapPrelim::default()).unwrap();
+        content.mount_mut(&mut t1).unwrap().push(" world").unwrap();
+        nested.mount_mut(&mut t1).unwrap().insert("a", 1.0).unwrap();
+        content.mount_mut(&mut t2).unwrap().push("!").unwrap();
+        meta.mount_mut(&mut t2)
+            .unwrap()
+            .insert("author", "2")
+            .unwrap();
+        sync([&mut t1, &mut t2]);
+        content
+            .mount_mut(&mut t1)
+            .unwrap()
+            .remove_range(0..1)
+            .unwrap();
+        meta.mount_mut(&mut t1).unwrap().remove("title").unwrap();
+        t1.commit(None).unwrap();
+
+        for version in [Encoding::V1, Encoding::V2] {
+            // changes made in this scope are aborted at the end of each iteration
+            let mut t1 = d1.transact_mut("test").unwrap();
+            let update = t1
+                .diff_update_filtered(&StateVector::default(), version, |root| root == "metadata")
+                .unwrap();
+            let (d3, _dir3) = multi_doc(3);
+            let mut t3 = d3.transact_mut("test").unwrap();
+            let missing = t3.apply_update(&update, version).unwrap();
+
+            assert!(missing.is_empty());
+            assert!(t3.pending_update().unwrap().is_none());
+            assert_eq!(t3.state_vector().unwrap(), t1.state_vector().unwrap());
+            assert_eq!(
+                meta.mount(&t3).unwrap().to_value().unwrap(),
+                meta.mount(&t1).unwrap().to_value().unwrap()
+            );
+            assert_eq!(content.mount(&t3).unwrap().to_string(), "");
+
+            // later changes can be synced incrementally on top of a filtered update
+            content.mount_mut(&mut t1).unwrap().push("?").unwrap();
+            meta.mount_mut(&mut t1)
+                .unwrap()
+                .insert("version", 2.0)
+                .unwrap();
+            let sv = t3.state_vector().unwrap();
+            let update = t1
+                .diff_update_filtered(&sv, version, |root| root == "metadata")
+                .unwrap();
+            assert!(t3.apply_update(&update, version).unwrap().is_empty());
+            assert_eq!(t3.state_vector().unwrap(), t1.state_vector().unwrap());
+            assert_eq!(
+                meta.mount(&t3).unwrap().to_value().unwrap(),
+                meta.mount(&t1).unwrap().to_value().unwrap()
+            );
+            assert_eq!(content.mount(&t3).unwrap().to_string(), "");
+        }
+    }
+
+    #[test]
+    fn transaction_debug_changes() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(5, " world").unwrap();
+        t.remove_range(0..1).unwrap();
+        map.mount_mut(&mut tx).unwrap().insert("key", true).unwrap();
+
+        let text_id = Node::root_named("text").id();
+        let map_id = Node::root_named("map").id();
+        let expected = format!(
+            "inserted:
+  <00000001:5>, parent: {text_id}, clock-len: 6, left: <00000001:4>, origin-l: <00000001:4>, start:  world - string
+  <00000001:11>, parent: {map_id}, hash_key: 3017358048, clock-len: 1, start: true - atom
+deleted:
+  00000001: [0..1)
+changed:
+  {text_id}
+  {map_id}
+"
+        );
+        assert_eq!(tx.debug_changes().to_string(), expected);
+    }
+
+    #[test]
+    fn blocks_for_client_range() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().insert(0, "abc").unwrap();
+        txt.mount_mut(&mut t2).unwrap().insert(0, "xyz").unwrap();
+        sync([&mut t1, &mut t2]);
+        txt.mount_mut(&mut t1).unwrap().insert(4, "d").unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("key", 1).unwrap();
+        txt.mount_mut(&mut t2).unwrap().insert(1, "w").unwrap();
+        sync([&mut t1, &mut t2]);
+
+        let collect = |tx: &Transaction, client: u32, clocks: std::ops::Range<u32>| {
+            tx.blocks_for_client(client.into(), clocks.start.into()..clocks.end.into())
+                .unwrap()
+                .map(|res| {
+                    let (block, content) = res.unwrap();
+                    let content: Vec<_> = content.iter().map(|c| c.to_string()).collect();
+                    (*block.id(), block.content_type(), content)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // first block is included if it contains the start of the range
+        assert_eq!(
+            collect(&t1, 1, 2..4),
+            vec![
+                (
+                    ID::new(1.into(), 1.into()),
+                    ContentType::String,
+                    vec!["bc".to_string()]
+                ),
+                (
+                    ID::new(1.into(), 3.into()),
+                    ContentType::String,
+                    vec!["d".to_string()]
+                ),
+            ]
+        );
+        // iteration stops at the first block of a different client
+        let blocks = collect(&t1, 1, 0..u32::MAX);
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            blocks[3],
+            (
+                ID::new(1.into(), 4.into()),
+                ContentType::Atom,
+                vec!["1".to_string()]
+            )
+        );
+        let blocks = collect(&t2, 2, 0..u32::MAX);
+        let ids: Vec<_> = blocks.iter().map(|(id, _, _)| *id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                ID::new(2.into(), 0.into()),
+                ID::new(2.into(), 1.into()),
+                ID::new(2.into(), 3.into())
+            ]
+        );
+        assert!(collect(&t1, 3, 0..u32::MAX).is_empty());
+    }
+
     #[test]
     fn partially_duplicated_update() {
         let txt: Unmounted<Text> = Unmounted::root("type");
@@ -236,6 +1111,860 @@ mod test {
         assert_eq!(txt1.to_string(), txt2.to_string());
     }
 
+    #[test]
+    fn integration_limits_exceeded() {
+        const CLIENTS: u32 = 8;
+        let txt: Unmounted<Text> = Unmounted::root("text");
+
+        // every client appends to the text, making its block depend on the previous client
+        let mut update = Vec::new();
+        let mut docs = Vec::new();
+        for client in 1..=CLIENTS {
+            let (doc, dir) = multi_doc(client);
+            let mut tx = doc.transact_mut("test").unwrap();
+            if !update.is_empty() {
+                tx.apply_update(&update, Encoding::V1).unwrap();
+            }
+            txt.mount_mut(&mut tx).unwrap().push("a").unwrap();
+            update = tx
+                .diff_update(&StateVector::default(), Encoding::V1)
+                .unwrap();
+            tx.commit(None).unwrap();
+            docs.push((doc, dir));
+        }
+        // encode clients in descending order, the way Yjs does, so that blocks are read before
+        // the blocks they depend on and need to wait for them on the integration stack
+        let decoded = Update::decode(&update, Encoding::V1).unwrap();
+        let mut encoder = EncoderV1::new(Vec::new());
+        encoder.write_var(decoded.blocks.len()).unwrap();
+        for (&client, carriers) in decoded.blocks.iter().rev() {
+            encoder.write_var(carriers.len()).unwrap();
+            encoder.write_client(client).unwrap();
+            encoder.write_var(carriers[0].id().clock).unwrap();
+            for carrier in carriers {
+                carrier.encode(&mut encoder).unwrap();
+            }
+        }
+        decoded.delete_set.encode_with(&mut encoder).unwrap();
+        let update = encoder.into_inner();
+
+        let (d1, _dir1) = multi_doc(CLIENTS + 1);
+        let d1 = d1.with_integration_limits(IntegrationLimits {
+            max_stack_depth: CLIENTS as usize / 2,
+            ..IntegrationLimits::default()
+        });
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let err = t1.apply_update(&update, Encoding::V1).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::IntegrationLimitExceeded {
+                limit: "stack depth",
+                ..
+            }
+        ));
+
+        let (d2, _dir2) = multi_doc(CLIENTS + 2);
+        let d2 = d2.with_integration_limits(IntegrationLimits {
+            max_carriers: CLIENTS as usize - 1,
+            ..IntegrationLimits::default()
+        });
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let err = t2.apply_update(&update, Encoding::V1).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::IntegrationLimitExceeded {
+                limit: "carriers",
+                ..
+            }
+        ));
+
+        // chain fits within the limits
+        let (d3, _dir3) = multi_doc(CLIENTS + 3);
+        let d3 = d3.with_integration_limits(IntegrationLimits {
+            max_stack_depth: CLIENTS as usize,
+            max_carriers: CLIENTS as usize * 2,
+        });
+        let mut t3 = d3.transact_mut("test").unwrap();
+        t3.apply_update(&update, Encoding::V1).unwrap();
+        assert_eq!(
+            txt.mount(&t3).unwrap().to_string(),
+            "a".repeat(CLIENTS as usize)
+        );
+    }
+
+    #[test]
+    fn apply_update_returns_missing_state() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let mut summary = TransactionSummary::new(CommitFlags::UPDATE_V1);
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let (d3, _dir3) = multi_doc(3);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("a").unwrap();
+        t1.commit(Some(&mut summary)).unwrap();
+        let u1 = std::mem::take(&mut summary.update);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("k", 1.0).unwrap();
+        t1.commit(Some(&mut summary)).unwrap();
+        let u2 = std::mem::take(&mut summary.update);
+
+        // preceding block of the same client is missing
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let missing = t2.apply_update(&u2, Encoding::V1).unwrap();
+        assert_eq!(missing, StateVector::from_iter([(1.into(), Clock::new(0))]));
+
+        // once missing blocks arrive, nothing else is missing
+        let missing = t2.apply_update(&u1, Encoding::V1).unwrap();
+        assert!(missing.is_empty());
+        assert_eq!(map.mount(&t2).unwrap().get::<_, f64>("k").unwrap(), 1.0);
+
+        // block of another client, which this one depends on, is missing
+        txt.mount_mut(&mut t2).unwrap().push("b").unwrap();
+        let since = StateVector::from_iter([(1.into(), Clock::new(2))]);
+        let update = t2.diff_update(&since, Encoding::V1).unwrap();
+        let mut t3 = d3.transact_mut("test").unwrap();
+        let missing = t3.apply_update(&update, Encoding::V1).unwrap();
+        assert_eq!(missing, StateVector::from_iter([(1.into(), Clock::new(0))]));
+    }
+
+    #[test]
+    fn keystroke_update() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+
+        // first character has no neighbors, it must be sent as a regular update
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("a").unwrap();
+        assert_eq!(t1.keystroke_update().unwrap(), None);
+        let update = t1.incremental_update(Encoding::V1).unwrap();
+        t1.commit(None).unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        t2.apply_update(&update, Encoding::V1).unwrap();
+        t2.commit(None).unwrap();
+
+        let edits: [fn(&mut TextRef<&mut Transaction>); 4] = [
+            |t| t.push("c").unwrap(),
+            |t| t.insert(1, "b").unwrap(),
+            |t| t.insert(0, "😀").unwrap(),
+            |t| t.remove_range(3..4).unwrap(),
+        ];
+        for edit in edits {
+            let mut t1 = d1.transact_mut("test").unwrap();
+            edit(&mut txt.mount_mut(&mut t1).unwrap());
+            let keystroke = t1.keystroke_update().unwrap().unwrap();
+            let update = t1.incremental_update(Encoding::V1).unwrap();
+            assert!(keystroke.len() < update.len());
+            t1.commit(None).unwrap();
+
+            let mut t2 = d2.transact_mut("test").unwrap();
+            t2.apply_keystroke(&keystroke).unwrap();
+            t2.commit(None).unwrap();
+        }
+
+        let t1 = d1.transact("test").unwrap();
+        let t2 = d2.transact("test").unwrap();
+        assert_eq!(txt.mount(&t1).unwrap().to_string(), "😀ac");
+        assert_eq!(txt.mount(&t2).unwrap().to_string(), "😀ac");
+
+        // multiple characters don't fit into a single keystroke
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("de").unwrap();
+        assert_eq!(t1.keystroke_update().unwrap(), None);
+    }
+
+    #[test]
+    fn keystroke_roundtrip() {
+        let keystroke = Keystroke::Insert {
+            id: ID::new(1.into(), 10.into()),
+            chr: 'ż',
+            origin_left: Some(ID::new(1.into(), 9.into())),
+            origin_right: Some(ID::new(2.into(), 3.into())),
+        };
+        let bytes = keystroke.to_bytes().unwrap();
+        assert_eq!(Keystroke::from_bytes(&bytes).unwrap(), keystroke);
+
+        let keystroke = Keystroke::Delete {
+            id: ID::new(2.into(), 3.into()),
+            len: 2.into(),
+        };
+        let bytes = keystroke.to_bytes().unwrap();
+        assert_eq!(Keystroke::from_bytes(&bytes).unwrap(), keystroke);
+
+        // origin of the same client can't follow the inserted character
+        let keystroke = Keystroke::Insert {
+            id: ID::new(1.into(), 10.into()),
+            chr: 'a',
+            origin_left: None,
+            origin_right: Some(ID::new(1.into(), 10.into())),
+        };
+        assert!(matches!(
+            keystroke.to_bytes(),
+            Err(Error::InvalidMapping("Keystroke"))
+        ));
+    }
+
+    #[test]
+    fn reconcile_state_vector() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("hello").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t2).unwrap().push("world").unwrap();
+        sync([&mut t1, &mut t2]);
+        assert!(t1.reconcile_state_vector().unwrap().is_empty());
+        t1.commit(None).unwrap();
+
+        let expected = d1.transact("test").unwrap().state_vector().unwrap();
+        assert!(
+            d1.transact("test")
+                .unwrap()
+                .reconcile_state_vector()
+                .unwrap()
+                .is_empty()
+        );
+
+        // corrupt stored state vector
+        let t1 = d1.transact_mut("test").unwrap();
+        let mut sv_store = t1.db.get().state_vector();
+        sv_store.set(1.into(), 2.into()).unwrap();
+        sv_store.set(3.into(), 7.into()).unwrap();
+        t1.commit(None).unwrap();
+
+        let t1 = d1.transact("test").unwrap();
+        let divergent = t1.reconcile_state_vector().unwrap();
+        assert_eq!(
+            divergent,
+            StateVector::from_iter([(1.into(), 5.into()), (3.into(), 0.into())])
+        );
+        drop(t1);
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        assert_eq!(t1.repair_state_vector().unwrap(), divergent);
+        // persisted blocks are not reported as changes of the repairing transaction
+        assert!(!t1.is_dirty());
+        assert_eq!(t1.change_count(), 0);
+        t1.commit(None).unwrap();
+
+        let t1 = d1.transact("test").unwrap();
+        assert!(t1.reconcile_state_vector().unwrap().is_empty());
+        assert_eq!(t1.state_vector().unwrap(), expected);
+    }
+
+    #[test]
+    fn transaction_from_raw() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t2 = d2.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t2).unwrap().push("hello").unwrap();
+        let update = t2.incremental_update(Encoding::V1).unwrap();
+        t2.commit(None).unwrap();
+
+        let env = d1.env();
+        let app = env.create_db("app", MDB_CREATE).unwrap();
+        let handle = d1.doc_handle("test").unwrap();
+
+        // aborted transaction doesn't persist neither app data nor document changes
+        {
+            let txn = env.begin_rw_txn().unwrap();
+            txn.bind(&app).put(b"key", b"aborted").unwrap();
+            let mut tx = Transaction::from_raw(txn, &handle, None).unwrap();
+            tx.apply_update(&update, Encoding::V1).unwrap();
+        }
+        let txn = env.begin_ro_txn().unwrap();
+        assert!(txn.bind(&app).get(b"key").is_err());
+        drop(txn);
+        let tx = d1.transact_mut("test").unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "");
+        drop(tx);
+
+        // committed transaction persists both
+        let txn = env.begin_rw_txn().unwrap();
+        txn.bind(&app).put(b"key", b"value").unwrap();
+        let mut tx = Transaction::from_raw(txn, &handle, None).unwrap();
+        tx.apply_update(&update, Encoding::V1).unwrap();
+        tx.raw_txn().bind(&app).put(b"applied", b"true").unwrap();
+        tx.commit(None).unwrap();
+
+        let txn = env.begin_ro_txn().unwrap();
+        assert_eq!(txn.bind(&app).get(b"key").unwrap(), b"value");
+        assert_eq!(txn.bind(&app).get(b"applied").unwrap(), b"true");
+        drop(txn);
+        let tx = d1.transact("test").unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+        drop(tx);
+
+        // settings of the multi-doc are applied
+        let d1 = d1.with_doc_quota(16);
+        let env = d1.env();
+        let handle = d1.doc_handle("test").unwrap();
+        let txn = env.begin_rw_txn().unwrap();
+        let mut tx = Transaction::from_raw(txn, &handle, None).unwrap();
+        txt.mount_mut(&mut tx).unwrap().push(" world").unwrap();
+        let err = tx.commit(None).unwrap_err();
+        assert!(matches!(err, Error::QuotaExceeded { quota: 16, .. }));
+    }
+
+    #[test]
+    fn pending_update() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut updates = Vec::new();
+        for key in ["a", "b", "c"] {
+            let since = t1.state_vector().unwrap();
+            map.mount_mut(&mut t1).unwrap().insert(key, 1.0).unwrap();
+            updates.push(t1.diff_update(&since, Encoding::V1).unwrap());
+        }
+
+        let mut t2 = d2.transact_mut("test").unwrap();
+        assert!(t2.pending_update().unwrap().is_none());
+
+        // both updates are preceded by the first one, which didn't arrive yet
+        let missing = StateVector::from_iter([(1.into(), Clock::new(0))]);
+        t2.apply_update(&updates[1], Encoding::V1).unwrap();
+        let (missing_sv, first_size) = t2.pending_update().unwrap().unwrap();
+        assert_eq!(missing_sv, missing);
+
+        // second update is merged into the already pending one
+        t2.apply_update(&updates[2], Encoding::V1).unwrap();
+        let pending = t2.pending_update().unwrap().unwrap();
+        assert_eq!(pending.0, missing);
+        assert!(pending.1 > first_size);
+        t2.commit(None).unwrap();
+
+        // pending update is persisted
+        let mut t2 = d2.transact_mut("test").unwrap();
+        assert_eq!(t2.pending_update().unwrap(), Some(pending));
+
+        t2.apply_update(&updates[0], Encoding::V1).unwrap();
+        assert!(t2.pending_update().unwrap().is_none());
+        let value = map.mount(&t2).unwrap().to_value().unwrap();
+        assert_eq!(value, lib0!({"a": 1.0, "b": 1.0, "c": 1.0}));
+    }
+
+    #[test]
+    fn optimize_applied_updates() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+
+        let mut updates = Vec::new();
+        for c in ["a", "b", "c", "d"] {
+            let mut t1 = d1.transact_mut("test").unwrap();
+            txt.mount_mut(&mut t1).unwrap().push(c).unwrap();
+            updates.push(t1.incremental_update(Encoding::V1).unwrap());
+            t1.commit(None).unwrap();
+        }
+
+        let block_count = |tx: &Transaction| {
+            let db = tx.db.get();
+            let blocks = db.blocks();
+            let mut next = blocks.get(txt.node_id()).unwrap().start().copied();
+            let mut count = 0;
+            while let Some(id) = next {
+                count += 1;
+                next = blocks.get(id).unwrap().right().copied();
+            }
+            count
+        };
+
+        let mut t2 = d2.transact_mut("test").unwrap();
+        for update in updates.iter() {
+            t2.apply_update(update, Encoding::V1).unwrap();
+        }
+        assert_eq!(block_count(&t2), 4);
+
+        t2.optimize().unwrap();
+        assert_eq!(block_count(&t2), 1);
+        assert_eq!(txt.mount(&t2).unwrap().to_string(), "abcd");
+
+        // blocks integrated after optimization are still merged on commit
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("e").unwrap();
+        t2.apply_update(&t1.incremental_update(Encoding::V1).unwrap(), Encoding::V1)
+            .unwrap();
+        t2.commit(None).unwrap();
+
+        let t2 = d2.transact("test").unwrap();
+        assert_eq!(block_count(&t2), 1);
+        assert_eq!(txt.mount(&t2).unwrap().to_string(), "abcde");
+    }
+
+    #[test]
+    fn tombstone_retention() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (d1, _dir1) = multi_doc(1);
+        let d1 = d1.with_tombstone_retention(TombstoneRetention::Commits(2));
+        let (d2, _dir2) = multi_doc(2);
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("abc").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2]);
+        t1.commit(None).unwrap();
+        t2.commit(None).unwrap();
+
+        // tombstones created by the current transaction are retained
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().remove_range(1..2).unwrap();
+        let ds = t1.delete_set().cloned().unwrap();
+        t1.gc(&ds).unwrap();
+        assert_eq!(t1.gc_stats().unwrap().gc_blocks, 0);
+        t1.commit(None).unwrap();
+
+        // one commit later, tombstone is still within the retention window
+        let mut t1 = d1.transact_mut("test").unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("x", 1.0).unwrap();
+        t1.commit(None).unwrap();
+        let mut t1 = d1.transact_mut("test").unwrap();
+        t1.gc(&ds).unwrap();
+        assert_eq!(t1.gc_stats().unwrap().gc_blocks, 0);
+
+        // lagging peer learns about deletion while the tombstone is retained
+        let mut t2 = d2.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2]);
+        t1.commit(None).unwrap();
+        t2.commit(None).unwrap();
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        map.mount_mut(&mut t1).unwrap().insert("y", 2.0).unwrap();
+        t1.commit(None).unwrap();
+
+        // retention window has passed
+        let mut t1 = d1.transact_mut("test").unwrap();
+        t1.gc(&ds).unwrap();
+        assert_eq!(t1.gc_stats().unwrap().gc_blocks, 1);
+
+        let mut t2 = d2.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2]);
+        assert_eq!(txt.mount(&t1).unwrap().to_string(), "ac");
+        assert_eq!(txt.mount(&t2).unwrap().to_string(), "ac");
+    }
+
+    #[test]
+    fn too_many_readers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let env = crate::lmdb::Env::builder()
+            .max_dbs(10)
+            .max_readers(1)
+            .map_size(10 * 1024 * 1024)
+            .open(dir.path(), 0o600)
+            .unwrap();
+        let mdoc = MultiDoc::new(env, Some(1.into()));
+        mdoc.transact_mut("test").unwrap().commit(None).unwrap();
+
+        // current thread occupies the only reader slot
+        let tx = mdoc.transact("test").unwrap();
+        std::thread::scope(|s| {
+            let result = s.spawn(|| mdoc.transact("test").map(|_| ())).join();
+            assert!(matches!(result.unwrap(), Err(Error::TooManyReaders)));
+        });
+
+        // reader slot is reused by subsequent transactions of the same thread
+        drop(tx);
+        mdoc.transact("test").unwrap();
+    }
+
+    /// Observer accumulating text content inserted into a document.
+    #[derive(Default)]
+    struct TextCollector {
+        inserted: Mutex<String>,
+        deleted: Mutex<u32>,
+        formats: Mutex<Vec<String>>,
+    }
+
+    impl IntegrationObserver for TextCollector {
+        fn block_inserted(
+            &self,
+            _db: &Database<'_>,
+            _id: &ID,
+            _parent: &NodeID,
+            content: &[Content<'_>],
+        ) -> crate::Result<()> {
+            let mut inserted = self.inserted.lock().unwrap();
+            for content in content {
+                if content.content_type() == ContentType::String {
+                    inserted.push_str(content.as_str()?);
+                }
+            }
+            Ok(())
+        }
+
+        fn block_deleted(
+            &self,
+            _db: &Database<'_>,
+            _id: &ID,
+            len: Clock,
+            _parent: &NodeID,
+        ) -> crate::Result<()> {
+            *self.deleted.lock().unwrap() += len.get();
+            Ok(())
+        }
+
+        fn format_applied(
+            &self,
+            _db: &Database<'_>,
+            _id: &ID,
+            _parent: &NodeID,
+            attribute: &Content<'_>,
+        ) -> crate::Result<()> {
+            let key = attribute.as_format()?.key().to_owned();
+            self.formats.lock().unwrap().push(key);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn integration_observer() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let o1 = Arc::new(TextCollector::default());
+        let (d1, _dir1) = multi_doc(1);
+        let d1 = d1.with_observer(o1.clone());
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.push("hello").unwrap();
+        // extends the block created above in place
+        t.push(" world").unwrap();
+        tx.commit(None).unwrap();
+        let mut tx = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(11, "!").unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = d1.transact("test").unwrap();
+        let expected = txt.mount(&tx).unwrap().to_string();
+        assert_eq!(*o1.inserted.lock().unwrap(), expected);
+        drop(tx);
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(0..6).unwrap();
+        t.format(0..5, [("bold", true)]).unwrap();
+        assert_eq!(t.to_string(), "world!");
+        tx.commit(None).unwrap();
+        assert_eq!(*o1.deleted.lock().unwrap(), 6);
+        assert!(!o1.formats.lock().unwrap().is_empty());
+
+        // remote updates are observed the same way
+        let o2 = Arc::new(TextCollector::default());
+        let (d2, _dir2) = multi_doc(2);
+        let d2 = d2.with_observer(o2.clone());
+        let tx = d1.transact("test").unwrap();
+        let update = tx
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        drop(tx);
+        let mut tx = d2.transact_mut("test").unwrap();
+        tx.apply_update(&update, Encoding::V1).unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "world!");
+        tx.commit(None).unwrap();
+        assert_eq!(*o2.inserted.lock().unwrap(), expected);
+        assert_eq!(*o2.deleted.lock().unwrap(), 6);
+        assert_eq!(*o2.formats.lock().unwrap(), *o1.formats.lock().unwrap());
+    }
+
+    #[test]
+    fn merge_from() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        map.mount_mut(&mut tx).unwrap().insert("a", 1.0).unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = d2.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "world").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("a", 2.0).unwrap();
+        m.insert("b", "x").unwrap();
+        tx.commit(None).unwrap();
+
+        // d1 merges d2, then both make further edits, so that the next merge in the opposite
+        // direction starts in the middle of already known blocks
+        let mut tx = d1.transact_mut("test").unwrap();
+        let other = d2.transact("test").unwrap();
+        assert!(tx.merge_from(&other).unwrap().is_empty());
+        drop(other);
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.push("!").unwrap();
+        t.remove_range(0..2).unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = d2.transact_mut("test").unwrap();
+        map.mount_mut(&mut tx).unwrap().remove("b").unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = d2.transact_mut("test").unwrap();
+        let other = d1.transact("test").unwrap();
+        assert!(tx.merge_from(&other).unwrap().is_empty());
+        drop(other);
+        tx.commit(None).unwrap();
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        let other = d2.transact("test").unwrap();
+        assert!(tx.merge_from(&other).unwrap().is_empty());
+        drop(other);
+        tx.commit(None).unwrap();
+
+        let tx1 = d1.transact("test").unwrap();
+        let tx2 = d2.transact("test").unwrap();
+        assert_eq!(tx1.state_vector().unwrap(), tx2.state_vector().unwrap());
+        let t1 = txt.mount(&tx1).unwrap().to_string();
+        assert_eq!(t1, txt.mount(&tx2).unwrap().to_string());
+        assert_eq!(t1.len(), 9);
+        let m1 = map.mount(&tx1).unwrap();
+        let m2 = map.mount(&tx2).unwrap();
+        assert_eq!(
+            m1.get::<_, f64>("a").unwrap(),
+            m2.get::<_, f64>("a").unwrap()
+        );
+        assert!(m1.get::<_, String>("b").optional().unwrap().is_none());
+        assert!(m2.get::<_, String>("b").optional().unwrap().is_none());
+    }
+
+    #[test]
+    fn named_snapshots_persistence() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+
+        let (mdoc, dir) = multi_doc(1);
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        let v1 = tx.save_snapshot("v1").unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(0..1).unwrap();
+        t.insert(4, " world").unwrap();
+        let v2 = tx.save_snapshot("v2").unwrap();
+        tx.commit(None).unwrap();
+        drop(mdoc);
+
+        let mdoc = reopen(&dir, 1);
+        let snapshots = mdoc.list_snapshots("test").unwrap();
+        let names: Vec<_> = snapshots.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["v1", "v2"]);
+        assert!(snapshots[0].1 == v1);
+        assert!(snapshots[1].1 == v2);
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let snapshot = tx.load_snapshot("v1").unwrap().unwrap();
+        let state: Vec<_> = txt
+            .mount(&tx)
+            .unwrap()
+            .chunks_between(None, Some(&snapshot))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(state, vec![Chunk::new("hello")]);
+        assert!(tx.load_snapshot("v3").unwrap().is_none());
+
+        assert!(tx.remove_snapshot("v1").unwrap());
+        assert!(!tx.remove_snapshot("v1").unwrap());
+        tx.commit(None).unwrap();
+
+        let snapshots = mdoc.list_snapshots("test").unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].0, "v2");
+    }
+
+    #[test]
+    fn apply_update_from_reader() {
+        const ENTRIES: usize = 3000;
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut t2).unwrap();
+        for i in 0..ENTRIES {
+            m.insert(format!("key{i}"), i as f64).unwrap();
+        }
+        let update = t2
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+
+        // blocks of client 1 are encoded before blocks of client 2 they depend on
+        let mut t1 = d1.transact_mut("test").unwrap();
+        t1.apply_update(&update, Encoding::V1).unwrap();
+        let mut m = map.mount_mut(&mut t1).unwrap();
+        for i in 0..100 {
+            m.insert(format!("key{i}"), -1.0).unwrap();
+        }
+        m.remove("key100").unwrap();
+
+        for version in [Encoding::V1, Encoding::V2] {
+            let update = t1.diff_update(&StateVector::default(), version).unwrap();
+            let (d3, _dir3) = multi_doc(3);
+            let mut t3 = d3.transact_mut("test").unwrap();
+            let missing = t3.apply_update_from(update.as_slice(), version).unwrap();
+            assert!(missing.is_empty());
+            assert_eq!(t3.state_vector().unwrap(), t1.state_vector().unwrap());
+            assert_eq!(
+                map.mount(&t3).unwrap().to_value().unwrap(),
+                map.mount(&t1).unwrap().to_value().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn compact_fragmented_updates() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let mut updates = Vec::new();
+        let mut summary = TransactionSummary::new(CommitFlags::UPDATE_V1);
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        for (i, chunk) in ["hello", " world", "!"].into_iter().enumerate() {
+            let mut tx = d1.transact_mut("test").unwrap();
+            let mut t = txt.mount_mut(&mut tx).unwrap();
+            let len = t.len();
+            t.insert(len, chunk).unwrap();
+            map.mount_mut(&mut tx)
+                .unwrap()
+                .insert("key", i as f64)
+                .unwrap();
+            tx.commit(Some(&mut summary)).unwrap();
+            updates.push(summary.update.clone());
+            summary.clear();
+        }
+        let mut tx = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().remove_range(0..1).unwrap();
+        tx.commit(Some(&mut summary)).unwrap();
+        updates.push(summary.update.clone());
+
+        let compacted = crate::compact_updates(&updates, Encoding::V1).unwrap();
+        let update = Update::decode(&compacted, Encoding::V1).unwrap();
+        assert_eq!(update.blocks.len(), 1);
+
+        let t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        t2.apply_update(&compacted, Encoding::V1).unwrap();
+        assert_eq!(t2.state_vector().unwrap(), t1.state_vector().unwrap());
+        assert_eq!(txt.mount(&t2).unwrap().to_string(), "ello world!");
+        assert_eq!(
+            map.mount(&t2).unwrap().to_value().unwrap(),
+            map.mount(&t1).unwrap().to_value().unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_update() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let list: Unmounted<List> = Unmounted::root("list");
+        let edit = |mdoc: &MultiDoc| {
+            for chunk in ["hello", " world"] {
+                let mut tx = mdoc.transact_mut("test").unwrap();
+                let mut t = txt.mount_mut(&mut tx).unwrap();
+                for c in chunk.chars() {
+                    t.push(c.to_string()).unwrap();
+                }
+                let mut l = list.mount_mut(&mut tx).unwrap();
+                for c in chunk.chars() {
+                    l.push_back(c.to_string()).unwrap();
+                }
+                tx.commit(None).unwrap();
+            }
+            let mut tx = mdoc.transact_mut("test").unwrap();
+            let mut t = txt.mount_mut(&mut tx).unwrap();
+            t.remove_range(3..4).unwrap();
+            t.remove_range(4..6).unwrap();
+            tx.commit(None).unwrap();
+        };
+
+        // the same changes made by the same client, once with and once without block merging
+        let (d1, _dir1) = multi_doc(1);
+        edit(&d1);
+        let (d2, _dir2) = multi_doc(1);
+        let d2 = d2.with_block_merging(false);
+        edit(&d2);
+
+        let tx1 = d1.transact("test").unwrap();
+        let tx2 = d2.transact("test").unwrap();
+        assert_eq!(
+            txt.mount(&tx1).unwrap().to_string(),
+            txt.mount(&tx2).unwrap().to_string()
+        );
+        for version in [Encoding::V1, Encoding::V2] {
+            let u1 = tx1.diff_update(&StateVector::default(), version).unwrap();
+            let u2 = tx2.diff_update(&StateVector::default(), version).unwrap();
+            assert_ne!(u1, u2);
+            let c1 = crate::canonical_update(&u1, version).unwrap();
+            let c2 = crate::canonical_update(&u2, version).unwrap();
+            assert_eq!(c1, c2);
+            // canonical form is stable
+            assert_eq!(crate::canonical_update(&c1, version).unwrap(), c1);
+        }
+    }
+
+    #[test]
+    fn compact_and_canonical_tagged_updates() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        for version in [Encoding::V1, Encoding::V2] {
+            let (d1, _dir1) = multi_doc(1);
+            let mut updates = Vec::new();
+            let mut sv = StateVector::default();
+            for chunk in ["hello", " world"] {
+                let mut tx = d1.transact_mut("test").unwrap();
+                let mut t = txt.mount_mut(&mut tx).unwrap();
+                let len = t.len();
+                t.insert(len, chunk).unwrap();
+                updates.push(crate::tag_update(
+                    "doc-a",
+                    &tx.diff_update(&sv, version).unwrap(),
+                ));
+                sv = tx.state_vector().unwrap();
+                tx.commit(None).unwrap();
+            }
+
+            let compacted = crate::compact_updates(&updates, version).unwrap();
+            let (guid, update) = crate::untag_update(&compacted).unwrap();
+            assert_eq!(guid, Some("doc-a"));
+            assert!(matches!(
+                Update::decode(&compacted, version),
+                Err(Error::TaggedUpdate(guid)) if guid == "doc-a"
+            ));
+            let (guid, decoded) = Update::decode_tagged(&compacted, version).unwrap();
+            assert_eq!(guid.as_deref(), Some("doc-a"));
+            assert_eq!(decoded.blocks.len(), 1);
+
+            let canonical = crate::canonical_update(&compacted, version).unwrap();
+            assert_eq!(
+                crate::untag_update(&canonical).unwrap(),
+                (
+                    Some("doc-a"),
+                    crate::canonical_update(update, version).unwrap().as_slice()
+                )
+            );
+
+            let (d2, _dir2) = multi_doc(2);
+            let mut tx = d2.transact_mut("test").unwrap();
+            tx.apply_update(&canonical, version).unwrap();
+            assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello world");
+            drop(tx);
+
+            // updates of different documents can't be compacted together
+            let other = crate::tag_update("doc-b", crate::untag_update(&updates[1]).unwrap().1);
+            assert!(matches!(
+                crate::compact_updates(&[&updates[0], &other], version),
+                Err(Error::WrongDocument { expected, actual }) if expected == "doc-a" && actual == "doc-b"
+            ));
+        }
+    }
+
     #[test]
     fn out_of_order_updates() {
         let map: Unmounted<Map> = Unmounted::root("type");
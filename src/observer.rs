@@ -0,0 +1,66 @@
+use crate::content::Content;
+use crate::lmdb::Database;
+use crate::node::NodeID;
+use crate::{Clock, ID};
+use std::fmt::{Debug, Formatter};
+
+/// Hook notified about every block change made to the document store, no matter if it comes from
+/// a local edit or from a remote update. It's meant for building extensions, like derived indices,
+/// which need to stay in sync with the document contents.
+///
+/// Observers are registered with [crate::MultiDoc::with_observer] and called synchronously within
+/// the read-write transaction making the change. `db` gives access to the document database of
+/// that transaction, so that any data written by an observer is committed or rolled back together
+/// with the change itself. Returning an error aborts the operation that triggered the event.
+///
+/// All methods do nothing by default.
+pub trait IntegrationObserver: Send + Sync {
+    /// Called after a new block has been inserted into the document. `id` is the ID of
+    /// the first element of inserted `content`, which may span over multiple elements (i.e.
+    /// characters of a string). Appending text to a block created by the same transaction is
+    /// reported as a separate insertion as well.
+    ///
+    /// Formatting attributes are reported through [IntegrationObserver::format_applied] instead.
+    fn block_inserted(
+        &self,
+        db: &Database<'_>,
+        id: &ID,
+        parent: &NodeID,
+        content: &[Content<'_>],
+    ) -> crate::Result<()> {
+        let _ = (db, id, parent, content);
+        Ok(())
+    }
+
+    /// Called after a range of `len` elements, starting at `id`, has been marked as deleted.
+    /// Content of deleted block is still present in the store at this point.
+    fn block_deleted(
+        &self,
+        db: &Database<'_>,
+        id: &ID,
+        len: Clock,
+        parent: &NodeID,
+    ) -> crate::Result<()> {
+        let _ = (db, id, len, parent);
+        Ok(())
+    }
+
+    /// Called after a formatting attribute block has been inserted into a text. Use
+    /// [Content::as_format] to read attribute's key and value.
+    fn format_applied(
+        &self,
+        db: &Database<'_>,
+        id: &ID,
+        parent: &NodeID,
+        attribute: &Content<'_>,
+    ) -> crate::Result<()> {
+        let _ = (db, id, parent, attribute);
+        Ok(())
+    }
+}
+
+impl Debug for dyn IntegrationObserver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("IntegrationObserver")
+    }
+}
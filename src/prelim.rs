@@ -13,6 +13,15 @@ pub trait Prelim {
 
     fn prepare(&self) -> crate::Result<Prepare>;
 
+    /// Same as [Prelim::prepare], but used when values are supposed to be stored as
+    /// [ContentType::Json] (see [crate::ValueEncoding::Json]). By default, it falls back to
+    /// [Prelim::prepare].
+    ///
+    /// [ContentType::Json]: crate::ContentType::Json
+    fn prepare_json(&self) -> crate::Result<Prepare> {
+        self.prepare()
+    }
+
     fn integrate<'tx>(
         self,
         parent: &mut BlockMut,
@@ -40,6 +49,36 @@ where
         Ok(Prepare::Values(smallvec![Content::atom(self)?]))
     }
 
+    fn prepare_json(&self) -> crate::Result<Prepare> {
+        Ok(Prepare::Values(smallvec![Content::json(self)?]))
+    }
+
+    fn integrate<'tx>(
+        self,
+        _parent: &mut BlockMut,
+        _tx: &mut TxMutScope<'tx>,
+    ) -> crate::Result<Self::Return> {
+        Ok(())
+    }
+}
+
+/// Preliminary [ContentType::Custom] content, stored as a single, opaque block.
+///
+/// [ContentType::Custom]: crate::ContentType::Custom
+#[repr(transparent)]
+pub(crate) struct CustomPrelim(pub Content<'static>);
+impl Prelim for CustomPrelim {
+    type Return = ();
+
+    #[inline]
+    fn clock_len(&self) -> Clock {
+        Clock::new(1)
+    }
+
+    fn prepare(&self) -> crate::Result<Prepare> {
+        Ok(Prepare::Values(smallvec![self.0.clone()]))
+    }
+
     fn integrate<'tx>(
         self,
         _parent: &mut BlockMut,
@@ -70,6 +109,7 @@ impl Prelim for DeltaPrelim {
             In::Value(value) => Ok(Prepare::Values(smallvec![Content::embed(value)?])),
             In::List(prelim) => prelim.prepare(),
             In::Map(prelim) => prelim.prepare(),
+            In::Text(prelim) => prelim.prepare(),
         }
     }
 
@@ -86,6 +126,9 @@ impl Prelim for DeltaPrelim {
             In::Map(prelim) => {
                 prelim.integrate(parent, tx)?;
             }
+            In::Text(prelim) => {
+                prelim.integrate(parent, tx)?;
+            }
         }
         Ok(())
     }
@@ -104,6 +147,14 @@ impl Prelim for In {
             In::Value(value) => value.prepare(),
             In::List(prelim) => prelim.prepare(),
             In::Map(prelim) => prelim.prepare(),
+            In::Text(prelim) => prelim.prepare(),
+        }
+    }
+
+    fn prepare_json(&self) -> crate::Result<Prepare> {
+        match self {
+            In::Value(value) => value.prepare_json(),
+            _ => self.prepare(),
         }
     }
 
@@ -116,6 +167,7 @@ impl Prelim for In {
             In::Value(value) => Ok(Out::Value(value)),
             In::List(prelim) => Ok(Out::Node(prelim.integrate(parent, tx)?.node_id())),
             In::Map(prelim) => Ok(Out::Node(prelim.integrate(parent, tx)?.node_id())),
+            In::Text(prelim) => Ok(Out::Node(prelim.integrate(parent, tx)?.node_id())),
         }
     }
 }
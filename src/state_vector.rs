@@ -87,6 +87,16 @@ impl StateVector {
         *e = (*e).max(clock);
     }
 
+    /// Sets a clock sequence number observed for a given `client`, no matter if it's lower or
+    /// higher than the current one. Setting clock to zero removes the client entry.
+    pub fn set(&mut self, client: ClientID, clock: Clock) {
+        if clock == 0 {
+            self.0.remove(&client);
+        } else {
+            self.0.insert(client, clock);
+        }
+    }
+
     /// Returns an iterator which enables to traverse over all clients and their known clock values
     /// described by a current state vector.
     pub fn iter(&self) -> std::collections::btree_map::Iter<'_, ClientID, Clock> {
@@ -219,7 +229,12 @@ impl PartialOrd for StateVector {
 
 /// Snapshot describes a state of a document store at a given point in (logical) time. In practice
 /// it's a combination of [StateVector] (a summary of all observed insert/update operations)
-/// and a [DeleteSet] (a summary of all observed deletions).
+/// and a [IDSet] (a summary of all observed deletions).
+///
+/// Snapshots can be persisted using [Encode::encode] and restored with [Decode::decode], which
+/// makes it possible to store a version of the document and read its state (e.g. via
+/// [crate::TextRef::chunks_between]) later on, as long as the blocks it refers to were not
+/// garbage collected.
 #[derive(Default, Clone, PartialEq, Eq)]
 pub struct Snapshot {
     /// Compressed information about all deleted blocks at current snapshot time.
@@ -239,6 +254,27 @@ impl Snapshot {
     pub(crate) fn is_visible(&self, id: &ID) -> bool {
         self.state_map.get(&id.client) > id.clock && !self.delete_set.contains(id)
     }
+
+    /// Returns the clock (exclusive and capped at `end`) up to which all elements starting from
+    /// a given `id` share the same visibility within this snapshot. Blocks merged after
+    /// the snapshot was taken may be only partially visible in it.
+    pub(crate) fn visible_until(&self, id: &ID, end: Clock) -> Clock {
+        let mut until = end;
+        let observed = self.state_map.get(&id.client);
+        if observed > id.clock {
+            until = until.min(observed);
+        }
+        if let Some(ranges) = self.delete_set.get(&id.client) {
+            for range in ranges.iter() {
+                if range.start > id.clock {
+                    until = until.min(range.start);
+                } else if range.end > id.clock {
+                    until = until.min(range.end);
+                }
+            }
+        }
+        until
+    }
 }
 
 impl Encode for Snapshot {
@@ -258,7 +294,9 @@ impl Decode for Snapshot {
 
 #[cfg(test)]
 mod test {
-    use crate::{Clock, StateVector};
+    use crate::lib0::{Decode, Encode, Encoding};
+    use crate::state_vector::Snapshot;
+    use crate::{Clock, ID, IDSet, StateVector};
     use std::cmp::Ordering;
     use std::iter::FromIterator;
 
@@ -312,4 +350,22 @@ mod test {
         let b = StateVector::default();
         assert_eq!(a.partial_cmp(&b), Some(Ordering::Equal));
     }
+
+    #[test]
+    fn snapshot_encoding_roundtrip() {
+        let sv = StateVector::from_iter([(1.into(), 10.into()), (2.into(), 3.into())]);
+        let mut ds = IDSet::default();
+        ds.insert(ID::new(1.into(), 2.into()), Clock::new(3));
+        ds.insert(ID::new(1.into(), 7.into()), Clock::new(1));
+        ds.insert(ID::new(2.into(), 0.into()), Clock::new(2));
+        let snapshot = Snapshot::new(sv, ds);
+
+        for version in [Encoding::V1, Encoding::V2] {
+            let bytes = snapshot.encode(version).unwrap();
+            let decoded = Snapshot::decode(&bytes, version).unwrap();
+            assert!(decoded == snapshot, "{version:?}");
+        }
+        let empty = Snapshot::default().encode(Encoding::V1).unwrap();
+        assert!(Snapshot::decode(&empty, Encoding::V1).unwrap() == Snapshot::default());
+    }
 }
@@ -2,11 +2,16 @@ use crate::block::BlockFlags;
 use crate::content::ContentType;
 use crate::id_set::IDSet;
 use crate::lmdb::{Cursor, Database, Error as LmdbError};
-use crate::node::{Named, Node, NodeType};
+use crate::node::{Named, Node, NodeID, NodeType};
 use crate::store::KEY_PREFIX_BLOCK;
 use crate::store::content_store::ContentStore;
 use crate::store::intern_strings::InternStringsStore;
-use crate::{Block, BlockHeader, BlockMut, ClientID, Clock, Error, ID, Optional, lmdb};
+use crate::store::state_vector::StateVectorStore;
+use crate::{
+    Block, BlockHeader, BlockMut, ClientID, Clock, Error, ID, Optional, StateVector, lmdb,
+};
+use lmdb_master_sys::MDB_NOOVERWRITE;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Formatter};
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, TryFromBytes};
 
@@ -25,6 +30,10 @@ impl<'tx> BlockStore<'tx> {
         BlockCursor::new(self.db)
     }
 
+    pub fn prefetch(&self) -> crate::Result<BlockPrefetch<'tx>> {
+        Ok(BlockPrefetch::new(self.cursor()?))
+    }
+
     pub fn contents(self) -> ContentStore<'tx> {
         ContentStore::new(self.db)
     }
@@ -50,9 +59,46 @@ impl<'tx> BlockStore<'tx> {
         cursor.split(id)
     }
 
+    /// Recomputes the state vector from the blocks actually present in the store, ie. for every
+    /// client, the clock following its last block.
+    pub fn state_vector(&self) -> crate::Result<StateVector> {
+        let mut state_vector = StateVector::default();
+        let mut cursor = self.cursor()?;
+        // root nodes are not part of the state vector
+        let start = ID::new(unsafe { ClientID::new_unchecked(1) }, 0.into());
+        match cursor.start_from(start) {
+            Ok(()) => {}
+            Err(Error::NotFound) => return Ok(state_vector),
+            Err(e) => return Err(e),
+        }
+        let mut current = cursor.current().optional()?;
+        while let Some(block) = current {
+            let end = block.id().clock + block.clock_len();
+            state_vector.set_max(block.id().client, end);
+            current = cursor.next()?;
+        }
+        Ok(state_vector)
+    }
+
+    /// Compares the stored state vector with the one recomputed from the blocks (see
+    /// [BlockStore::state_vector]). Returns the clients, which entries diverge, together with
+    /// their actual clocks. Clients that have an entry, but no blocks are returned with
+    /// a zero clock.
+    pub fn reconcile_state_vector(&self) -> crate::Result<StateVector> {
+        let stored = StateVectorStore::new(self.db).state_vector()?;
+        Ok(divergence(&self.state_vector()?, &stored))
+    }
+
     pub fn inspect(&self) -> Inspector<'_> {
         Inspector { db: self.db }
     }
+
+    /// Returns an iterator over all blocks of a given [ContentType], in the order of their
+    /// block keys. Non-matching blocks are skipped at the cursor level.
+    #[allow(unused)]
+    pub fn iter_content(&self, content_type: ContentType) -> BlockIter<'tx> {
+        BlockIter::new(self.db, content_type)
+    }
 }
 
 impl<'tx> From<BlockStore<'tx>> for Database<'tx> {
@@ -74,11 +120,18 @@ impl<'tx> BlockCursor<'tx> {
         Ok(BlockCursor { cursor, db })
     }
 
+    /// Inserts a newly integrated block. Fails with [Error::DuplicateBlock] if a block with the
+    /// same ID already exists, as that means that the same clock has been allocated twice.
     pub fn insert(&mut self, block: Block<'_>) -> crate::Result<()> {
         let key = BlockKey::new(*block.id());
-        self.cursor
-            .put(key.as_bytes(), block.header().as_bytes(), 0)?;
-        Ok(())
+        match self
+            .cursor
+            .put(key.as_bytes(), block.header().as_bytes(), MDB_NOOVERWRITE)
+        {
+            Ok(()) => Ok(()),
+            Err(LmdbError::KEY_EXISTS) => Err(Error::DuplicateBlock(*block.id())),
+            Err(e) => Err(e.into()),
+        }
     }
 
     pub fn remove(&mut self, block_id: ID) -> crate::Result<()> {
@@ -222,6 +275,18 @@ impl<'tx> BlockCursor<'tx> {
         }
     }
 
+    /// Moves current cursor position to a next block with a given [ContentType], returning it.
+    /// Blocks of other content types are skipped over.
+    /// Returns `None` if current cursor position is outside the block boundaries.
+    pub fn next_of_type(&mut self, content_type: ContentType) -> crate::Result<Option<Block<'tx>>> {
+        while let Some(block) = self.next()? {
+            if block.content_type() == content_type {
+                return Ok(Some(block));
+            }
+        }
+        Ok(None)
+    }
+
     /// Moves current cursor position to a previous block, returning it.
     /// Returns `None` if current cursor position is outside the block boundaries.
     pub fn prev(&mut self) -> crate::Result<Option<Block<'tx>>> {
@@ -351,6 +416,71 @@ impl<'tx> BlockCursor<'tx> {
     }
 }
 
+/// Returns entries of `actual` state vector, which are different from the `expected` one.
+/// Entries missing from `actual` are returned with a zero clock.
+pub(crate) fn divergence(actual: &StateVector, expected: &StateVector) -> StateVector {
+    let mut diff = BTreeMap::new();
+    for (&client, &clock) in actual.iter() {
+        if expected.get(&client) != clock {
+            diff.insert(client, clock);
+        }
+    }
+    for (&client, &clock) in expected.iter() {
+        if clock != 0 && !actual.contains_client(&client) {
+            diff.insert(client, Clock::new(0));
+        }
+    }
+    StateVector::new(diff)
+}
+
+/// Read-ahead buffer used by traversals walking over blocks of a single node via their right
+/// pointers. Logically consecutive blocks are often stored far from each other, so following
+/// the pointers one by one results in random reads.
+///
+/// Whenever a block missing from the buffer is requested, blocks of the same client stored after
+/// it are read in a single forward scan and kept in the buffer for the following requests. The scan
+/// stops after [BlockPrefetch::BATCH_LEN] blocks, at the first block having a different parent, or
+/// when reaching a range read by one of the previous scans.
+pub struct BlockPrefetch<'tx> {
+    cursor: BlockCursor<'tx>,
+    parent: Option<NodeID>,
+    blocks: HashMap<ID, Block<'tx>>,
+}
+
+impl<'tx> BlockPrefetch<'tx> {
+    /// Max number of blocks read by a single forward scan.
+    pub const BATCH_LEN: usize = 1024;
+
+    pub fn new(cursor: BlockCursor<'tx>) -> Self {
+        BlockPrefetch {
+            cursor,
+            parent: None,
+            blocks: HashMap::new(),
+        }
+    }
+
+    /// Returns a block starting with a given [ID], same as [BlockCursor::seek].
+    pub fn get(&mut self, id: ID) -> crate::Result<Block<'tx>> {
+        if let Some(block) = self.blocks.get(&id) {
+            return Ok(*block);
+        }
+        let block = self.cursor.seek(id)?;
+        let parent = *self.parent.get_or_insert(*block.parent());
+        for _ in 0..Self::BATCH_LEN {
+            match self.cursor.next()? {
+                Some(next) if next.id().client == id.client && next.parent() == &parent => {
+                    if self.blocks.insert(*next.id(), next).is_some() {
+                        // the rest of the range has been read by one of the previous scans
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(block)
+    }
+}
+
 pub enum SplitResult {
     Unchanged(BlockMut),
     Split(BlockMut, BlockMut),
@@ -372,6 +502,64 @@ impl BlockKey {
     }
 }
 
+pub struct BlockIter<'tx> {
+    state: BlockIterState<'tx>,
+    content_type: ContentType,
+}
+
+enum BlockIterState<'tx> {
+    Uninit(Database<'tx>),
+    Init(BlockCursor<'tx>),
+    Finished,
+}
+
+impl<'tx> BlockIter<'tx> {
+    pub fn new(db: Database<'tx>, content_type: ContentType) -> Self {
+        BlockIter {
+            state: BlockIterState::Uninit(db),
+            content_type,
+        }
+    }
+
+    pub fn next(&mut self) -> crate::Result<Option<Block<'tx>>> {
+        let block = match &mut self.state {
+            BlockIterState::Uninit(db) => {
+                let mut cursor = BlockCursor::new(*db)?;
+                let first = match cursor.start_from(ID::new(0.into(), 0.into())) {
+                    Ok(()) => cursor.current().optional()?,
+                    Err(Error::NotFound) => None,
+                    Err(e) => return Err(e),
+                };
+                let block = match first {
+                    Some(block) if block.content_type() == self.content_type => Some(block),
+                    Some(_) => cursor.next_of_type(self.content_type)?,
+                    None => None,
+                };
+                self.state = BlockIterState::Init(cursor);
+                block
+            }
+            BlockIterState::Init(cursor) => cursor.next_of_type(self.content_type)?,
+            BlockIterState::Finished => return Ok(None),
+        };
+        if block.is_none() {
+            self.state = BlockIterState::Finished;
+        }
+        Ok(block)
+    }
+}
+
+impl<'tx> Iterator for BlockIter<'tx> {
+    type Item = crate::Result<Block<'tx>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next() {
+            Ok(None) => None,
+            Ok(Some(block)) => Some(Ok(block)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
 pub struct Inspector<'tx> {
     db: Database<'tx>,
 }
@@ -390,3 +578,53 @@ impl<'tx> Debug for Inspector<'tx> {
         s.finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::content::ContentType;
+    use crate::store::Db;
+    use crate::test_util::multi_doc;
+    use crate::{List, Map, Text, Unmounted};
+
+    #[test]
+    fn iter_content_filters_by_content_type() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let list: Unmounted<List> = Unmounted::root("list");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        {
+            let mut t = txt.mount_mut(&mut tx).unwrap();
+            t.insert(0, "hello").unwrap();
+        }
+        {
+            let mut l = list.mount_mut(&mut tx).unwrap();
+            l.push_back(1).unwrap();
+            l.push_back("a").unwrap();
+        }
+        {
+            let mut m = map.mount_mut(&mut tx).unwrap();
+            m.insert("key", true).unwrap();
+        }
+        {
+            let mut t = txt.mount_mut(&mut tx).unwrap();
+            t.insert(5, " world").unwrap();
+        }
+
+        let db = tx.db.get();
+        let blocks: Vec<_> = db
+            .blocks()
+            .iter_content(ContentType::String)
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert!(!blocks.is_empty());
+        for block in blocks.iter() {
+            assert_eq!(block.content_type(), ContentType::String);
+        }
+        let len: u32 = blocks.iter().map(|b| b.clock_len().get()).sum();
+        assert_eq!(len, "hello world".len() as u32);
+
+        let embeds = db.blocks().iter_content(ContentType::Embed).count();
+        assert_eq!(embeds, 0);
+    }
+}
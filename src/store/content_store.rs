@@ -1,8 +1,10 @@
 use crate::block_reader::BlockRange;
-use crate::content::{Content, ContentType, utf16_to_utf8};
+use crate::content::{Content, ContentType, split_utf16};
 use crate::lmdb::{Cursor, Database, Error as LmdbError};
-use crate::store::{KEY_PREFIX_CONTENT, ReadableBytes};
-use crate::{Clock, ID, Optional};
+use crate::store::{
+    KEY_PREFIX_CONTENT, KEY_PREFIX_CONTENT_BLOB, KEY_PREFIX_CONTENT_REF, ReadableBytes,
+};
+use crate::{Clock, ID, Optional, U32, U64};
 use std::borrow::Cow;
 use std::fmt::{Debug, Formatter};
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
@@ -20,15 +22,93 @@ impl<'a> ContentStore<'a> {
         ContentStore { db }
     }
 
+    /// Returns content data stored for a block with a given ID. If there's no such content,
+    /// [Error::ContentNotFound](crate::Error::ContentNotFound) is returned.
     pub fn get(&self, key: ID) -> crate::Result<&'a [u8]> {
-        let key = BlockContentKey::new(key);
+        let content_key = BlockContentKey::new(key);
+        match self.db.get(content_key.as_bytes()) {
+            Ok(value) => Ok(value),
+            Err(LmdbError::NOT_FOUND) => self.get_interned(key),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_interned(&self, id: ID) -> crate::Result<&'a [u8]> {
+        let key = ContentRefKey::new(id);
+        let hash = match self.db.get(key.as_bytes()) {
+            Ok(value) => U64::read_from_bytes(value)
+                .map_err(|_| crate::Error::InvalidMapping("content hash"))?,
+            Err(LmdbError::NOT_FOUND) => return Err(crate::Error::ContentNotFound(id)),
+            Err(e) => return Err(e.into()),
+        };
+        let key = ContentBlobKey::new(hash, ContentBlobKey::DATA);
         match self.db.get(key.as_bytes()) {
             Ok(value) => Ok(value),
-            Err(LmdbError::NOT_FOUND) => Err(crate::Error::NotFound),
+            Err(LmdbError::NOT_FOUND) => Err(crate::Error::ContentNotFound(id)),
             Err(e) => Err(e.into()),
         }
     }
 
+    /// Stores `data` of a block with a given `id` in an interned form: identical payloads are
+    /// stored only once under their content hash and reference counted. This is used for large,
+    /// repeatable [ContentType::Binary] and [ContentType::Embed] payloads.
+    ///
+    /// Interned content is transparently resolved by [ContentStore::get].
+    pub fn intern(&self, id: ID, data: &[u8]) -> crate::Result<()> {
+        let hash = U64::new(twox_hash::XxHash64::oneshot(0, data));
+        let data_key = ContentBlobKey::new(hash, ContentBlobKey::DATA);
+        let refs_key = ContentBlobKey::new(hash, ContentBlobKey::REFS);
+        let refs = match self.db.get(data_key.as_bytes()) {
+            Ok(existing) if existing != data => {
+                // hash collision: store the content under the block itself
+                return self.insert(id, data);
+            }
+            Ok(_) => {
+                let refs = self.db.get(refs_key.as_bytes())?;
+                let refs = U32::read_from_bytes(refs)
+                    .map_err(|_| crate::Error::InvalidMapping("content refs"))?;
+                refs.get() + 1
+            }
+            Err(LmdbError::NOT_FOUND) => {
+                self.db.put(data_key.as_bytes(), data)?;
+                1
+            }
+            Err(e) => return Err(e.into()),
+        };
+        self.db
+            .put(refs_key.as_bytes(), U32::new(refs).as_bytes())?;
+        self.db
+            .put(ContentRefKey::new(id).as_bytes(), hash.as_bytes())?;
+        Ok(())
+    }
+
+    /// Removes interned content reference of a block with a given `id`. Interned payload is
+    /// removed once the last block referencing it is gone. Returns `false` if given block had
+    /// no interned content.
+    fn release_interned(&self, id: ID) -> crate::Result<bool> {
+        let ref_key = ContentRefKey::new(id);
+        let hash = match self.db.get(ref_key.as_bytes()) {
+            Ok(value) => U64::read_from_bytes(value)
+                .map_err(|_| crate::Error::InvalidMapping("content hash"))?,
+            Err(LmdbError::NOT_FOUND) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        self.db.del(ref_key.as_bytes())?;
+
+        let refs_key = ContentBlobKey::new(hash, ContentBlobKey::REFS);
+        let refs = U32::read_from_bytes(self.db.get(refs_key.as_bytes())?)
+            .map_err(|_| crate::Error::InvalidMapping("content refs"))?;
+        if refs.get() <= 1 {
+            self.db.del(refs_key.as_bytes())?;
+            self.db
+                .del(ContentBlobKey::new(hash, ContentBlobKey::DATA).as_bytes())?;
+        } else {
+            self.db
+                .put(refs_key.as_bytes(), U32::new(refs.get() - 1).as_bytes())?;
+        }
+        Ok(true)
+    }
+
     pub fn insert(&self, id: ID, data: &[u8]) -> crate::Result<()> {
         let key = BlockContentKey::new(id);
         self.db.put(key.as_bytes(), data)?;
@@ -51,10 +131,26 @@ impl<'a> ContentStore<'a> {
         range: &BlockRange,
     ) -> crate::Result<usize> {
         let is_multipart = match content_type {
-            ContentType::Deleted | ContentType::Node | ContentType::Embed => {
-                return Ok(0); // these types don't have their content stored in ContentStore
+            ContentType::Deleted => {
+                return Ok(0); // deleted blocks don't have their content stored in ContentStore
+            }
+            ContentType::Node => {
+                // XML elements keep their tag under the node ID, other nodes have no content
+                let key = BlockContentKey::new(*range.head());
+                return match self.db.del(key.as_bytes()) {
+                    Ok(()) => Ok(1),
+                    Err(LmdbError::NOT_FOUND) => Ok(0),
+                    Err(e) => Err(e.into()),
+                };
+            }
+            ContentType::Binary | ContentType::Embed => {
+                // these types are stored on a single content entry, which may be interned
+                if self.release_interned(*range.head())? {
+                    return Ok(1);
+                }
+                false
             }
-            ContentType::Binary | ContentType::String | ContentType::Format | ContentType::Doc => {
+            ContentType::String | ContentType::Format | ContentType::Doc | ContentType::Custom => {
                 false // these types are always stored on a single content entry
             }
             ContentType::Json | ContentType::Atom => {
@@ -94,13 +190,11 @@ impl<'a> ContentStore<'a> {
         let data = self.get(id)?;
         let source = unsafe { std::str::from_utf8_unchecked(data) };
         let utf16_offset = offset.get() as usize;
-        if let Some(utf8_offset) = utf16_to_utf8(source, utf16_offset) {
+        if let Some((left, right)) = split_utf16(source, utf16_offset) {
             // Copy data before writing, since LMDB may invalidate the pointer
-            let data = data.to_vec();
-            let left_bytes = &data[..utf8_offset];
-            let right_bytes = &data[utf8_offset..];
-            self.insert(id, left_bytes)?;
-            self.insert(id.add(offset), right_bytes)?;
+            let (left, right) = (left.into_owned(), right.into_owned());
+            self.insert(id, left.as_bytes())?;
+            self.insert(id.add(offset), right.as_bytes())?;
         }
         Ok(())
     }
@@ -242,3 +336,55 @@ impl BlockContentKey {
         }
     }
 }
+
+#[repr(C, packed)]
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Clone, Copy, Debug, PartialEq, Eq)]
+struct ContentRefKey {
+    tag: u8,
+    id: ID,
+}
+
+impl ContentRefKey {
+    fn new(id: ID) -> Self {
+        ContentRefKey {
+            tag: KEY_PREFIX_CONTENT_REF,
+            id,
+        }
+    }
+}
+
+/// Key of the interned content. Each interned payload is stored as two entries sharing
+/// the same content hash: the payload itself and the number of blocks referencing it.
+#[repr(C, packed)]
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentBlobKey {
+    tag: u8,
+    hash: U64,
+    kind: u8,
+}
+
+impl ContentBlobKey {
+    pub const DATA: u8 = 0;
+    pub const REFS: u8 = 1;
+
+    pub fn new(hash: U64, kind: u8) -> Self {
+        ContentBlobKey {
+            tag: KEY_PREFIX_CONTENT_BLOB,
+            hash,
+            kind,
+        }
+    }
+
+    pub fn parse(key: &[u8]) -> Option<&Self> {
+        if let Ok(this) = Self::ref_from_bytes(key)
+            && this.tag == KEY_PREFIX_CONTENT_BLOB
+        {
+            return Some(this);
+        }
+        None
+    }
+
+    pub fn is_data(&self) -> bool {
+        self.kind == Self::DATA
+    }
+}
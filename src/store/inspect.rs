@@ -1,5 +1,9 @@
-use crate::lmdb::Database;
-use crate::store::Db;
+use crate::content::ContentType;
+use crate::gc::GcStats;
+use crate::lmdb::{Database, Error as LmdbError};
+use crate::store::content_store::ContentBlobKey;
+use crate::store::{Db, KEY_PREFIX_BLOCK, KEY_PREFIX_CONTENT, KEY_PREFIX_CONTENT_BLOB};
+use crate::{Block, ID};
 use std::fmt::Debug;
 
 pub struct DbInspector<'tx> {
@@ -10,6 +14,54 @@ impl<'tx> DbInspector<'tx> {
     pub fn new(db: Database<'tx>) -> Self {
         DbInspector { db }
     }
+
+    /// Computes [GcStats] in a single scan over block and content key-spaces.
+    pub fn gc_stats(&self) -> crate::Result<GcStats> {
+        let mut stats = GcStats::default();
+        let mut cursor = self.db.cursor()?;
+        let (mut key, mut value) = match cursor.set_range(&[KEY_PREFIX_BLOCK]) {
+            Ok(kv) => kv,
+            Err(LmdbError::NOT_FOUND) => return Ok(stats),
+            Err(e) => return Err(e.into()),
+        };
+
+        loop {
+            match key.first() {
+                Some(&KEY_PREFIX_BLOCK) => {
+                    let &id = ID::parse(&key[1..])?;
+                    let block = Block::new(id, value)?;
+                    if block.content_type() == ContentType::Deleted {
+                        stats.gc_blocks += 1;
+                    } else if block.is_deleted() {
+                        stats.deleted_blocks += 1;
+                    } else {
+                        stats.live_blocks += 1;
+                    }
+                    if let Some(data) = block.try_inline_data() {
+                        stats.content_bytes += data.len();
+                    }
+                }
+                Some(&KEY_PREFIX_CONTENT) => stats.content_bytes += value.len(),
+                // interned content is accounted once, no matter how many blocks share it
+                Some(&KEY_PREFIX_CONTENT_BLOB)
+                    if ContentBlobKey::parse(key).is_some_and(ContentBlobKey::is_data) =>
+                {
+                    stats.content_bytes += value.len()
+                }
+                Some(&prefix) if prefix > KEY_PREFIX_CONTENT_BLOB => break,
+                _ => { /* other key-spaces in between */ }
+            }
+            match cursor.next() {
+                Ok(kv) => {
+                    key = kv.0;
+                    value = kv.1;
+                }
+                Err(LmdbError::NOT_FOUND) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(stats)
+    }
 }
 
 impl<'tx> Debug for DbInspector<'tx> {
@@ -53,7 +53,14 @@ impl<'tx> MapEntriesStore<'tx> {
         MapEntries::new(self.db, *node_id)
     }
 
-    #[allow(unused)]
+    /// Returns entries of a given node, which follow the entry with a given `key`
+    /// in the storage order.
+    pub fn entries_after(&self, node_id: &NodeID, key: &str) -> MapEntries<'tx> {
+        let mut entries = MapEntries::new(self.db, *node_id);
+        entries.after = Some(MapKey::create(node_id, key));
+        entries
+    }
+
     pub fn remove_all(&self, node_id: &NodeID) -> crate::Result<usize> {
         let key = MapEntriesKey::new(*node_id);
         let mut cursor = self.db.cursor()?;
@@ -168,6 +175,8 @@ impl<'tx> HashKeys<'tx> {
 pub struct MapEntries<'tx> {
     state: MapEntriesState<'tx>,
     node_id: NodeID,
+    /// If set, iteration starts right after this key.
+    after: Option<SmallVec<[u8; 16]>>,
 }
 
 enum MapEntriesState<'tx> {
@@ -181,6 +190,7 @@ impl<'tx> MapEntries<'tx> {
         MapEntries {
             state: MapEntriesState::Uninit(db),
             node_id,
+            after: None,
         }
     }
 
@@ -198,8 +208,16 @@ impl<'tx> MapEntries<'tx> {
         let (k, _) = match &mut self.state {
             MapEntriesState::Uninit(db) => {
                 let mut cursor = db.cursor()?;
-                let key = MapEntriesKey::new(self.node_id);
-                let kv = match cursor.set_range(key.as_bytes()) {
+                let prefix = MapEntriesKey::new(self.node_id);
+                let start = match &self.after {
+                    Some(after) => after.as_slice(),
+                    None => prefix.as_bytes(),
+                };
+                let kv = match cursor.set_range(start) {
+                    Ok((k, _)) if self.after.is_some() && k == start => cursor.next(),
+                    other => other,
+                };
+                let kv = match kv {
                     Ok(kv) => kv,
                     Err(LmdbError::NOT_FOUND) => {
                         self.state = MapEntriesState::Finished;
@@ -1,5 +1,7 @@
+use crate::id_set::IDSet;
 use crate::lib0::{Decode, Encode, Encoding};
 use crate::lmdb::{Cursor, Database, Error as LmdbError};
+use crate::state_vector::Snapshot;
 use crate::store::{KEY_PREFIX_META, ReadableBytes};
 use crate::transaction::PendingUpdate;
 use crate::{ClientID, StateVector};
@@ -21,6 +23,26 @@ impl<'tx> MetaStore<'tx> {
     pub const KEY_PENDING_DS: &'static str = "$pending_ds";
     /// Metadata key for missing state vector data.
     pub const KEY_MISSING_SV: &'static str = "$missing_sv";
+    /// Metadata key marking pending update which should be retried by the next update
+    /// regardless of its missing state vector.
+    pub const KEY_PENDING_RETRY: &'static str = "$pending_retry";
+
+    /// Metadata key prefix for named snapshots.
+    pub const KEY_SNAPSHOT_PREFIX: &'static str = "$snapshot:";
+
+    /// Metadata key for the sequence number of the last commit tracked for tombstone retention.
+    pub const KEY_COMMIT_SEQ: &'static str = "$commit_seq";
+    /// Metadata key prefix for delete sets retained from GC, followed by the commit sequence
+    /// number (hex-encoded, so that entries are ordered by it).
+    pub const KEY_TOMBSTONES_PREFIX: &'static str = "$tombstones:";
+    /// Metadata key for the number of clients reported by the last commit exceeding
+    /// [crate::ClientLimit::Report] threshold.
+    pub const KEY_CLIENT_LIMIT_EXCEEDED: &'static str = "$client_limit_exceeded";
+    /// Metadata key for the total size of document entries, maintained when a document quota
+    /// is enforced.
+    pub const KEY_DOC_SIZE: &'static str = "$doc_size";
+    /// Metadata key for the document GUID.
+    pub const KEY_GUID: &'static str = "$guid";
 
     pub fn new(db: Database<'tx>) -> Self {
         Self { db }
@@ -39,6 +61,16 @@ impl<'tx> MetaStore<'tx> {
         }
     }
 
+    /// Returns the document GUID, if it has been assigned.
+    pub fn guid(&self) -> crate::Result<Option<&'tx str>> {
+        match self.get(Self::KEY_GUID)? {
+            None => Ok(None),
+            Some(data) => std::str::from_utf8(data)
+                .map(Some)
+                .map_err(|_| crate::Error::InvalidMapping("guid")),
+        }
+    }
+
     /// Get pending update if any exists.
     pub fn pending(&self) -> crate::Result<Option<PendingUpdate<'tx>>> {
         if let Some(missing_sv) = self.get(Self::KEY_MISSING_SV)? {
@@ -47,7 +79,9 @@ impl<'tx> MetaStore<'tx> {
             let ds = self
                 .get(Self::KEY_PENDING_DS)?
                 .ok_or(crate::Error::NotFound)?;
-            Ok(Some(PendingUpdate::new(update, ds, missing_sv)))
+            let mut pending = PendingUpdate::new(update, ds, missing_sv);
+            pending.retry = self.get(Self::KEY_PENDING_RETRY)?.is_some();
+            Ok(Some(pending))
         } else {
             Ok(None)
         }
@@ -61,6 +95,11 @@ impl<'tx> MetaStore<'tx> {
         )?;
         self.insert(Self::KEY_PENDING, pending.update)?;
         self.insert(Self::KEY_PENDING_DS, pending.delete_set)?;
+        if pending.retry {
+            self.insert(Self::KEY_PENDING_RETRY, &[])?;
+        } else if self.get(Self::KEY_PENDING_RETRY)?.is_some() {
+            self.remove(Self::KEY_PENDING_RETRY)?;
+        }
         Ok(())
     }
 
@@ -68,9 +107,176 @@ impl<'tx> MetaStore<'tx> {
         self.remove(Self::KEY_MISSING_SV)?;
         self.remove(Self::KEY_PENDING)?;
         self.remove(Self::KEY_PENDING_DS)?;
+        if self.get(Self::KEY_PENDING_RETRY)?.is_some() {
+            self.remove(Self::KEY_PENDING_RETRY)?;
+        }
         Ok(())
     }
 
+    /// Get a snapshot stored under a given `name`, if any exists.
+    pub fn snapshot(&self, name: &str) -> crate::Result<Option<Snapshot>> {
+        let key = format!("{}{}", Self::KEY_SNAPSHOT_PREFIX, name);
+        match self.get(&key)? {
+            Some(data) => Ok(Some(Snapshot::decode(data, Encoding::V1)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Insert a snapshot under a given `name`, possibly replacing existing one.
+    pub fn insert_snapshot(&self, name: &str, snapshot: &Snapshot) -> crate::Result<()> {
+        let key = format!("{}{}", Self::KEY_SNAPSHOT_PREFIX, name);
+        self.insert(&key, &snapshot.encode(Encoding::V1)?)
+    }
+
+    /// Remove a snapshot stored under a given `name`. Returns `false` if it didn't exist.
+    pub fn remove_snapshot(&self, name: &str) -> crate::Result<bool> {
+        let key = format!("{}{}", Self::KEY_SNAPSHOT_PREFIX, name);
+        if self.get(&key)?.is_none() {
+            return Ok(false);
+        }
+        self.remove(&key)?;
+        Ok(true)
+    }
+
+    /// Returns the sequence number of the last commit tracked for tombstone retention.
+    pub fn commit_seq(&self) -> crate::Result<u64> {
+        match self.get(Self::KEY_COMMIT_SEQ)? {
+            None => Ok(0),
+            Some(data) => {
+                let bytes = data.try_into().map_err(|_| crate::Error::OutOfRange)?;
+                Ok(u64::from_be_bytes(bytes))
+            }
+        }
+    }
+
+    /// Increments the commit sequence number, returning the new value.
+    pub fn next_commit_seq(&self) -> crate::Result<u64> {
+        let seq = self.commit_seq()? + 1;
+        self.insert(Self::KEY_COMMIT_SEQ, &seq.to_be_bytes())?;
+        Ok(seq)
+    }
+
+    /// Returns the number of clients reported by the last commit exceeding
+    /// [crate::ClientLimit::Report] threshold, if any.
+    pub fn client_limit_exceeded(&self) -> crate::Result<Option<usize>> {
+        match self.get(Self::KEY_CLIENT_LIMIT_EXCEEDED)? {
+            None => Ok(None),
+            Some(data) => {
+                let bytes = data.try_into().map_err(|_| crate::Error::OutOfRange)?;
+                Ok(Some(u64::from_be_bytes(bytes) as usize))
+            }
+        }
+    }
+
+    /// Records the number of clients of a commit exceeding [crate::ClientLimit::Report] threshold.
+    pub fn set_client_limit_exceeded(&self, clients: usize) -> crate::Result<()> {
+        self.insert(
+            Self::KEY_CLIENT_LIMIT_EXCEEDED,
+            &(clients as u64).to_be_bytes(),
+        )
+    }
+
+    /// Returns the total size (in bytes) of document entries, as persisted by the last commit
+    /// which enforced a document quota.
+    pub fn doc_size(&self) -> crate::Result<Option<usize>> {
+        match self.get(Self::KEY_DOC_SIZE)? {
+            None => Ok(None),
+            Some(data) => {
+                let bytes = data.try_into().map_err(|_| crate::Error::OutOfRange)?;
+                Ok(Some(u64::from_be_bytes(bytes) as usize))
+            }
+        }
+    }
+
+    /// Persists the total size (in bytes) of document entries.
+    pub fn set_doc_size(&self, size: usize) -> crate::Result<()> {
+        self.insert(Self::KEY_DOC_SIZE, &(size as u64).to_be_bytes())
+    }
+
+    /// Removes persisted document size, so that it's computed anew by the next commit which
+    /// enforces a document quota.
+    pub fn clear_doc_size(&self) -> crate::Result<()> {
+        if self.get(Self::KEY_DOC_SIZE)?.is_some() {
+            self.remove(Self::KEY_DOC_SIZE)?;
+        }
+        Ok(())
+    }
+
+    /// Stores a `delete_set` of a commit with a given sequence number, which happened at
+    /// a given `timestamp` (in milliseconds since UNIX epoch).
+    pub fn insert_tombstones(
+        &self,
+        seq: u64,
+        timestamp: u64,
+        delete_set: &IDSet,
+    ) -> crate::Result<()> {
+        let key = format!("{}{:016x}", Self::KEY_TOMBSTONES_PREFIX, seq);
+        let mut value = timestamp.to_be_bytes().to_vec();
+        value.extend_from_slice(&delete_set.encode(Encoding::V1)?);
+        self.insert(&key, &value)
+    }
+
+    /// Returns all stored delete sets together with sequence numbers and timestamps of commits,
+    /// which produced them, ordered by sequence number.
+    pub fn tombstones(&self) -> crate::Result<Vec<(u64, u64, IDSet)>> {
+        let prefix = meta_key(Self::KEY_TOMBSTONES_PREFIX);
+        let mut cursor = self.db.cursor()?;
+        let mut kv = match cursor.set_range(&prefix) {
+            Ok(kv) => Some(kv),
+            Err(LmdbError::NOT_FOUND) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let mut result = Vec::new();
+        while let Some((key, value)) = kv
+            && let Some(seq) = key.strip_prefix(prefix.as_slice())
+        {
+            let seq = unsafe { std::str::from_utf8_unchecked(seq) };
+            let seq = u64::from_str_radix(seq, 16).map_err(|_| crate::Error::OutOfRange)?;
+            if value.len() < 8 {
+                return Err(crate::Error::EndOfBuffer);
+            }
+            let (timestamp, ds) = value.split_at(8);
+            let timestamp = u64::from_be_bytes(timestamp.try_into().unwrap());
+            result.push((seq, timestamp, IDSet::decode(ds, Encoding::V1)?));
+            kv = match cursor.next() {
+                Ok(kv) => Some(kv),
+                Err(LmdbError::NOT_FOUND) => None,
+                Err(e) => return Err(e.into()),
+            };
+        }
+        Ok(result)
+    }
+
+    /// Removes a delete set stored for a commit with a given sequence number.
+    pub fn remove_tombstones(&self, seq: u64) -> crate::Result<()> {
+        let key = format!("{}{:016x}", Self::KEY_TOMBSTONES_PREFIX, seq);
+        self.remove(&key)
+    }
+
+    /// Returns all stored snapshots together with their names, ordered by name.
+    pub fn snapshots(&self) -> crate::Result<Vec<(String, Snapshot)>> {
+        let prefix = meta_key(Self::KEY_SNAPSHOT_PREFIX);
+        let mut cursor = self.db.cursor()?;
+        let mut kv = match cursor.set_range(&prefix) {
+            Ok(kv) => Some(kv),
+            Err(LmdbError::NOT_FOUND) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let mut snapshots = Vec::new();
+        while let Some((key, value)) = kv
+            && let Some(name) = key.strip_prefix(prefix.as_slice())
+        {
+            let name = unsafe { std::str::from_utf8_unchecked(name) };
+            snapshots.push((name.to_string(), Snapshot::decode(value, Encoding::V1)?));
+            kv = match cursor.next() {
+                Ok(kv) => Some(kv),
+                Err(LmdbError::NOT_FOUND) => None,
+                Err(e) => return Err(e.into()),
+            };
+        }
+        Ok(snapshots)
+    }
+
     pub fn get(&self, key: &str) -> crate::Result<Option<&'tx [u8]>> {
         let key = meta_key(key);
         match self.db.get(key.as_ref()) {
@@ -7,6 +7,7 @@ use crate::store::intern_strings::InternStringsStore;
 pub(crate) use crate::store::map_entries::MapEntriesStore;
 use crate::store::meta_store::MetaStore;
 use crate::store::state_vector::StateVectorStore;
+use crate::store::text_index::TextIndexStore;
 use std::fmt::{Debug, Formatter};
 
 pub(crate) mod block_store;
@@ -17,6 +18,7 @@ pub(crate) mod intern_strings;
 pub(crate) mod map_entries;
 pub(crate) mod meta_store;
 pub(crate) mod state_vector;
+pub(crate) mod text_index;
 
 pub(super) const KEY_PREFIX_META: u8 = 0x00;
 pub(super) const KEY_PREFIX_INTERN_STR: u8 = 0x01;
@@ -24,6 +26,10 @@ pub(super) const KEY_PREFIX_STATE_VECTOR: u8 = 0x02;
 pub(super) const KEY_PREFIX_BLOCK: u8 = 0x03;
 pub(super) const KEY_PREFIX_MAP: u8 = 0x04;
 pub(super) const KEY_PREFIX_CONTENT: u8 = 0x05;
+pub(super) const KEY_PREFIX_CONTENT_REF: u8 = 0x06;
+pub(super) const KEY_PREFIX_CONTENT_BLOB: u8 = 0x07;
+pub(super) const KEY_PREFIX_TEXT_INDEX: u8 = 0x08;
+pub(super) const KEY_PREFIX_TEXT_INDEX_IDS: u8 = 0x09;
 
 pub trait Db<'tx> {
     fn meta(&self) -> MetaStore<'tx>;
@@ -33,6 +39,7 @@ pub trait Db<'tx> {
     fn map_entries(&self) -> MapEntriesStore<'tx>;
     fn state_vector(&self) -> StateVectorStore<'tx>;
     fn delete_set(&self) -> DeleteSetStore<'tx>;
+    fn text_index(&self) -> TextIndexStore<'tx>;
     fn inspect(&self) -> DbInspector<'tx>;
 }
 
@@ -66,6 +73,10 @@ impl<'tx> Db<'tx> for Database<'tx> {
         DeleteSetStore::new(*self)
     }
 
+    fn text_index(&self) -> TextIndexStore<'tx> {
+        TextIndexStore::new(*self)
+    }
+
     #[allow(unused)]
     fn inspect(&self) -> DbInspector<'tx> {
         DbInspector::new(*self)
@@ -45,6 +45,58 @@ impl<'tx> StateVectorStore<'tx> {
         Ok(StateVector::new(buf))
     }
 
+    /// Returns IDs of all clients which have contributed to the document, without reading their
+    /// clocks.
+    pub fn clients(&self) -> crate::Result<Vec<ClientID>> {
+        let mut clients = Vec::new();
+        let mut cursor = self.db.cursor()?;
+
+        let key = StateVectorKey::new(unsafe { ClientID::new_unchecked(0) });
+        let mut k = match cursor.set_range(key.as_bytes()) {
+            Ok((k, _)) => k,
+            Err(LmdbError::NOT_FOUND) => return Ok(clients),
+            Err(e) => return Err(crate::Error::from(e)),
+        };
+        while let Some(key) = StateVectorKey::parse(k) {
+            clients.push(key.client);
+            k = match cursor.next() {
+                Ok((k, _)) => k,
+                Err(LmdbError::NOT_FOUND) => break,
+                Err(e) => return Err(e.into()),
+            };
+        }
+        Ok(clients)
+    }
+
+    /// Returns a clock value stored for a given `client`, if it has contributed to the document.
+    pub fn clock(&self, client: ClientID) -> crate::Result<Option<Clock>> {
+        let key = StateVectorKey::new(client);
+        match self.db.get(key.as_bytes()) {
+            Ok(value) => {
+                let clock = *Clock::ref_from_bytes(value)
+                    .map_err(|_| crate::Error::InvalidMapping("Clock"))?;
+                Ok(Some(clock))
+            }
+            Err(LmdbError::NOT_FOUND) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Overrides a clock value stored for a given `client`, even if it's lower than the current
+    /// one. Setting clock to zero removes the client entry.
+    pub fn set(&mut self, client: ClientID, clock: Clock) -> crate::Result<()> {
+        let key = StateVectorKey::new(client);
+        if clock == 0 {
+            match self.db.del(key.as_bytes()) {
+                Ok(()) | Err(LmdbError::NOT_FOUND) => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        } else {
+            self.db.put(key.as_bytes(), clock.as_bytes())?;
+            Ok(())
+        }
+    }
+
     pub fn update(&mut self, client: ClientID, clock: Clock) -> crate::Result<Clock> {
         let key = StateVectorKey::new(client);
         let key_bytes = key.as_bytes();
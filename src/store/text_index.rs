@@ -0,0 +1,580 @@
+use crate::content::{Content, ContentType, utf16_to_utf8};
+use crate::lmdb::Database;
+use crate::node::NodeID;
+use crate::observer::IntegrationObserver;
+use crate::store::block_store::BlockCursor;
+use crate::store::content_store::ContentStore;
+use crate::store::{Db, KEY_PREFIX_TEXT_INDEX, KEY_PREFIX_TEXT_INDEX_IDS};
+use crate::types::text::try_get_content;
+use crate::{Block, Clock, ID, Optional};
+use zerocopy::{FromBytes, IntoBytes};
+
+/// Inverted index of words found in text blocks. Every indexed word occurrence is identified by
+/// the ID of its first character. It's stored under two keyspaces:
+///
+/// - `{KEY_PREFIX_TEXT_INDEX}{term}\0{ID}` with no value, used to search for a term.
+/// - `{KEY_PREFIX_TEXT_INDEX_IDS}{ID}` with `{U32 utf-16 length}{term}` value, used to find terms
+///   overlapping with deleted ranges of elements.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct TextIndexStore<'tx> {
+    db: Database<'tx>,
+}
+
+impl<'tx> TextIndexStore<'tx> {
+    /// Max length of an indexed term, both in bytes and in utf-16 code units. Longer words are not
+    /// indexed.
+    pub const MAX_TERM_LEN: usize = 128;
+
+    pub fn new(db: Database<'tx>) -> Self {
+        Self { db }
+    }
+
+    /// Inserts a `term` of a given utf-16 `len`, which starts at `id`, into the index.
+    pub fn insert(&self, id: &ID, len: u32, term: &str) -> crate::Result<()> {
+        self.db.put(&Self::term_key(term, id), &[])?;
+        let mut value = Vec::with_capacity(4 + term.len());
+        value.extend_from_slice(Clock::new(len).as_bytes());
+        value.extend_from_slice(term.as_bytes());
+        self.db.put(&Self::id_key(id), &value)?;
+        Ok(())
+    }
+
+    /// Removes all words, which start within a range of `len` elements starting at `id`.
+    pub fn remove_range(&self, id: &ID, len: Clock) -> crate::Result<()> {
+        let end = id.clock + len;
+        let from = Self::id_key(id);
+
+        let mut removed = Vec::new();
+        let mut cursor = self.db.cursor()?;
+        let mut entry = cursor.set_range(&from).optional()?;
+        while let Some((key, value)) = entry {
+            if key.len() != 1 + ID::SIZE || key[0] != KEY_PREFIX_TEXT_INDEX_IDS {
+                break;
+            }
+            let start = *ID::parse(&key[1..])?;
+            if start.client != id.client || start.clock >= end {
+                break;
+            }
+            let (_, term) =
+                Clock::read_from_prefix(value).map_err(|_| crate::Error::InvalidMapping("term"))?;
+            removed.push((start, term));
+            entry = cursor.next().optional()?;
+        }
+
+        for (start, term) in removed {
+            let term =
+                std::str::from_utf8(term).map_err(|_| crate::Error::InvalidMapping("term"))?;
+            self.db.del(&Self::term_key(term, &start))?;
+            self.db.del(&Self::id_key(&start))?;
+        }
+        Ok(())
+    }
+
+    /// Returns IDs of all indexed occurrences of a given `term`, which must be already normalized
+    /// to lowercase.
+    pub fn search(&self, term: &str) -> crate::Result<Vec<ID>> {
+        let mut prefix = Vec::with_capacity(2 + term.len());
+        prefix.push(KEY_PREFIX_TEXT_INDEX);
+        prefix.extend_from_slice(term.as_bytes());
+        prefix.push(0);
+
+        let mut result = Vec::new();
+        let mut cursor = self.db.cursor()?;
+        let mut entry = cursor.set_range(&prefix).optional()?;
+        while let Some((key, _)) = entry {
+            if !key.starts_with(&prefix) || key.len() != prefix.len() + ID::SIZE {
+                break;
+            }
+            result.push(*ID::parse(&key[prefix.len()..])?);
+            entry = cursor.next().optional()?;
+        }
+        Ok(result)
+    }
+
+    fn term_key(term: &str, id: &ID) -> Vec<u8> {
+        let mut key = Vec::with_capacity(2 + term.len() + ID::SIZE);
+        key.push(KEY_PREFIX_TEXT_INDEX);
+        key.extend_from_slice(term.as_bytes());
+        key.push(0);
+        key.extend_from_slice(id.as_bytes());
+        key
+    }
+
+    fn id_key(id: &ID) -> [u8; 1 + ID::SIZE] {
+        let mut key = [KEY_PREFIX_TEXT_INDEX_IDS; 1 + ID::SIZE];
+        key[1..].copy_from_slice(id.as_bytes());
+        key
+    }
+}
+
+/// Splits `text` into lowercase words made of alphanumeric characters. Returns utf-16 offset and
+/// utf-16 length of every word together with the word itself.
+pub(crate) fn tokenize(text: &str) -> Vec<(u32, u32, String)> {
+    let mut result = Vec::new();
+    let mut offset = 0u32;
+    let mut current: Option<(u32, String)> = None;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            current
+                .get_or_insert_with(|| (offset, String::new()))
+                .1
+                .extend(c.to_lowercase());
+        } else if let Some((start, word)) = current.take() {
+            result.push((start, offset - start, word));
+        }
+        offset += c.len_utf16() as u32;
+    }
+    if let Some((start, word)) = current {
+        result.push((start, offset - start, word));
+    }
+    result.retain(|(_, len, word)| {
+        *len as usize <= TextIndexStore::MAX_TERM_LEN && word.len() <= TextIndexStore::MAX_TERM_LEN
+    });
+    result
+}
+
+/// [IntegrationObserver] keeping [TextIndexStore] in sync with inserted and deleted text.
+///
+/// Words may span over many blocks, e.g. when they were typed one character at a time. That's
+/// why every change re-tokenizes the whole word(s) around it: visible text neighboring the changed
+/// block is read up to the nearest word boundaries, words starting within that range are removed
+/// from the index and the range is tokenized again.
+pub(crate) struct FullTextIndex;
+
+impl FullTextIndex {
+    /// Re-indexes words overlapping with `len` elements starting at `id`, which have just been
+    /// inserted or deleted.
+    fn reindex(db: &Database<'_>, id: &ID, len: Clock) -> crate::Result<()> {
+        let contents = db.contents();
+        let mut cursor = db.blocks().cursor()?;
+        let block = cursor.seek_containing(*id)?;
+        let offset = id.clock - block.id().clock;
+        // visible embeds and nodes are not a part of any word, but they split words around them
+        let boundary = !block.is_deleted() && block.content_type() != ContentType::String;
+
+        let mut runs = Vec::new();
+        if !boundary {
+            Self::collect_left(&mut cursor, &contents, block, offset, &mut runs)?;
+        } else if let Some(left) = block.left().copied() {
+            let block = cursor.seek_containing(left)?;
+            let end = left.clock - block.id().clock + Clock::new(1);
+            Self::collect_left(&mut cursor, &contents, block, end, &mut runs)?;
+        }
+        runs.reverse();
+        // IDs of the deleted range are not visible anymore, but they may still start indexed words
+        let mut stale = vec![(*id, len)];
+        let block = cursor.seek_containing(*id)?;
+        if boundary {
+            runs.push((*id, " ".to_owned()));
+        } else if !block.is_deleted()
+            && let Some(content) = try_get_content(&block, &contents)?
+        {
+            let str = content.as_str()?;
+            let start = utf16_to_utf8(str, offset.get() as usize).unwrap_or(str.len());
+            let end = utf16_to_utf8(str, (offset + len).get() as usize).unwrap_or(str.len());
+            runs.push((*id, str[start..end].to_owned()));
+            stale.clear();
+        }
+        if !boundary {
+            Self::collect_right(&mut cursor, &contents, block, offset + len, &mut runs)?;
+        } else if let Some(right) = block.right().copied() {
+            let block = cursor.seek(right)?;
+            Self::collect_right(&mut cursor, &contents, block, Clock::new(0), &mut runs)?;
+        }
+
+        let index = db.text_index();
+        let mut text = String::new();
+        let mut starts = Vec::with_capacity(runs.len());
+        let mut text_len = Clock::new(0);
+        for (id, str) in runs.iter() {
+            let len = Clock::new(str.encode_utf16().count() as u32);
+            starts.push(text_len);
+            stale.push((*id, len));
+            text.push_str(str);
+            text_len += len;
+        }
+        for (id, len) in stale {
+            index.remove_range(&id, len)?;
+        }
+        for (offset, len, term) in tokenize(&text) {
+            // find the run containing the first character of the word
+            let i = starts.partition_point(|start| start.get() <= offset) - 1;
+            let (run_id, _) = &runs[i];
+            let id = ID::new(run_id.client, run_id.clock + Clock::new(offset) - starts[i]);
+            index.insert(&id, len, &term)?;
+        }
+        Ok(())
+    }
+
+    /// Collects alphanumeric characters of visible text preceding first `end` elements of
+    /// a `block`, up to the nearest word boundary. Runs of text are pushed in reversed order.
+    fn collect_left<'tx>(
+        cursor: &mut BlockCursor<'tx>,
+        contents: &ContentStore<'tx>,
+        block: Block<'tx>,
+        end: Clock,
+        runs: &mut Vec<(ID, String)>,
+    ) -> crate::Result<()> {
+        let mut collected = 0;
+        let mut next = Some((block, end));
+        while let Some((block, end)) = next.take() {
+            if !block.is_deleted() {
+                match block.content_type() {
+                    ContentType::String => {
+                        let Some(content) = try_get_content(&block, contents)? else {
+                            break;
+                        };
+                        let str = content.as_str()?;
+                        let str = &str[..utf16_to_utf8(str, end.get() as usize).unwrap_or(0)];
+                        let word = str
+                            .char_indices()
+                            .rev()
+                            .take_while(|(_, c)| c.is_alphanumeric())
+                            .last()
+                            .map_or("", |(i, _)| &str[i..]);
+                        if !word.is_empty() {
+                            let len = word.encode_utf16().count();
+                            let id = block.id();
+                            let id = ID::new(id.client, id.clock + end - Clock::new(len as u32));
+                            runs.push((id, word.to_owned()));
+                            collected += len;
+                        }
+                        if word.len() < str.len() || collected > TextIndexStore::MAX_TERM_LEN {
+                            // word boundary found, or the word is too long to be indexed anyway
+                            break;
+                        }
+                    }
+                    ContentType::Format => { /* formatting doesn't split words */ }
+                    _ => break,
+                }
+            }
+            if let Some(left) = block.left().copied() {
+                let block = cursor.seek_containing(left)?;
+                let end = left.clock - block.id().clock + Clock::new(1);
+                next = Some((block, end));
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects alphanumeric characters of visible text following a `block` from a given `start`
+    /// offset, up to the nearest word boundary.
+    fn collect_right<'tx>(
+        cursor: &mut BlockCursor<'tx>,
+        contents: &ContentStore<'tx>,
+        block: Block<'tx>,
+        start: Clock,
+        runs: &mut Vec<(ID, String)>,
+    ) -> crate::Result<()> {
+        let mut collected = 0;
+        let mut next = Some((block, start));
+        while let Some((block, start)) = next.take() {
+            if !block.is_deleted() {
+                match block.content_type() {
+                    ContentType::String => {
+                        let Some(content) = try_get_content(&block, contents)? else {
+                            break;
+                        };
+                        let str = content.as_str()?;
+                        let offset = utf16_to_utf8(str, start.get() as usize).unwrap_or(str.len());
+                        let str = &str[offset..];
+                        let word = str
+                            .char_indices()
+                            .find(|(_, c)| !c.is_alphanumeric())
+                            .map_or(str, |(i, _)| &str[..i]);
+                        if !word.is_empty() {
+                            let id = block.id();
+                            runs.push((ID::new(id.client, id.clock + start), word.to_owned()));
+                            collected += word.encode_utf16().count();
+                        }
+                        if word.len() < str.len() || collected > TextIndexStore::MAX_TERM_LEN {
+                            break;
+                        }
+                    }
+                    ContentType::Format => { /* formatting doesn't split words */ }
+                    _ => break,
+                }
+            }
+            if let Some(right) = block.right().copied() {
+                next = Some((cursor.seek(right)?, Clock::new(0)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IntegrationObserver for FullTextIndex {
+    fn block_inserted(
+        &self,
+        db: &Database<'_>,
+        id: &ID,
+        _parent: &NodeID,
+        content: &[Content<'_>],
+    ) -> crate::Result<()> {
+        let len = match content {
+            [content] if content.content_type() == ContentType::String => {
+                content.as_str()?.encode_utf16().count()
+            }
+            content => content.len(),
+        };
+        Self::reindex(db, id, Clock::new(len as u32))
+    }
+
+    fn block_deleted(
+        &self,
+        db: &Database<'_>,
+        id: &ID,
+        len: Clock,
+        _parent: &NodeID,
+    ) -> crate::Result<()> {
+        let block = db.blocks().get(*id)?;
+        if block.content_type().is_countable() {
+            Self::reindex(db, id, len)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lib0::Encoding;
+    use crate::test_util::{multi_doc, sync};
+    use crate::{ID, StateVector, Text, Unmounted};
+
+    #[test]
+    fn full_text_search() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let d1 = d1.with_full_text_index();
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "The quick brown fox").unwrap();
+        t.push(" jumps over the lazy dog").unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = d1.transact("test").unwrap();
+        assert_eq!(
+            tx.search("fox").unwrap(),
+            vec![ID::new(1.into(), 16.into())]
+        );
+        assert_eq!(
+            tx.search("THE").unwrap(),
+            vec![ID::new(1.into(), 0.into()), ID::new(1.into(), 31.into())]
+        );
+        assert!(tx.search("cat").unwrap().is_empty());
+        drop(tx);
+
+        // delete "brown " and a part of "lazy"
+        let mut tx = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(10..16).unwrap();
+        t.remove_range(29..31).unwrap();
+        assert_eq!(t.to_string(), "The quick fox jumps over the zy dog");
+        tx.commit(None).unwrap();
+
+        let tx = d1.transact("test").unwrap();
+        assert!(tx.search("brown").unwrap().is_empty());
+        assert!(tx.search("lazy").unwrap().is_empty());
+        assert_eq!(
+            tx.search("fox").unwrap(),
+            vec![ID::new(1.into(), 16.into())]
+        );
+        assert_eq!(
+            tx.search("dog").unwrap(),
+            vec![ID::new(1.into(), 40.into())]
+        );
+
+        // index is built the same way from remote updates
+        let update = tx
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        drop(tx);
+        let (d2, _dir2) = multi_doc(2);
+        let d2 = d2.with_full_text_index();
+        let mut tx = d2.transact_mut("test").unwrap();
+        tx.apply_update(&update, Encoding::V1).unwrap();
+        tx.commit(None).unwrap();
+        let tx = d2.transact("test").unwrap();
+        assert!(tx.search("brown").unwrap().is_empty());
+        assert!(tx.search("lazy").unwrap().is_empty());
+        assert_eq!(tx.search("the").unwrap().len(), 2);
+        assert_eq!(
+            tx.search("fox").unwrap(),
+            vec![ID::new(1.into(), 16.into())]
+        );
+    }
+
+    #[test]
+    fn full_text_search_embeds_split_words() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let d1 = d1.with_full_text_index();
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "helloworld").unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert_embed(5, 1.0).unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = d1.transact("test").unwrap();
+        assert!(tx.search("helloworld").unwrap().is_empty());
+        assert_eq!(
+            tx.search("hello").unwrap(),
+            vec![ID::new(1.into(), 0.into())]
+        );
+        assert_eq!(
+            tx.search("world").unwrap(),
+            vec![ID::new(1.into(), 5.into())]
+        );
+        drop(tx);
+
+        // removing the embed joins the words back together
+        let mut tx = d1.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(5..6).unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = d1.transact("test").unwrap();
+        assert!(tx.search("hello").unwrap().is_empty());
+        assert!(tx.search("world").unwrap().is_empty());
+        assert_eq!(
+            tx.search("helloworld").unwrap(),
+            vec![ID::new(1.into(), 0.into())]
+        );
+    }
+
+    #[test]
+    fn full_text_search_typed_per_keystroke() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let d1 = d1.with_full_text_index();
+
+        // single transaction: every keystroke extends the same block
+        let mut tx = d1.transact_mut("test").unwrap();
+        for (i, c) in "hello world hello".chars().enumerate() {
+            let mut t = txt.mount_mut(&mut tx).unwrap();
+            t.insert(i, c.to_string()).unwrap();
+        }
+        assert_eq!(
+            tx.search("hello").unwrap(),
+            vec![ID::new(1.into(), 0.into()), ID::new(1.into(), 12.into())]
+        );
+        assert_eq!(
+            tx.search("world").unwrap(),
+            vec![ID::new(1.into(), 6.into())]
+        );
+        assert!(tx.search("h").unwrap().is_empty());
+        assert!(tx.search("hell").unwrap().is_empty());
+        tx.commit(None).unwrap();
+
+        // transaction per keystroke: every character is a separate block
+        let other: Unmounted<Text> = Unmounted::root("other");
+        for (i, c) in "foo bar".chars().enumerate() {
+            let mut tx = d1.transact_mut("test").unwrap();
+            let mut t = other.mount_mut(&mut tx).unwrap();
+            t.insert(i, c.to_string()).unwrap();
+            tx.commit(None).unwrap();
+        }
+        let tx = d1.transact("test").unwrap();
+        assert_eq!(
+            tx.search("foo").unwrap(),
+            vec![ID::new(1.into(), 17.into())]
+        );
+        assert_eq!(
+            tx.search("bar").unwrap(),
+            vec![ID::new(1.into(), 21.into())]
+        );
+        assert!(tx.search("fo").unwrap().is_empty());
+        assert!(tx.search("o").unwrap().is_empty());
+    }
+
+    #[test]
+    fn full_text_search_mid_word_edits() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let d1 = d1.with_full_text_index();
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx)
+            .unwrap()
+            .insert(0, "helo there")
+            .unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(2, "l").unwrap();
+        assert_eq!(
+            tx.search("hello").unwrap(),
+            vec![ID::new(1.into(), 0.into())]
+        );
+        assert!(tx.search("helo").unwrap().is_empty());
+        assert!(tx.search("l").unwrap().is_empty());
+        assert_eq!(
+            tx.search("there").unwrap(),
+            vec![ID::new(1.into(), 5.into())]
+        );
+
+        // removing a separator joins words, removing a part of a word leaves the rest indexed
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(5..6).unwrap();
+        assert_eq!(t.to_string(), "hellothere");
+        assert_eq!(
+            tx.search("hellothere").unwrap(),
+            vec![ID::new(1.into(), 0.into())]
+        );
+        assert!(tx.search("hello").unwrap().is_empty());
+        assert!(tx.search("there").unwrap().is_empty());
+
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(0..5).unwrap();
+        assert_eq!(
+            tx.search("there").unwrap(),
+            vec![ID::new(1.into(), 5.into())]
+        );
+        assert!(tx.search("hellothere").unwrap().is_empty());
+        tx.commit(None).unwrap();
+    }
+
+    #[test]
+    fn full_text_search_remote_split_word() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let d2 = d2.with_full_text_index();
+
+        // every transaction produces a separate block, splitting words between them
+        for (index, chunk) in [(0, "hel"), (3, "lo wo"), (8, "rld")] {
+            let mut tx = d1.transact_mut("test").unwrap();
+            txt.mount_mut(&mut tx)
+                .unwrap()
+                .insert(index, chunk)
+                .unwrap();
+            tx.commit(None).unwrap();
+        }
+        // another edit inserted in the middle of "world" after the first sync
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let mut t1 = d1.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2]);
+        txt.mount_mut(&mut t1).unwrap().insert(8, "!").unwrap();
+        sync([&mut t1, &mut t2]);
+        t2.commit(None).unwrap();
+
+        let tx = d2.transact("test").unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello wo!rld");
+        assert_eq!(
+            tx.search("hello").unwrap(),
+            vec![ID::new(1.into(), 0.into())]
+        );
+        assert_eq!(tx.search("wo").unwrap(), vec![ID::new(1.into(), 6.into())]);
+        assert_eq!(tx.search("rld").unwrap(), vec![ID::new(1.into(), 8.into())]);
+        assert!(tx.search("world").unwrap().is_empty());
+        assert!(tx.search("hel").unwrap().is_empty());
+        assert!(tx.search("lo").unwrap().is_empty());
+    }
+}
@@ -7,13 +7,21 @@ where
     C: Into<ClientID>,
 {
     let dir = TempDir::new().unwrap();
+    let multi_doc = reopen(&dir, client_id);
+    (multi_doc, dir)
+}
+
+/// Opens a [MultiDoc] over a database directory created by [multi_doc].
+pub fn reopen<C>(dir: &TempDir, client_id: C) -> crate::MultiDoc
+where
+    C: Into<ClientID>,
+{
     let env = crate::lmdb::Env::builder()
         .max_dbs(10)
         .map_size(10 * 1024 * 1024) // 10 MB
         .open(dir.path(), 0o600)
         .unwrap();
-    let multi_doc = MultiDoc::new(env, Some(client_id.into()));
-    (multi_doc, dir)
+    MultiDoc::new(env, Some(client_id.into()))
 }
 
 pub fn sync<const N: usize>(txns: [&mut Transaction<'_>; N]) {
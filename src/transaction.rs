@@ -1,25 +1,38 @@
-use crate::block::{Block, BlockMut, ID};
-use crate::block_reader::{Carrier, Update};
-use crate::content::{ContentType, FormatAttribute};
-use crate::gc::GarbageCollector;
+use crate::block::{Block, BlockMut, CONTENT_TYPE_GC, ID, InsertBlockData};
+use crate::block_reader::{BlockRange, Carrier, CarrierReader, Update};
+use crate::content::{Content, ContentType, FormatAttribute};
+use crate::gc::{GarbageCollector, GcStats};
 use crate::id_set::IDSet;
+use crate::keystroke::Keystroke;
 use crate::lib0::v1::{DecoderV1, EncoderV1};
 use crate::lib0::v2::{DecoderV2, EncoderV2};
 use crate::lib0::{Decode, Decoder, Encode, Encoder, Encoding, WriteExt};
-use crate::lmdb::{Database, Dbi, RwTxn};
-use crate::node::{Node, NodeID};
+use crate::lmdb::{Database, Dbi, Error as LmdbError, RwTxn};
+use crate::node::{Node, NodeID, NodeType};
+use crate::observer::IntegrationObserver;
 use crate::state_vector::Snapshot;
-use crate::store::block_store::BlockCursor;
+use crate::store::block_store::{BlockCursor, BlockStore, divergence};
 use crate::store::content_store::ContentStore;
 use crate::store::intern_strings::InternStringsStore;
 use crate::store::meta_store::MetaStore;
 use crate::store::{Db, MapEntriesStore};
-use crate::{BlockHeader, ClientID, Clock, Error, Optional, StateVector, U32, lib0};
+use crate::types::ValueEncoding;
+use crate::types::map::MapResolver;
+use crate::{BlockHeader, ClientID, Clock, DocHandle, Error, Optional, StateVector, U32, lib0};
 use bitflags::bitflags;
+use bytes::Bytes;
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::{Display, Formatter};
-use std::ops::{Deref, DerefMut};
+use std::hash::Hasher;
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut, Range};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use twox_hash::XxHash64;
 use zerocopy::IntoBytes;
 
 pub(crate) struct TransactionState {
@@ -30,6 +43,11 @@ pub(crate) struct TransactionState {
     pub delete_set: IDSet,
     pub changed: HashMap<NodeID, HashSet<U32>>,
     pub merge_blocks: BTreeSet<ID>,
+    /// Last known tail blocks of text nodes, used to speed up appending. These are only hints and
+    /// must be verified before use.
+    pub text_tails: HashMap<NodeID, ID>,
+    /// Encodings of values inserted into collections, if other than [ValueEncoding::Atom].
+    pub value_encodings: HashMap<NodeID, ValueEncoding>,
 }
 
 impl TransactionState {
@@ -43,6 +61,8 @@ impl TransactionState {
             delete_set: IDSet::default(),
             changed: HashMap::default(),
             merge_blocks: BTreeSet::default(),
+            text_tails: HashMap::default(),
+            value_encodings: HashMap::default(),
         }
     }
 
@@ -72,19 +92,35 @@ impl TransactionState {
         id.clock >= self.begin_state.get(&id.client)
     }
 
-    /// Checks if item with a given `id` has been deleted within this transaction.
-    pub fn has_deleted(&self, id: &ID) -> bool {
-        self.delete_set.contains(id)
+    /// Checks if any elements have been inserted or deleted within this transaction.
+    pub fn is_dirty(&self) -> bool {
+        self.begin_state != self.current_state || !self.delete_set.is_empty()
+    }
+
+    /// Returns number of elements (in clock units) inserted and deleted within this transaction.
+    pub fn change_count(&self) -> usize {
+        let inserted: usize = self
+            .current_state
+            .iter()
+            .map(|(client, &clock)| (clock - self.begin_state.get(client)).get() as usize)
+            .sum();
+        let deleted: usize = self
+            .delete_set
+            .iter()
+            .flat_map(|(_, range)| range.iter())
+            .map(|range| (range.end - range.start).get() as usize)
+            .sum();
+        inserted + deleted
     }
 
     fn precommit(
         &mut self,
         db: Database<'_>,
         mut summary: Option<&mut TransactionSummary>,
+        config: &TransactionConfig,
     ) -> crate::Result<()> {
         // squash delete set
         self.delete_set.squash();
-        let blocks = db.blocks();
 
         // transaction.afterState = getStateVector(transaction.doc.store)
 
@@ -92,6 +128,12 @@ impl TransactionState {
             && summary.flags.contains(CommitFlags::OBSERVE_NODES)
         {
             summary.changed_nodes.extend(self.changed.keys());
+            for (node_id, key_hashes) in self.changed.iter() {
+                if !key_hashes.is_empty() {
+                    let e = summary.changed_keys.entry(*node_id).or_default();
+                    e.extend(key_hashes.iter().map(|hash| hash.get()));
+                }
+            }
             // todo!();
             // if summary.flags.contains(CommitFlags::OBSERVE_NODES_DEEP) {
             //     // bubble up changes to parent nodes and gather them as well
@@ -99,7 +141,40 @@ impl TransactionState {
             // }
         }
 
+        if config.block_merging {
+            self.merge_blocks(&db, config.max_block_content_len)?;
+        }
+
+        // persist updated state vector
+        let mut sv_store = db.state_vector();
+        for (client, &clock) in self.current_state.iter() {
+            sv_store.update(*client, clock)?;
+        }
+
+        // create incremental update
+        if let Some(summary) = summary
+            && self.is_dirty()
+        {
+            if summary.flags.contains(CommitFlags::UPDATE_V1) {
+                let mut encoder = EncoderV1::new(&mut summary.update);
+                self.incremental_update(&db, &mut encoder)?;
+            } else if summary.flags.contains(CommitFlags::UPDATE_V2) {
+                let mut encoder = EncoderV2::new(&mut summary.update);
+                self.incremental_update(&db, &mut encoder)?;
+                encoder.into_inner()?;
+            }
+        }
+
+        //TODO: subdoc events
+
+        Ok(())
+    }
+
+    /// Merges blocks of all clients, which clocks have advanced within this transaction, with
+    /// their left neighbors whenever possible. This includes blocks integrated from remote updates.
+    fn merge_blocks(&self, db: &Database<'_>, max_content_len: usize) -> crate::Result<()> {
         // on all affected store.clients props, try to merge
+        let blocks = db.blocks();
         let mut cursor = blocks.cursor()?;
         let mut merged = BTreeSet::new();
         for (client, &after_clock) in self.current_state.iter() {
@@ -111,7 +186,12 @@ impl TransactionState {
                 {
                     let mut block = BlockMut::from(block);
                     while block.id().client == *client && block.id().clock >= before_clock {
-                        if Self::merge_with_lefts(&mut block, &mut cursor, &mut merged)? {
+                        if Self::merge_with_lefts(
+                            &mut block,
+                            &mut cursor,
+                            &mut merged,
+                            max_content_len,
+                        )? {
                             break; // we reached the end
                         }
                     }
@@ -123,7 +203,7 @@ impl TransactionState {
         for id in self.merge_blocks.iter() {
             if let Some(block) = cursor.seek_containing(*id).optional()? {
                 let mut block = BlockMut::from(block);
-                Self::merge_with_lefts(&mut block, &mut cursor, &mut merged)?;
+                Self::merge_with_lefts(&mut block, &mut cursor, &mut merged, max_content_len)?;
             }
         }
 
@@ -131,28 +211,6 @@ impl TransactionState {
         for id in merged {
             cursor.remove(id)?;
         }
-
-        // persist updated state vector
-        let mut sv_store = db.state_vector();
-        for (client, &clock) in self.current_state.iter() {
-            sv_store.update(*client, clock)?;
-        }
-
-        // create incremental update
-        if let Some(summary) = summary
-            && (self.begin_state != self.current_state || !self.delete_set.is_empty())
-        {
-            if summary.flags.contains(CommitFlags::UPDATE_V1) {
-                let mut encoder = EncoderV1::new(&mut summary.update);
-                self.incremental_update(&db, &mut encoder)?;
-            } else if summary.flags.contains(CommitFlags::UPDATE_V2) {
-                let mut encoder = EncoderV2::new(&mut summary.update);
-                self.incremental_update(&db, &mut encoder)?;
-            }
-        }
-
-        //TODO: subdoc events
-
         Ok(())
     }
 
@@ -206,16 +264,13 @@ impl TransactionState {
             let start_clock = begin_state.get(&client_id);
 
             // for incremental update we can buffer them all
-            let mut block = cursor
-                .seek_containing(ID::new(client_id, start_clock))
-                .optional()?;
-            while let Some(current) = block
-                && current.id().client == client_id
-                && current.last_id().clock <= end_clock
-            {
-                block_buf.push(current);
-                block = cursor.next()?;
-            }
+            Self::collect_blocks(
+                &mut cursor,
+                client_id,
+                start_clock,
+                end_clock,
+                &mut block_buf,
+            )?;
 
             // then we can write the blocks for the same client
             writer.write_var(block_buf.len())?;
@@ -251,20 +306,205 @@ impl TransactionState {
         Ok(())
     }
 
+    /// Collects all consecutive blocks of a given client, which fit between `start_clock`
+    /// and `end_clock`. The first block may start before `start_clock`.
+    fn collect_blocks<'tx>(
+        cursor: &mut BlockCursor<'tx>,
+        client_id: ClientID,
+        start_clock: Clock,
+        end_clock: Clock,
+        buf: &mut Vec<Block<'tx>>,
+    ) -> crate::Result<()> {
+        let mut block = cursor
+            .seek_containing(ID::new(client_id, start_clock))
+            .optional()?;
+        while let Some(current) = block
+            && current.id().client == client_id
+            && current.last_id().clock <= end_clock
+        {
+            buf.push(current);
+            block = cursor.next()?;
+        }
+        Ok(())
+    }
+
+    /// Returns changes made within current transaction scope as a [Keystroke], if they consist
+    /// of either a single character inserted next to an existing element or a single continuous
+    /// range of deleted elements.
+    fn keystroke(&self, db: &Database<'_>) -> crate::Result<Option<Keystroke>> {
+        let mut changed_state = self
+            .current_state
+            .iter()
+            .filter(|(client_id, end_clock)| **end_clock > self.begin_state.get(client_id));
+        let inserted = changed_state.next();
+        if changed_state.next().is_some() {
+            return Ok(None);
+        }
+        match inserted {
+            None => {
+                let mut delete_set = self.delete_set.clone();
+                delete_set.squash();
+                let mut ranges = delete_set
+                    .iter()
+                    .flat_map(|(&client, range)| range.iter().map(move |r| (client, r)));
+                match (ranges.next(), ranges.next()) {
+                    (Some((client, range)), None) => Ok(Some(Keystroke::Delete {
+                        id: ID::new(client, range.start),
+                        len: range.end - range.start,
+                    })),
+                    _ => Ok(None),
+                }
+            }
+            Some((&client_id, &end_clock)) => {
+                let start_clock = self.begin_state.get(&client_id);
+                if !self.delete_set.is_empty() || end_clock - start_clock > Clock::new(2) {
+                    return Ok(None);
+                }
+                let id = ID::new(client_id, start_clock);
+                let mut cursor = db.blocks().cursor()?;
+                let block = cursor.seek_containing(id)?;
+                if block.content_type() != ContentType::String {
+                    return Ok(None);
+                }
+                let content = match block.try_inline_data() {
+                    Some(data) => data,
+                    None => db.contents().get(*block.id())?,
+                };
+                let content = unsafe { std::str::from_utf8_unchecked(content) };
+                let offset = (start_clock - block.id().clock).get() as usize;
+                let mut utf16 = 0;
+                let chr = content.chars().find(|c| {
+                    let found = utf16 == offset;
+                    utf16 += c.len_utf16();
+                    found
+                });
+                let Some(chr) = chr else {
+                    return Ok(None);
+                };
+                if chr.len_utf16() != (end_clock - start_clock).get() as usize {
+                    return Ok(None);
+                }
+                let origin_left = if offset > 0 {
+                    Some(ID::new(client_id, start_clock - 1))
+                } else {
+                    block.origin_left().copied()
+                };
+                let origin_right = block.origin_right().copied();
+                if origin_left.is_none() && origin_right.is_none() {
+                    // parent would have to be encoded
+                    return Ok(None);
+                }
+                Ok(Some(Keystroke::Insert {
+                    id,
+                    chr,
+                    origin_left,
+                    origin_right,
+                }))
+            }
+        }
+    }
+
+    /// Same as [TransactionState::incremental_update] using [Encoding::V1], but splits the
+    /// update on block boundaries into multiple updates, each one not exceeding `max_bytes`.
+    fn incremental_update_chunked(
+        &self,
+        db: &Database<'_>,
+        max_bytes: usize,
+    ) -> crate::Result<Vec<Vec<u8>>> {
+        let contents = db.contents();
+        let intern_strings = db.intern_strings();
+        let map_entries = db.map_entries();
+        let mut cursor = db.blocks().cursor()?;
+
+        let mut chunker = UpdateChunker::new(max_bytes);
+        let mut block_buf = Vec::new();
+        let mut block_bytes = Vec::new();
+        for (&client_id, &end_clock) in self.current_state.iter() {
+            let start_clock = self.begin_state.get(&client_id);
+            if end_clock <= start_clock {
+                continue;
+            }
+            block_buf.clear();
+            Self::collect_blocks(
+                &mut cursor,
+                client_id,
+                start_clock,
+                end_clock,
+                &mut block_buf,
+            )?;
+
+            for (i, block) in block_buf.iter().enumerate() {
+                // first block may start at offset inside the block
+                let (offset, clock) = if i == 0 {
+                    (start_clock - block.id().clock, start_clock)
+                } else {
+                    (Clock::new(0), block.id().clock)
+                };
+                block_bytes.clear();
+                Transaction::write_block(
+                    block,
+                    offset,
+                    &contents,
+                    &map_entries,
+                    &intern_strings,
+                    &mut EncoderV1::new(&mut block_bytes),
+                )?;
+                chunker.push_block(client_id, clock, &block_bytes)?;
+            }
+        }
+
+        chunker.finish(&self.delete_set)
+    }
+
+    /// Returns the size (in bytes) of a given `block` content, no matter if it was inlined or
+    /// stored in a content store.
+    fn content_len(block: &BlockMut, contents: &ContentStore<'_>) -> crate::Result<usize> {
+        if let Some(data) = block.try_inline_data() {
+            return Ok(data.len());
+        }
+        match block.content_type() {
+            ContentType::String => Ok(contents.get(*block.id())?.len()),
+            ContentType::Atom | ContentType::Json => {
+                let mut len = 0;
+                for content in contents.read_range(block.content_type(), block.as_block().range()) {
+                    len += content?.len();
+                }
+                Ok(len)
+            }
+            _ => Ok(0),
+        }
+    }
+
     fn merge_with_lefts<'tx>(
         right: &mut BlockMut,
         cursor: &mut BlockCursor<'tx>,
         merged: &mut BTreeSet<ID>,
+        max_content_len: usize,
     ) -> crate::Result<bool> {
+        let contents = cursor.content_store();
         let mut reached_end = true;
+        let mut right_len = None;
         while let Some(left) = cursor.prev()?
             && left.id().client == right.id().client
         {
             reached_end = false;
             let mut merge_to = BlockMut::from(left);
 
-            if merge_to.merge(right.as_block()) {
+            let mut merged_len = 0;
+            let mut can_merge = merge_to.can_merge(&right.as_block());
+            if can_merge {
+                // don't let merged block content grow over the limit
+                let len = match right_len {
+                    Some(len) => len,
+                    None => Self::content_len(right, &contents)?,
+                };
+                merged_len = Self::content_len(&merge_to, &contents)? + len;
+                can_merge = merged_len <= max_content_len;
+            }
+
+            if can_merge && merge_to.merge(right.as_block()) {
                 merged.insert(*right.id());
+                right_len = Some(merged_len);
 
                 // once blocks are merged we need to check for their contents
                 match merge_to.content_type() {
@@ -332,11 +572,25 @@ impl TransactionState {
 pub struct DbHandle<'db> {
     txn: RwTxn<'db>,
     handle: Dbi,
+    /// Change in size of the document entries written so far, tracked only if document quota
+    /// is enforced.
+    size_delta: Option<Cell<isize>>,
 }
 
 impl<'db> DbHandle<'db> {
+    fn new(txn: RwTxn<'db>, handle: Dbi, track_size: bool) -> Self {
+        DbHandle {
+            txn,
+            handle,
+            size_delta: track_size.then(|| Cell::new(0)),
+        }
+    }
+
     pub fn get(&self) -> Database<'_> {
-        self.txn.bind(&self.handle)
+        match &self.size_delta {
+            None => self.txn.bind(&self.handle),
+            Some(size_delta) => self.txn.bind_tracked(&self.handle, size_delta),
+        }
     }
 
     pub(crate) fn commit(self) -> crate::Result<()> {
@@ -364,6 +618,10 @@ impl LazyState {
         self.inner.as_deref()
     }
 
+    pub(crate) fn get_mut(&mut self) -> Option<&mut TransactionState> {
+        self.inner.as_deref_mut()
+    }
+
     pub(crate) fn get_or_init(&mut self, db: Database<'_>) -> &mut TransactionState {
         self.inner.get_or_insert_with(|| {
             let client_id = db.meta().client_id().unwrap();
@@ -386,14 +644,16 @@ impl LazyState {
 pub struct Transaction<'db> {
     pub db: DbHandle<'db>,
     pub state: LazyState,
+    config: TransactionConfig,
 }
 
 impl<'db> Transaction<'db> {
     pub(crate) fn read_only(txn: RwTxn<'db>, handle: Dbi) -> Self {
-        let db = DbHandle { txn, handle };
+        let db = DbHandle::new(txn, handle, false);
         Transaction {
             db,
             state: LazyState::new(),
+            config: TransactionConfig::default(),
         }
     }
 
@@ -402,8 +662,9 @@ impl<'db> Transaction<'db> {
         handle: Dbi,
         client_id: Option<ClientID>,
         origin: Option<Origin>,
+        config: TransactionConfig,
     ) -> crate::Result<Self> {
-        let db = DbHandle { txn, handle };
+        let db = DbHandle::new(txn, handle, config.doc_quota.is_some());
         if let Some(client_id) = client_id {
             db.get()
                 .meta()
@@ -418,7 +679,31 @@ impl<'db> Transaction<'db> {
                 LazyState::eager(TransactionState::new(client_id, begin_state, Some(origin)))
             }
         };
-        Ok(Self { db, state })
+        Ok(Self { db, state, config })
+    }
+
+    /// Creates a new read-write transaction over a document, adopting an externally created LMDB
+    /// transaction `txn`. This way changes made to the document can be committed atomically
+    /// together with any other data that application stores within the same LMDB environment.
+    /// Use [Transaction::raw_txn] to write the application data after the transaction has been
+    /// adopted.
+    ///
+    /// The document database `handle` must be opened (see [crate::MultiDoc::doc_handle]) before
+    /// `txn` has been started, as LMDB allows only one read-write transaction at the time.
+    /// Transaction uses the settings of the [crate::MultiDoc] which opened the `handle`.
+    pub fn from_raw(
+        txn: RwTxn<'db>,
+        handle: &DocHandle,
+        client_id: Option<ClientID>,
+    ) -> crate::Result<Self> {
+        Self::read_write(txn, handle.dbi, client_id, None, handle.config.clone())
+    }
+
+    /// Returns the underlying LMDB transaction. It can be used to read and write application data
+    /// stored in other databases of the same LMDB environment, which will be committed (or aborted)
+    /// together with the current transaction.
+    pub fn raw_txn(&self) -> &RwTxn<'db> {
+        &self.db.txn
     }
 
     /// Returns a globally unique identifier of the current client.
@@ -446,6 +731,174 @@ impl<'db> Transaction<'db> {
         }
     }
 
+    /// Returns IDs of all clients which have contributed changes to the document, in ascending
+    /// order. This is a lighter alternative to [Transaction::state_vector] when clock values are
+    /// not needed.
+    ///
+    /// For read-write transactions it includes clients of changes made by current transaction.
+    pub fn clients(&self) -> crate::Result<Vec<ClientID>> {
+        if let Some(state) = self.state.get() {
+            Ok(state
+                .current_state
+                .iter()
+                .map(|(&client, _)| client)
+                .collect())
+        } else {
+            self.db.get().state_vector().clients()
+        }
+    }
+
+    /// Returns the number of clients reported by the last commit, which introduced new clients
+    /// and exceeded [ClientLimit::Report] threshold. Unlike
+    /// [TransactionSummary::client_limit_exceeded], it's also recorded for commits made without
+    /// a transaction summary.
+    pub fn client_limit_exceeded(&self) -> crate::Result<Option<usize>> {
+        self.db.get().meta().client_limit_exceeded()
+    }
+
+    /// Returns the clock of a given `client` as seen in the state vector of the current document,
+    /// or `None` if that client has not contributed any changes yet. This is a cheaper
+    /// alternative to [Transaction::state_vector] when only a single entry is needed.
+    ///
+    /// For read-write transactions it includes changes made by current transaction.
+    pub fn client_clock(&self, client: ClientID) -> crate::Result<Option<Clock>> {
+        if let Some(state) = self.state.get() {
+            Ok(state
+                .current_state
+                .contains_client(&client)
+                .then(|| state.current_state.get(&client)))
+        } else {
+            self.db.get().state_vector().clock(client)
+        }
+    }
+
+    /// Returns the total size (in bytes) of all keys and values stored for the current document,
+    /// including changes made by this transaction. See [crate::MultiDoc::doc_size].
+    pub fn doc_size(&self) -> crate::Result<usize> {
+        let db = self.db.get();
+        let mut cursor = db.cursor()?;
+        let mut size = 0;
+        // LMDB doesn't accept empty keys, so a single zero byte is the lowest possible key
+        let mut entry = cursor.set_range(&[0]);
+        loop {
+            match entry {
+                Ok((key, value)) => size += key.len() + value.len(),
+                Err(LmdbError::NOT_FOUND) => break,
+                Err(e) => return Err(e.into()),
+            }
+            entry = cursor.next();
+        }
+        Ok(size)
+    }
+
+    /// Returns a hash of the logical content of the document, which can be used to cheaply check
+    /// if two documents have converged to the same state, without exporting their values.
+    ///
+    /// Only live (non-deleted) elements are taken into account, traversed in ID order. The hash
+    /// doesn't depend on how elements were merged into or split between blocks, nor on whether
+    /// deleted elements have been garbage collected.
+    pub fn content_hash(&self) -> crate::Result<u64> {
+        let db = self.db.get();
+        let contents = db.contents();
+        let blocks = db.blocks();
+        let mut cursor = blocks.cursor()?;
+        let mut hasher = XxHash64::with_seed(0);
+        // root blocks are skipped, as they are not part of the document content
+        match cursor.start_from(ID::new(1.into(), 0.into())) {
+            Ok(_) => {}
+            Err(Error::NotFound) => return Ok(hasher.finish()),
+            Err(e) => return Err(e),
+        }
+        let mut run: Option<ContentRun> = None;
+        let mut current = cursor.current().optional()?;
+        while let Some(block) = current.take() {
+            if block.is_deleted() || block.content_type() == ContentType::Deleted {
+                if let Some(run) = run.take() {
+                    run.finish(&mut hasher);
+                }
+            } else {
+                if !run.as_ref().is_some_and(|run| run.continues(&block)) {
+                    if let Some(run) = run.take() {
+                        run.finish(&mut hasher);
+                    }
+                    run = Some(ContentRun::start(&block, &mut hasher));
+                }
+                Self::hash_content(&block, &contents, &mut hasher)?;
+                if let Some(run) = &mut run {
+                    run.end += block.clock_len();
+                }
+            }
+            current = cursor.next()?;
+        }
+        if let Some(run) = run {
+            run.finish(&mut hasher);
+        }
+        Ok(hasher.finish())
+    }
+
+    fn hash_content(
+        block: &Block<'_>,
+        contents: &ContentStore<'_>,
+        hasher: &mut XxHash64,
+    ) -> crate::Result<()> {
+        let content_type = block.content_type();
+        let data = block.try_inline_data();
+        match content_type {
+            ContentType::Deleted => {}
+            ContentType::Node => {
+                let node_type = *block.node_type().unwrap();
+                hasher.write_u8(node_type as u8);
+                if node_type == NodeType::XmlElement {
+                    let tag = contents.get(*block.id())?;
+                    hasher.write_usize(tag.len());
+                    hasher.write(tag);
+                }
+            }
+            // string contents of adjacent blocks are hashed as one continuous string
+            ContentType::String => match data {
+                Some(data) => hasher.write(data),
+                None => hasher.write(contents.get(*block.id())?),
+            },
+            ContentType::Atom | ContentType::Json if data.is_none() => {
+                let mut i = contents.read_range(content_type, block.range());
+                while let Some(content) = i.next()? {
+                    let content = content.bytes();
+                    hasher.write_usize(content.len());
+                    hasher.write(content);
+                }
+            }
+            _ => {
+                let data = match data {
+                    Some(data) => data,
+                    None => contents.get(*block.id())?,
+                };
+                hasher.write_usize(data.len());
+                hasher.write(data);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a human-readable dump of changes made by the current transaction so far: inserted
+    /// blocks with their contents, deleted ID ranges and changed nodes. It's meant to be used for
+    /// debugging and in tests. Read-only transactions don't report any changes.
+    pub fn debug_changes(&self) -> DebugChanges<'_, 'db> {
+        DebugChanges { tx: self }
+    }
+
+    /// Checks if a root node with a given `name` and `node_type` exists in the document. Unlike
+    /// [crate::Unmounted::mount], this method never creates a missing root node.
+    ///
+    /// [NodeType::Unknown] matches root node of any type.
+    pub fn root_exists(&self, name: &str, node_type: NodeType) -> crate::Result<bool> {
+        let node_id = Node::root_named(name).id();
+        match self.db.get().blocks().get(node_id).optional()? {
+            None => Ok(false),
+            Some(_) if node_type == NodeType::Unknown => Ok(true),
+            Some(block) => Ok(block.node_type() == Some(&node_type)),
+        }
+    }
+
     /// Removes all the contents of the document, but keeping the empty document itself.
     /// This doesn't cause the database file to shrink, but it releases the space occupied by this
     /// document to be reused by other documents and their changes.
@@ -469,11 +922,53 @@ impl<'db> Transaction<'db> {
             Encoding::V2 => {
                 let mut encoder = EncoderV2::new(&mut buf);
                 self.incremental_update_with(&mut encoder)?;
+                encoder.into_inner()?;
             }
         }
         Ok(buf)
     }
 
+    /// Returns the number of bytes that [Transaction::incremental_update] would produce
+    /// using [Encoding::V1] for the changes made so far. The update is encoded into a counting
+    /// sink, so no buffer holding it is ever allocated.
+    pub fn pending_update_size(&self) -> crate::Result<usize> {
+        let mut counter = ByteCounter::default();
+        let mut encoder = EncoderV1::new(&mut counter);
+        self.incremental_update_with(&mut encoder)?;
+        Ok(counter.0)
+    }
+
+    /// Returns changes made by the current transaction as a sequence of [Encoding::V1] updates,
+    /// each one not exceeding `max_bytes`. This is useful for transports with limited message
+    /// size. Updates are split on block boundaries and must be applied in the order they were
+    /// returned in. The delete set follows all the blocks, split on deleted range boundaries
+    /// into as many updates as necessary.
+    ///
+    /// A single block which encoding alone exceeds `max_bytes` is sent in its own update.
+    pub fn incremental_update_chunked(&self, max_bytes: usize) -> crate::Result<Vec<Vec<u8>>> {
+        match self.state.get() {
+            Some(state) => state.incremental_update_chunked(&self.db.get(), max_bytes),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns changes made by the current transaction in a compact [Keystroke] format, which is
+    /// considerably smaller than [Transaction::incremental_update] for a single typed character.
+    ///
+    /// Returns `None` if changes don't fit into a single keystroke, ie. more than one character was
+    /// inserted, both insertions and deletions were made or the inserted character has no
+    /// neighbors. In that case [Transaction::incremental_update] should be used instead.
+    pub fn keystroke_update(&self) -> crate::Result<Option<Vec<u8>>> {
+        let keystroke = match self.state.get() {
+            Some(state) => state.keystroke(&self.db.get())?,
+            None => None,
+        };
+        match keystroke {
+            Some(keystroke) => Ok(Some(keystroke.to_bytes()?)),
+            None => Ok(None),
+        }
+    }
+
     /// Returns an update that contains all changes that happened `since` a given state vector.
     pub fn diff_update(&self, since: &StateVector, version: Encoding) -> crate::Result<Vec<u8>> {
         let mut buf = Vec::new();
@@ -485,17 +980,112 @@ impl<'db> Transaction<'db> {
             Encoding::V2 => {
                 let mut encoder = EncoderV2::new(&mut buf);
                 self.diff_update_with(since, &mut encoder)?;
+                encoder.into_inner()?;
             }
         }
         Ok(buf)
     }
 
+    /// Returns an update containing the entire document state: blocks of all clients together with
+    /// the complete delete set. Applying it to an empty document reproduces the current one, which
+    /// makes it suitable for snapshots, backups and initial sync of new peers.
+    ///
+    /// Updates kept aside as pending (see [Transaction::pending_update]) are not included, as they
+    /// depend on blocks missing from the current document.
+    pub fn full_update(&self, version: Encoding) -> crate::Result<Vec<u8>> {
+        self.diff_update(&StateVector::default(), version)
+    }
+
+    /// Returns an iterator over blocks created by a given `client` within a given `clocks` range,
+    /// together with their contents, in clock order. The first block may start before
+    /// `clocks.start` if it contains it.
+    pub fn blocks_for_client(
+        &self,
+        client: ClientID,
+        clocks: Range<Clock>,
+    ) -> crate::Result<ClientBlocks<'_>> {
+        let db = self.db.get();
+        let mut cursor = BlockCursor::new(db)?;
+        let next = cursor
+            .seek_containing(ID::new(client, clocks.start))
+            .optional()?;
+        Ok(ClientBlocks {
+            cursor,
+            contents: ContentStore::new(db),
+            client,
+            end: clocks.end,
+            next,
+        })
+    }
+
     /// Returns an update that contains all changes that happened `since` a given state vector.
     pub fn diff_update_with<E: Encoder>(
         &self,
         since: &StateVector,
         writer: &mut E,
     ) -> crate::Result<()> {
+        self.diff_update_internal(since, None::<RootFilter<'_, fn(&str) -> bool>>, writer)
+    }
+
+    /// Returns an update that contains changes that happened `since` a given state vector, limited
+    /// to root types which names are accepted by a given `filter` and their nested descendants.
+    ///
+    /// Blocks belonging to other roots are encoded as garbage collected ranges, so that clocks of
+    /// every client stay continuous and the update can be integrated without missing dependencies.
+    /// Keep in mind that a receiver will consider these ranges as already integrated: elements
+    /// filtered out this way won't be included in any later [Transaction::diff_update] computed
+    /// against the receiver's state vector.
+    pub fn diff_update_filtered<F>(
+        &self,
+        since: &StateVector,
+        version: Encoding,
+        filter: F,
+    ) -> crate::Result<Vec<u8>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut buf = Vec::new();
+        match version {
+            Encoding::V1 => {
+                let mut encoder = EncoderV1::new(&mut buf);
+                self.diff_update_filtered_with(since, filter, &mut encoder)?;
+            }
+            Encoding::V2 => {
+                let mut encoder = EncoderV2::new(&mut buf);
+                self.diff_update_filtered_with(since, filter, &mut encoder)?;
+                encoder.into_inner()?;
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Returns an update that contains changes that happened `since` a given state vector, limited
+    /// to root types which names are accepted by a given `filter`.
+    /// See [Transaction::diff_update_filtered] for details.
+    pub fn diff_update_filtered_with<E, F>(
+        &self,
+        since: &StateVector,
+        filter: F,
+        writer: &mut E,
+    ) -> crate::Result<()>
+    where
+        E: Encoder,
+        F: Fn(&str) -> bool,
+    {
+        let filter = RootFilter::new(self.db.get(), filter);
+        self.diff_update_internal(since, Some(filter), writer)
+    }
+
+    fn diff_update_internal<E, F>(
+        &self,
+        since: &StateVector,
+        mut filter: Option<RootFilter<'_, F>>,
+        writer: &mut E,
+    ) -> crate::Result<()>
+    where
+        E: Encoder,
+        F: Fn(&str) -> bool,
+    {
         // wrote updates
         let current_state = self.state_vector()?;
         let db = self.db.get();
@@ -521,8 +1111,10 @@ impl<'db> Transaction<'db> {
         // and determine number of blocks we're going to encode (required by lib0 v1 encoding)
         let mut blocks = BTreeMap::new();
         let mut ds = IDSet::default();
-        let mut client_block_count = 0;
+        let mut client_block_count = 0usize;
+        let mut client_carrier_count = 0usize;
         let mut first_block_clock = Clock::new(0);
+        let mut prev_excluded = false;
         let mut current = block_cursor.current().optional()?;
         while let Some(block) = current.take() {
             let id = block.id();
@@ -531,17 +1123,27 @@ impl<'db> Transaction<'db> {
             // we moved to blocks in the next client, we need to update range
             if current_client != id.client {
                 if client_block_count != 0 {
-                    blocks.insert(current_client, (client_block_count, first_block_clock));
+                    blocks.insert(
+                        current_client,
+                        (client_block_count, client_carrier_count, first_block_clock),
+                    );
                     client_block_count = 0;
+                    client_carrier_count = 0;
                     first_block_clock = Clock::new(0);
                 }
 
                 current_client = id.client;
                 min_state = since.get(&current_client);
                 max_state = current_state.get(&current_client);
+                prev_excluded = false;
             }
 
-            if block.is_deleted() {
+            let included = match &mut filter {
+                None => true,
+                Some(filter) => filter.includes(&block)?,
+            };
+
+            if block.is_deleted() && included {
                 ds.insert(*id, len);
             }
 
@@ -551,6 +1153,11 @@ impl<'db> Transaction<'db> {
                     first_block_clock = id.clock;
                 }
                 client_block_count += 1;
+                // consecutive blocks filtered out are encoded as a single GC range
+                if included || !prev_excluded {
+                    client_carrier_count += 1;
+                }
+                prev_excluded = !included;
             }
 
             // move to next block
@@ -558,7 +1165,10 @@ impl<'db> Transaction<'db> {
         }
 
         if client_block_count != 0 {
-            blocks.insert(current_client, (client_block_count, first_block_clock));
+            blocks.insert(
+                current_client,
+                (client_block_count, client_carrier_count, first_block_clock),
+            );
         }
 
         // on the second pass we go through blocks we're going to serialize
@@ -569,35 +1179,47 @@ impl<'db> Transaction<'db> {
         let intern_strings = db.intern_strings();
 
         writer.write_var(blocks.len())?;
-        for (client_id, (block_count, first_clock)) in blocks {
-            writer.write_var(block_count)?;
+        for (client_id, (block_count, carrier_count, first_clock)) in blocks {
+            writer.write_var(carrier_count)?;
             writer.write_client(client_id)?;
 
             let block = block_cursor.seek(ID::new(client_id, first_clock))?;
             let clock = since.get(&client_id).max(block.id().clock);
             writer.write_var(clock)?;
-            // write first block
-            Self::write_block(
-                &block,
-                clock - block.id().clock,
-                &contents,
-                &map_entries,
-                &intern_strings,
-                writer,
-            )?;
-            // write rest of the blocks
-            for _ in 1..block_count {
-                match block_cursor.next()? {
-                    None => break,
-                    Some(block) => Self::write_block(
+            // only the first block may be partially covered by the `since` state
+            let mut offset = clock - block.id().clock;
+            // length of the pending range of filtered out blocks
+            let mut excluded = Clock::new(0);
+            let mut current = Some(block);
+            for i in 0..block_count {
+                let Some(block) = current.take() else { break };
+                let included = match &mut filter {
+                    None => true,
+                    Some(filter) => filter.includes(&block)?,
+                };
+                if included {
+                    if excluded > Clock::new(0) {
+                        Self::write_gc(excluded, writer)?;
+                        excluded = Clock::new(0);
+                    }
+                    Self::write_block(
                         &block,
-                        Clock::new(0),
+                        offset,
                         &contents,
                         &map_entries,
                         &intern_strings,
                         writer,
-                    )?,
+                    )?;
+                } else {
+                    excluded = excluded + block.clock_len() - offset;
                 }
+                offset = Clock::new(0);
+                if i + 1 < block_count {
+                    current = block_cursor.next()?;
+                }
+            }
+            if excluded > Clock::new(0) {
+                Self::write_gc(excluded, writer)?;
             }
         }
 
@@ -616,6 +1238,12 @@ impl<'db> Transaction<'db> {
         Ok(())
     }
 
+    fn write_gc<E: Encoder>(len: Clock, writer: &mut E) -> crate::Result<()> {
+        writer.write_info(CONTENT_TYPE_GC)?;
+        writer.write_len(len)?;
+        Ok(())
+    }
+
     fn write_block<E: Encoder>(
         block: &Block<'_>,
         offset: Clock,
@@ -662,7 +1290,7 @@ impl<'db> Transaction<'db> {
             ContentType::Deleted => {
                 writer.write_len(block.clock_len())?;
             }
-            ContentType::Binary => {
+            ContentType::Binary | ContentType::Custom => {
                 let content = match data {
                     Some(data) => data,
                     None => content_store.get(*block.id())?,
@@ -696,7 +1324,12 @@ impl<'db> Transaction<'db> {
                 writer.write_json(&fmt.value::<lib0::Value>()?)?;
             }
             ContentType::Node => {
-                writer.write_type_ref(*block.node_type().unwrap() as u8)?;
+                let node_type = *block.node_type().unwrap();
+                writer.write_type_ref(node_type as u8)?;
+                if node_type == NodeType::XmlElement {
+                    let tag = content_store.get(*block.id())?;
+                    writer.write_key(unsafe { std::str::from_utf8_unchecked(tag) })?;
+                }
             }
             ContentType::Atom | ContentType::Json => match data {
                 Some(data) => {
@@ -736,22 +1369,92 @@ impl<'db> Transaction<'db> {
         found.ok_or_else(|| Error::NotFound)
     }
 
+    /// Returns the GUID assigned to current document with [Transaction::set_guid], if any.
+    pub fn guid(&self) -> crate::Result<Option<String>> {
+        let db = self.db.get();
+        Ok(db.meta().guid()?.map(str::to_string))
+    }
+
+    /// Assigns a GUID to current document. Updates tagged with a different GUID (see
+    /// [crate::tag_update]) will be rejected by [Transaction::apply_update].
+    pub fn set_guid(&mut self, guid: &str) -> crate::Result<()> {
+        let db = self.db.get();
+        db.meta().insert(MetaStore::KEY_GUID, guid.as_bytes())
+    }
+
     /// Returns the delete set of elements removed in a current transaction.
     pub fn delete_set(&self) -> Option<&IDSet> {
         let state = self.state.get()?;
         Some(&state.delete_set)
     }
 
+    /// Checks if current transaction has inserted or deleted any elements, without the need to
+    /// build an incremental update. Read-only transactions are never dirty.
+    pub fn is_dirty(&self) -> bool {
+        self.state.get().is_some_and(TransactionState::is_dirty)
+    }
+
+    /// Returns number of elements (in clock units) inserted and deleted by current transaction.
+    /// Elements both inserted and deleted within the same transaction are counted twice.
+    pub fn change_count(&self) -> usize {
+        self.state.get().map_or(0, TransactionState::change_count)
+    }
+
     /// Decodes an incoming `update` (which will be decoded using provided lib0 `version`) and
     /// integrates the changes it provided into current document.
     ///
     /// Any missing updates that would block the changes from being integrated will be stashed
     /// (and persisted) aside as pending updates (you can access them using [MetaStore::pending]
     /// method).
-    pub fn apply_update(&mut self, update: &[u8], version: Encoding) -> crate::Result<()> {
+    ///
+    /// Returns a state vector of clients, which blocks are still missing, together with the clock
+    /// of the first missing block of each of them. It can be used to request missing blocks from
+    /// a peer right away. If all changes were integrated, returned state vector is empty.
+    ///
+    /// If `update` was tagged with a document GUID (see [crate::tag_update]) and current document
+    /// has a GUID assigned (see [Transaction::set_guid]), they must be equal, otherwise
+    /// [Error::WrongDocument] is returned and nothing is integrated. The same applies to all
+    /// other methods applying updates.
+    ///
+    /// The whole update is decoded before any of its blocks are integrated, so a truncated or
+    /// malformed update fails without changing the document.
+    pub fn apply_update(&mut self, update: &[u8], version: Encoding) -> crate::Result<StateVector> {
+        let (guid, update) = Update::decode_tagged(update, version)?;
+        self.check_guid(guid)?;
+        self.apply_decoded(update)
+    }
+
+    /// Integrates a keystroke produced by [Transaction::keystroke_update]. It's re-encoded into
+    /// a regular update and applied the same way as [Transaction::apply_update].
+    pub fn apply_keystroke(&mut self, keystroke: &[u8]) -> crate::Result<StateVector> {
+        let update = Keystroke::from_bytes(keystroke)?.encode(Encoding::V1)?;
+        self.apply_update(&update, Encoding::V1)
+    }
+
+    /// Decodes an update read from a given `reader` (using provided lib0 `version`) and
+    /// integrates the changes it provided into current document, same as
+    /// [Transaction::apply_update].
+    ///
+    /// Blocks are decoded and integrated incrementally while reading, so the update doesn't need
+    /// to be buffered in memory as a whole. Only the blocks which depend on changes that come
+    /// later in the update are kept aside until the end. [Encoding::V2] can be streamed only
+    /// partially: its block metadata columns are read upfront, while block contents are not.
+    ///
+    /// Unlike [Transaction::apply_update], this is not atomic: the update is not validated
+    /// upfront, so if it turns out to be truncated or malformed, an error is returned, but blocks
+    /// decoded before the malformed part have already been integrated. In such case the
+    /// transaction should be dropped without committing it, which rolls back all of its changes.
+    pub fn apply_update_from<R: Read>(
+        &mut self,
+        reader: R,
+        version: Encoding,
+    ) -> crate::Result<StateVector> {
         match version {
-            Encoding::V1 => self.apply_update_with(&mut DecoderV1::from_slice(update)),
-            Encoding::V2 => self.apply_update_with(&mut DecoderV2::from_slice(update)?),
+            Encoding::V1 => {
+                let (guid, reader) = crate::block_reader::untag_reader(reader)?;
+                self.apply_update_streamed(&mut DecoderV1::tagged(reader, guid))
+            }
+            Encoding::V2 => self.apply_update_streamed(&mut DecoderV2::new(reader)?),
         }
     }
 
@@ -761,26 +1464,338 @@ impl<'db> Transaction<'db> {
     /// Any missing updates that would block the changes from being integrated will be stashed
     /// (and persisted) aside as pending updates (you can access them using [MetaStore::pending]
     /// method).
-    pub fn apply_update_with<D: Decoder>(&mut self, decoder: &mut D) -> crate::Result<()> {
+    ///
+    /// The whole update is decoded before it's integrated, same as in [Transaction::apply_update].
+    ///
+    /// Returns a state vector of missing blocks, same as [Transaction::apply_update].
+    pub fn apply_update_with<D: Decoder>(&mut self, decoder: &mut D) -> crate::Result<StateVector> {
+        self.check_guid_tag(decoder)?;
+        let update = Update::decode_with(decoder)?;
+        self.apply_decoded(update)
+    }
+
+    /// Integrates an already decoded `update`, followed by pending updates it unblocked.
+    fn apply_decoded(&mut self, update: Update) -> crate::Result<StateVector> {
+        let mut missing_sv = StateVector::default();
+        let mut processed = 0;
+        let mut current = Some(update);
+        while let Some(update) = current.take() {
+            let remaining = self.integrate_update(update, &mut missing_sv, &mut processed)?;
+            current = self.handle_pending(remaining)?;
+        }
+        Ok(self.still_missing(missing_sv))
+    }
+
+    /// Integrates blocks in batches while they are decoded, so that the update doesn't have to
+    /// be kept in memory as a whole (see [Transaction::apply_update_from]).
+    fn apply_update_streamed<D: Decoder>(&mut self, decoder: &mut D) -> crate::Result<StateVector> {
+        self.check_guid_tag(decoder)?;
+        let mut missing_sv = StateVector::default();
+        let mut processed = 0;
+        let mut reader = CarrierReader::new(decoder)?;
+        let mut deferred: BTreeMap<ClientID, VecDeque<Carrier>> = BTreeMap::new();
+        {
+            let mut tx = self.write_context()?;
+            while let Some((client, carriers)) = reader.next_batch(STREAMED_BATCH_LEN)? {
+                if let Some(waiting) = deferred.get_mut(&client) {
+                    // preceding blocks of the same client are already waiting for dependencies
+                    waiting.extend(carriers);
+                    continue;
+                }
+                // dependencies missing now may still come later in the update, so they are
+                // reported only once all the blocks have been read
+                let len = carriers.len();
+                let batch = BTreeMap::from([(client, carriers)]);
+                let counted = processed;
+                let remaining =
+                    tx.apply_update_internal(batch, &mut StateVector::default(), &mut processed)?;
+                // deferred carriers are not counted until they are retried at the end
+                let deferred_len: usize = remaining.values().map(VecDeque::len).sum();
+                processed = counted + len - deferred_len;
+                for (client, carriers) in remaining {
+                    deferred.entry(client).or_default().extend(carriers);
+                }
+            }
+        }
+        let delete_set = reader.finish()?;
+        let mut current = Some(Update {
+            blocks: deferred,
+            delete_set,
+        });
+        while let Some(update) = current.take() {
+            let remaining = self.integrate_update(update, &mut missing_sv, &mut processed)?;
+            current = self.handle_pending(remaining)?;
+        }
+        Ok(self.still_missing(missing_sv))
+    }
+
+    /// Decodes an incoming update and integrates only the blocks and deletions of clients
+    /// accepted by a given `filter`.
+    ///
+    /// Changes made by all other clients are not integrated. Instead, they are stashed aside
+    /// together with other pending updates (see [MetaStore::pending]) and will be retried by
+    /// the next applied update, whether it's related to them or not.
+    ///
+    /// Returns a state vector of missing blocks of accepted clients, same as
+    /// [Transaction::apply_update].
+    pub fn apply_update_filtered<D, F>(
+        &mut self,
+        decoder: &mut D,
+        filter: F,
+    ) -> crate::Result<StateVector>
+    where
+        D: Decoder,
+        F: Fn(ClientID) -> bool,
+    {
+        self.check_guid_tag(decoder)?;
+        let mut missing_sv = StateVector::default();
+        let mut processed = 0;
+        let mut deferred = Update::default();
         let mut current = Some(Update::decode_with(decoder)?);
         while let Some(update) = current.take() {
+            let (accepted, rejected) = update.partition(&filter);
+            deferred =
+                Update::merge_updates_with(deferred, rejected, self.config.max_block_content_len);
+            let remaining = self.integrate_update(accepted, &mut missing_sv, &mut processed)?;
+            current = self.handle_pending(remaining)?;
+        }
+        self.defer_pending(deferred)?;
+        Ok(self.still_missing(missing_sv))
+    }
+
+    /// Decodes an incoming `update` and integrates its blocks client by client, in a given `order`
+    /// of clients, instead of the order driven by client IDs. Clients missing from `order` are
+    /// integrated afterwards. It's used by tests to reproduce order-dependent integration bugs.
+    ///
+    /// Blocks depending on clients which were not integrated yet are deferred until all clients
+    /// have been processed, the same way as in [Transaction::apply_update_from].
+    #[cfg(test)]
+    pub(crate) fn apply_update_ordered(
+        &mut self,
+        update: &[u8],
+        version: Encoding,
+        order: &[ClientID],
+    ) -> crate::Result<StateVector> {
+        let mut missing_sv = StateVector::default();
+        let mut processed = 0;
+        let mut update = Update::decode(update, version)?;
+        let mut deferred: BTreeMap<ClientID, VecDeque<Carrier>> = BTreeMap::new();
+        {
             let mut tx = self.write_context()?;
-            let remaining = if !update.blocks.is_empty() {
-                tx.apply_update_internal(update.blocks)?
+            let rest: Vec<_> = update
+                .blocks
+                .keys()
+                .filter(|client| !order.contains(client))
+                .copied()
+                .collect();
+            for client in order.iter().chain(rest.iter()) {
+                let Some(carriers) = update.blocks.remove(client) else {
+                    continue;
+                };
+                let batch = BTreeMap::from([(*client, carriers)]);
+                let remaining = tx.apply_update_internal(batch, &mut missing_sv, &mut processed)?;
+                for (client, carriers) in remaining {
+                    deferred.entry(client).or_default().extend(carriers);
+                }
+            }
+        }
+        // deferred blocks were reported as missing only because of the integration order
+        missing_sv = StateVector::default();
+        let mut current = Some(Update {
+            blocks: deferred,
+            delete_set: update.delete_set,
+        });
+        while let Some(update) = current.take() {
+            let remaining = self.integrate_update(update, &mut missing_sv, &mut processed)?;
+            current = self.handle_pending(remaining)?;
+        }
+        Ok(self.still_missing(missing_sv))
+    }
+
+    /// Integrates all changes of `other` document, which are missing in current one, without
+    /// encoding them into an update first. Blocks missing in current document are read directly
+    /// from the `other` transaction's store, and its deletions are applied the same way as the
+    /// delete set of an update.
+    ///
+    /// `other` may belong to a different [crate::MultiDoc] (i.e. another LMDB environment). Returns
+    /// a state vector of missing blocks, same as [Transaction::apply_update].
+    pub fn merge_from(&mut self, other: &Transaction<'_>) -> crate::Result<StateVector> {
+        let since = self.state_vector()?;
+        let update = other.missing_blocks(&since)?;
+        let mut missing_sv = StateVector::default();
+        let mut processed = 0;
+        let mut current = Some(update);
+        while let Some(update) = current.take() {
+            let remaining = self.integrate_update(update, &mut missing_sv, &mut processed)?;
+            current = self.handle_pending(remaining)?;
+        }
+        Ok(self.still_missing(missing_sv))
+    }
+
+    /// Collects blocks of current document which are not covered by `since` state vector,
+    /// together with a delete set of the whole document, into an in-memory [Update].
+    fn missing_blocks(&self, since: &StateVector) -> crate::Result<Update> {
+        let db = self.db.get();
+        let blocks = db.blocks();
+        let mut block_cursor = blocks.cursor()?;
+        // in order to build delete set we need to go through all the blocks anyway
+        let mut update = Update {
+            blocks: BTreeMap::new(),
+            delete_set: block_cursor.delete_set()?,
+        };
+        let contents = db.contents();
+        let map_entries = db.map_entries();
+        let intern_strings = db.intern_strings();
+
+        // blocks are read only from the first clock of every client missing in `since`
+        for (&client, &end) in self.state_vector()?.iter() {
+            let start = since.get(&client);
+            if end <= start {
+                continue;
+            }
+            let carriers = update.blocks.entry(client).or_default();
+            let mut current = Some(block_cursor.seek_containing(ID::new(client, start))?);
+            while let Some(block) = current.take() {
+                if block.id().client != client {
+                    break;
+                }
+                // blocks partially covered by `since` are sliced during integration
+                let insert =
+                    Self::read_insert_block(&block, &contents, &map_entries, &intern_strings)?;
+                carriers.push_back(Carrier::Block(insert));
+                current = block_cursor.next()?;
+            }
+        }
+        Ok(update)
+    }
+
+    /// Builds an owned copy of a stored `block`, the same one that would be decoded from
+    /// an update containing it (see [Transaction::write_block]).
+    fn read_insert_block(
+        block: &Block<'_>,
+        content_store: &ContentStore<'_>,
+        map_entries: &MapEntriesStore<'_>,
+        strings: &InternStringsStore<'_>,
+    ) -> crate::Result<InsertBlockData> {
+        let id = *block.id();
+        let content_type = block.content_type();
+        let mut header = BlockHeader::empty();
+        let mut parent = None;
+        let mut entry = None;
+        if let Some(origin_left) = block.origin_left() {
+            header.set_origin_left(*origin_left);
+        }
+        if let Some(origin_right) = block.origin_right() {
+            header.set_origin_right(*origin_right);
+        }
+        if block.origin_left().is_none() && block.origin_right().is_none() {
+            // parent info cannot be inferred from origins
+            let parent_id = *block.parent();
+            let parent_node = if parent_id.is_root() {
+                Node::root_named(strings.get(parent_id.clock)?.to_string())
             } else {
-                BTreeMap::default()
+                Node::Nested(parent_id)
             };
-            let pending_delete_set = tx.apply_delete(&update.delete_set)?;
-            drop(tx);
+            header.set_parent(parent_id);
+            parent = Some(parent_node);
+            if let Some(&key_hash) = block.key_hash() {
+                let entry_key = Self::entry_key_for(map_entries, parent_id, key_hash, &id)?;
+                header.set_key_hash(Some(key_hash));
+                entry = Some(Bytes::copy_from_slice(entry_key.as_bytes()));
+            }
+        }
+        header.set_content_type(content_type);
+        header.set_clock_len(block.clock_len());
+
+        let mut content = SmallVec::new();
+        match content_type {
+            ContentType::Deleted => {}
+            ContentType::Node => {
+                let node_type = *block.node_type().unwrap();
+                header.set_node_type(node_type);
+                if node_type == NodeType::XmlElement {
+                    let tag = content_store.get(id)?;
+                    content.push(Content::new(ContentType::Node, Cow::Owned(tag.to_vec())));
+                }
+            }
+            ContentType::Doc => {
+                return Err(Error::UnsupportedContent(ContentType::Doc as u8));
+            }
+            ContentType::Atom | ContentType::Json if block.try_inline_data().is_none() => {
+                let mut i = content_store.read_range(content_type, block.range());
+                while let Some(item) = i.next()? {
+                    content.push(item.to_owned());
+                }
+            }
+            _ => match block.try_inline_content() {
+                Some(inline) => content.push(inline.to_owned()),
+                None => {
+                    let data = content_store.get(id)?;
+                    content.push(Content::new(content_type, Cow::Owned(data.to_vec())));
+                }
+            },
+        }
+        Ok(InsertBlockData {
+            block: BlockMut::new(id, header),
+            content,
+            parent,
+            entry,
+        })
+    }
 
-            current = self.handle_pending(Update {
-                blocks: remaining,
-                delete_set: pending_delete_set,
-            })?;
+    /// Integrates blocks and deletions of a given `update`, returning the part of it that could
+    /// not be integrated because of missing dependencies. Clients which blocks are missing are
+    /// recorded in `missing_sv`, while `processed` counts integrated carriers.
+    fn integrate_update(
+        &mut self,
+        update: Update,
+        missing_sv: &mut StateVector,
+        processed: &mut usize,
+    ) -> crate::Result<Update> {
+        let mut tx = self.write_context()?;
+        let remaining = if !update.blocks.is_empty() {
+            tx.apply_update_internal(update.blocks, missing_sv, processed)?
+        } else {
+            BTreeMap::default()
+        };
+        let pending_delete_set = tx.apply_delete(&update.delete_set)?;
+        Ok(Update {
+            blocks: remaining,
+            delete_set: pending_delete_set,
+        })
+    }
+
+    /// Reads an optional document GUID tag preceding an update decoded by a given `decoder`.
+    /// Fails with [Error::WrongDocument] if it doesn't match the GUID of current document.
+    fn check_guid_tag<D: Decoder>(&self, decoder: &mut D) -> crate::Result<()> {
+        let guid = decoder.read_guid_tag()?;
+        self.check_guid(guid)
+    }
+
+    /// Fails with [Error::WrongDocument] if a GUID an update was tagged with doesn't match the GUID
+    /// of current document.
+    fn check_guid(&self, guid: Option<String>) -> crate::Result<()> {
+        if let Some(actual) = guid
+            && let Some(expected) = self.guid()?
+            && actual != expected
+        {
+            return Err(Error::WrongDocument { expected, actual });
         }
         Ok(())
     }
 
+    /// Filters out entries of `missing_sv` which blocks were integrated since they were recorded.
+    fn still_missing(&self, missing_sv: StateVector) -> StateVector {
+        match self.state.get() {
+            None => missing_sv,
+            Some(state) => missing_sv
+                .iter()
+                .filter(|&(client, clock)| state.current_state.get(client) == *clock)
+                .map(|(&client, &clock)| (client, clock))
+                .collect(),
+        }
+    }
+
     fn handle_pending(&mut self, update: Update) -> crate::Result<Option<Update>> {
         let db = self.db.get();
         let meta = db.meta();
@@ -796,6 +1811,7 @@ impl<'db> Transaction<'db> {
         let mut pending = match pending {
             None => PendingUpdate::default(),
             Some(pending) => {
+                retry = pending.retry;
                 for (client, clock) in pending.missing_sv.iter() {
                     if clock < &state.current_state.get(client) {
                         retry = true;
@@ -805,60 +1821,82 @@ impl<'db> Transaction<'db> {
                 pending
             }
         };
-        if !update.blocks.is_empty() {
-            for (client, blocks) in update.blocks.iter() {
-                if let Some(first) = blocks.front() {
-                    pending
-                        .missing_sv
-                        .set_min(*client, first.id().clock - Clock::new(1));
-                }
+        Self::track_missing(&mut pending.missing_sv, &update);
+
+        let missing_sv = std::mem::take(&mut pending.missing_sv);
+        let pending = Self::decode_pending(&pending)?;
+        let pending =
+            Update::merge_updates_with(pending, update, self.config.max_block_content_len);
+        if retry {
+            meta.clear_pending()?;
+            Ok(Some(pending))
+        } else {
+            Self::insert_pending(&meta, pending, missing_sv, false)?;
+            Ok(None)
+        }
+    }
+
+    /// Stashes changes filtered out by [Transaction::apply_update_filtered] together with other
+    /// pending updates. Since these changes were never tried to be integrated, they are marked
+    /// to be retried by the next applied update, no matter if their dependencies are met or not.
+    fn defer_pending(&mut self, update: Update) -> crate::Result<()> {
+        if update.blocks.is_empty() && update.delete_set.is_empty() {
+            return Ok(());
+        }
+        let meta = self.db.get().meta();
+        let (pending, mut missing_sv) = match meta.pending()? {
+            None => (Update::default(), StateVector::default()),
+            Some(mut pending) => {
+                let missing_sv = std::mem::take(&mut pending.missing_sv);
+                (Self::decode_pending(&pending)?, missing_sv)
+            }
+        };
+        Self::track_missing(&mut missing_sv, &update);
+        let pending =
+            Update::merge_updates_with(pending, update, self.config.max_block_content_len);
+        Self::insert_pending(&meta, pending, missing_sv, true)
+    }
+
+    fn track_missing(missing_sv: &mut StateVector, update: &Update) {
+        for (client, blocks) in update.blocks.iter() {
+            if let Some(first) = blocks.front() {
+                // blocks starting at clock 0 are not missing any predecessors of their own
+                let clock = first.id().clock.get().saturating_sub(1);
+                missing_sv.set_min(*client, Clock::new(clock));
             }
         }
+    }
 
-        let mut pending_update = if pending.update.is_empty() {
+    fn decode_pending(pending: &PendingUpdate<'_>) -> crate::Result<Update> {
+        let mut update = if pending.update.is_empty() {
             Update::default()
         } else {
             Update::decode(pending.update, Encoding::V1)?
         };
         if !pending.delete_set.is_empty() {
-            pending_update.delete_set = IDSet::decode(pending.delete_set, Encoding::V1)?;
-        }
-
-        let missing_sv = pending.missing_sv;
-        let pending = Update::merge_updates(pending_update, update);
-        if retry {
-            meta.clear_pending()?;
-            Ok(Some(pending))
-        } else {
-            Self::insert_pending(&meta, pending, missing_sv)?;
-            Ok(None)
+            update.delete_set = IDSet::decode(pending.delete_set, Encoding::V1)?;
         }
+        Ok(update)
     }
 
     fn insert_pending(
         meta: &MetaStore,
         update: Update,
         missing_sv: StateVector,
+        retry: bool,
     ) -> crate::Result<()> {
         let mut buf = Vec::new();
         let mut writer = EncoderV1::new(&mut buf);
 
-        writer.write_var(update.blocks.len())?;
-        for (&client_id, carriers) in update.blocks.iter() {
-            writer.write_var(carriers.len())?;
-            writer.write_client(client_id)?;
-            writer.write_var(carriers[0].id().clock)?;
-
-            for carrier in carriers {
-                carrier.encode(&mut writer)?;
-            }
-        }
+        update.encode_blocks(&mut writer)?;
         buf.write_var(0)?; // assume empty delete set (we'll provide it separately)
 
         let mut ds = Vec::new();
         let mut writer = EncoderV1::new(&mut ds);
         update.delete_set.encode_with(&mut writer)?;
-        meta.insert_pending(&PendingUpdate::new(&buf, &ds, missing_sv))?;
+        let mut pending = PendingUpdate::new(&buf, &ds, missing_sv);
+        pending.retry = retry;
+        meta.insert_pending(&pending)?;
         Ok(())
     }
 
@@ -873,14 +1911,148 @@ impl<'db> Transaction<'db> {
     ///   commit via [Transaction::incremental_update], but that update may be larger).
     /// - [CommitFlags::OBSERVE_NODES] will include [NodeID] of all the nodes modified as part of
     ///   this transaction.
-    pub fn commit(mut self, summary: Option<&mut TransactionSummary>) -> crate::Result<()> {
+    pub fn commit(mut self, mut summary: Option<&mut TransactionSummary>) -> crate::Result<()> {
         if let Some(mut state) = self.state.take() {
+            if let Some(limit) = &self.config.client_limit {
+                // only transactions introducing new clients are checked, so that documents
+                // already over the limit can still be edited by their existing clients
+                let added = state
+                    .current_state
+                    .iter()
+                    .filter(|(client, _)| !state.begin_state.contains_client(client))
+                    .count();
+                let clients = state.current_state.len();
+                if added > 0 && clients > limit.max_clients() {
+                    match limit {
+                        ClientLimit::Report(_) => {
+                            self.db.get().meta().set_client_limit_exceeded(clients)?;
+                            if let Some(summary) = summary.as_deref_mut() {
+                                summary.client_limit_exceeded = Some(clients);
+                            }
+                        }
+                        ClientLimit::Reject(max_clients) => {
+                            return Err(crate::Error::ClientLimitExceeded {
+                                max_clients: *max_clients,
+                                clients,
+                            });
+                        }
+                    }
+                }
+            }
             let db = self.db.get();
-            state.precommit(db, summary)?;
+            state.precommit(db, summary, &self.config)?;
+            if self.config.tombstone_retention.is_some() && state.is_dirty() {
+                let meta = db.meta();
+                let seq = meta.next_commit_seq()?;
+                if !state.delete_set.is_empty() {
+                    let now = TombstoneRetention::now();
+                    meta.insert_tombstones(seq, now, &state.delete_set)?;
+                }
+            }
+            if self.config.doc_quota.is_none() && state.is_dirty() {
+                // changes made without tracking make the stored size outdated
+                db.meta().clear_doc_size()?;
+            }
+        }
+        if let Some(quota) = self.config.doc_quota {
+            self.check_doc_quota(quota)?;
         }
         self.db.commit()
     }
 
+    /// Adds the size of entries written by this transaction to the document size persisted by
+    /// previous commits and checks it against a given `quota`. If there's no persisted size yet,
+    /// it's computed with [Transaction::doc_size] once.
+    fn check_doc_quota(&self, quota: usize) -> crate::Result<()> {
+        let size_delta = self.db.size_delta.as_ref().map(Cell::get).unwrap_or(0);
+        if size_delta == 0 {
+            return Ok(());
+        }
+        let meta = self.db.get().meta();
+        let size = match meta.doc_size()? {
+            Some(size) => size.saturating_add_signed(size_delta),
+            None => {
+                // make the size entry itself a part of the computed size
+                meta.set_doc_size(0)?;
+                self.doc_size()?
+            }
+        };
+        if size > quota {
+            return Err(crate::Error::QuotaExceeded { quota, size });
+        }
+        meta.set_doc_size(size)
+    }
+
+    /// Returns the client that authored a block with a given `id`. Block IDs are always generated
+    /// by clients inserting them, so this information is available even for deleted blocks.
+    pub fn author_of(&self, id: &ID) -> ClientID {
+        id.client
+    }
+
+    /// Returns IDs of the first characters of all occurrences of a given word, found by
+    /// the full-text index enabled with [crate::MultiDoc::with_full_text_index]. Search is
+    /// case-insensitive. Returns an empty result if the index was not enabled.
+    pub fn search(&self, term: &str) -> crate::Result<Vec<ID>> {
+        let term = term.to_lowercase();
+        self.db.get().text_index().search(&term)
+    }
+
+    /// Returns content of a block containing a given `id` the way it's stored, prefixed with
+    /// a byte of its [ContentType]. Content is not parsed, which makes it useful for debugging
+    /// and for reading content not supported by the high-level API.
+    ///
+    /// [ContentType::Json] and [ContentType::Atom] elements are stored separately under their own
+    /// clocks, so for these only the element at `id` is returned. [NodeType::XmlElement] blocks
+    /// return their tag name, while other [ContentType::Node] and [ContentType::Deleted] blocks
+    /// return only the content type byte.
+    pub fn raw_content(&self, id: ID) -> crate::Result<Bytes> {
+        let db = self.db.get();
+        let mut cursor = db.blocks().cursor()?;
+        let block = cursor.seek_containing(id)?;
+        let content_type = block.content_type();
+        let mut raw = vec![content_type as u8];
+        match content_type {
+            ContentType::Deleted => {}
+            ContentType::Node => {
+                // inline data of node blocks is the ID of their first element, not their content
+                if block.node_type() == Some(&NodeType::XmlElement) {
+                    raw.extend_from_slice(db.contents().get(*block.id())?);
+                }
+            }
+            ContentType::Json | ContentType::Atom => match block.try_inline_data() {
+                Some(data) => {
+                    // only single element blocks are inlined, so `id` is the block's ID
+                    debug_assert_eq!(block.clock_len(), Clock::new(1));
+                    raw.extend_from_slice(data);
+                }
+                None => raw.extend_from_slice(db.contents().get(id)?),
+            },
+            _ => match block.try_inline_data() {
+                Some(data) => raw.extend_from_slice(data),
+                None => raw.extend_from_slice(db.contents().get(*block.id())?),
+            },
+        }
+        Ok(Bytes::from(raw))
+    }
+
+    /// Merges fragmented blocks, that have been inserted or integrated from remote updates within
+    /// the current transaction, with their neighbors. Blocks integrated by [Transaction::apply_update]
+    /// usually come in one block per original operation, which makes reads traversing them more
+    /// expensive. The same compaction is always done on commit, so this method only needs to be
+    /// called when the document is going to be read before the transaction is committed.
+    ///
+    /// Does nothing if block merging was disabled with [crate::MultiDoc::with_block_merging].
+    pub fn optimize(&mut self) -> crate::Result<()> {
+        if !self.config.block_merging {
+            return Ok(());
+        }
+        if let Some(state) = self.state.get() {
+            let db = self.db.get();
+            state.merge_blocks(&db, self.config.max_block_content_len)?;
+        }
+        Ok(())
+    }
+
     /// Returns a snapshot representing a committed state.
     pub fn snapshot_committed(&self) -> crate::Result<Snapshot> {
         let db = self.db.get();
@@ -906,6 +2078,57 @@ impl<'db> Transaction<'db> {
         Ok(Snapshot::new(sv, ds))
     }
 
+    /// Returns IDs of blocks, which have been deleted since a given `snapshot` was taken
+    /// (including deletions made by the current transaction). These include both blocks that
+    /// existed at the time of the snapshot and the ones inserted later.
+    ///
+    /// Returned ID set can be encoded and shipped to a peer which has already observed the
+    /// `snapshot` state, to propagate just the deletions.
+    pub fn delete_set_since(&self, snapshot: &Snapshot) -> crate::Result<IDSet> {
+        let current = self.snapshot_uncommitted()?;
+        Ok(current.delete_set.difference(&snapshot.delete_set))
+    }
+
+    /// Creates a snapshot of the current document state (including uncommitted changes) and
+    /// stores it under a given `name`, replacing any snapshot previously saved under that name.
+    ///
+    /// Saved snapshots are not protected from garbage collection: running [Transaction::gc]
+    /// may remove blocks, which are required to restore a document view from older snapshots.
+    pub fn save_snapshot(&mut self, name: &str) -> crate::Result<Snapshot> {
+        let snapshot = self.snapshot_uncommitted()?;
+        self.db.get().meta().insert_snapshot(name, &snapshot)?;
+        Ok(snapshot)
+    }
+
+    /// Returns a snapshot previously stored with [Transaction::save_snapshot] under a given `name`.
+    pub fn load_snapshot(&self, name: &str) -> crate::Result<Option<Snapshot>> {
+        self.db.get().meta().snapshot(name)
+    }
+
+    /// Removes a snapshot stored under a given `name`. Returns `false` if no such snapshot existed.
+    pub fn remove_snapshot(&mut self, name: &str) -> crate::Result<bool> {
+        self.db.get().meta().remove_snapshot(name)
+    }
+
+    /// Returns all named snapshots stored for the current document, ordered by their names.
+    pub fn snapshots(&self) -> crate::Result<Vec<(String, Snapshot)>> {
+        self.db.get().meta().snapshots()
+    }
+
+    /// Returns an update that has been persisted aside, because it's waiting for missing blocks
+    /// to arrive. It's returned as a state vector of blocks that are still missing and a size
+    /// (in bytes) of the stored update together with its delete set.
+    ///
+    /// Incoming updates that cannot be integrated are merged with an already pending one, so
+    /// there's at most one pending update stored at any time.
+    pub fn pending_update(&self) -> crate::Result<Option<(StateVector, usize)>> {
+        let meta = self.db.get().meta();
+        Ok(meta.pending()?.map(|pending| {
+            let size = pending.update.len() + pending.delete_set.len();
+            (pending.missing_sv, size)
+        }))
+    }
+
     /// Performs a garbage collection of items marked in the provided `delete_set`:
     /// - Only unreachable collections and their children can be collected cleanly from the database.
     /// - Other elements, which still could be referenced elsewhere, will only be tombstoned
@@ -937,12 +2160,188 @@ impl<'db> Transaction<'db> {
     ///
     /// tx.commit(None)?;
     /// ```
+    ///
+    /// If [TombstoneRetention] policy was configured, blocks deleted within the retention window
+    /// (including the ones deleted by the current transaction) are skipped.
     pub fn gc(&mut self, delete_set: &IDSet) -> crate::Result<()> {
+        if delete_set.is_empty() {
+            return Ok(());
+        }
+        let delete_set = match self.config.tombstone_retention {
+            None => Cow::Borrowed(delete_set),
+            Some(retention) => {
+                let retained = self.retained_tombstones(retention)?;
+                Cow::Owned(delete_set.difference(&retained))
+            }
+        };
         if delete_set.is_empty() {
             return Ok(());
         }
         let mut gc = GarbageCollector::new(self.write_context()?);
-        gc.collect(delete_set)
+        gc.collect(&delete_set)
+    }
+
+    /// Returns blocks which cannot be garbage collected yet, because of a given `retention`
+    /// policy. Delete sets recorded by commits which are past the retention window are removed.
+    fn retained_tombstones(&mut self, retention: TombstoneRetention) -> crate::Result<IDSet> {
+        let mut retained = match self.state.get() {
+            Some(state) => state.delete_set.clone(),
+            None => IDSet::default(),
+        };
+        let meta = self.db.get().meta();
+        let current_seq = meta.commit_seq()?;
+        let now = TombstoneRetention::now();
+        for (seq, timestamp, delete_set) in meta.tombstones()? {
+            if retention.is_expired(seq, timestamp, current_seq, now) {
+                meta.remove_tombstones(seq)?;
+            } else {
+                retained.merge(delete_set);
+            }
+        }
+        Ok(retained)
+    }
+
+    /// Returns [GcStats] with counts of live, deleted and garbage collected blocks, which can be
+    /// used to decide when to run [Transaction::gc] or compact the database. This only includes
+    /// changes made by the current transaction that were already written to the database.
+    pub fn gc_stats(&self) -> crate::Result<GcStats> {
+        self.db.get().inspect().gc_stats()
+    }
+
+    /// Checks if the state vector is consistent with the blocks actually present in the document.
+    /// Returns the clients which state vector entries diverge, together with their actual clocks.
+    /// Returned state vector is empty if no discrepancies were found.
+    ///
+    /// For read-write transactions, changes made by the current transaction are included.
+    pub fn reconcile_state_vector(&self) -> crate::Result<StateVector> {
+        let blocks = self.db.get().blocks();
+        match self.state.get() {
+            Some(state) => Ok(divergence(&blocks.state_vector()?, &state.current_state)),
+            None => blocks.reconcile_state_vector(),
+        }
+    }
+
+    /// Overrides state vector entries, which diverge from the blocks actually present in the
+    /// document (see [Transaction::reconcile_state_vector]). Returns the entries that have been
+    /// fixed.
+    ///
+    /// Repair itself is not a change made by the current transaction: blocks that were persisted
+    /// before it started are not included in its incremental update nor its change count.
+    pub fn repair_state_vector(&mut self) -> crate::Result<StateVector> {
+        let divergent = self.reconcile_state_vector()?;
+        let mut sv_store = self.db.get().state_vector();
+        for (&client, &clock) in divergent.iter() {
+            sv_store.set(client, clock)?;
+        }
+        if let Some(state) = self.state.inner.as_deref_mut() {
+            for (&client, &clock) in divergent.iter() {
+                // shift begin state together with the current one, keeping only the clock range
+                // inserted by this transaction as its own changes
+                let own = state
+                    .current_state
+                    .get(&client)
+                    .get()
+                    .saturating_sub(state.begin_state.get(&client).get());
+                state.current_state.set(client, clock);
+                state
+                    .begin_state
+                    .set(client, Clock::new(clock.get().saturating_sub(own)));
+            }
+        }
+        Ok(divergent)
+    }
+
+    /// Renames a root type from `old` to `new` name, eg. as a part of schema migration. Root types
+    /// are identified by hashes of their names, so all blocks directly contained by the root are
+    /// re-parented and its map entries are moved under the new name.
+    ///
+    /// Renaming is not a part of any update. It must be applied by every peer on its own, otherwise
+    /// changes made by others under the old name will not be visible under the new one.
+    ///
+    /// Fails with [Error::NotFound] if `old` root doesn't exist, with [Error::NodeTypeMismatch] if
+    /// it's not of a given `node_type` ([NodeType::Unknown] accepts any type) and with
+    /// [Error::RootAlreadyExists] if there's already some content under the `new` name.
+    pub fn rename_root(&mut self, old: &str, new: &str, node_type: NodeType) -> crate::Result<()> {
+        let old_id = Node::root_named(old).id();
+        let new_id = Node::root_named(new).id();
+        if old == new {
+            return Ok(());
+        } else if old_id == new_id {
+            return Err(Error::HashCollision(new_id.clock));
+        }
+
+        let db = self.db.get();
+        let blocks = db.blocks();
+        let root = blocks.get(old_id)?;
+        match root.node_type() {
+            Some(&actual)
+                if node_type != NodeType::Unknown
+                    && actual != NodeType::Unknown
+                    && actual != node_type =>
+            {
+                return Err(Error::NodeTypeMismatch {
+                    expected: node_type,
+                    actual,
+                });
+            }
+            _ => {}
+        }
+
+        // root blocks belong to a reserved client, so we skip them here
+        let mut cursor = blocks.cursor()?;
+        let mut children = Vec::new();
+        let mut current = match cursor.start_from(ID::new(1.into(), 0.into())) {
+            Ok(()) => cursor.current().optional()?,
+            Err(Error::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+        while let Some(block) = current.take() {
+            if *block.parent() == old_id {
+                children.push(*block.id());
+            } else if *block.parent() == new_id {
+                return Err(Error::RootAlreadyExists(new.into()));
+            }
+            current = cursor.next()?;
+        }
+
+        for id in children {
+            let mut block: BlockMut = cursor.seek(id)?.into();
+            block.set_parent(new_id);
+            cursor.update(block.as_block())?;
+        }
+
+        let map_entries = db.map_entries();
+        let mut entries = Vec::new();
+        let mut iter = map_entries.entries(&old_id);
+        while let Some(key) = iter.next()? {
+            entries.push((key.key().to_owned(), *iter.block_id()?));
+        }
+        for (key, id) in entries.iter() {
+            map_entries.insert(&new_id, key, id)?;
+        }
+        map_entries.remove_all(&old_id)?;
+
+        // an empty root may have been created already, ie. by mounting it
+        let root = BlockMut::new(new_id, root.header().clone());
+        if blocks.get(new_id).optional()?.is_some() {
+            cursor.remove(new_id)?;
+        }
+        cursor.remove(old_id)?;
+        db.intern_strings().intern(new)?;
+        cursor.insert(root.as_block())?;
+
+        if let Some(state) = self.state.inner.as_deref_mut() {
+            if let Some(changed) = state.changed.remove(&old_id) {
+                state.changed.entry(new_id).or_default().extend(changed);
+            }
+            if let Some(tail) = state.text_tails.remove(&old_id) {
+                state.text_tails.insert(new_id, tail);
+            }
+            if let Some(encoding) = state.value_encodings.remove(&old_id) {
+                state.value_encodings.insert(new_id, encoding);
+            }
+        }
+        Ok(())
     }
 
     pub fn read_context(&self) -> crate::Result<TxScope<'_>> {
@@ -954,12 +2353,197 @@ impl<'db> Transaction<'db> {
     }
 }
 
+/// Continuous range of live elements of the same client, parent and content type, hashed by
+/// [Transaction::content_hash] as a whole, no matter how many blocks it spans.
+struct ContentRun {
+    id: ID,
+    end: Clock,
+    parent: NodeID,
+    key_hash: Option<U32>,
+    content_type: ContentType,
+}
+
+impl ContentRun {
+    fn start(block: &Block<'_>, hasher: &mut XxHash64) -> Self {
+        let run = ContentRun {
+            id: *block.id(),
+            end: block.id().clock,
+            parent: *block.parent(),
+            key_hash: block.key_hash().copied(),
+            content_type: block.content_type(),
+        };
+        hasher.write(run.id.as_bytes());
+        hasher.write(run.parent.as_bytes());
+        hasher.write_u32(run.key_hash.map_or(0, U32::get));
+        hasher.write_u8(run.content_type as u8);
+        run
+    }
+
+    fn continues(&self, block: &Block<'_>) -> bool {
+        let id = block.id();
+        id.client == self.id.client
+            && id.clock == self.end
+            && block.parent() == &self.parent
+            && block.key_hash() == self.key_hash.as_ref()
+            && block.content_type() == self.content_type
+    }
+
+    fn finish(self, hasher: &mut XxHash64) {
+        hasher.write_u32((self.end - self.id.clock).get());
+    }
+}
+
+/// Groups encoded blocks into self-contained [Encoding::V1] updates of a limited size.
+struct UpdateChunker {
+    max_bytes: usize,
+    chunks: Vec<Vec<u8>>,
+    sections: Vec<ChunkSection>,
+    /// Upper bound of the encoded size of a current chunk.
+    size: usize,
+}
+
+/// Consecutive blocks of a single client within an update chunk.
+struct ChunkSection {
+    client_id: ClientID,
+    start: Clock,
+    len: u32,
+    blocks: Vec<u8>,
+}
+
+impl UpdateChunker {
+    /// Max length of a variable length encoded number of clients.
+    const HEADER_LEN: usize = 5;
+    /// Max length of variable length encoded block count, client ID and start clock.
+    const SECTION_HEADER_LEN: usize = 5 + 10 + 5;
+    /// Length of an empty delete set.
+    const EMPTY_DS_LEN: usize = 1;
+    /// Max length of variable length encoded number of delete set clients, over the length of
+    /// an empty delete set.
+    const DS_HEADER_LEN: usize = 5 - Self::EMPTY_DS_LEN;
+    /// Max length of variable length encoded client ID and number of its deleted ranges.
+    const DS_CLIENT_LEN: usize = 5 + 5;
+    /// Max length of variable length encoded start clock and length of a deleted range.
+    const DS_RANGE_LEN: usize = 5 + 5;
+
+    fn new(max_bytes: usize) -> Self {
+        UpdateChunker {
+            max_bytes,
+            chunks: Vec::new(),
+            sections: Vec::new(),
+            size: Self::HEADER_LEN + Self::EMPTY_DS_LEN,
+        }
+    }
+
+    fn push_block(&mut self, client_id: ClientID, clock: Clock, block: &[u8]) -> crate::Result<()> {
+        let continues = matches!(self.sections.last(), Some(s) if s.client_id == client_id);
+        let added = if continues {
+            block.len()
+        } else {
+            block.len() + Self::SECTION_HEADER_LEN
+        };
+        if !self.sections.is_empty() && self.size + added > self.max_bytes {
+            let chunk = self.flush(&IDSet::default())?;
+            self.chunks.push(chunk);
+        }
+        match self.sections.last_mut() {
+            Some(section) if section.client_id == client_id => {
+                section.len += 1;
+                section.blocks.extend_from_slice(block);
+                self.size += block.len();
+            }
+            _ => {
+                self.sections.push(ChunkSection {
+                    client_id,
+                    start: clock,
+                    len: 1,
+                    blocks: block.to_vec(),
+                });
+                self.size += block.len() + Self::SECTION_HEADER_LEN;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the `delete_set` after all pushed blocks, splitting it on range boundaries into
+    /// as many chunks as necessary, and returns all chunks.
+    fn finish(mut self, delete_set: &IDSet) -> crate::Result<Vec<Vec<u8>>> {
+        let mut delete_set = delete_set.clone();
+        delete_set.squash();
+        let mut pending = IDSet::default();
+        for (&client_id, ranges) in delete_set.iter() {
+            for range in ranges.iter() {
+                let mut added = Self::DS_RANGE_LEN;
+                if pending.get(&client_id).is_none() {
+                    added += Self::DS_CLIENT_LEN;
+                }
+                if pending.is_empty() {
+                    added += Self::DS_HEADER_LEN;
+                }
+                let is_empty = self.sections.is_empty() && pending.is_empty();
+                if !is_empty && self.size + added > self.max_bytes {
+                    let chunk = self.flush(&pending)?;
+                    self.chunks.push(chunk);
+                    pending = IDSet::default();
+                    added = Self::DS_HEADER_LEN + Self::DS_CLIENT_LEN + Self::DS_RANGE_LEN;
+                }
+                pending.insert(ID::new(client_id, range.start), range.end - range.start);
+                self.size += added;
+            }
+        }
+        if !self.sections.is_empty() || !pending.is_empty() {
+            let chunk = self.flush(&pending)?;
+            self.chunks.push(chunk);
+        }
+        Ok(self.chunks)
+    }
+
+    fn flush(&mut self, delete_set: &IDSet) -> crate::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(self.size);
+        let mut encoder = EncoderV1::new(&mut buf);
+        encoder.write_var(self.sections.len())?;
+        for section in self.sections.drain(..) {
+            encoder.write_var(section.len)?;
+            encoder.write_client(section.client_id)?;
+            encoder.write_var(section.start)?;
+            encoder.write_all(&section.blocks)?;
+        }
+        delete_set.encode_with(&mut encoder)?;
+        self.size = Self::HEADER_LEN + Self::EMPTY_DS_LEN;
+        Ok(buf)
+    }
+}
+
+/// [std::io::Write] sink which only counts the bytes written into it.
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 /// Summary of transaction changes.
 #[derive(Debug, Default, Clone)]
 pub struct TransactionSummary {
     pub flags: CommitFlags,
     pub update: Vec<u8>,
     pub changed_nodes: HashSet<NodeID>,
+    /// Hashes of map entry keys changed within modified nodes, reported together with
+    /// [TransactionSummary::changed_nodes]. Keys are hashed with XxHash32 (seed 0) over their
+    /// UTF-8 bytes. Use [crate::MapRef::changed_keys] to resolve them.
+    pub changed_keys: HashMap<NodeID, HashSet<u32>>,
+    /// Number of clients that contributed to the document, set only when the transaction
+    /// introduced new clients and crossed the [ClientLimit::Report] threshold configured via
+    /// [crate::MultiDoc::with_client_limit]. See also [Transaction::client_limit_exceeded].
+    pub client_limit_exceeded: Option<usize>,
 }
 
 impl TransactionSummary {
@@ -968,12 +2552,133 @@ impl TransactionSummary {
             flags,
             update: Vec::new(),
             changed_nodes: HashSet::new(),
+            changed_keys: HashMap::new(),
+            client_limit_exceeded: None,
         }
     }
 
     pub fn clear(&mut self) {
         self.update.clear();
         self.changed_nodes.clear();
+        self.changed_keys.clear();
+        self.client_limit_exceeded = None;
+    }
+}
+
+/// Threshold of distinct clients (entries in a document state vector) checked when a read-write
+/// transaction is committed. Documents accumulating changes from many ephemeral clients grow
+/// their state vectors and every update exchanged between peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientLimit {
+    /// Commit succeeds, but [TransactionSummary::client_limit_exceeded] and
+    /// [Transaction::client_limit_exceeded] report the number of clients once it exceeds a given
+    /// threshold.
+    Report(usize),
+    /// Commit fails with [crate::Error::ClientLimitExceeded] if it introduces new clients and
+    /// the number of clients exceeds a given threshold.
+    Reject(usize),
+}
+
+impl ClientLimit {
+    /// Returns the maximum number of clients allowed in a document.
+    pub fn max_clients(&self) -> usize {
+        match self {
+            ClientLimit::Report(max_clients) | ClientLimit::Reject(max_clients) => *max_clients,
+        }
+    }
+}
+
+/// Policy protecting recently deleted blocks from being garbage collected by [Transaction::gc],
+/// so that peers which haven't synced the deletion yet, still can learn about it.
+///
+/// Delete sets of commits are recorded only while the policy is configured. Tombstones of blocks
+/// deleted before that are not protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TombstoneRetention {
+    /// Deleted blocks can be collected only after a given number of subsequent commits
+    /// (that changed the document) have happened.
+    Commits(u64),
+    /// Deleted blocks can be collected only once a given time has passed since the commit
+    /// that deleted them.
+    Duration(Duration),
+}
+
+impl TombstoneRetention {
+    fn is_expired(&self, seq: u64, timestamp: u64, current_seq: u64, now: u64) -> bool {
+        match self {
+            TombstoneRetention::Commits(commits) => current_seq.saturating_sub(seq) >= *commits,
+            TombstoneRetention::Duration(duration) => {
+                now.saturating_sub(timestamp) >= duration.as_millis() as u64
+            }
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// Max number of carriers decoded at once by [Transaction::apply_update_from].
+const STREAMED_BATCH_LEN: usize = 1024;
+
+/// Limits of the update integration loop, guarding against maliciously crafted updates (e.g.
+/// long chains of blocks depending on each other) exhausting resources of the process. Once
+/// exceeded, [crate::Error::IntegrationLimitExceeded] is returned and the transaction should be
+/// dropped, since the update may have been integrated only partially.
+///
+/// By default, no limits are set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegrationLimits {
+    /// Max number of blocks waiting on the stack for their dependencies to be integrated first.
+    pub max_stack_depth: usize,
+    /// Max number of blocks processed while integrating a single update.
+    pub max_carriers: usize,
+}
+
+impl Default for IntegrationLimits {
+    fn default() -> Self {
+        IntegrationLimits {
+            max_stack_depth: usize::MAX,
+            max_carriers: usize::MAX,
+        }
+    }
+}
+
+/// Settings of read-write transactions, configured via [crate::MultiDoc] builder methods.
+#[derive(Debug, Clone)]
+pub(crate) struct TransactionConfig {
+    pub client_limit: Option<ClientLimit>,
+    pub max_block_content_len: usize,
+    /// If set, Binary and Embed contents of inserted blocks are stored in an interned form.
+    pub intern_content: bool,
+    pub integration_limits: IntegrationLimits,
+    /// If set, it's used instead of last-writer-wins rule to resolve concurrent map values.
+    pub map_resolver: Option<MapResolver>,
+    pub tombstone_retention: Option<TombstoneRetention>,
+    /// If set, commits leaving the document bigger than this number of bytes are rejected.
+    pub doc_quota: Option<usize>,
+    /// If unset, adjacent blocks are never merged, so that every operation stays a distinct block.
+    pub block_merging: bool,
+    /// Notified about every block inserted or deleted by a transaction.
+    pub observers: Vec<Arc<dyn IntegrationObserver>>,
+}
+
+impl Default for TransactionConfig {
+    fn default() -> Self {
+        TransactionConfig {
+            client_limit: None,
+            max_block_content_len: BlockHeader::DEFAULT_MAX_CONTENT_LEN,
+            intern_content: false,
+            integration_limits: IntegrationLimits::default(),
+            map_resolver: None,
+            tombstone_retention: None,
+            doc_quota: None,
+            block_merging: true,
+            observers: Vec::new(),
+        }
     }
 }
 
@@ -1033,6 +2738,172 @@ where
     }
 }
 
+/// Iterator over blocks of a single client returned by [Transaction::blocks_for_client].
+pub struct ClientBlocks<'tx> {
+    cursor: BlockCursor<'tx>,
+    contents: ContentStore<'tx>,
+    client: ClientID,
+    end: Clock,
+    next: Option<Block<'tx>>,
+}
+
+impl<'tx> ClientBlocks<'tx> {
+    fn read_content(&self, block: &Block<'tx>) -> crate::Result<Vec<Content<'tx>>> {
+        if let Some(content) = block.try_inline_content() {
+            return Ok(vec![content]);
+        }
+        let content_type = block.content_type();
+        match content_type {
+            ContentType::Deleted | ContentType::Node => Ok(Vec::new()),
+            ContentType::Atom | ContentType::Json => {
+                // multi-value blocks store every element as a separate content entry
+                let mut content = Vec::with_capacity(block.clock_len().get() as usize);
+                for offset in 0..block.clock_len().get() {
+                    let data = self.contents.get(block.id().add(Clock::new(offset)))?;
+                    content.push(Content::new(content_type, Cow::Borrowed(data)));
+                }
+                Ok(content)
+            }
+            _ => {
+                let data = self.contents.get(*block.id())?;
+                Ok(vec![Content::new(content_type, Cow::Borrowed(data))])
+            }
+        }
+    }
+}
+
+impl<'tx> Iterator for ClientBlocks<'tx> {
+    type Item = crate::Result<(Block<'tx>, Vec<Content<'tx>>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.next.take()?;
+        if block.id().client != self.client || block.id().clock >= self.end {
+            return None;
+        }
+        let content = match self.read_content(&block) {
+            Ok(content) => content,
+            Err(e) => return Some(Err(e)),
+        };
+        self.next = match self.cursor.next() {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(Ok((block, content)))
+    }
+}
+
+/// Decides if blocks belong to one of the root types accepted by a filter function, used by
+/// [Transaction::diff_update_filtered]. Resolved root ancestors are cached by parent node.
+struct RootFilter<'tx, F> {
+    filter: F,
+    blocks: BlockStore<'tx>,
+    strings: InternStringsStore<'tx>,
+    cache: HashMap<NodeID, bool>,
+}
+
+impl<'tx, F> RootFilter<'tx, F>
+where
+    F: Fn(&str) -> bool,
+{
+    fn new(db: Database<'tx>, filter: F) -> Self {
+        RootFilter {
+            filter,
+            blocks: db.blocks(),
+            strings: db.intern_strings(),
+            cache: HashMap::new(),
+        }
+    }
+
+    fn includes(&mut self, block: &Block<'_>) -> crate::Result<bool> {
+        let mut node = *block.parent();
+        let mut path = Vec::new();
+        let included = loop {
+            if let Some(&included) = self.cache.get(&node) {
+                break included;
+            }
+            path.push(node);
+            if node.is_root() {
+                break (self.filter)(self.strings.get(node.clock)?);
+            }
+            match self.blocks.get(node).optional()? {
+                Some(parent) => node = *parent.parent(),
+                // parent node has been garbage collected together with its descendants
+                None => break false,
+            }
+        };
+        for node in path {
+            self.cache.insert(node, included);
+        }
+        Ok(included)
+    }
+}
+
+/// Human-readable dump of changes made by a transaction. See [Transaction::debug_changes].
+pub struct DebugChanges<'a, 'db> {
+    tx: &'a Transaction<'db>,
+}
+
+impl<'a, 'db> Display for DebugChanges<'a, 'db> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = match self.tx.state.get() {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let db = self.tx.db.get();
+        let contents = db.contents();
+        let mut cursor = db.blocks().cursor()?;
+        let mut blocks = Vec::new();
+
+        writeln!(f, "inserted:")?;
+        for (&client_id, &end_clock) in state.current_state.iter() {
+            let start_clock = state.begin_state.get(&client_id);
+            if end_clock <= start_clock {
+                continue;
+            }
+            blocks.clear();
+            TransactionState::collect_blocks(
+                &mut cursor,
+                client_id,
+                start_clock,
+                end_clock,
+                &mut blocks,
+            )?;
+            for block in blocks.iter() {
+                write!(f, "  {}, {}", block.id(), block.header())?;
+                if block.try_inline_content().is_none() {
+                    let range = BlockRange::new(*block.id(), block.last_id().clock);
+                    for content in contents.read_range(block.content_type(), range) {
+                        write!(f, " {}", content?)?;
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+
+        writeln!(f, "deleted:")?;
+        for (client_id, ranges) in state.delete_set.iter() {
+            write!(f, "  {}:", client_id)?;
+            for range in ranges.iter() {
+                write!(f, " [{}..{})", range.start, range.end)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "changed:")?;
+        let changed: BTreeSet<_> = state.changed.keys().collect();
+        for node_id in changed {
+            writeln!(f, "  {}", node_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'db> std::fmt::Debug for DebugChanges<'a, 'db> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
 impl Display for Origin {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match std::str::from_utf8(&self.0) {
@@ -1054,6 +2925,9 @@ pub struct PendingUpdate<'tx> {
     pub update: &'tx [u8],
     pub delete_set: &'tx [u8],
     pub missing_sv: StateVector,
+    /// If set, pending update contains blocks that were never tried to be integrated (see
+    /// [Transaction::apply_update_filtered]) and should be retried by the next update.
+    pub retry: bool,
 }
 
 impl<'tx> PendingUpdate<'tx> {
@@ -1062,6 +2936,7 @@ impl<'tx> PendingUpdate<'tx> {
             update,
             delete_set,
             missing_sv,
+            retry: false,
         }
     }
 
@@ -1087,16 +2962,19 @@ impl<'tx> TxScope<'tx> {
 pub struct TxMutScope<'tx> {
     inner: TxScope<'tx>,
     pub(crate) state: &'tx mut TransactionState,
+    pub(crate) config: &'tx TransactionConfig,
 }
 
 impl<'tx> TxMutScope<'tx> {
     pub fn new(tx: &'tx mut Transaction<'_>) -> crate::Result<Self> {
         let db = tx.db.get();
         let cursor = BlockCursor::new(db)?;
+        let config = &tx.config;
         let state = tx.state.get_or_init(db);
         Ok(Self {
             inner: TxScope { db, cursor },
             state,
+            config,
         })
     }
 
@@ -1112,6 +2990,7 @@ impl<'tx> TxMutScope<'tx> {
         self.cursor.update(block.as_block())?;
 
         self.state.delete_set.insert(*block.id(), block.clock_len());
+        self.notify_deleted(block.id(), block.clock_len(), block.parent())?;
         self.state
             .add_changed_type(*block.parent(), parent_deleted, block.key_hash());
 
@@ -1129,6 +3008,32 @@ impl<'tx> TxMutScope<'tx> {
         Ok(true)
     }
 
+    /// Notifies all [IntegrationObserver]s about `content` inserted at a given `id`.
+    pub(crate) fn notify_inserted(
+        &self,
+        id: &ID,
+        parent: &NodeID,
+        content: &[Content<'_>],
+    ) -> crate::Result<()> {
+        for observer in self.config.observers.iter() {
+            match content {
+                [attr] if attr.content_type() == ContentType::Format => {
+                    observer.format_applied(&self.db, id, parent, attr)?
+                }
+                _ => observer.block_inserted(&self.db, id, parent, content)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Notifies all [IntegrationObserver]s about `len` elements deleted starting at a given `id`.
+    fn notify_deleted(&self, id: &ID, len: Clock, parent: &NodeID) -> crate::Result<()> {
+        for observer in self.config.observers.iter() {
+            observer.block_deleted(&self.db, id, len, parent)?;
+        }
+        Ok(())
+    }
+
     fn delete_list_members(&mut self, start: ID) -> crate::Result<()> {
         let mut current = Some(start);
         while let Some(id) = current {
@@ -1170,11 +3075,17 @@ impl<'tx> TxMutScope<'tx> {
         Ok(())
     }
 
+    /// Integrates given `blocks`, returning the ones that could not be integrated because of
+    /// missing dependencies. For every client with missing blocks, `missing_sv` is updated with
+    /// the clock of the first missing block. `processed` carriers are checked against
+    /// [IntegrationLimits::max_carriers].
     fn apply_update_internal(
         &mut self,
         mut blocks: BTreeMap<ClientID, VecDeque<Carrier>>,
+        missing_sv: &mut StateVector,
+        processed: &mut usize,
     ) -> crate::Result<BTreeMap<ClientID, VecDeque<Carrier>>> {
-        let mut missing_sv = StateVector::default();
+        let limits = self.config.integration_limits;
         let mut remaining = BTreeMap::new();
         let mut stack = Vec::new();
 
@@ -1185,6 +3096,13 @@ impl<'tx> TxMutScope<'tx> {
         };
 
         while let Some(carrier) = stack_head.take() {
+            *processed += 1;
+            if *processed > limits.max_carriers {
+                return Err(crate::Error::IntegrationLimitExceeded {
+                    limit: "carriers",
+                    max: limits.max_carriers,
+                });
+            }
             if !carrier.is_skip() {
                 let id = *carrier.id();
                 if self.state.current_state.contains(&id) {
@@ -1193,6 +3111,12 @@ impl<'tx> TxMutScope<'tx> {
                     if let Some(dep) = Self::missing_dependency(&carrier, &self.state.current_state)
                     {
                         // current block is missing a dependency
+                        if stack.len() >= limits.max_stack_depth {
+                            return Err(crate::Error::IntegrationLimitExceeded {
+                                limit: "stack depth",
+                                max: limits.max_stack_depth,
+                            });
+                        }
                         stack.push(carrier);
                         match blocks.entry(dep) {
                             Entry::Occupied(mut e) if !e.get().is_empty() => {
@@ -1203,7 +3127,7 @@ impl<'tx> TxMutScope<'tx> {
                             }
                             _ => {
                                 // This update message causally depends on another update message that doesn't exist yet
-                                missing_sv.set_min(dep, self.state.current_state.get(&dep));
+                                missing_sv.set_max(dep, self.state.current_state.get(&dep));
                                 Self::unapplicable(&mut stack, &mut blocks, &mut remaining);
                                 current_client = blocks.last_entry();
                             }
@@ -1213,7 +3137,8 @@ impl<'tx> TxMutScope<'tx> {
                     }
                 } else {
                     // update from the same client is missing
-                    missing_sv.set_min(id.client, id.clock - 1);
+                    let clock = self.state.current_state.get(&id.client);
+                    missing_sv.set_max(id.client, clock);
                     stack.push(carrier);
                     Self::unapplicable(&mut stack, &mut blocks, &mut remaining);
                     current_client = blocks.last_entry();
@@ -1288,6 +3213,7 @@ impl<'tx> TxMutScope<'tx> {
                                 block.set_deleted();
                                 self.cursor.update_current(*block.id(), block.header())?;
                                 self.state.delete_set.insert(*block.id(), block.clock_len());
+                                self.notify_deleted(block.id(), block.clock_len(), block.parent())?;
                             }
                             block = match self.cursor.next()? {
                                 Some(b) => b,
@@ -1366,3 +3292,654 @@ impl<'tx> DerefMut for TxMutScope<'tx> {
         &mut self.inner
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::block_reader::Carrier;
+    use crate::id_set::IDSet;
+    use crate::lib0::v1::{DecoderV1, EncoderV1};
+    use crate::lib0::v2::DecoderV2;
+    use crate::lib0::{Encode, Encoder, Encoding, WriteExt};
+    use crate::store::Db;
+    use crate::test_util::{multi_doc, sync};
+    use crate::{
+        ClientID, Clock, ContentType, Error, ID, List, Map, MapPrelim, MultiDoc, NodeType,
+        StateVector, Text, Transaction, Unmounted, Update, XmlElementPrelim, XmlFragment, lib0,
+    };
+
+    #[test]
+    fn root_exists() {
+        let txt: Unmounted<Text> = Unmounted::root("type");
+        let (mdoc, _dir) = multi_doc(1);
+
+        let tx = mdoc.transact_mut("test").unwrap();
+        assert!(!tx.root_exists("type", NodeType::Text).unwrap());
+        assert!(!tx.root_exists("type", NodeType::Unknown).unwrap());
+        tx.commit(None).unwrap();
+
+        let tx = mdoc.transact("test").unwrap();
+        assert!(!tx.root_exists("type", NodeType::Text).unwrap());
+        drop(tx);
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = mdoc.transact("test").unwrap();
+        assert!(tx.root_exists("type", NodeType::Text).unwrap());
+        assert!(tx.root_exists("type", NodeType::Unknown).unwrap());
+        assert!(!tx.root_exists("type", NodeType::Map).unwrap());
+        assert!(!tx.root_exists("other", NodeType::Text).unwrap());
+    }
+
+    #[test]
+    fn client_clock() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let c1 = ClientID::from(1);
+        let c2 = ClientID::from(2);
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        assert_eq!(t1.client_clock(c1).unwrap(), None);
+        txt.mount_mut(&mut t1).unwrap().insert(0, "abc").unwrap();
+        assert_eq!(t1.client_clock(c1).unwrap(), Some(Clock::new(3)));
+        txt.mount_mut(&mut t1).unwrap().insert(3, "de").unwrap();
+        assert_eq!(t1.client_clock(c1).unwrap(), Some(Clock::new(5)));
+        assert_eq!(t1.client_clock(c2).unwrap(), None);
+
+        let mut t2 = d2.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2]);
+        t2.commit(None).unwrap();
+
+        let t2 = d2.transact("test").unwrap();
+        assert_eq!(t2.client_clock(c1).unwrap(), Some(Clock::new(5)));
+        assert_eq!(t2.client_clock(c2).unwrap(), None);
+    }
+
+    #[test]
+    fn duplicate_block_id() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _dir) = multi_doc(1);
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().push("hello").unwrap();
+        map.mount_mut(&mut tx).unwrap().insert("a", 1.0).unwrap();
+        tx.commit(None).unwrap();
+
+        // lose persisted state vector, so that the next insert reuses already allocated clocks
+        let tx = doc.transact_mut("test").unwrap();
+        tx.db.get().state_vector().set(1.into(), 0.into()).unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        let err = txt.mount_mut(&mut tx).unwrap().push("world").unwrap_err();
+        assert!(matches!(err, Error::DuplicateBlock(id) if id == ID::new(1.into(), 0.into())));
+        drop(tx);
+
+        // previously inserted blocks were not overwritten
+        let tx = doc.transact("test").unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+        assert_eq!(map.mount(&tx).unwrap().get::<_, f64>("a").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn cross_parent_origin() {
+        let a: Unmounted<Text> = Unmounted::root("a");
+        let b: Unmounted<Text> = Unmounted::root("b");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        a.mount_mut(&mut t1).unwrap().push("xx").unwrap();
+        b.mount_mut(&mut t1).unwrap().push("yy").unwrap();
+        sync([&mut t1, &mut t2]);
+        t1.commit(None).unwrap();
+        t2.commit(None).unwrap();
+
+        // insert between `yy` and then craft the block to have its left origin in another text
+        let mut t2 = d2.transact_mut("test").unwrap();
+        b.mount_mut(&mut t2).unwrap().insert(1, "z").unwrap();
+        let update = t2
+            .diff_update(
+                &d1.transact("test").unwrap().state_vector().unwrap(),
+                Encoding::V1,
+            )
+            .unwrap();
+        drop(t2);
+
+        let mut decoded = Update::decode(&update, Encoding::V1).unwrap();
+        let carriers = decoded.blocks.get_mut(&ClientID::from(2)).unwrap();
+        let Carrier::Block(block) = &mut carriers[0] else {
+            panic!("expected block carrier");
+        };
+        assert_eq!(
+            block.block.origin_left(),
+            Some(&ID::new(1.into(), 2.into()))
+        );
+        block.block.set_origin_left(ID::new(1.into(), 0.into()));
+        let mut encoder = EncoderV1::new(Vec::new());
+        encoder.write_var(decoded.blocks.len()).unwrap();
+        for (&client, carriers) in decoded.blocks.iter() {
+            encoder.write_var(carriers.len()).unwrap();
+            encoder.write_client(client).unwrap();
+            encoder.write_var(carriers[0].id().clock).unwrap();
+            for carrier in carriers {
+                carrier.encode(&mut encoder).unwrap();
+            }
+        }
+        decoded.delete_set.encode_with(&mut encoder).unwrap();
+        let update = encoder.into_inner();
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let err = t1.apply_update(&update, Encoding::V1).unwrap_err();
+        assert!(matches!(err, Error::MalformedBlock(id) if id == ID::new(2.into(), 0.into())));
+        drop(t1);
+
+        let tx = d1.transact("test").unwrap();
+        assert_eq!(a.mount(&tx).unwrap().to_string(), "xx");
+        assert_eq!(b.mount(&tx).unwrap().to_string(), "yy");
+    }
+
+    #[test]
+    fn raw_content() {
+        let short: Unmounted<Text> = Unmounted::root("short");
+        let long: Unmounted<Text> = Unmounted::root("long");
+        let list: Unmounted<List> = Unmounted::root("list");
+        let long_str = "a".repeat(100);
+        let (doc, _dir) = multi_doc(1);
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        short.mount_mut(&mut tx).unwrap().push("hello").unwrap();
+        long.mount_mut(&mut tx).unwrap().push(&long_str).unwrap();
+        list.mount_mut(&mut tx)
+            .unwrap()
+            .push_back(MapPrelim::default())
+            .unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = doc.transact("test").unwrap();
+        let raw = tx.raw_content(ID::new(1.into(), 2.into())).unwrap();
+        assert_eq!(raw[0], ContentType::String as u8);
+        assert_eq!(&raw[1..], b"hello");
+
+        let raw = tx.raw_content(ID::new(1.into(), 5.into())).unwrap();
+        assert_eq!(raw[0], ContentType::String as u8);
+        assert_eq!(&raw[1..], long_str.as_bytes());
+
+        let raw = tx.raw_content(ID::new(1.into(), 105.into())).unwrap();
+        assert_eq!(&raw[..], &[ContentType::Node as u8]);
+
+        let err = tx.raw_content(ID::new(1.into(), 106.into())).unwrap_err();
+        assert!(matches!(err, Error::NotFound));
+    }
+
+    #[test]
+    fn raw_content_elements_and_xml_tags() {
+        let list: Unmounted<List> = Unmounted::root("list");
+        let xml: Unmounted<XmlFragment> = Unmounted::root("xml");
+        let (doc, _dir) = multi_doc(1);
+
+        // single element block keeps its content inline
+        let mut tx = doc.transact_mut("test").unwrap();
+        list.mount_mut(&mut tx).unwrap().push_back(1.0).unwrap();
+        let raw = tx.raw_content(ID::new(1.into(), 0.into())).unwrap();
+        assert_eq!(raw[0], ContentType::Atom as u8);
+        assert_eq!(&raw[1..], lib0::to_vec(&1.0).unwrap().as_slice());
+        tx.commit(None).unwrap();
+
+        // merged with the next elements, every element has its own entry
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.push_back("two").unwrap();
+        l.push_back(true).unwrap();
+        xml.mount_mut(&mut tx)
+            .unwrap()
+            .push_back(XmlElementPrelim::new("paragraph"))
+            .unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = doc.transact("test").unwrap();
+        for (clock, expected) in [(0, lib0!(1.0)), (1, lib0!("two")), (2, lib0!(true))] {
+            let raw = tx.raw_content(ID::new(1.into(), clock.into())).unwrap();
+            assert_eq!(raw[0], ContentType::Atom as u8);
+            let value: lib0::Value = lib0::from_slice(&raw[1..]).unwrap();
+            assert_eq!(value, expected);
+        }
+
+        let raw = tx.raw_content(ID::new(1.into(), 3.into())).unwrap();
+        assert_eq!(raw[0], ContentType::Node as u8);
+        assert_eq!(&raw[1..], b"paragraph");
+    }
+
+    #[test]
+    fn apply_update_ordered() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (d0, _dir0) = multi_doc(10);
+        let mut t0 = d0.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t0).unwrap().push("base").unwrap();
+        let base = t0.full_update(Encoding::V1).unwrap();
+        t0.commit(None).unwrap();
+
+        // clients make concurrent edits at the same positions
+        let mut updates = Vec::new();
+        let mut dirs = Vec::new();
+        for client in 1..=3u32 {
+            let (doc, dir) = multi_doc(client);
+            let mut tx = doc.transact_mut("test").unwrap();
+            tx.apply_update(&base, Encoding::V1).unwrap();
+            let mut t = txt.mount_mut(&mut tx).unwrap();
+            t.insert(2, format!("<{client}>")).unwrap();
+            t.push(client.to_string()).unwrap();
+            t.remove_range(0..1).unwrap();
+            map.mount_mut(&mut tx)
+                .unwrap()
+                .insert("key", client as f64)
+                .unwrap();
+            updates.push(tx.incremental_update(Encoding::V1).unwrap());
+            tx.commit(None).unwrap();
+            dirs.push((doc, dir));
+        }
+        let mut t0 = d0.transact_mut("test").unwrap();
+        for update in updates.iter() {
+            t0.apply_update(update, Encoding::V1).unwrap();
+        }
+        let update = t0.full_update(Encoding::V1).unwrap();
+        let expected_hash = t0.content_hash().unwrap();
+        let expected = txt.mount(&t0).unwrap().to_string();
+        t0.commit(None).unwrap();
+
+        let orders: [&[ClientID]; 4] = [
+            &[1.into(), 2.into(), 3.into(), 10.into()],
+            &[3.into(), 2.into(), 1.into(), 10.into()],
+            &[10.into(), 2.into(), 3.into(), 1.into()],
+            &[3.into()],
+        ];
+        for (i, order) in orders.into_iter().enumerate() {
+            let (doc, _dir) = multi_doc(20 + i as u32);
+            let mut tx = doc.transact_mut("test").unwrap();
+            let missing = tx
+                .apply_update_ordered(&update, Encoding::V1, order)
+                .unwrap();
+            assert!(missing.is_empty());
+            assert_eq!(txt.mount(&tx).unwrap().to_string(), expected);
+            assert_eq!(tx.content_hash().unwrap(), expected_hash);
+        }
+    }
+
+    #[test]
+    fn rename_root() {
+        let body: Unmounted<Map> = Unmounted::root("body");
+        let content: Unmounted<Map> = Unmounted::root("content");
+        let title: Unmounted<Text> = Unmounted::root("title");
+        let (doc, _dir) = multi_doc(1);
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut m = body.mount_mut(&mut tx).unwrap();
+        m.insert("a", 1.0).unwrap();
+        m.insert("a", 2.0).unwrap();
+        m.insert("b", "hello").unwrap();
+        m.insert("c", true).unwrap();
+        m.remove("c").unwrap();
+        let nested: Unmounted<Map> = m.insert("nested", MapPrelim::default()).unwrap();
+        nested.mount_mut(&mut tx).unwrap().insert("x", 3.0).unwrap();
+        title.mount_mut(&mut tx).unwrap().push("title").unwrap();
+        let expected = body.mount(&tx).unwrap().to_value().unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        assert!(matches!(
+            tx.rename_root("body", "content", NodeType::List),
+            Err(Error::NodeTypeMismatch { .. })
+        ));
+        assert!(matches!(
+            tx.rename_root("body", "title", NodeType::Map),
+            Err(Error::RootAlreadyExists(name)) if name == "title"
+        ));
+        tx.rename_root("body", "content", NodeType::Map).unwrap();
+        tx.commit(None).unwrap();
+
+        let mut tx = doc.transact_mut("test").unwrap();
+        assert_eq!(content.mount(&tx).unwrap().to_value().unwrap(), expected);
+        assert_eq!(body.mount(&tx).unwrap().to_value().unwrap(), lib0!({}));
+        assert!(matches!(
+            tx.rename_root("missing", "other", NodeType::Map),
+            Err(Error::NotFound)
+        ));
+
+        // renamed root can be edited and synced as usual
+        let mut m = content.mount_mut(&mut tx).unwrap();
+        m.insert("a", 4.0).unwrap();
+        m.remove("b").unwrap();
+        let expected = content.mount(&tx).unwrap().to_value().unwrap();
+        let update = tx.full_update(Encoding::V1).unwrap();
+        let (d2, _dir2) = multi_doc(2);
+        let mut t2 = d2.transact_mut("test").unwrap();
+        t2.apply_update(&update, Encoding::V1).unwrap();
+        assert_eq!(content.mount(&t2).unwrap().to_value().unwrap(), expected);
+    }
+
+    #[test]
+    fn doc_size() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (mdoc, _dir) = multi_doc(1);
+        mdoc.transact_mut("test").unwrap().commit(None).unwrap();
+        mdoc.transact_mut("other").unwrap().commit(None).unwrap();
+        let empty = mdoc.doc_size("test").unwrap();
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().push("hello").unwrap();
+        tx.commit(None).unwrap();
+        let with_text = mdoc.doc_size("test").unwrap();
+        assert!(with_text > empty);
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("key", "x".repeat(1000)).unwrap();
+        tx.commit(None).unwrap();
+        let with_map = mdoc.doc_size("test").unwrap();
+        assert!(with_map > with_text + 1000);
+
+        // other documents are not affected
+        assert_eq!(mdoc.doc_size("other").unwrap(), empty);
+    }
+
+    #[test]
+    fn doc_quota() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (mdoc, _dir) = multi_doc(1);
+        let mdoc = mdoc.with_doc_quota(1024);
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().push("hello").unwrap();
+        tx.commit(None).unwrap();
+        let size = mdoc.doc_size("test").unwrap();
+        assert!(size <= 1024);
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx)
+            .unwrap()
+            .push("x".repeat(2048))
+            .unwrap();
+        let err = tx.commit(None).unwrap_err();
+        assert!(matches!(err, Error::QuotaExceeded { quota: 1024, size } if size > 1024));
+
+        // rejected transaction was rolled back
+        assert_eq!(mdoc.doc_size("test").unwrap(), size);
+        let tx = mdoc.transact("test").unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+        drop(tx);
+
+        // changes fitting into the quota are still accepted
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().push(" world").unwrap();
+        tx.commit(None).unwrap();
+        let tx = mdoc.transact("test").unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello world");
+    }
+
+    #[test]
+    fn doc_quota_tracked_size() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (mdoc, _dir) = multi_doc(1);
+        let stored_size = |mdoc: &MultiDoc| {
+            let tx = mdoc.transact("test").unwrap();
+            tx.db.get().meta().doc_size().unwrap()
+        };
+
+        // document edited without a quota doesn't keep its size
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().push("hello").unwrap();
+        tx.commit(None).unwrap();
+        assert_eq!(stored_size(&mdoc), None);
+
+        let mdoc = mdoc.with_doc_quota(1 << 20);
+        let edits: [&dyn Fn(&mut Transaction); 4] = [
+            &|tx| txt.mount_mut(tx).unwrap().push(" world").unwrap(),
+            &|tx| {
+                let mut m = map.mount_mut(tx).unwrap();
+                m.insert("a", "x".repeat(100)).unwrap();
+                m.insert("b", 1.0).unwrap();
+            },
+            &|tx| map.mount_mut(tx).unwrap().insert("a", "y").unwrap(),
+            &|tx| {
+                txt.mount_mut(tx).unwrap().remove_range(0..6).unwrap();
+                map.mount_mut(tx).unwrap().remove("b").unwrap();
+                let ds = tx.delete_set().cloned().unwrap();
+                tx.gc(&ds).unwrap();
+            },
+        ];
+        for edit in edits {
+            let mut tx = mdoc.transact_mut("test").unwrap();
+            edit(&mut tx);
+            tx.commit(None).unwrap();
+            assert_eq!(stored_size(&mdoc), Some(mdoc.doc_size("test").unwrap()));
+        }
+    }
+
+    #[test]
+    fn block_merging_disabled() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let d1 = d1.with_block_merging(false);
+        let (d2, _dir2) = multi_doc(2);
+        let d2 = d2.with_block_merging(false);
+        let (d3, _dir3) = multi_doc(3);
+
+        for (doc, word) in [(&d1, "hello"), (&d2, "world")] {
+            for c in word.chars() {
+                let mut tx = doc.transact_mut("test").unwrap();
+                txt.mount_mut(&mut tx).unwrap().push(c.to_string()).unwrap();
+                tx.commit(None).unwrap();
+            }
+            let mut tx = doc.transact_mut("test").unwrap();
+            txt.mount_mut(&mut tx).unwrap().insert(2, "--").unwrap();
+            tx.commit(None).unwrap();
+        }
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let mut t3 = d3.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2, &mut t3]);
+        t1.commit(None).unwrap();
+        t2.commit(None).unwrap();
+        t3.commit(None).unwrap();
+
+        let string_blocks = |doc: &MultiDoc| {
+            let tx = doc.transact("test").unwrap();
+            let content = txt.mount(&tx).unwrap().to_string();
+            let blocks = tx
+                .db
+                .get()
+                .blocks()
+                .iter_content(ContentType::String)
+                .count();
+            (content, blocks)
+        };
+        let (c1, b1) = string_blocks(&d1);
+        let (c2, b2) = string_blocks(&d2);
+        let (c3, b3) = string_blocks(&d3);
+        assert_eq!(c1, c2);
+        assert_eq!(c2, c3);
+        assert_eq!(c1.len(), 14);
+        // every pushed character and insert stays a distinct block
+        assert_eq!(b1, 12);
+        assert_eq!(b2, 12);
+        assert!(
+            b3 < b1,
+            "merging enabled peer should have fewer blocks: {b3}"
+        );
+    }
+
+    #[test]
+    fn delete_set_since() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+
+        let (mdoc, _dir) = multi_doc(1);
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "hello").unwrap();
+        t.remove_range(0..1).unwrap();
+        tx.commit(None).unwrap();
+
+        let tx = mdoc.transact("test").unwrap();
+        let snapshot = tx.snapshot_committed().unwrap();
+        assert!(tx.delete_set_since(&snapshot).unwrap().is_empty());
+        drop(tx);
+
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().remove_range(1..3).unwrap();
+
+        let mut expected = IDSet::default();
+        expected.insert(ID::new(1.into(), 2.into()), 2.into());
+        assert!(tx.delete_set_since(&snapshot).unwrap() == expected);
+        tx.commit(None).unwrap();
+
+        let tx = mdoc.transact("test").unwrap();
+        assert!(tx.delete_set_since(&snapshot).unwrap() == expected);
+    }
+
+    #[test]
+    fn missing_blocks_since() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().push("abc").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2]);
+        txt.mount_mut(&mut t2).unwrap().push("def").unwrap();
+        txt.mount_mut(&mut t2).unwrap().remove_range(0..1).unwrap();
+
+        let update = t2.missing_blocks(&t1.state_vector().unwrap()).unwrap();
+        let clients: Vec<_> = update.blocks.keys().copied().collect();
+        assert_eq!(clients, vec![ClientID::from(2)]);
+        assert!(update.delete_set.contains(&ID::new(1.into(), 0.into())));
+
+        t1.merge_from(&t2).unwrap();
+        assert_eq!(txt.mount(&t1).unwrap().to_string(), "bcdef");
+    }
+
+    #[test]
+    fn apply_truncated_update() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let mut tx = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        let update = tx
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        drop(tx);
+        // cut off the delete set
+        let truncated = &update[..update.len() - 1];
+
+        let (d2, _dir2) = multi_doc(2);
+        let mut tx = d2.transact_mut("test").unwrap();
+        assert!(tx.apply_update(truncated, Encoding::V1).is_err());
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "");
+        assert!(tx.state_vector().unwrap().is_empty());
+
+        // streamed updates are integrated until the malformed part is reached
+        assert!(tx.apply_update_from(truncated, Encoding::V1).is_err());
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+    }
+
+    #[test]
+    fn wrong_document_update() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+
+        let mut tx = d1.transact_mut("test").unwrap();
+        tx.set_guid("doc-a").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        let update = tx
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        let guid = tx.guid().unwrap().unwrap();
+        tx.commit(None).unwrap();
+        let tagged = crate::tag_update(&guid, &update);
+        assert_eq!(
+            crate::untag_update(&tagged).unwrap(),
+            (Some("doc-a"), update.as_slice())
+        );
+        assert_eq!(
+            crate::untag_update(&update).unwrap(),
+            (None, update.as_slice())
+        );
+
+        let mut tx = d2.transact_mut("test").unwrap();
+        tx.set_guid("doc-b").unwrap();
+        let err = tx.apply_update(&tagged, Encoding::V1).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WrongDocument { expected, actual } if expected == "doc-b" && actual == "doc-a"
+        ));
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "");
+
+        // untagged updates are accepted by any document
+        tx.apply_update(&update, Encoding::V1).unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+        tx.commit(None).unwrap();
+
+        // tagged updates are accepted by matching documents and by ones without GUID
+        for guid in [Some("doc-a"), None] {
+            let (d3, _dir3) = multi_doc(3);
+            let mut tx = d3.transact_mut("test").unwrap();
+            if let Some(guid) = guid {
+                tx.set_guid(guid).unwrap();
+            }
+            tx.apply_update(&tagged, Encoding::V1).unwrap();
+            assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+        }
+    }
+
+    #[test]
+    fn wrong_document_update_all_entry_points() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _dir1) = multi_doc(1);
+        let mut tx = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut tx).unwrap().insert(0, "hello").unwrap();
+        let sv = StateVector::default();
+        let v1 = crate::tag_update("doc-a", &tx.diff_update(&sv, Encoding::V1).unwrap());
+        let v2 = crate::tag_update("doc-a", &tx.diff_update(&sv, Encoding::V2).unwrap());
+        drop(tx);
+
+        let (d2, _dir2) = multi_doc(2);
+        let mut tx = d2.transact_mut("test").unwrap();
+        tx.set_guid("doc-b").unwrap();
+        let results = [
+            tx.apply_update(&v2, Encoding::V2),
+            tx.apply_update_from(v1.as_slice(), Encoding::V1),
+            tx.apply_update_from(v2.as_slice(), Encoding::V2),
+            tx.apply_update_with(&mut DecoderV1::from_slice(&v1)),
+            tx.apply_update_with(&mut DecoderV2::from_slice(&v2).unwrap()),
+            tx.apply_update_filtered(&mut DecoderV1::from_slice(&v1), |_| true),
+        ];
+        for result in results {
+            assert!(matches!(result, Err(Error::WrongDocument { .. })));
+        }
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "");
+        drop(tx);
+
+        // tagged updates are decoded by every entry point of a matching document
+        for version in [Encoding::V1, Encoding::V2] {
+            let tagged = if version == Encoding::V1 { &v1 } else { &v2 };
+            let (d3, _dir3) = multi_doc(3);
+            let mut tx = d3.transact_mut("test").unwrap();
+            tx.set_guid("doc-a").unwrap();
+            tx.apply_update_from(tagged.as_slice(), version).unwrap();
+            assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+        }
+        let (d4, _dir4) = multi_doc(4);
+        let mut tx = d4.transact_mut("test").unwrap();
+        tx.apply_update_filtered(&mut DecoderV1::from_slice(&v1), |_| true)
+            .unwrap();
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+    }
+}
@@ -1,7 +1,13 @@
+use crate::content::ContentType;
+use crate::de::Materialize;
 use crate::lib0::Value;
+use crate::lmdb::Database;
 use crate::node::NodeType;
 use crate::types::Capability;
-use crate::{ListRef, MapRef, Mounted, TextRef, Transaction};
+use crate::{
+    Block, List, ListRef, Map, MapRef, Mounted, Text, TextRef, Transaction, Unmounted, XmlElement,
+    XmlElementRef, XmlFragment, XmlFragmentRef, XmlText, XmlTextRef,
+};
 
 pub type DynRef<Txn> = Mounted<Dyn, Txn>;
 
@@ -35,9 +41,82 @@ impl<'tx, 'db> DynRef<&'tx Transaction<'db>> {
                 let text: TextRef<_> = Mounted::new(self.block.clone(), self.tx);
                 Ok(Value::String(text.to_string()))
             }
-            NodeType::XmlFragment | NodeType::XmlElement | NodeType::XmlText => {
-                unimplemented!();
+            NodeType::XmlFragment => {
+                let xml: XmlFragmentRef<_> = Mounted::new(self.block.clone(), self.tx);
+                Ok(Value::String(xml.to_xml()?))
             }
+            NodeType::XmlElement => {
+                let xml: XmlElementRef<_> = Mounted::new(self.block.clone(), self.tx);
+                Ok(Value::String(xml.to_xml()?))
+            }
+            NodeType::XmlText => {
+                let xml: XmlTextRef<_> = Mounted::new(self.block.clone(), self.tx);
+                Ok(Value::String(xml.to_string()))
+            }
+        }
+    }
+}
+
+/// Handle to a nested node, which capability is resolved from the node type stored in its block.
+/// Unlike [DynRef::to_value], obtaining it doesn't read any of the node's contents: it can be
+/// mounted later on to read only the parts that are needed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnmountedNode {
+    List(Unmounted<List>),
+    Map(Unmounted<Map>),
+    Text(Unmounted<Text>),
+    XmlFragment(Unmounted<XmlFragment>),
+    XmlElement(Unmounted<XmlElement>),
+    XmlText(Unmounted<XmlText>),
+    /// Node which type has not been established yet.
+    Unknown(Unmounted<Dyn>),
+}
+
+impl UnmountedNode {
+    pub fn node_type(&self) -> NodeType {
+        match self {
+            UnmountedNode::List(_) => NodeType::List,
+            UnmountedNode::Map(_) => NodeType::Map,
+            UnmountedNode::Text(_) => NodeType::Text,
+            UnmountedNode::XmlFragment(_) => NodeType::XmlFragment,
+            UnmountedNode::XmlElement(_) => NodeType::XmlElement,
+            UnmountedNode::XmlText(_) => NodeType::XmlText,
+            UnmountedNode::Unknown(_) => NodeType::Unknown,
+        }
+    }
+}
+
+impl Materialize for UnmountedNode {
+    fn materialize<'tx, 'db>(block: Block<'tx>, _: &'tx Database<'db>) -> crate::Result<Self> {
+        if block.is_deleted() {
+            return Err(crate::Error::NotFound);
+        }
+        if block.content_type() != ContentType::Node {
+            return Err(crate::Error::InvalidMapping("node"));
+        }
+        let id = *block.id();
+        let node_type = block.node_type().copied().unwrap_or_default();
+        Ok(match node_type {
+            NodeType::List => UnmountedNode::List(Unmounted::nested(id)),
+            NodeType::Map => UnmountedNode::Map(Unmounted::nested(id)),
+            NodeType::Text => UnmountedNode::Text(Unmounted::nested(id)),
+            NodeType::XmlFragment => UnmountedNode::XmlFragment(Unmounted::nested(id)),
+            NodeType::XmlElement => UnmountedNode::XmlElement(Unmounted::nested(id)),
+            NodeType::XmlText => UnmountedNode::XmlText(Unmounted::nested(id)),
+            NodeType::Unknown => UnmountedNode::Unknown(Unmounted::nested(id)),
+        })
+    }
+
+    fn materialize_fragment<'tx, 'db>(
+        block: Block<'tx>,
+        db: &'tx Database<'db>,
+        offset: usize,
+    ) -> crate::Result<Self> {
+        if offset == 0 {
+            // node blocks are never merged, so they always have a length of 1
+            Self::materialize(block, db)
+        } else {
+            Err(crate::Error::OutOfRange)
         }
     }
 }
@@ -5,12 +5,14 @@ use crate::lmdb::Database;
 use crate::node::{Node, NodeType};
 use crate::prelim::Prelim;
 use crate::store::Db;
-use crate::store::block_store::SplitResult;
+use crate::store::block_store::{BlockPrefetch, SplitResult};
 use crate::transaction::{TxMutScope, TxScope};
-use crate::types::Capability;
+use crate::types::{Capability, clock_from_index};
 use crate::{
-    BlockMut, Clock, DynRef, ID, In, Mounted, Optional, Out, Prepare, Transaction, Unmounted, lib0,
+    BlockMut, ClientID, Clock, DynRef, ID, In, Mounted, Optional, Out, Prepare, Transaction,
+    Unmounted, lib0,
 };
+use serde::Serialize;
 use std::collections::Bound;
 use std::ops::{Deref, DerefMut, RangeBounds};
 
@@ -27,6 +29,17 @@ impl Capability for List {
 
 impl<'tx, 'db> ListRef<&'tx Transaction<'db>> {
     pub fn get<T>(&self, index: usize) -> crate::Result<T>
+    where
+        T: Materialize,
+    {
+        let (_, value) = self.get_with_id(index)?;
+        Ok(value)
+    }
+
+    /// Returns an element at a given `index` together with its unique [ID]. Unlike an index,
+    /// an element ID doesn't change when other elements are inserted or removed, so it can be
+    /// used to refer to that element across concurrent updates.
+    pub fn get_with_id<T>(&self, index: usize) -> crate::Result<(ID, T)>
     where
         T: Materialize,
     {
@@ -41,7 +54,9 @@ impl<'tx, 'db> ListRef<&'tx Transaction<'db>> {
                 if !block.is_deleted() && block.is_countable() {
                     let block_len = block.clock_len().get() as usize;
                     if block_len > remaining {
-                        return T::materialize_fragment(block, &db, remaining);
+                        let id = block.id().add(Clock::new(remaining as u32));
+                        let value = T::materialize_fragment(block, &db, remaining)?;
+                        return Ok((id, value));
                     }
                     remaining -= block_len;
                 }
@@ -61,6 +76,12 @@ impl<'tx, 'db> ListRef<&'tx Transaction<'db>> {
         self.block.node_len()
     }
 
+    /// Checks if this list has no elements. Length of a list is kept up to date in its node
+    /// block, so this check doesn't need to traverse the list elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn iter<T>(&self) -> Iter<'_, T>
     where
         T: Materialize,
@@ -68,6 +89,34 @@ impl<'tx, 'db> ListRef<&'tx Transaction<'db>> {
         Iter::new(self.tx, self.block.start().copied())
     }
 
+    /// Returns an index of the first element equal to a given `value` or `None` if there's no
+    /// such element. Nested collections are compared using their materialized [Value].
+    ///
+    /// This method walks over list elements, so its cost is linear to the list length.
+    pub fn index_of<T>(&self, value: &T) -> crate::Result<Option<usize>>
+    where
+        T: Serialize + ?Sized,
+    {
+        // pass the value through lib0 encoding, so that it's represented the same way as
+        // values read from the list
+        let expected: Value = lib0::from_slice(&lib0::to_vec(value)?)?;
+        for (index, element) in self.iter::<Value>().enumerate() {
+            if element? == expected {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checks if any of this list elements is equal to a given `value`.
+    /// See [ListRef::index_of] for details.
+    pub fn contains<T>(&self, value: &T) -> crate::Result<bool>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(self.index_of(value)?.is_some())
+    }
+
     pub fn to_value(&self) -> crate::Result<Value> {
         let mut buf = Vec::new();
         let iter = self.iter::<crate::Out>();
@@ -92,7 +141,7 @@ impl<'tx, 'db> ListRef<&'tx mut Transaction<'db>> {
         start: Option<ID>,
         index: usize,
     ) -> crate::Result<(Option<ID>, Option<ID>)> {
-        let mut remaining = Clock::new(index as u32);
+        let mut remaining = clock_from_index(index)?;
         let mut left: Option<ID> = None;
         let mut right: Option<ID> = start;
 
@@ -100,17 +149,19 @@ impl<'tx, 'db> ListRef<&'tx mut Transaction<'db>> {
             && remaining > Clock::new(0)
         {
             let block = ctx.cursor.seek(id)?;
-            if block.clock_len() > remaining {
-                let id = block.id();
-                left = Some(ID::new(id.client, id.clock + remaining));
-                right = Some(ID::new(id.client, id.clock + remaining + 1));
-                remaining = Clock::new(0);
-                break;
-            } else {
+            if !block.is_deleted() && block.is_countable() {
+                if block.clock_len() > remaining {
+                    // index points inside of this block: it will be split at `remaining` offset
+                    let id = block.id();
+                    left = Some(ID::new(id.client, id.clock + remaining - Clock::new(1)));
+                    right = Some(ID::new(id.client, id.clock + remaining));
+                    remaining = Clock::new(0);
+                    break;
+                }
                 remaining -= block.clock_len();
-                left = Some(block.last_id());
-                right = block.right().copied();
             }
+            left = Some(block.last_id());
+            right = block.right().copied();
         }
 
         if remaining != 0 {
@@ -120,31 +171,66 @@ impl<'tx, 'db> ListRef<&'tx mut Transaction<'db>> {
         Ok((left, right))
     }
 
+    /// Inserts a `value` at a given `index`. Indexes past the end of the list are clamped, so
+    /// that the value is appended. Use [ListRef::try_insert] to fail on such indexes instead.
+    ///
+    /// Returns [crate::Error::OutOfRange] only if `index` doesn't fit into the clock range.
     pub fn insert<T>(&mut self, index: usize, value: T) -> crate::Result<T::Return>
     where
         T: Prelim,
     {
+        let index = self.clamp_index(index)?;
+        self.ensure_node_type()?;
         let mut ctx = self.tx.write_context()?;
-        let start = self.block.start().copied();
-        let (left, right) = Self::seek(&mut ctx, start, index)?;
-
-        let (_, result) = InsertBlockData::insert_block(
-            &mut ctx,
-            &mut self.block,
-            left.as_ref(),
-            right.as_ref(),
-            None,
-            value,
-        )?;
+        Self::insert_internal(&mut self.block, &mut ctx, index, value)
+    }
+
+    /// Inserts a `value` at a given `index`. Unlike [ListRef::insert], returns
+    /// [crate::Error::OutOfRange] if `index` is greater than the length of the list.
+    pub fn try_insert<T>(&mut self, index: usize, value: T) -> crate::Result<T::Return>
+    where
+        T: Prelim,
+    {
+        if index > self.len() {
+            return Err(crate::Error::OutOfRange);
+        }
+        self.insert(index, value)
+    }
+
+    fn clamp_index(&self, index: usize) -> crate::Result<usize> {
+        clock_from_index(index)?;
+        Ok(index.min(self.len()))
+    }
+
+    /// Inserts a single `value` at a given `index` of a sequence of elements of a `block` node.
+    /// Used by other sequence-like node types, which share the same list semantics.
+    pub(crate) fn insert_internal<T>(
+        block: &mut BlockMut,
+        tx: &mut TxMutScope<'_>,
+        index: usize,
+        value: T,
+    ) -> crate::Result<T::Return>
+    where
+        T: Prelim,
+    {
+        let start = block.start().copied();
+        let (left, right) = Self::seek(tx, start, index)?;
+
+        let (_, result) =
+            InsertBlockData::insert_block(tx, block, left.as_ref(), right.as_ref(), None, value)?;
 
         Ok(result)
     }
 
+    /// Inserts all `values` starting at a given `index`. Like [ListRef::insert], indexes past
+    /// the end of the list are clamped.
     pub fn insert_range<T, I>(&mut self, index: usize, values: I) -> crate::Result<()>
     where
         T: Prelim,
         I: IntoIterator<Item = T>,
     {
+        let index = self.clamp_index(index)?;
+        self.ensure_node_type()?;
         let mut tx = self.tx.write_context()?;
         Self::insert_range_internal(&mut self.block, &mut tx, index, values)
     }
@@ -188,6 +274,16 @@ impl<'tx, 'db> ListRef<&'tx mut Transaction<'db>> {
         self.insert(0, value)
     }
 
+    /// Removes the oldest (front) elements of this list, so that at most `keep` last elements
+    /// remain. Elements are removed in a single range deletion.
+    pub fn truncate_front(&mut self, keep: usize) -> crate::Result<()> {
+        let len = self.len();
+        if len > keep {
+            self.remove_range(0..len - keep)?;
+        }
+        Ok(())
+    }
+
     pub fn remove(&mut self, index: usize) -> crate::Result<()> {
         //TODO: optimize?
         self.remove_range(index..index + 1)
@@ -211,6 +307,7 @@ impl<'tx, 'db> ListRef<&'tx mut Transaction<'db>> {
         if start > end {
             return Ok(());
         }
+        clock_from_index(end)?;
 
         let mut to_delete = end - start + 1;
 
@@ -226,7 +323,7 @@ impl<'tx, 'db> ListRef<&'tx mut Transaction<'db>> {
             if !block.is_deleted() && block.is_countable() {
                 let block_len = block.clock_len().get() as usize;
                 if block_len > start {
-                    match tx.cursor.split_current(Clock::new(start as u32))? {
+                    match tx.cursor.split_current(clock_from_index(start)?)? {
                         SplitResult::Unchanged(left) => left,
                         SplitResult::Split(left, _right) => left,
                     };
@@ -248,7 +345,7 @@ impl<'tx, 'db> ListRef<&'tx mut Transaction<'db>> {
                 let mut block: BlockMut = block.into();
                 let block_len = block.clock_len().get() as usize;
                 if block_len > to_delete {
-                    block = match tx.cursor.split_current(Clock::new(to_delete as u32))? {
+                    block = match tx.cursor.split_current(clock_from_index(to_delete)?)? {
                         SplitResult::Unchanged(left) => left,
                         SplitResult::Split(left, _) => left,
                     };
@@ -296,6 +393,7 @@ enum IterState<'a> {
     },
     Init {
         db: Database<'a>,
+        blocks: BlockPrefetch<'a>,
         current: BlockMut,
         offset: usize,
     },
@@ -313,7 +411,12 @@ where
         }
     }
 
-    fn move_next(&mut self) -> crate::Result<Option<T>> {
+    /// Turns this iterator into one, which also returns the client that inserted each element.
+    pub fn with_authors(self) -> WithAuthors<'a, T> {
+        WithAuthors(self)
+    }
+
+    fn move_next(&mut self) -> crate::Result<Option<(ClientID, T)>> {
         match &mut self.state {
             IterState::Uninit { tx, start } => {
                 let start = match start {
@@ -321,7 +424,7 @@ where
                     Some(id) => *id,
                 };
                 let db = tx.db.get();
-                let blocks = db.blocks();
+                let mut blocks = db.blocks().prefetch()?;
                 let mut current = blocks.get(start)?;
                 while current.is_deleted() {
                     match current.right() {
@@ -331,40 +434,45 @@ where
                         }
                     }
                 }
-                let result = T::materialize_fragment(current, &db, 0)?;
                 self.state = IterState::Init {
                     db,
+                    blocks,
                     current: current.into(),
-                    offset: 1,
+                    offset: 0,
                 };
-                Ok(Some(result))
+                self.move_next()
             }
             IterState::Init {
                 db,
+                blocks,
                 current,
                 offset,
-            } => {
+            } => loop {
                 while current.is_deleted() || *offset >= current.clock_len().get() as usize {
                     // jump to next block
                     match current.right() {
                         None => return self.finish(),
                         Some(&right) => {
-                            let blocks = db.blocks();
                             *current = blocks.get(right)?.into();
                             *offset = 0;
                         }
                     }
                 }
 
-                let value = T::materialize_fragment(current.as_block(), db, *offset)?;
+                let result = T::materialize_fragment(current.as_block(), db, *offset);
                 *offset += 1;
-                Ok(Some(value))
-            }
+                match result {
+                    Ok(value) => return Ok(Some((current.id().client, value))),
+                    // element with missing content is skipped, not to abort the whole iteration
+                    Err(crate::Error::ContentNotFound(_)) => continue,
+                    Err(e) => return Err(e),
+                }
+            },
             IterState::Finished => Ok(None),
         }
     }
 
-    fn finish(&mut self) -> crate::Result<Option<T>> {
+    fn finish(&mut self) -> crate::Result<Option<(ClientID, T)>> {
         self.state = IterState::Finished;
         Ok(None)
     }
@@ -378,13 +486,28 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.move_next() {
-            Ok(Some(value)) => Some(Ok(value)),
+            Ok(Some((_, value))) => Some(Ok(value)),
             Ok(None) => None,
             Err(err) => Some(Err(err)),
         }
     }
 }
 
+/// Iterator over list elements together with the clients that inserted them,
+/// created with [Iter::with_authors].
+pub struct WithAuthors<'a, T>(Iter<'a, T>);
+
+impl<'a, T> Iterator for WithAuthors<'a, T>
+where
+    T: for<'b> Materialize,
+{
+    type Item = crate::Result<(ClientID, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.move_next().transpose()
+    }
+}
+
 #[repr(transparent)]
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct ListPrelim(Vec<In>);
@@ -430,10 +553,196 @@ impl From<Vec<In>> for ListPrelim {
 #[cfg(test)]
 mod test {
     use crate::lib0::{Encoding, Value};
+    use crate::store::Db;
     use crate::test_util::{multi_doc, sync};
-    use crate::{In, List, MapPrelim, Optional, StateVector, Transaction, Unmounted, lib0};
+    use crate::{
+        ClientID, ID, In, List, MapPrelim, Optional, StateVector, Text, TextPrelim, Transaction,
+        Unmounted, lib0,
+    };
     use std::collections::BTreeMap;
 
+    /// Returns clock lengths of all blocks of a given root `list`, in their sequence order.
+    fn block_lengths(tx: &Transaction, list: &Unmounted<List>) -> Vec<u32> {
+        let db = tx.db.get();
+        let blocks = db.blocks();
+        let mut next = blocks.get(list.node_id()).unwrap().start().copied();
+        let mut lengths = Vec::new();
+        while let Some(id) = next {
+            let block = blocks.get(id).unwrap();
+            lengths.push(block.clock_len().get());
+            next = block.right().copied();
+        }
+        lengths
+    }
+
+    #[test]
+    fn iter_with_authors() {
+        let list: Unmounted<List> = Unmounted::root("list");
+        let (d1, _) = multi_doc(1);
+        let (d2, _) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        list.mount_mut(&mut t1).unwrap().push_back(1).unwrap();
+        sync([&mut t1, &mut t2]);
+        let mut l = list.mount_mut(&mut t2).unwrap();
+        l.push_back(2).unwrap();
+        l.push_back(3).unwrap();
+        sync([&mut t1, &mut t2]);
+
+        for tx in [&t1, &t2] {
+            let l = list.mount(tx).unwrap();
+            let items: Vec<(ClientID, i64)> = l.iter().with_authors().map(Result::unwrap).collect();
+            assert_eq!(items, vec![(1.into(), 1), (2.into(), 2), (2.into(), 3)]);
+        }
+    }
+
+    #[test]
+    fn is_empty() {
+        let list: Unmounted<List> = Unmounted::root("list");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+
+        assert!(list.mount(&tx).unwrap().is_empty());
+
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.push_back(1).unwrap();
+        l.push_back(2).unwrap();
+        assert!(!list.mount(&tx).unwrap().is_empty());
+
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.remove(0).unwrap();
+        assert!(!list.mount(&tx).unwrap().is_empty());
+
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.remove(0).unwrap();
+        assert!(list.mount(&tx).unwrap().is_empty());
+    }
+
+    #[test]
+    fn get_with_id() {
+        let list: Unmounted<List> = Unmounted::root("list");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.insert_range(0, ["a", "b", "c"]).unwrap();
+
+        let (id, value): (ID, String) = list.mount(&tx).unwrap().get_with_id(1).unwrap();
+        assert_eq!(id, ID::new(1.into(), 1.into()));
+        assert_eq!(value, "b");
+
+        // inserting unrelated element shifts indexes, but not the element ID
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.insert(0, "x").unwrap();
+        let (moved_id, value): (ID, String) = list.mount(&tx).unwrap().get_with_id(2).unwrap();
+        assert_eq!(moved_id, id);
+        assert_eq!(value, "b");
+
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.remove(3).unwrap();
+        let l = list.mount(&tx).unwrap();
+        assert!(matches!(
+            l.get_with_id::<String>(3),
+            Err(crate::Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn index_of() {
+        let list: Unmounted<List> = Unmounted::root("list");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.push_back("a").unwrap();
+        l.push_back(1.5).unwrap();
+        l.push_back(true).unwrap();
+        let mut m = BTreeMap::new();
+        m.insert("key".to_owned(), In::from("value"));
+        l.push_back(MapPrelim::from(m)).unwrap();
+        l.push_back(2).unwrap();
+        l.push_back("a").unwrap();
+        l.remove(0).unwrap();
+
+        let l = list.mount(&tx).unwrap();
+        assert_eq!(l.index_of(&1.5).unwrap(), Some(0));
+        assert_eq!(l.index_of(&true).unwrap(), Some(1));
+        assert_eq!(l.index_of(&lib0!({"key": "value"})).unwrap(), Some(2));
+        assert_eq!(l.index_of(&2).unwrap(), Some(3));
+        assert_eq!(l.index_of("a").unwrap(), Some(4));
+        assert!(!l.contains(&false).unwrap());
+        assert!(!l.contains(&lib0!({"key": "other"})).unwrap());
+        assert!(l.contains("a").unwrap());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn index_out_of_clock_range() {
+        let list: Unmounted<List> = Unmounted::root("list");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.push_back(1).unwrap();
+
+        let index = u32::MAX as usize + 1;
+        assert!(matches!(l.insert(index, 2), Err(crate::Error::OutOfRange)));
+        assert!(matches!(
+            l.remove_range(index..index + 1),
+            Err(crate::Error::OutOfRange)
+        ));
+        assert_eq!(list.mount(&tx).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn insert_past_the_end() {
+        let list: Unmounted<List> = Unmounted::root("list");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut l = list.mount_mut(&mut tx).unwrap();
+        l.push_back(1).unwrap();
+
+        assert!(matches!(l.try_insert(2, 2), Err(crate::Error::OutOfRange)));
+        assert_eq!(l.len(), 1);
+
+        l.try_insert(1, 2).unwrap();
+        l.insert(100, 3).unwrap();
+        l.insert_range(100, [4, 5]).unwrap();
+        assert_eq!(l.to_value().unwrap(), lib0!([1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn merged_block_content_limit() {
+        let arr: Unmounted<List> = Unmounted::root("type");
+        let values: Vec<_> = (0..20).map(|i| format!("value-{i:02}")).collect();
+
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        arr.mount_mut(&mut tx)
+            .unwrap()
+            .insert_range(0, values.clone())
+            .unwrap();
+        tx.commit(None).unwrap();
+        let tx = doc.transact("test").unwrap();
+        assert_eq!(block_lengths(&tx, &arr), vec![20]);
+        drop(tx);
+
+        // each value takes 10 bytes of lib0 encoded content
+        let (doc, _dir) = multi_doc(1);
+        let doc = doc.with_max_block_content_len(45);
+        let mut tx = doc.transact_mut("test").unwrap();
+        arr.mount_mut(&mut tx)
+            .unwrap()
+            .insert_range(0, values.clone())
+            .unwrap();
+        tx.commit(None).unwrap();
+        let tx = doc.transact("test").unwrap();
+        assert_eq!(block_lengths(&tx, &arr), vec![4, 4, 4, 4, 4]);
+        let expected: Vec<_> = values.into_iter().map(Value::from).collect();
+        assert_eq!(
+            arr.mount(&tx).unwrap().to_value().unwrap(),
+            Value::from(expected)
+        );
+    }
+
     #[test]
     fn push_back() {
         let arr: Unmounted<List> = Unmounted::root("type");
@@ -491,6 +800,57 @@ mod test {
         tx.commit(None).unwrap();
     }
 
+    #[test]
+    fn insert_into_merged_block_after_deleted() {
+        let arr: Unmounted<List> = Unmounted::root("type");
+
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        arr.mount_mut(&mut tx)
+            .unwrap()
+            .insert_range(0, ["a", "b", "c"])
+            .unwrap();
+        tx.commit(None).unwrap();
+
+        // insert in the middle of a block, which must be split
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut a = arr.mount_mut(&mut tx).unwrap();
+        a.insert(1, "x").unwrap();
+        // deleted elements must not be counted
+        a.remove(0).unwrap();
+        a.insert(1, "y").unwrap();
+
+        let actual: Vec<_> = a.iter::<String>().map(Result::unwrap).collect();
+        assert_eq!(
+            actual,
+            vec!["x".to_owned(), "y".into(), "b".into(), "c".into()]
+        );
+    }
+
+    #[test]
+    fn truncate_front() {
+        let arr: Unmounted<List> = Unmounted::root("type");
+
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut a = arr.mount_mut(&mut tx).unwrap();
+        a.insert_range(0, (0..1000).map(|i| i as f64)).unwrap();
+
+        a.truncate_front(100).unwrap();
+        assert_eq!(a.len(), 100);
+        let actual: Vec<f64> = a.iter().map(Result::unwrap).collect();
+        let expected: Vec<f64> = (900..1000).map(|i| i as f64).collect();
+        assert_eq!(actual, expected);
+
+        // truncating to a bigger size is no-op
+        a.truncate_front(200).unwrap();
+        assert_eq!(a.len(), 100);
+        tx.commit(None).unwrap();
+
+        let tx = doc.transact("test").unwrap();
+        assert_eq!(arr.mount(&tx).unwrap().len(), 100);
+    }
+
     #[test]
     fn basic() {
         let arr: Unmounted<List> = Unmounted::root("type");
@@ -677,6 +1037,84 @@ mod test {
         assert_eq!(a2, a3, "Peer 2 and peer 3 states are different");
     }
 
+    /// Returns all permutations of a given `items`.
+    fn permutations<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+        if items.len() <= 1 {
+            return vec![items.to_vec()];
+        }
+        let mut result = Vec::new();
+        for i in 0..items.len() {
+            let mut rest = items.to_vec();
+            let head = rest.remove(i);
+            for mut tail in permutations(&rest) {
+                tail.insert(0, head.clone());
+                result.push(tail);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn concurrent_insert_tiebreak_by_client_id() {
+        let arr: Unmounted<List> = Unmounted::root("array");
+        // client IDs are deliberately not in the order in which peers are created
+        let clients: [u32; 4] = [7, 2, 11, 5];
+
+        let (base, _base_dir) = multi_doc(100);
+        let base_update = {
+            let mut txn = base.transact_mut("test").unwrap();
+            arr.mount_mut(&mut txn)
+                .unwrap()
+                .insert_range(0, ["a", "b"])
+                .unwrap();
+            txn.commit(None).unwrap();
+            let txn = base.transact("test").unwrap();
+            txn.diff_update(&StateVector::default(), Encoding::V1)
+                .unwrap()
+        };
+
+        // every peer concurrently inserts its own element between "a" and "b"
+        let mut updates = Vec::new();
+        let mut _dirs = Vec::new();
+        for &client in clients.iter() {
+            let (doc, dir) = multi_doc(client);
+            let mut txn = doc.transact_mut("test").unwrap();
+            txn.apply_update(&base_update, Encoding::V1).unwrap();
+            txn.commit(None).unwrap();
+            let mut txn = doc.transact_mut("test").unwrap();
+            let sv = txn.state_vector().unwrap();
+            arr.mount_mut(&mut txn)
+                .unwrap()
+                .insert(1, client.to_string())
+                .unwrap();
+            updates.push(txn.diff_update(&sv, Encoding::V1).unwrap());
+            txn.commit(None).unwrap();
+            _dirs.push(dir);
+        }
+
+        let mut sorted = clients;
+        sorted.sort();
+        let mut expected = vec!["a".to_string()];
+        expected.extend(sorted.iter().map(u32::to_string));
+        expected.push("b".into());
+
+        for order in permutations(&[0, 1, 2, 3]) {
+            let (doc, _dir) = multi_doc(200);
+            let mut txn = doc.transact_mut("test").unwrap();
+            txn.apply_update(&base_update, Encoding::V1).unwrap();
+            for &i in order.iter() {
+                txn.apply_update(&updates[i], Encoding::V1).unwrap();
+            }
+            let actual: Vec<String> = arr
+                .mount(&txn)
+                .unwrap()
+                .iter()
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(actual, expected, "diverged for update order {order:?}");
+        }
+    }
+
     fn to_array(tx: &mut Transaction<'_>) -> Vec<Value> {
         let arr: Unmounted<List> = Unmounted::root("array");
         let a = arr.mount(tx).unwrap();
@@ -915,4 +1353,40 @@ mod test {
             vec![Value::Number(1.into()), Value::Number(2.into())]
         );
     }
+
+    #[test]
+    fn list_of_text_paragraphs() {
+        let arr: Unmounted<List> = Unmounted::root("paragraphs");
+
+        let (d1, _) = multi_doc(1);
+        let (d2, _) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        {
+            let mut array = arr.mount_mut(&mut t1).unwrap();
+            let first = array.push_back(TextPrelim::from("hello")).unwrap();
+            array
+                .push_back(In::from(TextPrelim::from("world")))
+                .unwrap();
+
+            let mut txt = first.mount_mut(&mut t1).unwrap();
+            txt.push(" there").unwrap();
+        }
+
+        let mut t2 = d2.transact_mut("test").unwrap();
+        sync([&mut t1, &mut t2]);
+
+        for tx in [&t1, &t2] {
+            let array = arr.mount(tx).unwrap();
+            assert_eq!(array.len(), 2);
+
+            let paragraphs: Vec<String> = array
+                .iter::<Unmounted<Text>>()
+                .map(|txt| txt.unwrap().mount(tx).unwrap().to_string())
+                .collect();
+            assert_eq!(paragraphs, vec!["hello there", "world"]);
+
+            let second: Unmounted<Text> = array.get(1).unwrap();
+            assert_eq!(second.mount(tx).unwrap().to_string(), "world");
+        }
+    }
 }
@@ -1,19 +1,40 @@
 use crate::block::{BlockMut, ID, InsertBlockData};
+use crate::content::Content;
 use crate::de::Materialize;
 use crate::lmdb::Database;
 use crate::node::{Node, NodeID, NodeType};
-use crate::prelim::Prelim;
+use crate::prelim::{CustomPrelim, Prelim};
 use crate::store::map_entries::{MapEntries, MapKey};
 use crate::store::{Db, MapEntriesStore};
-use crate::transaction::TxMutScope;
+use crate::transaction::{TransactionSummary, TxMutScope};
 use crate::types::Capability;
-use crate::{Clock, Error, In, Mounted, Optional, Prepare, Transaction, Unmounted, lib0};
+use crate::{
+    Clock, Error, In, Mounted, Optional, Out, Prepare, Transaction, Unmounted, UnmountedNode, lib0,
+};
+use bytes::Bytes;
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
 
 pub type MapRef<Txn> = Mounted<Map, Txn>;
 
+/// Custom conflict resolver for concurrent values of the same map entry. It's called with
+/// the map node ID, entry key, currently visible value and the incoming one, and it should
+/// return:
+/// - [Ordering::Greater] if the current value should stay visible,
+/// - [Ordering::Less] if the incoming value should replace it,
+/// - [Ordering::Equal] to fall back to the default last-writer-wins rule.
+///
+/// Values written by peers that have already observed the current value are always treated
+/// as overwrites and don't reach the resolver. To make all peers converge, the resolver must
+/// be deterministic and antisymmetric: swapping `current` and `incoming` must reverse the result.
+///
+/// [Ordering::Greater]: std::cmp::Ordering::Greater
+/// [Ordering::Less]: std::cmp::Ordering::Less
+/// [Ordering::Equal]: std::cmp::Ordering::Equal
+pub type MapResolver = fn(&NodeID, &str, &Content<'_>, &Content<'_>) -> std::cmp::Ordering;
+
 #[derive(Clone, Debug, Default, Eq, Ord, PartialOrd, PartialEq)]
 pub struct Map;
 
@@ -43,6 +64,41 @@ impl<'db, 'tx: 'db> MapRef<&'tx Transaction<'db>> {
         }
     }
 
+    /// Returns a handle to a nested node stored under a given `key`, without reading its contents.
+    /// The capability of returned handle matches the type of the node. Returns
+    /// [Error::InvalidMapping] if the entry is not a node.
+    pub fn get_node<K>(&self, key: K) -> crate::Result<UnmountedNode>
+    where
+        K: AsRef<str>,
+    {
+        self.get(key)
+    }
+
+    /// Returns user type byte and payload of a custom content stored under a given `key` with
+    /// [MapRef::insert_raw]. Returns [Error::InvalidMapping] if the entry contains any other
+    /// kind of value.
+    pub fn get_raw<K>(&self, key: K) -> crate::Result<(u8, Bytes)>
+    where
+        K: AsRef<str>,
+    {
+        let db = self.tx.db.get();
+        let map_entries = db.map_entries();
+        let entry_id = *map_entries
+            .get(self.block.id(), key.as_ref())?
+            .ok_or(Error::NotFound)?;
+        let block = db.blocks().get(entry_id)?;
+        if block.is_deleted() {
+            return Err(Error::NotFound);
+        }
+        let data = match block.try_inline_data() {
+            Some(data) => data,
+            None => db.contents().get(entry_id)?,
+        };
+        let content = Content::new(block.content_type(), Cow::Borrowed(data));
+        let (type_byte, payload) = content.as_custom()?;
+        Ok((type_byte, Bytes::copy_from_slice(payload)))
+    }
+
     pub fn len(&self) -> crate::Result<usize> {
         let db = self.tx.db.get();
         let map_entries = db.map_entries();
@@ -62,6 +118,24 @@ impl<'db, 'tx: 'db> MapRef<&'tx Transaction<'db>> {
         Ok(len)
     }
 
+    /// Checks if this map has no entries. Unlike [MapRef::len], it stops at the first entry
+    /// which value has not been deleted.
+    pub fn is_empty(&self) -> crate::Result<bool> {
+        let db = self.tx.db.get();
+        let map_entries = db.map_entries();
+        let blocks = db.blocks();
+        let mut blocks_cursor = blocks.cursor()?;
+        let mut iter = map_entries.entries(self.node_id());
+        while iter.next()?.is_some() {
+            if let Some(block) = blocks_cursor.seek(*iter.block_id()?).optional()?
+                && !block.is_deleted()
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     pub fn contains_key<K>(&self, key: K) -> crate::Result<bool>
     where
         K: AsRef<str>,
@@ -84,17 +158,132 @@ impl<'db, 'tx: 'db> MapRef<&'tx Transaction<'db>> {
         Iter::new(db, *self.node_id())
     }
 
+    /// Returns an iterator over live entries of this map, which reads them in chunks of up to
+    /// `batch` entries. Every chunk is read using a separate LMDB cursor, which is released before
+    /// the chunk is returned and the next one re-seeks past the last returned key.
+    ///
+    /// Compared to [MapRef::iter], this trades the cost of re-seeking for not keeping a single
+    /// cursor open over the entire iteration of a large map.
+    pub fn iter_chunked(&self, batch: usize) -> ChunkedIter<'tx> {
+        ChunkedIter {
+            db: self.tx.db.get(),
+            node_id: *self.node_id(),
+            batch: batch.max(1),
+            last_key: None,
+            finished: false,
+        }
+    }
+
     pub fn to_value(&self) -> crate::Result<lib0::Value> {
         let mut map = HashMap::default();
         let mut iter = self.iter();
         while let Some(e) = iter.next()? {
+            if e.is_deleted()? {
+                continue;
+            }
             let key = e.key().to_owned();
-            let value: lib0::Value = e.value()?;
+            let value: lib0::Value = match e.value() {
+                Ok(value) => value,
+                // entry with missing content is skipped, not to abort the whole conversion
+                Err(Error::ContentNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
             map.insert(key, value);
         }
 
         Ok(lib0::Value::Object(map))
     }
+
+    /// Returns keys of this map entries, which have been inserted, overwritten or removed by
+    /// a committed transaction, ordered by key. Transaction must have been committed with
+    /// [CommitFlags::OBSERVE_NODES] flag set, otherwise no keys are reported.
+    ///
+    /// Keys are resolved from their hashes, so in case of hash collision, keys which share the hash
+    /// with a changed one are reported as well.
+    ///
+    /// [CommitFlags::OBSERVE_NODES]: crate::CommitFlags::OBSERVE_NODES
+    pub fn changed_keys(&self, summary: &TransactionSummary) -> crate::Result<Vec<String>> {
+        let node_id = *self.node_id();
+        let Some(key_hashes) = summary.changed_keys.get(&node_id) else {
+            return Ok(Vec::new());
+        };
+        let map_entries = self.tx.db.get().map_entries();
+        let mut keys = Vec::new();
+        for &hash in key_hashes {
+            let mut iter = map_entries.keys_for_hash(node_id, hash.into());
+            while let Some((key, _)) = iter.next()? {
+                keys.push(key.to_owned());
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Compares the current state of this map with a `prior` value, previously captured using
+    /// [MapRef::to_value], and returns the list of changed keys ordered by key.
+    ///
+    /// This is a pure value-level comparison, which doesn't take CRDT history into account. If
+    /// `prior` is not an object, it's treated as an empty map.
+    pub fn diff(&self, prior: &lib0::Value) -> crate::Result<Vec<KeyChange>> {
+        let current = match self.to_value()? {
+            lib0::Value::Object(map) => map,
+            _ => HashMap::default(),
+        };
+        let empty = HashMap::default();
+        let prior = match prior {
+            lib0::Value::Object(map) => map,
+            _ => &empty,
+        };
+
+        let mut changes = Vec::new();
+        for (key, old_value) in prior.iter() {
+            match current.get(key) {
+                None => changes.push(KeyChange::Removed {
+                    key: key.clone(),
+                    old_value: old_value.clone(),
+                }),
+                Some(new_value) if new_value != old_value => changes.push(KeyChange::Changed {
+                    key: key.clone(),
+                    old_value: old_value.clone(),
+                    new_value: new_value.clone(),
+                }),
+                Some(_) => { /* unchanged */ }
+            }
+        }
+        for (key, value) in current {
+            if !prior.contains_key(&key) {
+                changes.push(KeyChange::Added { key, value });
+            }
+        }
+        changes.sort_by(|a, b| a.key().cmp(b.key()));
+        Ok(changes)
+    }
+}
+
+/// Value-level change of a single map entry, as reported by [MapRef::diff].
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyChange {
+    /// Entry didn't exist in the prior value.
+    Added { key: String, value: lib0::Value },
+    /// Entry existed in the prior value, but it's no longer present in the map.
+    Removed { key: String, old_value: lib0::Value },
+    /// Entry exists in both, but its value has changed.
+    Changed {
+        key: String,
+        old_value: lib0::Value,
+        new_value: lib0::Value,
+    },
+}
+
+impl KeyChange {
+    /// Returns the key of a changed map entry.
+    pub fn key(&self) -> &str {
+        match self {
+            KeyChange::Added { key, .. } => key,
+            KeyChange::Removed { key, .. } => key,
+            KeyChange::Changed { key, .. } => key,
+        }
+    }
 }
 
 impl<'tx, 'db> MapRef<&'tx mut Transaction<'db>> {
@@ -103,11 +292,27 @@ impl<'tx, 'db> MapRef<&'tx mut Transaction<'db>> {
         K: AsRef<str>,
         V: Prelim,
     {
+        self.ensure_node_type()?;
         let mut tx = self.tx.write_context()?;
         Self::insert_internal(&mut self.block, &mut tx, key.as_ref(), value)
     }
 
-    fn insert_internal<V: Prelim>(
+    /// Inserts a custom content under a given `key`. Content is opaque to this crate: it's stored
+    /// and replicated as a single, non-splittable block tagged with a user-defined `type_byte`,
+    /// which can be read back with [MapRef::get_raw].
+    ///
+    /// Custom content uses [ContentType::Custom], which is not recognized by Yjs peers.
+    ///
+    /// [ContentType::Custom]: crate::ContentType::Custom
+    pub fn insert_raw<K>(&mut self, key: K, type_byte: u8, payload: &[u8]) -> crate::Result<()>
+    where
+        K: AsRef<str>,
+    {
+        let content = Content::custom(type_byte, payload);
+        self.insert(key, CustomPrelim(content))
+    }
+
+    pub(crate) fn insert_internal<V: Prelim>(
         parent: &mut BlockMut,
         tx: &mut TxMutScope<'_>,
         key: &str,
@@ -226,6 +431,13 @@ impl<'a, 'db> Entry<'a, 'db> {
         let block = blocks.get(self.block_id)?;
         T::materialize(block, self.db)
     }
+
+    /// Checks if the current value of this entry has been removed.
+    pub fn is_deleted(&self) -> crate::Result<bool> {
+        let blocks = self.db.blocks();
+        let block = blocks.get(self.block_id)?;
+        Ok(block.is_deleted())
+    }
 }
 
 pub struct Iter<'a> {
@@ -265,6 +477,68 @@ impl<'db> Iter<'db> {
     }
 }
 
+/// Iterator over live map entries returned in owned chunks, created with [MapRef::iter_chunked].
+pub struct ChunkedIter<'db> {
+    db: Database<'db>,
+    node_id: NodeID,
+    batch: usize,
+    last_key: Option<String>,
+    finished: bool,
+}
+
+impl<'db> ChunkedIter<'db> {
+    fn next_chunk(&mut self) -> crate::Result<Vec<(String, Out)>> {
+        let map_entries = self.db.map_entries();
+        let blocks = self.db.blocks();
+        let mut entries = match &self.last_key {
+            None => map_entries.entries(&self.node_id),
+            Some(key) => map_entries.entries_after(&self.node_id, key),
+        };
+        let mut chunk = Vec::with_capacity(self.batch);
+        let mut last_key = None;
+        while chunk.len() < self.batch {
+            let Some(map_key) = entries.next()? else {
+                self.finished = true;
+                break;
+            };
+            let key = map_key.key();
+            last_key = Some(key);
+            let block = blocks.get(*entries.block_id()?)?;
+            if block.is_deleted() {
+                continue;
+            }
+            match Out::materialize(block, &self.db) {
+                Ok(value) => chunk.push((key.to_owned(), value)),
+                // entry with missing content is skipped, not to abort the whole iteration
+                Err(Error::ContentNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        if let Some(key) = last_key {
+            self.last_key = Some(key.to_owned());
+        }
+        Ok(chunk)
+    }
+}
+
+impl<'db> Iterator for ChunkedIter<'db> {
+    type Item = crate::Result<Vec<(String, Out)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.next_chunk() {
+            Ok(chunk) if chunk.is_empty() => None,
+            Ok(chunk) => Some(Ok(chunk)),
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
 #[repr(transparent)]
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct MapPrelim(BTreeMap<String, In>);
@@ -322,13 +596,265 @@ impl FromIterator<(String, In)> for MapPrelim {
 mod test {
     use crate::lib0::{Encoding, Value};
 
+    use crate::content::Content;
+    use crate::node::NodeID;
+    use crate::store::Db;
     use crate::test_util::{multi_doc, sync};
     use crate::{
-        In, List, ListPrelim, ListRef, Map, MapPrelim, Optional, StateVector, Unmounted, lib0,
+        CommitFlags, ContentType, Error, ID, In, KeyChange, List, ListPrelim, ListRef, Map,
+        MapPrelim, MapRef, Mounted, NodeType, Optional, Out, StateVector, TextPrelim, Transaction,
+        TransactionSummary, Unmounted, UnmountedNode, ValueEncoding, lib0,
     };
     use serde::Deserialize;
+    use std::cmp::Ordering;
     use std::collections::HashMap;
 
+    #[test]
+    fn changed_keys() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let other: Unmounted<Map> = Unmounted::root("other");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("a", 1).unwrap();
+        m.insert("b", 2).unwrap();
+        m.insert("c", 3).unwrap();
+        other.mount_mut(&mut tx).unwrap().insert("a", 1).unwrap();
+        tx.commit(None).unwrap();
+
+        let mut summary = TransactionSummary::new(CommitFlags::OBSERVE_NODES);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("a", 10).unwrap();
+        m.remove("c").unwrap();
+        tx.commit(Some(&mut summary)).unwrap();
+
+        let tx = doc.transact("test").unwrap();
+        let changed = map.mount(&tx).unwrap().changed_keys(&summary).unwrap();
+        assert_eq!(changed, vec!["a".to_string(), "c".to_string()]);
+        let changed = other.mount(&tx).unwrap().changed_keys(&summary).unwrap();
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn iter_chunked() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        for i in 0..1000 {
+            m.insert(format!("key-{i}"), i).unwrap();
+        }
+        for i in (0..1000).step_by(7) {
+            m.remove(format!("key-{i}")).unwrap();
+        }
+        tx.commit(None).unwrap();
+
+        let tx = doc.transact("test").unwrap();
+        let m = map.mount(&tx).unwrap();
+        let mut expected = Vec::new();
+        let mut iter = m.iter();
+        while let Some(e) = iter.next().unwrap() {
+            if !e.is_deleted().unwrap() {
+                expected.push((e.key().to_owned(), e.value::<Out>().unwrap()));
+            }
+        }
+        assert_eq!(expected.len(), 1000 - 143);
+
+        let mut actual = Vec::new();
+        for chunk in m.iter_chunked(64) {
+            let chunk = chunk.unwrap();
+            assert!(!chunk.is_empty() && chunk.len() <= 64);
+            actual.extend(chunk);
+        }
+        assert_eq!(actual, expected);
+
+        let chunks: Vec<_> = m.iter_chunked(0).map(|c| c.unwrap().len()).collect();
+        assert_eq!(chunks.len(), expected.len());
+    }
+
+    #[test]
+    fn raw_content_roundtrip() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let large = vec![7u8; 200];
+        let (d1, _) = multi_doc(1);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut m1 = map.mount_mut(&mut t1).unwrap();
+        m1.insert_raw("small", 0xf0, &[1, 2, 3]).unwrap();
+        m1.insert_raw("large", 0xf1, &large).unwrap();
+        m1.insert_raw("empty", 0, &[]).unwrap();
+        m1.insert("plain", 1).unwrap();
+
+        let m1 = map.mount(&t1).unwrap();
+        assert_eq!(m1.get_raw("small").unwrap(), (0xf0, vec![1, 2, 3].into()));
+        assert_eq!(m1.get_raw("large").unwrap(), (0xf1, large.clone().into()));
+        assert_eq!(m1.get_raw("empty").unwrap(), (0, Vec::new().into()));
+        assert!(matches!(
+            m1.get_raw("plain"),
+            Err(Error::InvalidMapping("custom content"))
+        ));
+        assert!(matches!(m1.get_raw("missing"), Err(Error::NotFound)));
+        assert_eq!(m1.len().unwrap(), 4);
+
+        for version in [Encoding::V1, Encoding::V2] {
+            let update = t1.diff_update(&StateVector::default(), version).unwrap();
+            let (d2, _) = multi_doc(2);
+            let mut t2 = d2.transact_mut("test").unwrap();
+            t2.apply_update(&update, version).unwrap();
+            let m2 = map.mount(&t2).unwrap();
+            assert_eq!(m2.get_raw("small").unwrap(), (0xf0, vec![1, 2, 3].into()));
+            assert_eq!(m2.get_raw("large").unwrap(), (0xf1, large.clone().into()));
+            assert_eq!(m2.get_raw("empty").unwrap(), (0, Vec::new().into()));
+        }
+    }
+
+    #[test]
+    fn json_value_encoding() {
+        let map = Unmounted::<Map>::root("map").with_value_encoding(ValueEncoding::Json);
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("number", 1).unwrap();
+        m.insert("object", lib0!({"key": "value"})).unwrap();
+        m.insert("nested", MapPrelim::default()).unwrap();
+
+        let blocks = tx.db.get().blocks();
+        assert_eq!(blocks.iter_content(ContentType::Json).count(), 2);
+        assert_eq!(blocks.iter_content(ContentType::Atom).count(), 0);
+
+        // values are read back regardless of their encoding
+        let m = map.mount(&tx).unwrap();
+        assert_eq!(m.get::<_, i64>("number").unwrap(), 1);
+        assert_eq!(
+            m.get::<_, serde_json::Value>("object").unwrap(),
+            serde_json::json!({"key": "value"})
+        );
+
+        // default encoding is atom
+        let mut m = Unmounted::<Map>::root("map").mount_mut(&mut tx).unwrap();
+        m.insert("atom", true).unwrap();
+        let blocks = tx.db.get().blocks();
+        assert_eq!(blocks.iter_content(ContentType::Atom).count(), 1);
+        assert!(map.mount(&tx).unwrap().get::<_, bool>("atom").unwrap());
+    }
+
+    #[test]
+    fn get_node() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert(
+            "nested",
+            MapPrelim::from_iter([
+                ("name".to_string(), In::from("nested")),
+                (
+                    "items".into(),
+                    In::from(ListPrelim::from(vec![In::from(1)])),
+                ),
+            ]),
+        )
+        .unwrap();
+        m.insert("text", TextPrelim::from("hello")).unwrap();
+        m.insert("plain", 1).unwrap();
+
+        let m = map.mount(&tx).unwrap();
+        let UnmountedNode::Map(nested) = m.get_node("nested").unwrap() else {
+            panic!("expected nested map");
+        };
+        let nested = nested.mount(&tx).unwrap();
+        assert_eq!(nested.get::<_, String>("name").unwrap(), "nested");
+        let items = nested.get_node("items").unwrap();
+        assert_eq!(items.node_type(), NodeType::List);
+
+        let text = m.get_node("text").unwrap();
+        assert_eq!(text.node_type(), NodeType::Text);
+        let UnmountedNode::Text(text) = text else {
+            unreachable!()
+        };
+        assert_eq!(text.mount(&tx).unwrap().to_string(), "hello");
+
+        assert!(matches!(
+            m.get_node("plain"),
+            Err(Error::InvalidMapping("node"))
+        ));
+        assert!(matches!(m.get_node("missing"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn is_empty() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+
+        assert!(map.mount(&tx).unwrap().is_empty().unwrap());
+
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("a", 1).unwrap();
+        m.insert("b", 2).unwrap();
+        assert!(!map.mount(&tx).unwrap().is_empty().unwrap());
+
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.remove("a").unwrap();
+        assert!(!map.mount(&tx).unwrap().is_empty().unwrap());
+
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.remove("b").unwrap();
+        assert!(map.mount(&tx).unwrap().is_empty().unwrap());
+    }
+
+    #[test]
+    fn mount_with_mismatched_type() {
+        let map: Unmounted<Map> = Unmounted::root("foo");
+        let list: Unmounted<List> = Unmounted::root("foo");
+
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        map.mount_mut(&mut tx).unwrap().insert("key", 1).unwrap();
+
+        let result = list.mount_mut(&mut tx);
+        assert!(matches!(
+            result,
+            Err(Error::NodeTypeMismatch {
+                expected: NodeType::List,
+                actual: NodeType::Map
+            })
+        ));
+        let result = list.mount(&tx);
+        assert!(matches!(result, Err(Error::NodeTypeMismatch { .. })));
+
+        // map view is still accessible
+        let m = map.mount(&tx).unwrap();
+        assert_eq!(m.get::<_, i32>("key").unwrap(), 1);
+    }
+
+    #[test]
+    fn insert_into_mismatched_node() {
+        let list: Unmounted<List> = Unmounted::root("list");
+
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        list.mount_mut(&mut tx).unwrap().push_back(1).unwrap();
+
+        // map view forced onto a list node
+        let (block, tx) = list.mount_mut(&mut tx).unwrap().split();
+        let mut m: MapRef<_> = Mounted::new(block, tx);
+        let result = m.insert("key", 2);
+        assert!(matches!(
+            result,
+            Err(Error::NodeTypeMismatch {
+                expected: NodeType::Map,
+                actual: NodeType::List
+            })
+        ));
+
+        // list view forced onto the same node is fine
+        let (block, tx) = m.split();
+        let mut l: ListRef<_> = Mounted::new(block, tx);
+        l.push_back(3).unwrap();
+        assert_eq!(l.to_value().unwrap(), lib0!([1, 3]));
+    }
+
     #[test]
     fn basic() {
         let map: Unmounted<Map> = Unmounted::root("map");
@@ -667,6 +1193,151 @@ mod test {
         }
     }
 
+    /// Returns IDs of all blocks ever assigned to a given map `key`, starting from the current
+    /// map entry and following left pointers until the head of the entry list.
+    fn entry_history(tx: &Transaction, map: &Unmounted<Map>, key: &str) -> Vec<ID> {
+        let db = tx.db.get();
+        let entry = *db.map_entries().get(&map.node_id(), key).unwrap().unwrap();
+        let blocks = db.blocks();
+        let mut cursor = blocks.cursor().unwrap();
+        let mut history = vec![*cursor.seek(entry).unwrap().id()];
+        while let Some(block) = cursor.left().unwrap() {
+            history.push(*block.id());
+        }
+        history.reverse();
+        history
+    }
+
+    #[test]
+    fn map_entry_leftmost_walk() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+
+        let (d1, _) = multi_doc(1);
+        let (d2, _) = multi_doc(2);
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        // client 2 builds up a history of the same key
+        let mut m2 = map.mount_mut(&mut t2).unwrap();
+        for i in 0..5 {
+            m2.insert("key", i).unwrap();
+        }
+        // client 1 concurrently inserts the same key without any history: since it has
+        // a lower client ID, it must be placed in front of the head of client 2 entries
+        map.mount_mut(&mut t1).unwrap().insert("key", 100).unwrap();
+
+        sync([&mut t1, &mut t2]);
+
+        let expected: Vec<_> = std::iter::once(ID::new(1.into(), 0.into()))
+            .chain((0..5).map(|clock| ID::new(2.into(), clock.into())))
+            .collect();
+        for tx in [&t1, &t2] {
+            assert_eq!(entry_history(tx, &map, "key"), expected);
+            let m = map.mount(tx).unwrap();
+            assert_eq!(m.get::<_, i32>("key").unwrap(), 4);
+            assert_eq!(m.len().unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn diff_against_prior_value() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("kept", 1).unwrap();
+        m.insert("changed", "old").unwrap();
+        m.insert("removed", true).unwrap();
+        let prior = m.to_value().unwrap();
+        assert!(map.mount(&tx).unwrap().diff(&prior).unwrap().is_empty());
+
+        let mut m = map.mount_mut(&mut tx).unwrap();
+        m.insert("changed", "new").unwrap();
+        m.remove("removed").unwrap();
+        m.insert("added", 2.5).unwrap();
+
+        let changes = map.mount(&tx).unwrap().diff(&prior).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                KeyChange::Added {
+                    key: "added".into(),
+                    value: Value::from(2.5),
+                },
+                KeyChange::Changed {
+                    key: "changed".into(),
+                    old_value: Value::from("old"),
+                    new_value: Value::from("new"),
+                },
+                KeyChange::Removed {
+                    key: "removed".into(),
+                    old_value: Value::from(true),
+                },
+            ]
+        );
+    }
+
+    fn max_wins(_: &NodeID, _: &str, current: &Content<'_>, incoming: &Content<'_>) -> Ordering {
+        match (current.as_atom::<i64>(), incoming.as_atom::<i64>()) {
+            (Ok(current), Ok(incoming)) => current.cmp(&incoming),
+            _ => Ordering::Equal,
+        }
+    }
+
+    #[test]
+    fn custom_map_resolver() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let (d3, _dir3) = multi_doc(3);
+        let (d1, d2, d3) = (
+            d1.with_map_resolver(max_wins),
+            d2.with_map_resolver(max_wins),
+            d3.with_map_resolver(max_wins),
+        );
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        let mut t3 = d3.transact_mut("test").unwrap();
+
+        // under last-writer-wins the value of the highest client ID would win
+        map.mount_mut(&mut t1).unwrap().insert("a", 10).unwrap();
+        map.mount_mut(&mut t2).unwrap().insert("a", 5).unwrap();
+        map.mount_mut(&mut t3).unwrap().insert("a", 7).unwrap();
+        sync([&mut t1, &mut t2, &mut t3]);
+
+        for tx in [&t1, &t2, &t3] {
+            let m = map.mount(tx).unwrap();
+            assert_eq!(m.get::<_, i64>("a").unwrap(), 10);
+            assert_eq!(m.len().unwrap(), 1);
+        }
+
+        // overwrite of an observed value is not subject to conflict resolution
+        map.mount_mut(&mut t2).unwrap().insert("a", 1).unwrap();
+        sync([&mut t1, &mut t2, &mut t3]);
+
+        for tx in [&t1, &t2, &t3] {
+            assert_eq!(map.mount(tx).unwrap().get::<_, i64>("a").unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn default_map_resolver_is_lww() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        map.mount_mut(&mut t1).unwrap().insert("a", 10).unwrap();
+        map.mount_mut(&mut t2).unwrap().insert("a", 5).unwrap();
+        sync([&mut t1, &mut t2]);
+
+        for tx in [&t1, &t2] {
+            assert_eq!(map.mount(tx).unwrap().get::<_, i64>("a").unwrap(), 5);
+        }
+    }
+
     #[test]
     fn get_value() {
         #[derive(Debug, PartialEq, Deserialize)]
@@ -1,7 +1,7 @@
-use crate::Transaction;
 use crate::block::{BlockMut, ID};
 use crate::node::{Node, NodeID, NodeType};
 use crate::store::Db;
+use crate::{Clock, Transaction};
 use std::borrow::{Borrow, Cow};
 use std::marker::PhantomData;
 
@@ -9,14 +9,32 @@ pub mod dynamic;
 pub mod list;
 pub mod map;
 pub mod text;
+pub mod xml;
 
 pub trait Capability {
     fn node_type() -> NodeType;
 }
 
+/// Encoding used to store values inserted into a collection, like list or map. Reads don't depend
+/// on it: values are decoded according to the content type they were stored with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ValueEncoding {
+    /// Values are stored as [ContentType::Atom] in a compact lib0 binary format.
+    ///
+    /// [ContentType::Atom]: crate::ContentType::Atom
+    #[default]
+    Atom,
+    /// Values are stored as [ContentType::Json], which is more verbose, but can be read by any
+    /// JSON parser.
+    ///
+    /// [ContentType::Json]: crate::ContentType::Json
+    Json,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Unmounted<Cap> {
     node: Node<'static>,
+    encoding: ValueEncoding,
     _capability: PhantomData<Cap>,
 }
 
@@ -24,6 +42,7 @@ impl<Cap> Unmounted<Cap> {
     pub fn new(node: Node<'static>) -> Self {
         Unmounted {
             node,
+            encoding: ValueEncoding::default(),
             _capability: PhantomData,
         }
     }
@@ -32,17 +51,18 @@ impl<Cap> Unmounted<Cap> {
     where
         S: Into<Cow<'static, str>>,
     {
-        Unmounted {
-            node: Node::root_named(name),
-            _capability: PhantomData,
-        }
+        Self::new(Node::root_named(name))
     }
 
     pub fn nested(id: ID) -> Self {
-        Unmounted {
-            node: Node::nested(id),
-            _capability: PhantomData,
-        }
+        Self::new(Node::nested(id))
+    }
+
+    /// Sets a [ValueEncoding] used to store values inserted into this collection, once it has been
+    /// mounted with [Unmounted::mount_mut]. It doesn't affect values of nested collections.
+    pub fn with_value_encoding(mut self, encoding: ValueEncoding) -> Self {
+        self.encoding = encoding;
+        self
     }
 
     pub fn node_id(&self) -> NodeID {
@@ -61,8 +81,23 @@ where
         let block = {
             let db = tx.db.get();
             let blocks = db.blocks();
-            let cursor = blocks.cursor()?;
-            cursor.get_or_insert_node(self.node.clone(), Cap::node_type())?
+            let mut cursor = blocks.cursor()?;
+            let mut block = cursor.get_or_insert_node(self.node.clone(), Cap::node_type())?;
+            Self::check_node_type(&block)?;
+            if self.encoding != ValueEncoding::Atom {
+                let state = tx.state.get_or_init(db);
+                state.value_encodings.insert(*block.id(), self.encoding);
+            } else if let Some(state) = tx.state.get_mut() {
+                state.value_encodings.remove(block.id());
+            }
+            let node_type = Cap::node_type();
+            if block.node_type() == Some(&NodeType::Unknown) && node_type != NodeType::Unknown {
+                // root created by a remote update doesn't know its type yet: assign it now,
+                // so that its block is the same as on the peer which created it locally
+                block.set_node_type(node_type);
+                cursor.update(block.as_block())?;
+            }
+            block
         };
         Ok(Mounted::new(block, tx))
     }
@@ -79,8 +114,25 @@ where
         let blocks = db.blocks();
         let cursor = blocks.cursor()?;
         let block: BlockMut = cursor.get_or_insert_node(self.node.clone(), Cap::node_type())?;
+        Self::check_node_type(&block)?;
         Ok(Mounted::new(block, borrowed))
     }
+
+    /// Checks if the node type of a mounted block matches the capability requested.
+    /// [NodeType::Unknown] on either side is treated as a wildcard.
+    fn check_node_type(block: &BlockMut) -> crate::Result<()> {
+        let expected = Cap::node_type();
+        match block.node_type() {
+            Some(&actual)
+                if expected != NodeType::Unknown
+                    && actual != NodeType::Unknown
+                    && actual != expected =>
+            {
+                Err(crate::Error::NodeTypeMismatch { expected, actual })
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<Cap> From<ID> for Unmounted<Cap> {
@@ -124,3 +176,27 @@ impl<Cap, Txn> Mounted<Cap, Txn> {
         self.tx
     }
 }
+
+impl<Cap: Capability, Txn> Mounted<Cap, Txn> {
+    /// Checks if the node type stored in a mounted block matches the capability, before its
+    /// contents are modified. Unlike mounting, [NodeType::Unknown] is not accepted here.
+    pub(crate) fn ensure_node_type(&self) -> crate::Result<()> {
+        let expected = Cap::node_type();
+        match self.block.node_type() {
+            Some(&actual) if actual == expected => Ok(()),
+            actual => Err(crate::Error::NodeTypeMismatch {
+                expected,
+                actual: actual.copied().unwrap_or(NodeType::Unknown),
+            }),
+        }
+    }
+}
+
+/// Converts a user-provided sequence `index` into [Clock] space, returning
+/// [crate::Error::OutOfRange] instead of silently truncating indexes exceeding `u32::MAX`.
+pub(crate) fn clock_from_index(index: usize) -> crate::Result<Clock> {
+    match u32::try_from(index) {
+        Ok(index) => Ok(Clock::new(index)),
+        Err(_) => Err(crate::Error::OutOfRange),
+    }
+}
@@ -1,21 +1,27 @@
 use crate::block::{ID, InsertBlockData};
-use crate::content::{Content, ContentType};
+use crate::content::{Content, ContentType, FormatAttribute, utf16_to_utf8};
+use crate::de::Materialize;
 use crate::lib0::Value;
+use crate::lmdb::Database;
 use crate::node::{Node, NodeType};
 use crate::prelim::{DeltaPrelim, Prelim, StringPrelim};
 use crate::state_vector::Snapshot;
 use crate::store::Db;
-use crate::store::block_store::{BlockCursor, SplitResult};
+use crate::store::block_store::{BlockCursor, BlockPrefetch, SplitResult};
 use crate::store::content_store::ContentStore;
-use crate::transaction::{TransactionState, TxMutScope, TxScope};
-use crate::types::Capability;
-use crate::{Block, BlockMut, Clock, In, Mounted, Out, Prepare, Transaction, Unmounted, lib0};
+use crate::transaction::{TxMutScope, TxScope};
+use crate::types::{Capability, clock_from_index};
+use crate::{
+    Block, BlockHeader, BlockMut, ClientID, Clock, IDSet, In, Mounted, Optional, Out, Prepare,
+    StateVector, Transaction, Unmounted, lib0,
+};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use smallvec::smallvec;
 use std::borrow::Cow;
 use std::collections::{BTreeMap, Bound};
 use std::fmt::{Display, Formatter};
-use std::ops::{Deref, DerefMut, RangeBounds};
+use std::ops::{ControlFlow, Deref, DerefMut, Range, RangeBounds};
 
 pub type TextRef<Txn> = Mounted<Text, Txn>;
 
@@ -33,12 +39,21 @@ impl<'db, 'tx: 'db> TextRef<&'tx Transaction<'db>> {
         self.block.node_len()
     }
 
+    /// Checks if this text has no characters or embeds. Length of a text is kept up to date in
+    /// its node block, so this check doesn't need to traverse the text blocks.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Returns an iterator over uncommitted changes (deltas) made to this text type
     /// within its current transaction scope.
     pub fn uncommitted(&self) -> Uncommitted<'tx> {
         let tx = self.tx.read_context().unwrap();
-        let state = self.tx.state.get();
-        Uncommitted::new(self.block.start().copied(), tx, state)
+        let changes = self.tx.state.get().map(|state| Changes {
+            since: Cow::Borrowed(&state.begin_state),
+            deleted: Cow::Borrowed(&state.delete_set),
+        });
+        Uncommitted::new(self.block.start().copied(), tx, changes)
     }
 
     /// Returns an iterator over all text and embedded chunks grouped by their applied attributes.
@@ -46,6 +61,29 @@ impl<'db, 'tx: 'db> TextRef<&'tx Transaction<'db>> {
         self.chunks_between(None, None)
     }
 
+    /// Same as [TextRef::chunks], but attributes of each chunk are deserialized into a given
+    /// type `A` through lib0 serde, eg. a struct describing text style used by an editor.
+    pub fn chunks_typed<A>(
+        &self,
+    ) -> impl Iterator<Item = crate::Result<TypedChunk<A>>> + use<'db, 'tx, A>
+    where
+        A: DeserializeOwned,
+    {
+        self.chunks().map(|chunk| {
+            let chunk = chunk?;
+            let attributes = match chunk.attributes {
+                Some(attrs) => Some(lib0::from_value(Value::Object(
+                    attrs.into_iter().collect(),
+                ))?),
+                None => None,
+            };
+            Ok(TypedChunk {
+                insert: chunk.insert,
+                attributes,
+            })
+        })
+    }
+
     /// Returns an iterator over all text and embedded chunks grouped by their applied attributes,
     /// scoped between two provided snapshots.
     pub fn chunks_between<'a>(
@@ -61,6 +99,182 @@ impl<'db, 'tx: 'db> TextRef<&'tx Transaction<'db>> {
 
         Chunks::new(tx, start, from, to)
     }
+
+    /// Returns an iterator over formatting spans of this text. Each span is a range of UTF-16
+    /// indexes together with a set of attributes applied over it. Unformatted ranges are skipped
+    /// and neighboring ranges sharing the same attributes are merged together.
+    pub fn format_spans(&self) -> FormatSpans<'tx> {
+        let tx = self
+            .tx
+            .read_context()
+            .expect("todo: handle errors in format spans iterator creation");
+        let start = self.block.start().copied();
+
+        FormatSpans::new(tx, start)
+    }
+
+    /// Returns the [ID] of an element (character or embed) at a given UTF-16 index, ie. the ID of
+    /// a block containing it, shifted by the element's offset within that block. Unlike inserts,
+    /// this doesn't split any blocks. It can be used to compute relative positions or to find
+    /// an author of the element under the cursor.
+    pub fn id_at(&self, utf16_index: usize) -> crate::Result<ID> {
+        let mut remaining = clock_from_index(utf16_index)?;
+        let mut next = self.block.start().copied();
+        let db = self.tx.db.get();
+        let mut cursor = db.blocks().cursor()?;
+        while let Some(right_id) = next {
+            let block = cursor.seek(right_id)?;
+            next = block.right().copied();
+            if !block.is_countable() || block.is_deleted() {
+                continue;
+            }
+            let len = block.clock_len();
+            if remaining < len {
+                let id = block.id();
+                return Ok(ID::new(id.client, id.clock + remaining));
+            }
+            remaining -= len;
+        }
+        Err(crate::Error::OutOfRange)
+    }
+
+    /// Returns the number of lines in this text, where lines are separated by `\n` characters.
+    /// An empty text has a single, empty line, and so does a text ending with a newline after it.
+    pub fn line_count(&self) -> crate::Result<usize> {
+        let mut count = 1;
+        self.scan_blocks(|_, str, _| {
+            if let Some(str) = str {
+                count += str.bytes().filter(|&b| b == b'\n').count();
+            }
+            ControlFlow::<()>::Continue(())
+        })?;
+        Ok(count)
+    }
+
+    /// Returns the contents of a given zero-based `line`, without its trailing `\n` character.
+    /// Embedded values and nested types are skipped, just like in [Display] implementation.
+    /// Returns [crate::Error::OutOfRange] if text has fewer lines.
+    pub fn line(&self, line: usize) -> crate::Result<String> {
+        let mut current = 0;
+        let mut buf = String::new();
+        self.scan_blocks(|_, str, _| {
+            let Some(mut str) = str else {
+                return ControlFlow::Continue(());
+            };
+            while current < line {
+                match str.find('\n') {
+                    Some(i) => {
+                        str = &str[i + 1..];
+                        current += 1;
+                    }
+                    None => return ControlFlow::Continue(()),
+                }
+            }
+            match str.find('\n') {
+                Some(i) => {
+                    buf.push_str(&str[..i]);
+                    ControlFlow::Break(())
+                }
+                None => {
+                    buf.push_str(str);
+                    ControlFlow::Continue(())
+                }
+            }
+        })?;
+        if current < line {
+            Err(crate::Error::OutOfRange)
+        } else {
+            Ok(buf)
+        }
+    }
+
+    /// Returns an iterator over all lines of this text, without their trailing `\n` characters.
+    /// Text is rendered the same way as by its [Display] implementation, so the number of
+    /// returned lines is equal to [TextRef::line_count].
+    pub fn lines(&self) -> impl Iterator<Item = String> + use<'db, 'tx> {
+        let text = self.to_string();
+        let lines: Vec<String> = text.split('\n').map(String::from).collect();
+        lines.into_iter()
+    }
+
+    /// Calls `f` for every visible block of this text in document order, passing its UTF-16
+    /// index, string content (or `None` for non-string elements) and UTF-16 length. Stops as soon
+    /// as `f` breaks, returning the value it broke with.
+    fn scan_blocks<F, B>(&self, mut f: F) -> crate::Result<Option<B>>
+    where
+        F: FnMut(usize, Option<&str>, usize) -> ControlFlow<B>,
+    {
+        let mut index = 0;
+        let mut next = self.block.start().copied();
+        let db = self.tx.db.get();
+        let mut blocks = db.blocks().prefetch()?;
+        let contents = db.contents();
+        while let Some(right_id) = next {
+            let block = blocks.get(right_id)?;
+            next = block.right().copied();
+            if !block.is_countable() || block.is_deleted() {
+                continue;
+            }
+            let len = block.clock_len().get() as usize;
+            let content = match block.content_type() {
+                ContentType::String => try_get_content(&block, &contents)?,
+                _ => None,
+            };
+            let str = match &content {
+                Some(content) => Some(content.as_str()?),
+                None => None,
+            };
+            if let ControlFlow::Break(result) = f(index, str, len) {
+                return Ok(Some(result));
+            }
+            index += len;
+        }
+        Ok(None)
+    }
+
+    /// Renders the contents of this text as a string, just like its [Display] implementation,
+    /// but instead of skipping embedded values and shared types, it calls `on_embed` for each one
+    /// of them with its UTF-16 index and value. The string returned by `on_embed` is placed in
+    /// the output at the embed's position.
+    pub fn render<F>(&self, mut on_embed: F) -> crate::Result<String>
+    where
+        F: FnMut(usize, Out) -> String,
+    {
+        let mut buf = String::new();
+        let mut index = 0;
+        let mut next = self.block.start().copied();
+        let db = self.tx.db.get();
+        let mut blocks = db.blocks().prefetch()?;
+        let contents = db.contents();
+        while let Some(right_id) = next {
+            let block = blocks.get(right_id)?;
+            next = block.right().cloned();
+            if !block.is_countable() || block.is_deleted() {
+                continue;
+            }
+            match block.content_type() {
+                ContentType::String => {
+                    if let Some(data) = try_get_content(&block, &contents)? {
+                        buf.push_str(data.as_str()?);
+                    }
+                }
+                ContentType::Embed => {
+                    if let Some(content) = try_get_content(&block, &contents)? {
+                        buf.push_str(&on_embed(index, Out::Value(content.as_embed()?)));
+                    }
+                }
+                ContentType::Node => buf.push_str(&on_embed(index, Out::Node(*block.id()))),
+                _ => {
+                    for offset in 0..block.clock_len().get() as usize {
+                        let value = Out::materialize_fragment(block, &db, offset)?;
+                        buf.push_str(&on_embed(index + offset, value));
+                    }
+                }
+            }
+            index += block.clock_len().get() as usize;
+        }
+        Ok(buf)
+    }
 }
 
 /// Individual chunk of data produced when calling [TextRef::chunks]/[TextRef::chunks_between] iterator.
@@ -69,6 +283,17 @@ pub struct Chunk {
     pub insert: Out,
     pub attributes: Option<Box<Attrs>>,
     pub operation: Option<Op>,
+    /// Client which inserted this chunk. Only present if [Chunks::with_authors] was used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<ClientID>,
+}
+
+/// Chunk of data produced by [TextRef::chunks_typed] iterator, with attributes deserialized into
+/// a user-defined type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedChunk<A> {
+    pub insert: Out,
+    pub attributes: Option<A>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -83,14 +308,14 @@ impl Chunk {
             insert: insert.into(),
             attributes: None,
             operation: None,
+            author: None,
         }
     }
 
     pub fn with_attrs(self, attrs: Attrs) -> Self {
         Self {
-            operation: self.operation,
-            insert: self.insert,
             attributes: Some(Box::new(attrs)),
+            ..self
         }
     }
 
@@ -98,27 +323,30 @@ impl Chunk {
         self.operation = Some(op);
         self
     }
+
+    pub fn with_author(mut self, author: ClientID) -> Self {
+        self.author = Some(author);
+        self
+    }
 }
 
 impl<'tx, 'db> Display for TextRef<&'tx Transaction<'db>> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut next = self.block.start().copied();
         let db = self.tx.db.get();
-        let blocks = db.blocks();
-        let mut cursor = blocks.cursor().map_err(|_| std::fmt::Error)?;
+        let mut blocks = db.blocks().prefetch().map_err(|_| std::fmt::Error)?;
         let contents = db.contents();
         while let Some(right_id) = next {
             // right id should always point at the beginning of the block, so
             // direct seek should be fine
-            let block = cursor.seek(right_id).map_err(|_| std::fmt::Error)?;
+            let block = blocks.get(right_id).map_err(|_| std::fmt::Error)?;
             if block.is_countable()
                 && !block.is_deleted()
                 && block.content_type() == ContentType::String
+                && let Some(data) = try_get_content(&block, &contents)?
+                && let Ok(str) = data.as_str()
             {
-                let data = get_content(&block, &contents)?;
-                if let Ok(str) = data.as_str() {
-                    str.fmt(f)?;
-                }
+                str.fmt(f)?;
             }
             next = block.right().cloned();
         }
@@ -162,7 +390,7 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
         if let Some(attrs) = attrs
             && !attrs.is_empty()
         {
-            let mut remaining = len as u32;
+            let mut remaining = clock_from_index(len)?.get();
             pos.minimize(&attrs, &mut tx.cursor)?;
             let mut negated = pos.insert_attributes(tx, attrs.clone())?;
 
@@ -234,7 +462,7 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
         pos: &mut BlockPosition,
         len: usize,
     ) -> crate::Result<()> {
-        let mut remaining = len;
+        let mut remaining = clock_from_index(len)?;
         let start = pos.right;
         let start_attrs = pos.attrs.clone();
 
@@ -247,24 +475,23 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
                 match block.content_type() {
                     ContentType::String | ContentType::Embed | ContentType::Node => {
                         let mut block: BlockMut = block.into();
-                        let len = block.clock_len().get() as usize;
+                        let len = block.clock_len();
                         let to_delete = if remaining < len {
                             // split block (and the matching content store entry)
-                            let split_result =
-                                tx.cursor.split_current((remaining as u32).into())?;
+                            let split_result = tx.cursor.split_current(remaining)?;
                             block = match split_result {
                                 SplitResult::Unchanged(block) => block,
                                 SplitResult::Split(left, _) => left,
                             };
                             let n = remaining;
-                            remaining = 0;
+                            remaining = Clock::new(0);
                             n
                         } else {
                             remaining -= len;
                             len
                         };
                         if tx.delete(&mut block, false)? {
-                            deleted_count += to_delete as u32;
+                            deleted_count += to_delete.get();
                         }
                     }
                     _ => { /* ignore */ }
@@ -294,6 +521,9 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
         Ok(())
     }
 
+    /// Inserts a plain `chunk` of text at a given `utf16_index`. If the index falls inside of
+    /// an existing block, that block is split. Indexes past the end of the text append the chunk,
+    /// use [TextRef::try_insert] to fail on such indexes instead.
     pub fn insert<S>(&mut self, utf16_index: usize, chunk: S) -> crate::Result<()>
     where
         S: AsRef<str>,
@@ -310,6 +540,28 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
         Ok(())
     }
 
+    /// Inserts a plain `chunk` of text at a given `utf16_index`. Unlike [TextRef::insert], returns
+    /// [crate::Error::OutOfRange] if `utf16_index` is greater than the length of the text.
+    pub fn try_insert<S>(&mut self, utf16_index: usize, chunk: S) -> crate::Result<()>
+    where
+        S: AsRef<str>,
+    {
+        if utf16_index > self.len() {
+            return Err(crate::Error::OutOfRange);
+        }
+        self.insert(utf16_index, chunk)
+    }
+
+    /// Inserts characters produced by a given iterator at a given `utf16_index`. Characters are
+    /// buffered and inserted as a single block, instead of creating a block per character.
+    pub fn insert_chars<I>(&mut self, utf16_index: usize, chars: I) -> crate::Result<()>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let chunk: String = chars.into_iter().collect();
+        self.insert(utf16_index, chunk)
+    }
+
     pub fn insert_with<S1, S2, A, V>(
         &mut self,
         utf16_index: usize,
@@ -370,14 +622,148 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
         Self::insert_at(&mut tx, &mut pos, EmbedPrelim(value), Some(Box::new(attrs)))
     }
 
+    /// Appends a `chunk` of text at the end of the current text.
+    ///
+    /// Consecutive appends are amortized: the last block of the text is cached within
+    /// a transaction, and if it was created by the current transaction, it's extended in place
+    /// instead of creating a new block for every call.
     pub fn push<S>(&mut self, chunk: S) -> crate::Result<()>
     where
         S: AsRef<str>,
     {
+        let chunk = chunk.as_ref();
+        if chunk.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.tx.write_context()?;
+        let node_id = *self.block.id();
+        let hint = tx.state.text_tails.get(&node_id).copied();
+        let tail = match hint {
+            Some(id) => tx.cursor.seek(id).optional()?,
+            None => Self::find_tail(&mut tx.cursor, self.block.start().copied())?,
+        };
+        let tail = match tail {
+            // tail is valid only if it's the last non-deleted string block in the text
+            Some(tail)
+                if tail.right().is_none()
+                    && tail.parent() == &node_id
+                    && !tail.is_deleted()
+                    && tail.content_type() == ContentType::String =>
+            {
+                BlockMut::from(tail)
+            }
+            _ => {
+                drop(tx);
+                let len = self.len();
+                return self.insert(len, chunk);
+            }
+        };
+
+        let tail_id = if Self::try_extend_tail(&mut tx, &mut self.block, tail.clone(), chunk)? {
+            *tail.id()
+        } else {
+            let left = tail.last_id();
+            let (block, _) = InsertBlockData::insert_block(
+                &mut tx,
+                &mut self.block,
+                Some(&left),
+                None,
+                None,
+                StringPrelim::new(chunk),
+            )?;
+            *block.id()
+        };
+        tx.state.text_tails.insert(node_id, tail_id);
+        Ok(())
+    }
+
+    /// Max size (in bytes) of a string block content, up to which [TextRef::push] will try to
+    /// extend existing tail block instead of creating a new one. This prevents appends from
+    /// rewriting large content entries over and over.
+    const MAX_TAIL_EXTEND_LEN: usize = 1024;
+
+    fn find_tail<'a>(
+        cursor: &mut BlockCursor<'a>,
+        start: Option<ID>,
+    ) -> crate::Result<Option<Block<'a>>> {
+        let mut tail = None;
+        let mut next = start;
+        while let Some(id) = next {
+            let block = cursor.seek(id)?;
+            next = block.right().copied();
+            tail = Some(block);
+        }
+        Ok(tail)
+    }
+
+    /// Tries to append a `chunk` directly to the content of a `tail` block. This is only possible
+    /// if `tail` was created by the current transaction and the clock of a new block would
+    /// directly follow it.
+    fn try_extend_tail(
+        tx: &mut TxMutScope<'_>,
+        parent: &mut BlockMut,
+        mut tail: BlockMut,
+        chunk: &str,
+    ) -> crate::Result<bool> {
+        let client = tx.state.client_id;
+        let next_clock = tx.state.current_state.get(&client);
+        if !tx.config.block_merging
+            || tail.id().client != client
+            || !tx.state.has_added(tail.id())
+            || tail.last_id().clock + 1 != next_clock
+            || tail.origin_right().is_some()
+            || (parent.id().is_nested() && parent.is_deleted())
+        {
+            return Ok(false);
+        }
+
+        let contents = tx.db.contents();
+        let current: &[u8] = match tail.try_inline_data() {
+            Some(data) => data,
+            None => contents.get(*tail.id())?,
+        };
+        if current.len() + chunk.len() > Self::MAX_TAIL_EXTEND_LEN {
+            return Ok(false);
+        }
+
+        let len = Clock::new(chunk.encode_utf16().count() as u32);
+        if current.len() + chunk.len() <= BlockHeader::INLINE_CONTENT_LEN {
+            tail.extend_inline_content(chunk.as_bytes());
+        } else {
+            let data = [current, chunk.as_bytes()].concat();
+            contents.insert(*tail.id(), &data)?;
+            tail.clear_inline_content();
+        }
+        let id = tx.state.next_id(len);
+        let tail_len = tail.clock_len();
+        tail.set_clock_len(tail_len + len);
+        tx.cursor.update(tail.as_block())?;
+        tx.notify_inserted(&id, parent.id(), &[Content::str(&chunk)])?;
+
+        let parent_len = Clock::new(parent.node_len() as u32);
+        parent.set_clock_len(parent_len + len);
+        tx.state.add_changed_type(*parent.id(), false, None);
+        tx.cursor.update(parent.as_block())?;
+        Ok(true)
+    }
+
+    /// Removes the oldest (front) content of this text, so that at most `max_utf16` last UTF-16
+    /// code units remain, like [ListRef::truncate_front]. Content is removed in a single range
+    /// deletion.
+    ///
+    /// [ListRef::truncate_front]: crate::ListRef::truncate_front
+    pub fn truncate(&mut self, max_utf16: usize) -> crate::Result<()> {
         let len = self.len();
-        self.insert(len, chunk)
+        if len > max_utf16 {
+            self.remove_range(0..len - max_utf16)?;
+        }
+        Ok(())
     }
 
+    /// Removes a range of UTF-16 code units from this text. Blocks at the range boundaries are
+    /// split if necessary. A range boundary falling between halves of a surrogate pair leaves
+    /// the remaining half of that character as a replacement character. Empty ranges are no-op.
     pub fn remove_range<R>(&mut self, utf16_range: R) -> crate::Result<()>
     where
         R: RangeBounds<usize>,
@@ -388,15 +774,15 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
             Bound::Unbounded => 0,
         };
         let end = match utf16_range.end_bound() {
-            Bound::Included(&index) => index,
-            Bound::Excluded(&index) => index - 1,
+            Bound::Included(&index) => index + 1,
+            Bound::Excluded(&index) => index,
             Bound::Unbounded => self.block.node_len(),
         };
 
-        if start > end {
+        if start >= end {
             return Ok(());
         }
-        let remove_len = end - start + 1;
+        let remove_len = end - start;
         let mut tx = self.tx.write_context()?;
         let mut pos = BlockPosition::seek(&mut tx.cursor, &mut self.block, start)?;
         Self::remove_at(&mut tx, &mut pos, remove_len)?;
@@ -425,22 +811,69 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
             Bound::Unbounded => 0,
         };
         let end = match utf16_range.end_bound() {
-            Bound::Included(&index) => index,
-            Bound::Excluded(&index) => index - 1,
+            Bound::Included(&index) => index + 1,
+            Bound::Excluded(&index) => index,
             Bound::Unbounded => self.block.node_len(),
         };
 
-        if end < start {
+        if end <= start {
             return Ok(());
         }
 
-        let len = end - start + 1;
+        let len = end - start;
         let mut tx = self.tx.write_context()?;
 
         let mut pos = BlockPosition::seek(&mut tx.cursor, &mut self.block, start)?;
         Self::format_at(&mut tx, &mut pos, len, Some(Box::new(attrs)))
     }
 
+    /// Same as [TextRef::format], but attributes are serialized from a given `style` (eg. a struct
+    /// describing text style used by an editor) through lib0 serde. Fields serialized as `null`
+    /// unset corresponding attributes.
+    pub fn format_typed<A, R>(&mut self, utf16_range: R, style: &A) -> crate::Result<()>
+    where
+        A: Serialize,
+        R: RangeBounds<usize>,
+    {
+        let attrs: Attrs = lib0::from_slice(&lib0::to_vec(style)?)?;
+        self.format(utf16_range, attrs)
+    }
+
+    /// Removes all formatting attributes applied within a given range. Only the attributes
+    /// that are active within that range will be unset.
+    pub fn clear_format<R>(&mut self, utf16_range: R) -> crate::Result<()>
+    where
+        R: RangeBounds<usize>,
+    {
+        let start = match utf16_range.start_bound() {
+            Bound::Included(&index) => index,
+            Bound::Excluded(&index) => index + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match utf16_range.end_bound() {
+            Bound::Included(&index) => index + 1,
+            Bound::Excluded(&index) => index,
+            Bound::Unbounded => self.block.node_len(),
+        };
+
+        if end <= start {
+            return Ok(());
+        }
+
+        let mut active = Attrs::new();
+        for span in self.format_spans() {
+            let (range, attrs) = span?;
+            if range.start >= end {
+                break;
+            }
+            if range.end > start {
+                active.extend(attrs.into_keys().map(|key| (key, Value::Null)));
+            }
+        }
+
+        self.format(start..end, active)
+    }
+
     pub fn apply_delta<I>(&mut self, delta: I) -> crate::Result<()>
     where
         I: IntoIterator<Item = Delta<In>>,
@@ -450,6 +883,31 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
         Self::apply_delta_internal(&mut tx, &mut pos, delta)
     }
 
+    /// Applies a given `delta` the same way as [TextRef::apply_delta], and returns the deltas
+    /// describing changes it actually made to this text. These may differ from the input, e.g.
+    /// operations which didn't change anything are skipped, and neighboring operations are
+    /// merged together. Changes made earlier within the same transaction are not included.
+    pub fn apply_delta_returning<I>(&mut self, delta: I) -> crate::Result<Vec<Delta<Out>>>
+    where
+        I: IntoIterator<Item = Delta<In>>,
+    {
+        let (since, deleted_before) = match self.tx.state.get() {
+            Some(state) => (state.current_state.clone(), state.delete_set.clone()),
+            None => (self.tx.state_vector()?, IDSet::default()),
+        };
+        self.apply_delta(delta)?;
+
+        let Some(state) = self.tx.state.get() else {
+            return Ok(Vec::new());
+        };
+        let changes = Changes {
+            since: Cow::Owned(since),
+            deleted: Cow::Owned(state.delete_set.difference(&deleted_before)),
+        };
+        let tx = self.tx.read_context()?;
+        Uncommitted::new(self.block.start().copied(), tx, Some(changes)).collect()
+    }
+
     fn apply_delta_internal<I>(
         tx: &mut TxMutScope<'_>,
         pos: &mut BlockPosition,
@@ -459,12 +917,15 @@ impl<'db, 'tx> TextRef<&'tx mut Transaction<'db>> {
         I: IntoIterator<Item = Delta<In>>,
     {
         for delta in delta {
+            // zero-length operations (sometimes produced by editors) are no-ops, they must not
+            // insert any formatting markers nor move the current position
             match delta {
                 Delta::Insert(value, fmt) => {
                     if !value.is_empty() {
                         Self::insert_at(tx, pos, DeltaPrelim(value), fmt)?;
                     }
                 }
+                Delta::Delete(0) | Delta::Retain(0, _) => {}
                 Delta::Delete(len) => Self::remove_at(tx, pos, len)?,
                 Delta::Retain(len, fmt) => Self::format_at(tx, pos, len, fmt)?,
             }
@@ -483,7 +944,6 @@ impl<'tx, 'db> Deref for TextRef<&'tx mut Transaction<'db>> {
     }
 }
 
-#[allow(unused)]
 #[repr(transparent)]
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct TextPrelim(Vec<Delta<In>>);
@@ -609,6 +1069,21 @@ where
 
 pub type Attrs = BTreeMap<String, Value>;
 
+/// Applies a formatting attribute of a [ContentType::Format] block to a set of currently active
+/// `attrs`. Null values unset the attribute. Returns `true` if `attrs` have changed, so that
+/// redundant format markers don't split runs of text sharing the same attributes.
+fn apply_format(attrs: &mut Attrs, fmt: &FormatAttribute<'_>) -> crate::Result<bool> {
+    let value: Value = fmt.value()?;
+    if value.is_null() {
+        Ok(attrs.remove(fmt.key()).is_some())
+    } else if attrs.get(fmt.key()) == Some(&value) {
+        Ok(false)
+    } else {
+        attrs.insert(fmt.key().to_owned(), value);
+        Ok(true)
+    }
+}
+
 /// A single change done over a text-like types: [Text] or [XmlText].
 #[derive(Debug, Clone, PartialEq)]
 pub enum Delta<T = Out> {
@@ -646,12 +1121,43 @@ impl<T> Delta<T> {
     }
 }
 
+/// Range of changes reported by [Uncommitted]: elements inserted after `since` state and elements
+/// present in `deleted` set.
+struct Changes<'tx> {
+    since: Cow<'tx, StateVector>,
+    deleted: Cow<'tx, IDSet>,
+}
+
+impl Changes<'_> {
+    /// Returns an offset within a block of given `id` and `len`, starting from which its elements
+    /// were added, or `None` if it has no added elements. Only blocks extended in place can have
+    /// a non-zero offset.
+    fn added_offset(&self, id: &ID, len: Clock) -> Option<Clock> {
+        let since = self.since.get(&id.client);
+        if id.clock >= since {
+            Some(Clock::new(0))
+        } else if id.clock + len > since {
+            Some(since - id.clock)
+        } else {
+            None
+        }
+    }
+
+    fn has_added(&self, id: &ID) -> bool {
+        id.clock >= self.since.get(&id.client)
+    }
+
+    fn has_deleted(&self, id: &ID) -> bool {
+        self.deleted.contains(id)
+    }
+}
+
 pub struct Uncommitted<'tx> {
     tx: TxScope<'tx>,
 
-    /// Transaction state. If `None`, means that the transaction was readonly and has no uncommitted
-    /// changes.
-    tx_state: Option<&'tx TransactionState>,
+    /// Changes to report. If `None`, means that the transaction was readonly and has no
+    /// uncommitted changes.
+    changes: Option<Changes<'tx>>,
 
     /// The block head we're currently on.
     current: Option<ID>,
@@ -669,10 +1175,10 @@ pub struct Uncommitted<'tx> {
 }
 
 impl<'tx> Uncommitted<'tx> {
-    fn new(current: Option<ID>, tx: TxScope<'tx>, tx_state: Option<&'tx TransactionState>) -> Self {
+    fn new(current: Option<ID>, tx: TxScope<'tx>, changes: Option<Changes<'tx>>) -> Self {
         Uncommitted {
             tx,
-            tx_state,
+            changes,
             current,
             current_attrs: Attrs::default(),
             old_attrs: Attrs::default(),
@@ -714,20 +1220,53 @@ impl<'tx> Uncommitted<'tx> {
         }
     }
 
-    fn update_attrs(&mut self, key: &str, value: lib0::Value) {
-        if value.is_null() {
-            self.current_attrs.remove(key);
+    /// Appends inserted string to the delta under construction, returning the previous delta if
+    /// it was of a different kind.
+    fn push_insert(&mut self, str: &str) -> Option<Delta<Out>> {
+        if let Some(Delta::Insert(Out::Value(Value::String(buf)), _)) = &mut self.delta {
+            buf.push_str(str);
+            None
+        } else {
+            let delta = self.add_op();
+            self.delta = Some(Delta::Insert(Out::Value(str.into()), None));
+            delta
+        }
+    }
+
+    /// Same as [Uncommitted::push_insert], but for deleted elements.
+    fn push_delete(&mut self, len: usize) -> Option<Delta<Out>> {
+        if let Some(Delta::Delete(current)) = &mut self.delta {
+            *current += len;
+            None
+        } else {
+            let delta = self.add_op();
+            self.delta = Some(Delta::Delete(len));
+            delta
+        }
+    }
+
+    /// Same as [Uncommitted::push_insert], but for retained elements.
+    fn push_retain(&mut self, len: usize) -> Option<Delta<Out>> {
+        if let Some(Delta::Retain(current, _)) = &mut self.delta {
+            *current += len;
+            None
         } else {
-            self.current_attrs.insert(key.to_string(), value);
+            let delta = self.add_op();
+            self.delta = Some(Delta::Retain(len, None));
+            delta
         }
     }
 
     fn move_next(&mut self) -> crate::Result<Option<Delta<Out>>> {
-        let state = match self.tx_state {
-            Some(state) => state,
-            None => return Ok(None),
+        let Some(state) = self.changes.take() else {
+            return Ok(None);
         };
+        let result = self.next_change(&state);
+        self.changes = Some(state);
+        result
+    }
 
+    fn next_change(&mut self, state: &Changes<'_>) -> crate::Result<Option<Delta<Out>>> {
         if let Some(delta) = self.pending_delta.take() {
             return Ok(Some(delta));
         }
@@ -740,42 +1279,41 @@ impl<'tx> Uncommitted<'tx> {
             let mut delta = None;
             match block.content_type() {
                 ContentType::String => {
-                    if state.has_added(&id) {
-                        if !state.has_deleted(&id) {
-                            let content = get_content(&block, &contents)?;
-                            let str = content.as_str()?;
-
-                            if !matches!(self.delta, Some(Delta::Insert(_, _))) {
-                                delta = self.add_op();
-                                self.delta = Some(Delta::Insert(Out::Value(str.into()), None));
-                            } else if let Some(Delta::Insert(Out::Value(Value::String(buf)), _)) =
-                                &mut self.delta
-                            {
-                                buf.push_str(str);
-                            } else {
-                                unreachable!()
+                    let block_len = block.clock_len();
+                    let deleted = state.has_deleted(&id);
+                    match state.added_offset(&id, block_len) {
+                        Some(offset) => {
+                            // elements before the offset existed already
+                            if offset > 0 {
+                                if deleted {
+                                    delta = self.push_delete(offset.get() as usize);
+                                } else if !block.is_deleted() {
+                                    delta = self.push_retain(offset.get() as usize);
+                                }
+                            }
+                            if !deleted {
+                                let content = get_content(&block, &contents)?;
+                                let mut str = content.as_str()?;
+                                if offset > 0 {
+                                    let start = utf16_to_utf8(str, offset.get() as usize)
+                                        .ok_or(crate::Error::OutOfRange)?;
+                                    str = &str[start..];
+                                }
+                                let flushed = self.push_insert(str);
+                                if delta.is_some() {
+                                    self.pending_delta = flushed;
+                                } else {
+                                    delta = flushed;
+                                }
                             }
                         }
-                    } else if state.has_deleted(&id) {
-                        let block_len = block.clock_len().get() as usize;
-                        if !matches!(self.delta, Some(Delta::Delete(_))) {
-                            delta = self.add_op();
-                            self.delta = Some(Delta::Delete(block_len));
-                        } else if let Some(Delta::Delete(len)) = &mut self.delta {
-                            *len += block_len;
-                        } else {
-                            unreachable!()
+                        None if deleted => {
+                            delta = self.push_delete(block_len.get() as usize);
                         }
-                    } else if !block.is_deleted() {
-                        let block_len = block.clock_len().get() as usize;
-                        if !matches!(self.delta, Some(Delta::Retain(_, _))) {
-                            delta = self.add_op();
-                            self.delta = Some(Delta::Retain(block_len, None));
-                        } else if let Some(Delta::Retain(len, _)) = &mut self.delta {
-                            *len += block_len;
-                        } else {
-                            unreachable!()
+                        None if !block.is_deleted() => {
+                            delta = self.push_retain(block_len.get() as usize);
                         }
+                        None => {}
                     }
                 }
                 ContentType::Format => {
@@ -860,7 +1398,7 @@ impl<'tx> Uncommitted<'tx> {
                             None
                         };
 
-                        self.update_attrs(fmt.key(), fmt.value()?);
+                        apply_format(&mut self.current_attrs, &fmt)?;
                         if delta.is_some() {
                             return Ok(delta);
                         }
@@ -934,15 +1472,22 @@ impl<'tx> Iterator for Uncommitted<'tx> {
 }
 
 pub struct Chunks<'a, 'tx> {
-    tx: TxScope<'tx>,
+    db: Database<'tx>,
+    blocks: BlockPrefetch<'tx>,
     current: Option<ID>,
+    /// Clock offset within the current block, at which the next slice of it starts.
+    offset: Clock,
     from: Option<&'a Snapshot>,
     to: Option<&'a Snapshot>,
 
     buf: String,
+    /// Attributes of the text collected in `buf`.
+    buf_attrs: Option<Box<Attrs>>,
     current_attrs: Option<Box<Attrs>>,
     current_op: Option<Op>,
     pending: Option<Chunk>,
+    authors: bool,
+    current_author: Option<ClientID>,
 }
 
 impl<'a, 'tx> Chunks<'a, 'tx> {
@@ -953,22 +1498,34 @@ impl<'a, 'tx> Chunks<'a, 'tx> {
         to: Option<&'a Snapshot>,
     ) -> Self {
         Chunks {
-            tx,
+            db: tx.db,
+            blocks: BlockPrefetch::new(tx.cursor),
             current: start,
+            offset: Clock::new(0),
             from,
             to,
             buf: String::new(),
+            buf_attrs: None,
             current_attrs: None,
             current_op: None,
             pending: None,
+            authors: false,
+            current_author: None,
         }
     }
 
+    /// Makes this iterator fill [Chunk::author] with the client that inserted each chunk.
+    /// Consecutive pieces of text inserted by different clients are emitted as separate chunks.
+    pub fn with_authors(mut self) -> Self {
+        self.authors = true;
+        self
+    }
+
     fn pack_str(&mut self) -> Option<Chunk> {
         if !self.buf.is_empty() {
-            let attributes = match &self.current_attrs {
+            let attributes = match self.buf_attrs.take() {
                 Some(attrs) if attrs.is_empty() => None,
-                other => other.clone(),
+                other => other,
             };
             let mut buf = std::mem::take(&mut self.buf);
             buf.shrink_to_fit();
@@ -976,49 +1533,51 @@ impl<'a, 'tx> Chunks<'a, 'tx> {
                 insert: Out::Value(buf.into()),
                 attributes,
                 operation: self.current_op.take(),
+                author: self.current_author,
             })
         } else {
             None
         }
     }
 
-    fn seen(snapshot: Option<&Snapshot>, block: &Block<'_>) -> bool {
+    fn seen(snapshot: Option<&Snapshot>, block: &Block<'_>, id: &ID) -> bool {
         if let Some(s) = snapshot {
-            s.is_visible(block.id())
+            s.is_visible(id)
         } else {
             !block.is_deleted()
         }
     }
 
-    fn update_attrs(&mut self, key: &str, value: lib0::Value) {
-        let attrs = self.current_attrs.get_or_insert_default();
-        if value.is_null() {
-            attrs.remove(key);
-        } else {
-            attrs.insert(key.to_string(), value);
+    /// Returns the clock (exclusive) at which a slice of a `block` starting at `id` ends. Blocks
+    /// merged after the snapshots were taken are sliced into parts of the same visibility.
+    fn slice_end(&self, block: &Block<'_>, id: &ID) -> Clock {
+        let mut end = block.id().clock + block.clock_len();
+        if block.content_type() == ContentType::String {
+            for snapshot in [self.from, self.to].into_iter().flatten() {
+                end = snapshot.visible_until(id, end);
+            }
         }
+        end
     }
 
-    fn stash_or_return(&mut self, out: Out) -> Chunk {
+    fn stash_or_return(&mut self, out: Out, author: ClientID) -> Chunk {
         let attributes = match &self.current_attrs {
             Some(attrs) if attrs.is_empty() => None,
             attrs => attrs.clone(),
         };
-        if let Some(chunk) = self.pack_str() {
+        let chunk = Chunk {
+            insert: out,
+            attributes,
+            operation: None,
+            author: self.authors.then_some(author),
+        };
+        if let Some(prev) = self.pack_str() {
             // There was already a string chunk that we were collecting, we need to
             // emit it first. Therefore, we store this chunk for the next method call
-            self.pending = Some(Chunk {
-                insert: out,
-                attributes,
-                operation: None,
-            });
-            chunk
+            self.pending = Some(chunk);
+            prev
         } else {
-            Chunk {
-                insert: out,
-                attributes,
-                operation: None,
-            }
+            chunk
         }
     }
 
@@ -1029,12 +1588,22 @@ impl<'a, 'tx> Chunks<'a, 'tx> {
             return Ok(Some(chunk));
         }
 
-        while let Some(id) = self.current.take() {
-            let block = self.tx.cursor.seek(id)?;
-            self.current = block.right().copied();
+        while let Some(block_id) = self.current.take() {
+            let block = self.blocks.get(block_id)?;
+            let offset = std::mem::replace(&mut self.offset, Clock::new(0));
+            let id = ID::new(block_id.client, block_id.clock + offset);
+            let end = self.slice_end(&block, &id);
+            if end < block_id.clock + block.clock_len() {
+                // the rest of the block will be processed as the next slice
+                self.current = Some(block_id);
+                self.offset = end - block_id.clock;
+            } else {
+                self.current = block.right().copied();
+            }
 
             // check if block is within the bounds we're looking after
-            if Self::seen(self.to, &block) || (self.from.is_some() && Self::seen(self.from, &block))
+            if Self::seen(self.to, &block, &id)
+                || (self.from.is_some() && Self::seen(self.from, &block, &id))
             {
                 match block.content_type() {
                     ContentType::String => {
@@ -1052,33 +1621,53 @@ impl<'a, 'tx> Chunks<'a, 'tx> {
                                 }
                             };
                         };
-                        let contents = self.tx.db.contents();
-                        let content = get_content(&block, &contents)?;
-                        let str = content.as_str()?;
-                        self.buf.push_str(str);
+                        if self.authors && self.current_author != Some(block_id.client) {
+                            if prev.is_none() {
+                                prev = self.pack_str();
+                            }
+                            self.current_author = Some(block_id.client);
+                        }
+                        // format markers which didn't end up formatting any text in between
+                        // don't split the chunk
+                        let attrs = self.current_attrs.as_deref().filter(|a| !a.is_empty());
+                        if prev.is_none()
+                            && !self.buf.is_empty()
+                            && self.buf_attrs.as_deref().filter(|a| !a.is_empty()) != attrs
+                        {
+                            prev = self.pack_str();
+                        }
+                        if self.buf.is_empty() {
+                            self.buf_attrs = self.current_attrs.clone();
+                        }
+                        let contents = self.db.contents();
+                        if let Some(content) = try_get_content(&block, &contents)? {
+                            let str = content.as_str()?;
+                            let start =
+                                utf16_to_utf8(str, offset.get() as usize).unwrap_or(str.len());
+                            let end = (end - block_id.clock).get() as usize;
+                            let end = utf16_to_utf8(str, end).unwrap_or(str.len());
+                            self.buf.push_str(&str[start..end]);
+                        }
                         if prev.is_some() {
                             return Ok(prev);
                         }
                     }
                     ContentType::Embed => {
-                        let contents = self.tx.db.contents();
-                        let content = get_content(&block, &contents)?;
-                        let out: Out = Out::Value(content.as_embed()?);
-                        return Ok(Some(self.stash_or_return(out)));
+                        let contents = self.db.contents();
+                        if let Some(content) = try_get_content(&block, &contents)? {
+                            let out: Out = Out::Value(content.as_embed()?);
+                            return Ok(Some(self.stash_or_return(out, block_id.client)));
+                        }
                     }
                     ContentType::Node => {
                         let out: Out = Out::Node(*block.id());
-                        return Ok(Some(self.stash_or_return(out)));
+                        return Ok(Some(self.stash_or_return(out, block_id.client)));
                     }
-                    ContentType::Format if Self::seen(self.to, &block) => {
-                        let chunk = self.pack_str();
-                        let contents = self.tx.db.contents();
-                        let content = get_content(&block, &contents)?;
-                        let fmt = content.as_format()?;
-                        self.update_attrs(fmt.key(), fmt.value()?);
-
-                        if let Some(chunk) = chunk {
-                            return Ok(Some(chunk));
+                    ContentType::Format if Self::seen(self.to, &block, &id) => {
+                        let contents = self.db.contents();
+                        if let Some(content) = try_get_content(&block, &contents)? {
+                            let fmt = content.as_format()?;
+                            apply_format(self.current_attrs.get_or_insert_default(), &fmt)?;
                         }
                     }
                     _ => { /* ignore */ }
@@ -1103,6 +1692,104 @@ impl<'a, 'tx> Iterator for Chunks<'a, 'tx> {
     }
 }
 
+/// Iterator produced by [TextRef::format_spans].
+pub struct FormatSpans<'tx> {
+    tx: TxScope<'tx>,
+    current: Option<ID>,
+    /// UTF-16 index of the current block.
+    index: usize,
+    /// UTF-16 index at which the current span starts.
+    span_start: usize,
+    /// Attributes active within the current span.
+    attrs: Attrs,
+    /// Last finished span, kept to be merged with the next one if they share attributes.
+    pending: Option<(Range<usize>, Attrs)>,
+}
+
+impl<'tx> FormatSpans<'tx> {
+    fn new(tx: TxScope<'tx>, start: Option<ID>) -> Self {
+        FormatSpans {
+            tx,
+            current: start,
+            index: 0,
+            span_start: 0,
+            attrs: Attrs::new(),
+            pending: None,
+        }
+    }
+
+    /// Replaces attributes of the current span, returning previous span if it was not empty.
+    fn start_span(&mut self, attrs: Attrs) -> Option<(Range<usize>, Attrs)> {
+        let range = self.span_start..self.index;
+        let prev = std::mem::replace(&mut self.attrs, attrs);
+        self.span_start = self.index;
+        if range.is_empty() || prev.is_empty() {
+            None
+        } else {
+            Some((range, prev))
+        }
+    }
+
+    /// Stashes a finished span, returning a previously stashed span unless both can be merged.
+    fn push_span(&mut self, span: (Range<usize>, Attrs)) -> Option<(Range<usize>, Attrs)> {
+        match &mut self.pending {
+            Some((range, attrs)) if range.end == span.0.start && attrs == &span.1 => {
+                range.end = span.0.end;
+                None
+            }
+            pending => pending.replace(span),
+        }
+    }
+
+    fn move_next(&mut self) -> crate::Result<Option<(Range<usize>, Attrs)>> {
+        while let Some(id) = self.current.take() {
+            let block = self.tx.cursor.seek(id)?;
+            self.current = block.right().copied();
+            if block.is_deleted() {
+                continue;
+            }
+
+            match block.content_type() {
+                ContentType::String => self.index += block.clock_len().get() as usize,
+                ContentType::Embed | ContentType::Node => self.index += 1,
+                ContentType::Format => {
+                    let contents = self.tx.db.contents();
+                    let content = get_content(&block, &contents)?;
+                    let fmt = content.as_format()?;
+                    let mut attrs = self.attrs.clone();
+                    if apply_format(&mut attrs, &fmt)?
+                        && let Some(span) = self.start_span(attrs)
+                        && let Some(span) = self.push_span(span)
+                    {
+                        return Ok(Some(span));
+                    }
+                }
+                _ => { /* ignore */ }
+            }
+        }
+
+        // flush the remaining spans
+        if let Some(span) = self.start_span(Attrs::new())
+            && let Some(span) = self.push_span(span)
+        {
+            return Ok(Some(span));
+        }
+        Ok(self.pending.take())
+    }
+}
+
+impl<'tx> Iterator for FormatSpans<'tx> {
+    type Item = crate::Result<(Range<usize>, Attrs)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.move_next() {
+            Ok(Some(span)) => Some(Ok(span)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
 impl Delta<In> {
     pub fn retain(len: usize) -> Self {
         Delta::Retain(len, None)
@@ -1142,7 +1829,7 @@ impl<'a> BlockPosition<'a> {
     }
 
     fn forward_by(&mut self, offset: usize, cursor: &mut BlockCursor) -> crate::Result<()> {
-        let mut remaining = offset;
+        let mut remaining = clock_from_index(offset)?;
         while let Some(right_id) = &self.right
             && remaining != 0
         {
@@ -1151,25 +1838,16 @@ impl<'a> BlockPosition<'a> {
                 if right.content_type() == ContentType::Format {
                     let content_store = cursor.content_store();
                     let content = get_content(&right, &content_store)?;
-                    let fmt = content.as_format()?;
-                    let fmt_value: Value = fmt.value()?;
-                    if fmt_value.is_null() {
-                        self.attrs.remove(fmt.key());
-                    } else {
-                        self.attrs.insert(fmt.key().to_owned(), fmt_value);
-                    }
+                    apply_format(&mut self.attrs, &content.as_format()?)?;
                 } else {
-                    let len = right.clock_len().get() as usize;
+                    let len = right.clock_len();
                     if remaining < len {
                         // Actually split the block in the store so that downstream
                         // consumers (insert_at, remove_at, ...) can address `pos.right`
                         // as a real block boundary. After the split, `pos.left` is the
                         // last id of the left portion and `pos.right` is the first id
                         // of the right portion.
-                        let split_id = ID::new(
-                            right_id.client,
-                            right_id.clock + Clock::new(remaining as u32),
-                        );
+                        let split_id = ID::new(right_id.client, right_id.clock + remaining);
                         // Drop the borrow on `block_cursor` before opening another cursor
                         // through `blocks.split`.
                         let _ = right;
@@ -1184,11 +1862,11 @@ impl<'a> BlockPosition<'a> {
                                 unreachable!("split point is strictly inside the block");
                             }
                         }
-                        self.utf16_index += remaining;
+                        self.utf16_index += remaining.get() as usize;
                         break;
                     } else {
                         remaining -= len;
-                        self.utf16_index += len;
+                        self.utf16_index += len.get() as usize;
                     }
                 }
             }
@@ -1335,14 +2013,7 @@ fn forward(pos: &mut BlockPosition, cursor: &mut BlockCursor) -> crate::Result<b
                 ContentType::Format => {
                     let content_store = cursor.content_store();
                     let data = get_content(&block, &content_store)?;
-                    let fmt = data.as_format()?;
-                    let key = fmt.key();
-                    let value: lib0::Value = fmt.value()?;
-                    if value.is_null() {
-                        pos.attrs.remove(key);
-                    } else {
-                        pos.attrs.insert(key.to_owned(), value);
-                    }
+                    apply_format(&mut pos.attrs, &data.as_format()?)?;
                 }
                 _ => { /* ignore */ }
             }
@@ -1371,14 +2042,7 @@ fn clean_format_gap<'tx>(
             ContentType::Format if !block.is_deleted() => {
                 let contents = tx.db.contents();
                 let content = get_content(&block, &contents)?;
-                let fmt = content.as_format()?;
-                let key = fmt.key();
-                let value: lib0::Value = fmt.value()?;
-                if value.is_null() {
-                    end_attrs.remove(key);
-                } else {
-                    end_attrs.insert(key.to_owned(), value);
-                }
+                apply_format(end_attrs, &content.as_format()?)?;
             }
             _ => { /* ignore */ }
         }
@@ -1419,13 +2083,181 @@ fn get_content<'a>(block: &Block<'a>, contents: &'a ContentStore) -> crate::Resu
     }
 }
 
+/// Same as [get_content], but returns `None` if the block's content is missing, e.g. after being
+/// partially garbage collected. Read-only traversals use it to skip such blocks.
+pub(crate) fn try_get_content<'a>(
+    block: &Block<'a>,
+    contents: &'a ContentStore,
+) -> crate::Result<Option<Content<'a>>> {
+    match get_content(block, contents) {
+        Ok(content) => Ok(Some(content)),
+        Err(crate::Error::ContentNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::block::ID;
+    use crate::block::{ID, InsertBlockData};
+    use crate::content::ContentType;
     use crate::lib0::{Decode, Encode, Encoding, Value};
+    use crate::prelim::StringPrelim;
+    use crate::state_vector::Snapshot;
+    use crate::store::Db;
+    use crate::store::content_store::ContentBlobKey;
     use crate::test_util::{multi_doc, sync};
-    use crate::types::text::{Attrs, Chunk, Delta, Op};
-    use crate::{ListPrelim, Map, MapPrelim, Out, StateVector, Text, Unmounted, lib0};
+    use crate::types::text::{Attrs, Chunk, Delta, Op, TypedChunk};
+    use crate::{
+        BlockHeader, ListPrelim, Map, MapPrelim, Optional, Out, StateVector, Text, Transaction,
+        U32, U64, Unmounted, lib0,
+    };
+    use serde::{Deserialize, Serialize};
+    use zerocopy::{FromBytes, IntoBytes};
+
+    #[test]
+    fn is_empty() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+
+        assert!(txt.mount(&tx).unwrap().is_empty());
+
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "hello").unwrap();
+        assert!(!txt.mount(&tx).unwrap().is_empty());
+
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(1..5).unwrap();
+        assert!(!txt.mount(&tx).unwrap().is_empty());
+
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.remove_range(0..1).unwrap();
+        assert!(txt.mount(&tx).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn index_out_of_clock_range() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "hello").unwrap();
+
+        let index = u32::MAX as usize + 1;
+        assert!(matches!(
+            t.insert(index, "x"),
+            Err(crate::Error::OutOfRange)
+        ));
+        assert!(matches!(
+            t.remove_range(index..index + 1),
+            Err(crate::Error::OutOfRange)
+        ));
+        assert!(matches!(
+            t.format(index..index + 1, [("bold", true)]),
+            Err(crate::Error::OutOfRange)
+        ));
+        assert_eq!(txt.mount(&tx).unwrap().to_string(), "hello");
+    }
+
+    #[test]
+    fn id_at() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "abcd").unwrap(); // 1:0..4
+        t.insert(1, "XY").unwrap(); // 1:4..6
+        t.format(3..4, [("bold", true)]).unwrap();
+        t.remove_range(5..6).unwrap(); // "d"
+
+        let t = txt.mount(&tx).unwrap();
+        assert_eq!(t.to_string(), "aXYbc");
+        let expected = [(1, 0), (1, 4), (1, 5), (1, 1), (1, 2)];
+        for (index, (client, clock)) in expected.into_iter().enumerate() {
+            assert_eq!(
+                t.id_at(index).unwrap(),
+                ID::new(client.into(), clock.into()),
+                "index {index}"
+            );
+        }
+        assert!(matches!(t.id_at(5), Err(crate::Error::OutOfRange)));
+    }
+
+    #[test]
+    fn lines() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let t = txt.mount(&tx).unwrap();
+        assert_eq!(t.line_count().unwrap(), 1);
+        assert_eq!(t.line(0).unwrap(), "");
+        assert!(matches!(t.line(1), Err(crate::Error::OutOfRange)));
+
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "first\nsec").unwrap();
+        t.push("ond\n\nfou").unwrap();
+        t.insert_embed(t.len(), 1.0).unwrap();
+        t.push("rth\n").unwrap();
+        t.format(0..3, [("bold", true)]).unwrap();
+
+        let t = txt.mount(&tx).unwrap();
+        let expected = ["first", "second", "", "fourth", ""];
+        assert_eq!(t.line_count().unwrap(), expected.len());
+        for (i, line) in expected.iter().enumerate() {
+            assert_eq!(&t.line(i).unwrap(), line, "line {i}");
+        }
+        assert!(matches!(t.line(5), Err(crate::Error::OutOfRange)));
+        assert_eq!(t.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn format_typed() {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct TextStyle {
+            bold: Option<bool>,
+            color: Option<String>,
+        }
+
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        t.insert(0, "hello world").unwrap();
+        let style = TextStyle {
+            bold: Some(true),
+            color: Some("red".into()),
+        };
+        t.format_typed(0..5, &style).unwrap();
+
+        let t = txt.mount(&tx).unwrap();
+        let chunks: Vec<TypedChunk<TextStyle>> = t.chunks_typed().map(Result::unwrap).collect();
+        assert_eq!(
+            chunks,
+            vec![
+                TypedChunk {
+                    insert: Out::Value("hello".into()),
+                    attributes: Some(style),
+                },
+                TypedChunk {
+                    insert: Out::Value(" world".into()),
+                    attributes: None,
+                },
+            ]
+        );
+
+        // fields serialized as null unset the attributes
+        let mut t = txt.mount_mut(&mut tx).unwrap();
+        let style = TextStyle {
+            bold: None,
+            color: Some("blue".into()),
+        };
+        t.format_typed(0..5, &style).unwrap();
+
+        let t = txt.mount(&tx).unwrap();
+        let chunks: Vec<TypedChunk<TextStyle>> = t.chunks_typed().map(Result::unwrap).collect();
+        assert_eq!(chunks[0].attributes, Some(style));
+    }
 
     #[test]
     fn insert_empty_string() {
@@ -1446,6 +2278,87 @@ mod test {
         tx.commit(None).unwrap();
     }
 
+    #[test]
+    fn insert_zero_length_block() {
+        let (mdoc, _dir) = multi_doc(1);
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let txt: Unmounted<Text> = Unmounted::root("type");
+        let mut txt = txt.mount_mut(&mut tx).unwrap();
+        txt.push("abc").unwrap();
+
+        let mut scope = txt.tx.write_context().unwrap();
+        let value = StringPrelim::new("");
+        let result =
+            InsertBlockData::insert_block(&mut scope, &mut txt.block, None, None, None, value);
+        assert!(matches!(result, Err(crate::Error::EmptyInsert)));
+        drop(scope);
+
+        // local clock was not advanced
+        let sv = tx.state_vector().unwrap();
+        assert_eq!(sv, StateVector::from_iter([(1.into(), 3.into())]));
+    }
+
+    #[test]
+    fn chunks_with_authors() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let (d1, _) = multi_doc(1);
+        let (d2, _) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        txt.mount_mut(&mut t1).unwrap().insert(0, "hello ").unwrap();
+        sync([&mut t1, &mut t2]);
+        let mut t = txt.mount_mut(&mut t2).unwrap();
+        t.push("world").unwrap();
+        t.insert_embed(6, lib0!({"src": "img.png"})).unwrap();
+        sync([&mut t1, &mut t2]);
+
+        for tx in [&t1, &t2] {
+            let t = txt.mount(tx).unwrap();
+            let chunks: Vec<_> = t.chunks().with_authors().map(Result::unwrap).collect();
+            assert_eq!(
+                chunks,
+                vec![
+                    Chunk::new("hello ").with_author(1.into()),
+                    Chunk::new(lib0!({"src": "img.png"})).with_author(2.into()),
+                    Chunk::new("world").with_author(2.into()),
+                ]
+            );
+            assert_eq!(tx.author_of(&ID::new(2.into(), 0.into())), 2.into());
+
+            // authors are not tracked by default
+            let chunks: Vec<_> = t.chunks().map(Result::unwrap).collect();
+            assert_eq!(chunks[0], Chunk::new("hello "));
+        }
+    }
+
+    #[test]
+    fn empty_insert_keeps_state_vector() {
+        let (mdoc, _dir) = multi_doc(1);
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let txt: Unmounted<Text> = Unmounted::root("type");
+        let mut txt = txt.mount_mut(&mut tx).unwrap();
+        txt.push("abc").unwrap();
+        let state = tx.state_vector().unwrap();
+
+        let txt: Unmounted<Text> = Unmounted::root("type");
+        let mut txt = txt.mount_mut(&mut tx).unwrap();
+        let attrs = [("bold".to_string(), Value::from(true))];
+        txt.insert(1, "").unwrap();
+        txt.insert_with(1, "", attrs.clone()).unwrap();
+        txt.push("").unwrap();
+        txt.apply_delta([
+            Delta::insert(""),
+            Delta::insert_with("", Attrs::from(attrs)),
+        ])
+        .unwrap();
+        assert_eq!(txt.to_string(), "abc");
+
+        // no blocks were created, so there's nothing new for the peers to sync
+        assert_eq!(tx.state_vector().unwrap(), state);
+        tx.commit(None).unwrap();
+    }
+
     #[test]
     fn append_single_character_blocks() {
         let (mdoc, _dir) = multi_doc(1);
@@ -1527,6 +2440,28 @@ mod test {
         tx.commit(None).unwrap();
     }
 
+    #[test]
+    fn insert_past_the_end() {
+        let (mdoc, _dir) = multi_doc(1);
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let txt: Unmounted<Text> = Unmounted::root("type");
+        let mut txt = txt.mount_mut(&mut tx).unwrap();
+
+        txt.insert(10, "world").unwrap();
+        txt.insert(0, "hello").unwrap();
+        txt.insert(100, "!").unwrap();
+        assert_eq!(txt.to_string(), "helloworld!");
+
+        assert!(matches!(
+            txt.try_insert(12, "?"),
+            Err(crate::Error::OutOfRange)
+        ));
+        txt.try_insert(11, "?").unwrap();
+        assert_eq!(txt.to_string(), "helloworld!?");
+
+        tx.commit(None).unwrap();
+    }
+
     #[test]
     fn insert_inside_of_block() {
         let (mdoc, _dir) = multi_doc(1);
@@ -2211,6 +3146,150 @@ mod test {
         t2.commit(None).unwrap();
     }
 
+    #[test]
+    fn text_remove_range_edge_cases() {
+        let (mdoc, _dir) = multi_doc(1);
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let mut txt = txt.mount_mut(&mut tx).unwrap();
+
+        txt.insert(0, "ab😭😊cd").unwrap();
+
+        // empty ranges are no-op
+        txt.remove_range(0..0).unwrap();
+        txt.remove_range(3..3).unwrap();
+        assert_eq!(txt.to_string(), "ab😭😊cd");
+        assert_eq!(txt.len(), 8);
+
+        // range starting in the middle of 😭 and ending in the middle of 😊
+        txt.remove_range(3..5).unwrap();
+        assert_eq!(txt.to_string(), "ab\u{fffd}\u{fffd}cd");
+        assert_eq!(txt.len(), 6);
+
+        txt.insert(6, "ef").unwrap();
+        txt.insert(8, "gh").unwrap();
+        // range spanning over several adjacent blocks
+        txt.remove_range(5..9).unwrap();
+        assert_eq!(txt.to_string(), "ab\u{fffd}\u{fffd}ch");
+        assert_eq!(txt.len(), 6);
+
+        txt.remove_range(..).unwrap();
+        assert_eq!(txt.to_string(), "");
+        assert_eq!(txt.len(), 0);
+
+        tx.commit(None).unwrap();
+    }
+
+    #[test]
+    fn format_redundant_and_empty_ranges() {
+        let (mdoc, _dir) = multi_doc(1);
+        let mut tx = mdoc.transact_mut("test").unwrap();
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let mut txt = txt.mount_mut(&mut tx).unwrap();
+
+        txt.insert(0, "hello world").unwrap();
+        txt.format(0..5, [("bold", true)]).unwrap();
+        let sv = tx.state_vector().unwrap();
+
+        // empty range and attributes already active within the range don't emit any blocks
+        let mut txt = Unmounted::<Text>::root("text").mount_mut(&mut tx).unwrap();
+        txt.format(0..0, [("italic", true)]).unwrap();
+        txt.format(1..3, [("bold", true)]).unwrap();
+        assert_eq!(tx.state_vector().unwrap(), sv);
+
+        let mut txt = Unmounted::<Text>::root("text").mount_mut(&mut tx).unwrap();
+        txt.format(6.., [("italic", true)]).unwrap();
+        assert_eq!(
+            txt.chunks().map(Result::unwrap).collect::<Vec<_>>(),
+            vec![
+                Chunk::new("hello").with_attrs(Attrs::from([("bold".into(), Value::Bool(true))])),
+                Chunk::new(" "),
+                Chunk::new("world").with_attrs(Attrs::from([("italic".into(), Value::Bool(true))])),
+            ]
+        );
+
+        tx.commit(None).unwrap();
+    }
+
+    #[test]
+    fn chunks_skip_redundant_format_markers() {
+        let txt: Unmounted<Text> = Unmounted::root("text");
+        let bold = Attrs::from([("bold".into(), Value::Bool(true))]);
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        txt.mount_mut(&mut t1).unwrap().insert(0, "abcd").unwrap();
+        sync([&mut t1, &mut t2]);
+
+        // concurrently format a range and remove its text, leaving adjacent markers behind
+        txt.mount_mut(&mut t1)
+            .unwrap()
+            .format(1..3, bold.clone())
+            .unwrap();
+        txt.mount_mut(&mut t2).unwrap().remove_range(1..3).unwrap();
+        sync([&mut t1, &mut t2]);
+
+        for tx in [&t1, &t2] {
+            let txt = txt.mount(tx).unwrap();
+            assert_eq!(
+                txt.chunks().map(Result::unwrap).collect::<Vec<_>>(),
+                vec![Chunk::new("ad")]
+            );
+        }
+
+        // consecutive blocks with the same attributes form a single chunk
+        let mut txt1 = txt.mount_mut(&mut t1).unwrap();
+        txt1.insert_with(1, "x", bold.clone()).unwrap();
+        txt1.insert_with(2, "y", bold.clone()).unwrap();
+        assert_eq!(
+            txt1.chunks().map(Result::unwrap).collect::<Vec<_>>(),
+            vec![
+                Chunk::new("a"),
+                Chunk::new("xy").with_attrs(bold.clone()),
+                Chunk::new("d")
+            ]
+        );
+    }
+
+    #[test]
+    fn text_insert_inside_surrogate_pair() {
+        let short: Unmounted<Text> = Unmounted::root("short");
+        let long: Unmounted<Text> = Unmounted::root("long");
+        let prefix = "a".repeat(100); // too long to be inlined into block header
+
+        let (d1, _) = multi_doc(1);
+        let (d2, _) = multi_doc(2);
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        short.mount_mut(&mut t1).unwrap().insert(0, "a😭b").unwrap();
+        let mut txt = long.mount_mut(&mut t1).unwrap();
+        txt.insert(0, format!("{prefix}😭b")).unwrap();
+
+        sync([&mut t1, &mut t2]);
+
+        // offsets land between the high and low surrogate of 😭
+        short.mount_mut(&mut t1).unwrap().insert(2, "x").unwrap();
+        let mut txt = long.mount_mut(&mut t1).unwrap();
+        txt.insert(prefix.len() + 1, "x").unwrap();
+
+        sync([&mut t1, &mut t2]);
+
+        for tx in [&t1, &t2] {
+            let txt = short.mount(tx).unwrap();
+            assert_eq!(txt.to_string(), "a\u{FFFD}x\u{FFFD}b");
+            assert_eq!(txt.len(), 5);
+            let txt = long.mount(tx).unwrap();
+            assert_eq!(txt.to_string(), format!("{prefix}\u{FFFD}x\u{FFFD}b"));
+        }
+
+        t1.commit(None).unwrap();
+        t2.commit(None).unwrap();
+    }
+
     #[test]
     fn text_remove_3_byte_range() {
         let txt: Unmounted<Text> = Unmounted::root("text");
@@ -2461,6 +3540,128 @@ mod test {
         );
     }
 
+    #[test]
+    fn render_with_embedded_items() {
+        let txt: Unmounted<Text> = Unmounted::root("article");
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut text = txt.mount_mut(&mut txn).unwrap();
+
+        text.insert(0, "hello world").unwrap();
+        let image = vec![1, 2, 3];
+        text.insert_embed(5, Value::from(image.clone())).unwrap();
+        let array = text.insert_embed(5, ListPrelim::default()).unwrap();
+        text.insert(2, "ŁŁ").unwrap();
+        text.format(0..4, Attrs::from([("b".into(), true.into())]))
+            .unwrap();
+        text.push("!").unwrap();
+        // plain string rendering skips embeds
+        assert_eq!(text.to_string(), "heŁŁllo world!");
+
+        let mut embeds = Vec::new();
+        let rendered = text
+            .render(|index, value| {
+                embeds.push((index, value));
+                format!("[{}]", index)
+            })
+            .unwrap();
+        assert_eq!(rendered, "heŁŁllo[7][8] world!");
+        assert_eq!(
+            embeds,
+            vec![
+                (7, Out::Node(array.node_id())),
+                (8, Out::Value(Value::from(image))),
+            ]
+        );
+    }
+
+    #[test]
+    fn interned_embeds() {
+        /// Returns the number of interned payloads and reference counts of each of them.
+        fn interned(tx: &Transaction) -> (usize, Vec<u32>) {
+            let db = tx.db.get();
+            let mut cursor = db.cursor().unwrap();
+            let first = ContentBlobKey::new(U64::new(0), ContentBlobKey::DATA);
+            let mut entry = cursor.set_range(first.as_bytes()).optional().unwrap();
+            let mut payloads = 0;
+            let mut refs = Vec::new();
+            while let Some((key, value)) = entry {
+                let Some(key) = ContentBlobKey::parse(key) else {
+                    break;
+                };
+                if key.is_data() {
+                    payloads += 1;
+                } else {
+                    refs.push(U32::read_from_bytes(value).unwrap().get());
+                }
+                entry = cursor.next().optional().unwrap();
+            }
+            (payloads, refs)
+        }
+
+        let txt: Unmounted<Text> = Unmounted::root("article");
+        // payload must not fit into inline block content to be interned
+        let image = Value::from("x".repeat(4 * BlockHeader::INLINE_CONTENT_LEN));
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let d1 = d1.with_content_interning(true);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+        {
+            let mut text = txt.mount_mut(&mut t1).unwrap();
+            text.insert(0, "abc").unwrap();
+            for i in 0..3 {
+                text.insert_embed(i * 2 + 1, image.clone()).unwrap();
+            }
+        }
+        assert_eq!(interned(&t1), (1, vec![3]));
+
+        // interned content is transparently read and encoded
+        sync([&mut t1, &mut t2]);
+        let expected: Vec<_> = txt
+            .mount(&t2)
+            .unwrap()
+            .chunks()
+            .map(Result::unwrap)
+            .collect();
+        let actual: Vec<_> = txt
+            .mount(&t1)
+            .unwrap()
+            .chunks()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(actual, expected);
+
+        t1.commit(None).unwrap();
+
+        // payload is kept as long as any block references it
+        let mut t1 = d1.transact_mut("test").unwrap();
+        txt.mount_mut(&mut t1).unwrap().remove_range(0..4).unwrap();
+        // deleted blocks still reference the payload until they are garbage collected
+        assert_eq!(interned(&t1), (1, vec![3]));
+        let ds = t1.delete_set().cloned().unwrap_or_default();
+        t1.gc(&ds).unwrap();
+        assert_eq!(interned(&t1), (1, vec![1]));
+        t1.commit(None).unwrap();
+
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let chunks: Vec<_> = txt
+            .mount(&t1)
+            .unwrap()
+            .chunks()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(chunks, expected[4..]);
+
+        txt.mount_mut(&mut t1).unwrap().remove_range(0..2).unwrap();
+        assert_eq!(interned(&t1), (1, vec![1]));
+        let ds = t1.delete_set().cloned().unwrap_or_default();
+        t1.gc(&ds).unwrap();
+        assert_eq!(interned(&t1), (0, vec![]));
+    }
+
     #[test]
     fn multiline_format() {
         let root: Unmounted<Text> = Unmounted::root("text");
@@ -2618,6 +3819,43 @@ mod test {
         assert_eq!(state1, vec![Chunk::new("abcd")]);
     }
 
+    #[test]
+    fn restore_view_from_decoded_snapshot() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        root.mount_mut(&mut txn)
+            .unwrap()
+            .insert(0, "hello")
+            .unwrap();
+        txn.commit(None).unwrap();
+
+        let txn = mdoc.transact("test").unwrap();
+        let stored = txn
+            .snapshot_committed()
+            .unwrap()
+            .encode(Encoding::V1)
+            .unwrap();
+        drop(txn);
+
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        txt.remove_range(0..1).unwrap();
+        txt.insert(4, " world").unwrap();
+        txn.commit(None).unwrap();
+
+        let txn = mdoc.transact("test").unwrap();
+        let snapshot = Snapshot::decode(&stored, Encoding::V1).unwrap();
+        let txt = root.mount(&txn).unwrap();
+        assert_eq!(txt.to_string(), "ello world");
+        let state: Vec<_> = txt
+            .chunks_between(None, Some(&snapshot))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(state, vec![Chunk::new("hello")]);
+    }
+
     #[test]
     fn empty_delta_chunks() {
         let root: Unmounted<Text> = Unmounted::root("text");
@@ -2652,4 +3890,329 @@ mod test {
         let txt = root.mount(&txn).unwrap();
         assert_eq!(txt.to_string(), "ab");
     }
+
+    #[test]
+    fn apply_delta_returning() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+        let bold = Attrs::from([("bold".into(), true.into())]);
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        root.mount_mut(&mut txn).unwrap().insert(0, "abcd").unwrap();
+        txn.commit(None).unwrap();
+
+        // returned deltas match uncommitted changes of a fresh transaction
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        let returned = txt
+            .apply_delta_returning([
+                Delta::Retain(1, None),
+                Delta::Delete(1),
+                Delta::Retain(1, Some(Box::new(bold.clone()))),
+                Delta::insert("x"),
+            ])
+            .unwrap();
+        assert_eq!(txt.to_string(), "acxd");
+        let uncommitted: Vec<_> = txt.uncommitted().map(Result::unwrap).collect();
+        assert_eq!(returned, uncommitted);
+        assert_eq!(
+            returned,
+            vec![
+                Delta::Retain(1, None),
+                Delta::Delete(1),
+                Delta::Retain(1, Some(Box::new(bold.clone()))),
+                Delta::Insert("x".into(), None),
+            ]
+        );
+
+        // changes made earlier within the same transaction are not reported, even if new
+        // characters were appended to a block created by them
+        let returned = txt
+            .apply_delta_returning([
+                Delta::Retain(3, None),
+                Delta::insert("yz"),
+                Delta::Retain(1, Some(Box::new(bold.clone()))),
+            ])
+            .unwrap();
+        assert_eq!(txt.to_string(), "acxyzd");
+        assert_eq!(
+            returned,
+            vec![
+                Delta::Retain(3, None),
+                Delta::Insert("yz".into(), None),
+                Delta::Retain(1, Some(Box::new(bold.clone()))),
+            ]
+        );
+
+        txn.commit(None).unwrap();
+
+        // operations that change nothing are not reported
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        let returned = txt
+            .apply_delta_returning([Delta::Retain(2, None), Delta::Retain(0, None)])
+            .unwrap();
+        assert!(returned.is_empty());
+        let returned = txt
+            .apply_delta_returning([Delta::Retain(1, None), Delta::Delete(4)])
+            .unwrap();
+        assert_eq!(txt.to_string(), "ad");
+        assert_eq!(returned, vec![Delta::Retain(1, None), Delta::Delete(4)]);
+        let uncommitted: Vec<_> = txt.uncommitted().map(Result::unwrap).collect();
+        assert_eq!(returned, uncommitted);
+    }
+
+    #[test]
+    fn apply_delta_zero_retain() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+        let bold = Attrs::from([("bold".into(), true.into())]);
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        root.mount_mut(&mut txn).unwrap().insert(0, "abc").unwrap();
+        txn.commit(None).unwrap();
+
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        txt.apply_delta([
+            Delta::Retain(1, None),
+            Delta::Retain(0, Some(Box::new(bold.clone()))),
+            Delta::Retain(0, None),
+        ])
+        .unwrap();
+        assert!(!txn.is_dirty());
+
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        txt.apply_delta([
+            Delta::Retain(1, None),
+            Delta::Retain(0, Some(Box::new(bold.clone()))),
+            Delta::insert("x"),
+        ])
+        .unwrap();
+        let txt = root.mount(&txn).unwrap();
+        let chunks: Vec<_> = txt.chunks().map(Result::unwrap).collect();
+        assert_eq!(chunks, vec![Chunk::new("axbc")]);
+    }
+
+    #[test]
+    fn apply_delta_zero_delete() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+        let bold = Attrs::from([("bold".into(), true.into())]);
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        txt.insert(0, "abc").unwrap();
+        txt.format(1..2, bold.clone()).unwrap();
+        txn.commit(None).unwrap();
+
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        txt.apply_delta([Delta::Delete(0), Delta::Retain(2, None), Delta::Delete(0)])
+            .unwrap();
+        assert!(!txn.is_dirty());
+
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        txt.apply_delta([Delta::Retain(1, None), Delta::Delete(0), Delta::insert("x")])
+            .unwrap();
+        let txt = root.mount(&txn).unwrap();
+        let chunks: Vec<_> = txt.chunks().map(Result::unwrap).collect();
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk::new("ax"),
+                Chunk::new("b").with_attrs(bold),
+                Chunk::new("c")
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_delta_empty_insert() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+        let bold = Attrs::from([("bold".into(), true.into())]);
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        root.mount_mut(&mut txn).unwrap().insert(0, "abc").unwrap();
+        txn.commit(None).unwrap();
+
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+        txt.apply_delta([
+            Delta::Retain(1, None),
+            Delta::Insert("".into(), Some(Box::new(bold))),
+            Delta::Insert("".into(), None),
+        ])
+        .unwrap();
+        assert!(!txn.is_dirty());
+        assert_eq!(root.mount(&txn).unwrap().to_string(), "abc");
+    }
+
+    #[test]
+    fn format_spans_nested() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+
+        txt.insert(0, "hello beautiful world").unwrap();
+        txt.format(0..15, [("bold", true)]).unwrap();
+        txt.format(6..21, [("italic", true)]).unwrap();
+        // formatting with already applied attributes should not produce new spans
+        txt.format(2..4, [("bold", true)]).unwrap();
+
+        let bold = Attrs::from([("bold".into(), true.into())]);
+        let bold_italic =
+            Attrs::from([("bold".into(), true.into()), ("italic".into(), true.into())]);
+        let italic = Attrs::from([("italic".into(), true.into())]);
+
+        let spans: Vec<_> = txt.format_spans().map(Result::unwrap).collect();
+        assert_eq!(
+            spans,
+            vec![(0..6, bold), (6..15, bold_italic), (15..21, italic)]
+        );
+
+        // clearing attributes makes spans collapse
+        txt.format(0..21, [("bold", Value::Null), ("italic", Value::Null)])
+            .unwrap();
+        let spans: Vec<_> = txt.format_spans().map(Result::unwrap).collect();
+        assert_eq!(spans, vec![]);
+    }
+
+    #[test]
+    fn clear_format() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+
+        txt.insert(0, "hello world").unwrap();
+        txt.format(0..5, [("bold", true)]).unwrap();
+        txt.format(3..8, [("italic", true)]).unwrap();
+        txt.format(9..11, [("underline", true)]).unwrap();
+
+        txt.clear_format(0..8).unwrap();
+        let chunks: Vec<_> = txt.chunks().map(Result::unwrap).collect();
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk::new("hello wor"),
+                Chunk::new("ld").with_attrs(Attrs::from([("underline".into(), true.into())]))
+            ]
+        );
+
+        txt.clear_format(..).unwrap();
+        let chunks: Vec<_> = txt.chunks().map(Result::unwrap).collect();
+        assert_eq!(chunks, vec![Chunk::new("hello world")]);
+        assert_eq!(txt.format_spans().count(), 0);
+    }
+
+    #[test]
+    fn push_many_characters() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+
+        let (d1, _) = multi_doc(1);
+        let (d2, _) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut expected = String::new();
+        {
+            let mut txt = root.mount_mut(&mut t1).unwrap();
+            for i in 0..10_000 {
+                let c = char::from(b'a' + (i % 26) as u8);
+                txt.push(c.to_string()).unwrap();
+                expected.push(c);
+            }
+            assert_eq!(txt.len(), 10_000);
+            assert_eq!(txt.to_string(), expected);
+        }
+
+        let blocks = t1
+            .db
+            .get()
+            .blocks()
+            .iter_content(ContentType::String)
+            .count();
+        assert!(
+            blocks <= 10,
+            "expected appends to be coalesced, got {blocks} blocks"
+        );
+
+        let update = t1
+            .diff_update(&StateVector::default(), Encoding::V1)
+            .unwrap();
+        t1.commit(None).unwrap();
+
+        let mut t2 = d2.transact_mut("test").unwrap();
+        t2.apply_update(&update, Encoding::V1).unwrap();
+        let txt = root.mount(&t2).unwrap();
+        assert_eq!(txt.to_string(), expected);
+    }
+
+    #[test]
+    fn insert_chars() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let input = "streamed 😭 input";
+        {
+            let mut txt = root.mount_mut(&mut tx).unwrap();
+            txt.insert_chars(0, input.chars()).unwrap();
+            assert_eq!(txt.to_string(), input);
+        }
+        let blocks = tx
+            .db
+            .get()
+            .blocks()
+            .iter_content(ContentType::String)
+            .count();
+        assert_eq!(blocks, 1);
+
+        // start position is a UTF-16 index: emoji takes 2 code units
+        let mut txt = root.mount_mut(&mut tx).unwrap();
+        txt.insert_chars(11, "ing".chars()).unwrap();
+        assert_eq!(txt.to_string(), "streamed 😭ing input");
+        txt.insert_chars(0, std::iter::empty()).unwrap();
+        assert_eq!(txt.to_string(), "streamed 😭ing input");
+    }
+
+    #[test]
+    fn truncate() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+        let (doc, _) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut tx).unwrap();
+        txt.insert(0, "hello world").unwrap();
+        txt.truncate(5).unwrap();
+        assert_eq!(txt.to_string(), "world");
+        assert_eq!(txt.len(), 5);
+
+        txt.truncate(10).unwrap();
+        assert_eq!(txt.to_string(), "world");
+        txt.truncate(0).unwrap();
+        assert!(txt.is_empty());
+    }
+
+    #[test]
+    fn push_inherits_formatting() {
+        let root: Unmounted<Text> = Unmounted::root("text");
+
+        let (mdoc, _) = multi_doc(1);
+        let mut txn = mdoc.transact_mut("test").unwrap();
+        let mut txt = root.mount_mut(&mut txn).unwrap();
+
+        let bold = Attrs::from([("bold".into(), true.into())]);
+        txt.push("abc").unwrap();
+        txt.format(1..3, bold.clone()).unwrap();
+        txt.push("d").unwrap();
+        txt.push("e").unwrap();
+
+        let chunks: Vec<_> = txt.chunks().map(Result::unwrap).collect();
+        assert_eq!(
+            chunks,
+            vec![Chunk::new("a"), Chunk::new("bcde").with_attrs(bold)]
+        );
+    }
 }
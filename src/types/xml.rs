@@ -0,0 +1,841 @@
+use crate::block::ID;
+use crate::node::{Node, NodeType};
+use crate::prelim::Prelim;
+use crate::store::Db;
+use crate::transaction::TxMutScope;
+use crate::types::Capability;
+use crate::{
+    BlockMut, Clock, ListRef, MapRef, Mounted, Optional, Prepare, TextPrelim, TextRef, Transaction,
+    Unmounted,
+};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::{Deref, RangeBounds};
+
+/// Node types which can contain a sequence of XML child nodes: elements and text.
+pub trait XmlContainer: Capability {}
+
+pub type XmlFragmentRef<Txn> = Mounted<XmlFragment, Txn>;
+
+/// Root of an XML tree: an ordered sequence of XML child nodes without a tag name or attributes.
+#[derive(Clone, Debug, Default, Eq, Ord, PartialOrd, PartialEq)]
+pub struct XmlFragment;
+
+impl Capability for XmlFragment {
+    fn node_type() -> NodeType {
+        NodeType::XmlFragment
+    }
+}
+
+impl XmlContainer for XmlFragment {}
+
+pub type XmlElementRef<Txn> = Mounted<XmlElement, Txn>;
+
+/// XML element with a tag name, a set of attributes and a sequence of child nodes.
+///
+/// Attributes are stored in the same way as [Map] entries: every attribute is resolved
+/// independently using last-writer-wins semantics. Child nodes are stored in the same way as
+/// [List] elements. Attributes are not part of the child nodes sequence.
+///
+/// Root elements (see [Unmounted::root]) don't store a tag name. Like in Yjs, their tag is
+/// [XmlElement::ROOT_TAG].
+///
+/// [Map]: crate::Map
+/// [List]: crate::List
+#[derive(Clone, Debug, Default, Eq, Ord, PartialOrd, PartialEq)]
+pub struct XmlElement;
+
+impl XmlElement {
+    /// Tag name of root XML elements, the same one that Yjs uses for them.
+    pub const ROOT_TAG: &'static str = "UNDEFINED";
+}
+
+impl Capability for XmlElement {
+    fn node_type() -> NodeType {
+        NodeType::XmlElement
+    }
+}
+
+impl XmlContainer for XmlElement {}
+
+pub type XmlTextRef<Txn> = Mounted<XmlText, Txn>;
+
+/// Text node living inside of an XML tree. It's a text-like type with the same semantics
+/// as [Text](crate::Text).
+#[derive(Clone, Debug, Default, Eq, Ord, PartialOrd, PartialEq)]
+pub struct XmlText;
+
+impl Capability for XmlText {
+    fn node_type() -> NodeType {
+        NodeType::XmlText
+    }
+}
+
+/// Child node of an XML container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XmlNode {
+    Element(Unmounted<XmlElement>),
+    Text(Unmounted<XmlText>),
+}
+
+impl XmlNode {
+    fn new(id: ID, node_type: NodeType) -> crate::Result<Self> {
+        match node_type {
+            NodeType::XmlElement => Ok(XmlNode::Element(Unmounted::nested(id))),
+            NodeType::XmlText => Ok(XmlNode::Text(Unmounted::nested(id))),
+            actual => Err(crate::Error::NodeTypeMismatch {
+                expected: NodeType::XmlElement,
+                actual,
+            }),
+        }
+    }
+}
+
+impl<'tx, 'db, Cap: XmlContainer> Mounted<Cap, &'tx Transaction<'db>> {
+    /// Returns the number of child nodes of this XML container.
+    pub fn len(&self) -> usize {
+        self.block.node_len()
+    }
+
+    /// Checks if this XML container has no child nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the child node at a given `index`.
+    pub fn get(&self, index: usize) -> crate::Result<XmlNode> {
+        self.children()?
+            .into_iter()
+            .nth(index)
+            .ok_or(crate::Error::NotFound)
+    }
+
+    /// Returns the child nodes of this XML container, in their document order.
+    pub fn children(&self) -> crate::Result<Vec<XmlNode>> {
+        let db = self.tx.db.get();
+        let blocks = db.blocks();
+        let mut cursor = blocks.cursor()?;
+        let mut children = Vec::with_capacity(self.len());
+        let mut next = self.block.start().copied();
+        while let Some(id) = next {
+            let block = cursor.seek(id)?;
+            if !block.is_deleted()
+                && block.is_countable()
+                && let Some(&node_type) = block.node_type()
+            {
+                children.push(XmlNode::new(id, node_type)?);
+            }
+            next = block.right().copied();
+        }
+        Ok(children)
+    }
+
+    /// Returns a depth-first walker over all descendants of this XML container, which reports
+    /// entering and leaving elements, as well as text nodes in their document order.
+    pub fn walk(&self) -> Walk<'tx, 'db> {
+        Walk {
+            tx: self.tx,
+            stack: vec![Frame {
+                element: None,
+                next: self.block.start().copied(),
+            }],
+        }
+    }
+}
+
+impl<'db, Cap: XmlContainer> Mounted<Cap, &mut Transaction<'db>> {
+    /// Inserts a new child node at a given `index`.
+    pub fn insert<P>(&mut self, index: usize, node: P) -> crate::Result<XmlNode>
+    where
+        P: Into<XmlPrelim>,
+    {
+        self.ensure_node_type()?;
+        let mut tx = self.tx.write_context()?;
+        ListRef::insert_internal(&mut self.block, &mut tx, index, node.into())
+    }
+
+    /// Appends a new child node at the end of this XML container.
+    pub fn push_back<P>(&mut self, node: P) -> crate::Result<XmlNode>
+    where
+        P: Into<XmlPrelim>,
+    {
+        let len = self.block.node_len();
+        self.insert(len, node)
+    }
+
+    /// Removes child nodes within a given range of indexes.
+    pub fn remove_range<R>(&mut self, range: R) -> crate::Result<()>
+    where
+        R: RangeBounds<usize>,
+    {
+        let mut list: ListRef<&mut Transaction<'db>> =
+            Mounted::new(self.block.clone(), &mut *self.tx);
+        let result = list.remove_range(range);
+        self.block = list.split().0;
+        result
+    }
+}
+
+impl<'tx, 'db, Cap: XmlContainer> Deref for Mounted<Cap, &'tx mut Transaction<'db>> {
+    type Target = Mounted<Cap, &'tx Transaction<'db>>;
+
+    fn deref(&self) -> &Self::Target {
+        // Assuming that the mutable reference can be dereferenced to an immutable reference
+        // This is a common pattern in Rust to allow shared access to the same data
+        unsafe { &*(self as *const _ as *const Mounted<Cap, &'tx Transaction<'db>>) }
+    }
+}
+
+impl<'db> XmlElementRef<&Transaction<'db>> {
+    /// Returns the tag name of this XML element. Root elements return [XmlElement::ROOT_TAG].
+    pub fn tag(&self) -> crate::Result<String> {
+        if self.block.id().is_root() {
+            return Ok(XmlElement::ROOT_TAG.to_owned());
+        }
+        let db = self.tx.db.get();
+        let tag = db.contents().get(*self.block.id())?;
+        Ok(String::from_utf8_lossy(tag).into_owned())
+    }
+
+    /// Serializes this element together with its contents as XML markup. Unlike [Display]
+    /// implementation, it returns errors encountered while reading the document.
+    pub fn to_xml(&self) -> crate::Result<String> {
+        let mut buf = String::new();
+        write_open_tag(&mut buf, &self.tag()?, &self.attributes()?);
+        write_xml(&mut buf, self.walk())?;
+        buf.push_str("</");
+        buf.push_str(&self.tag()?);
+        buf.push('>');
+        Ok(buf)
+    }
+
+    /// Returns the current value of an attribute with a given `name`, if it's set.
+    pub fn get_attribute<K>(&self, name: K) -> crate::Result<Option<String>>
+    where
+        K: AsRef<str>,
+    {
+        let map: MapRef<&Transaction<'db>> = Mounted::new(self.block.clone(), self.tx);
+        map.get(name).optional()
+    }
+
+    /// Returns all attributes currently set on this element, ordered by their names.
+    pub fn attributes(&self) -> crate::Result<BTreeMap<String, String>> {
+        let map: MapRef<&Transaction<'db>> = Mounted::new(self.block.clone(), self.tx);
+        let mut attributes = BTreeMap::new();
+        let mut iter = map.iter();
+        while let Some(e) = iter.next()? {
+            if !e.is_deleted()? {
+                attributes.insert(e.key().to_owned(), e.value()?);
+            }
+        }
+        Ok(attributes)
+    }
+}
+
+impl<'db> XmlElementRef<&mut Transaction<'db>> {
+    /// Sets an attribute with a given `name`. Concurrent updates of the same attribute are
+    /// resolved the same way as concurrent [MapRef::insert] calls.
+    pub fn set_attribute<K, V>(&mut self, name: K, value: V) -> crate::Result<()>
+    where
+        K: AsRef<str>,
+        V: Into<String>,
+    {
+        self.ensure_node_type()?;
+        let mut tx = self.tx.write_context()?;
+        MapRef::insert_internal(&mut self.block, &mut tx, name.as_ref(), value.into())
+    }
+
+    /// Removes an attribute with a given `name`. Returns `true` if the attribute was set.
+    pub fn remove_attribute<K>(&mut self, name: K) -> crate::Result<bool>
+    where
+        K: AsRef<str>,
+    {
+        let mut map: MapRef<&mut Transaction<'db>> =
+            Mounted::new(self.block.clone(), &mut *self.tx);
+        map.remove(name)
+    }
+}
+
+impl<'db> XmlFragmentRef<&Transaction<'db>> {
+    /// Serializes the contents of this fragment as XML markup. Unlike [Display] implementation,
+    /// it returns errors encountered while reading the document.
+    pub fn to_xml(&self) -> crate::Result<String> {
+        let mut buf = String::new();
+        write_xml(&mut buf, self.walk())?;
+        Ok(buf)
+    }
+}
+
+impl<'db> Display for XmlFragmentRef<&Transaction<'db>> {
+    /// Serializes the contents of this fragment as XML markup. Failure to read the document is
+    /// reported as [std::fmt::Error], use [XmlFragmentRef::to_xml] to get the actual error.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_xml().map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl<'db> Display for XmlElementRef<&Transaction<'db>> {
+    /// Serializes this element together with its contents as XML markup. Failure to read
+    /// the document is reported as [std::fmt::Error], use [XmlElementRef::to_xml] to get
+    /// the actual error.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_xml().map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl<'db> Display for XmlTextRef<&Transaction<'db>> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let text: TextRef<&Transaction<'db>> = Mounted::new(self.block.clone(), self.tx);
+        text.fmt(f)
+    }
+}
+
+impl<'db> XmlTextRef<&mut Transaction<'db>> {
+    /// Inserts a string `chunk` at a given UTF-16 index.
+    pub fn insert<S>(&mut self, utf16_index: usize, chunk: S) -> crate::Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let mut text: TextRef<&mut Transaction<'db>> =
+            Mounted::new(self.block.clone(), &mut *self.tx);
+        let result = text.insert(utf16_index, chunk);
+        self.block = text.split().0;
+        result
+    }
+
+    /// Appends a string `chunk` at the end of this text node.
+    pub fn push<S>(&mut self, chunk: S) -> crate::Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let mut text: TextRef<&mut Transaction<'db>> =
+            Mounted::new(self.block.clone(), &mut *self.tx);
+        let result = text.push(chunk);
+        self.block = text.split().0;
+        result
+    }
+
+    /// Removes a range of UTF-16 code units from this text node.
+    pub fn remove_range<R>(&mut self, utf16_range: R) -> crate::Result<()>
+    where
+        R: RangeBounds<usize>,
+    {
+        let mut text: TextRef<&mut Transaction<'db>> =
+            Mounted::new(self.block.clone(), &mut *self.tx);
+        let result = text.remove_range(utf16_range);
+        self.block = text.split().0;
+        result
+    }
+}
+
+impl<'tx, 'db> Deref for XmlTextRef<&'tx mut Transaction<'db>> {
+    type Target = XmlTextRef<&'tx Transaction<'db>>;
+
+    fn deref(&self) -> &Self::Target {
+        // Assuming that the mutable reference can be dereferenced to an immutable reference
+        // This is a common pattern in Rust to allow shared access to the same data
+        unsafe { &*(self as *const _ as *const XmlTextRef<&'tx Transaction<'db>>) }
+    }
+}
+
+/// Event reported by [Walk] when traversing an XML tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlEvent {
+    /// Walker entered an element. All events until the matching [XmlEvent::Leave] belong to
+    /// the element's descendants.
+    Enter {
+        node: Unmounted<XmlElement>,
+        tag: String,
+        attributes: BTreeMap<String, String>,
+    },
+    /// Walker left an element, after visiting all of its descendants.
+    Leave {
+        node: Unmounted<XmlElement>,
+        tag: String,
+    },
+    /// Walker visited a text node.
+    Text {
+        node: Unmounted<XmlText>,
+        text: String,
+    },
+}
+
+/// Depth-first walker over XML tree nodes, returned by [XmlFragmentRef::walk] and
+/// [XmlElementRef::walk].
+pub struct Walk<'tx, 'db> {
+    tx: &'tx Transaction<'db>,
+    stack: Vec<Frame>,
+}
+
+struct Frame {
+    /// Element which children are visited, or `None` for the container the walk started from.
+    element: Option<(Unmounted<XmlElement>, String)>,
+    /// ID of the next block to visit in the element's children sequence.
+    next: Option<ID>,
+}
+
+impl<'tx, 'db> Walk<'tx, 'db> {
+    fn move_next(&mut self) -> crate::Result<Option<XmlEvent>> {
+        while let Some(frame) = self.stack.last_mut() {
+            let child = match frame.next {
+                None => {
+                    let frame = self.stack.pop().unwrap();
+                    if let Some((node, tag)) = frame.element {
+                        return Ok(Some(XmlEvent::Leave { node, tag }));
+                    }
+                    continue;
+                }
+                Some(id) => {
+                    let db = self.tx.db.get();
+                    let block = db.blocks().get(id)?;
+                    frame.next = block.right().copied();
+                    match block.node_type() {
+                        Some(&node_type) if !block.is_deleted() && block.is_countable() => {
+                            XmlNode::new(id, node_type)?
+                        }
+                        _ => continue,
+                    }
+                }
+            };
+            match child {
+                XmlNode::Element(node) => {
+                    let element = node.mount(self.tx)?;
+                    let tag = element.tag()?;
+                    let attributes = element.attributes()?;
+                    self.stack.push(Frame {
+                        element: Some((node.clone(), tag.clone())),
+                        next: element.block.start().copied(),
+                    });
+                    return Ok(Some(XmlEvent::Enter {
+                        node,
+                        tag,
+                        attributes,
+                    }));
+                }
+                XmlNode::Text(node) => {
+                    let text = node.mount(self.tx)?.to_string();
+                    return Ok(Some(XmlEvent::Text { node, text }));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'tx, 'db> Iterator for Walk<'tx, 'db> {
+    type Item = crate::Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.move_next() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => None,
+            Err(e) => {
+                self.stack.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn write_xml(buf: &mut String, walk: Walk<'_, '_>) -> crate::Result<()> {
+    for event in walk {
+        match event? {
+            XmlEvent::Enter {
+                tag, attributes, ..
+            } => write_open_tag(buf, &tag, &attributes),
+            XmlEvent::Leave { tag, .. } => {
+                buf.push_str("</");
+                buf.push_str(&tag);
+                buf.push('>');
+            }
+            XmlEvent::Text { text, .. } => write_escaped(buf, &text, false),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn write_open_tag(buf: &mut String, tag: &str, attributes: &BTreeMap<String, String>) {
+    buf.push('<');
+    buf.push_str(tag);
+    for (name, value) in attributes {
+        buf.push(' ');
+        buf.push_str(name);
+        buf.push_str("=\"");
+        write_escaped(buf, value, true);
+        buf.push('"');
+    }
+    buf.push('>');
+}
+
+/// Writes `str` with XML special characters replaced by their entities. Quotes only need to be
+/// escaped inside of attribute values.
+pub(crate) fn write_escaped(buf: &mut String, str: &str, attribute: bool) {
+    let mut last = 0;
+    for (i, c) in str.char_indices() {
+        let entity = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' if attribute => "&quot;",
+            _ => continue,
+        };
+        buf.push_str(&str[last..i]);
+        buf.push_str(entity);
+        last = i + 1;
+    }
+    buf.push_str(&str[last..]);
+}
+
+/// Preliminary content of an XML node, which can be inserted into an XML container.
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlPrelim {
+    Element(XmlElementPrelim),
+    Text(XmlTextPrelim),
+}
+
+impl Prelim for XmlPrelim {
+    type Return = XmlNode;
+
+    #[inline]
+    fn clock_len(&self) -> Clock {
+        Clock::new(1)
+    }
+
+    fn prepare(&self) -> crate::Result<Prepare> {
+        match self {
+            XmlPrelim::Element(prelim) => prelim.prepare(),
+            XmlPrelim::Text(prelim) => prelim.prepare(),
+        }
+    }
+
+    fn integrate<'tx>(
+        self,
+        parent: &mut BlockMut,
+        tx: &mut TxMutScope<'tx>,
+    ) -> crate::Result<Self::Return> {
+        match self {
+            XmlPrelim::Element(prelim) => Ok(XmlNode::Element(prelim.integrate(parent, tx)?)),
+            XmlPrelim::Text(prelim) => Ok(XmlNode::Text(prelim.integrate(parent, tx)?)),
+        }
+    }
+}
+
+impl From<XmlElementPrelim> for XmlPrelim {
+    fn from(value: XmlElementPrelim) -> Self {
+        XmlPrelim::Element(value)
+    }
+}
+
+impl From<XmlTextPrelim> for XmlPrelim {
+    fn from(value: XmlTextPrelim) -> Self {
+        XmlPrelim::Text(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlElementPrelim {
+    tag: String,
+    attributes: BTreeMap<String, String>,
+    children: Vec<XmlPrelim>,
+}
+
+impl XmlElementPrelim {
+    pub fn new<S: Into<String>>(tag: S) -> Self {
+        XmlElementPrelim {
+            tag: tag.into(),
+            attributes: BTreeMap::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_attribute<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_child<P: Into<XmlPrelim>>(mut self, child: P) -> Self {
+        self.children.push(child.into());
+        self
+    }
+}
+
+impl Prelim for XmlElementPrelim {
+    type Return = Unmounted<XmlElement>;
+
+    #[inline]
+    fn clock_len(&self) -> Clock {
+        Clock::new(1) // the element itself is 1 element
+    }
+
+    fn prepare(&self) -> crate::Result<Prepare> {
+        Ok(Prepare::Node(NodeType::XmlElement))
+    }
+
+    fn integrate<'tx>(
+        self,
+        parent: &mut BlockMut,
+        tx: &mut TxMutScope<'tx>,
+    ) -> crate::Result<Self::Return> {
+        tx.db.contents().insert(*parent.id(), self.tag.as_bytes())?;
+        for (name, value) in self.attributes {
+            MapRef::insert_internal(parent, tx, &name, value)?;
+        }
+        for (index, child) in self.children.into_iter().enumerate() {
+            ListRef::insert_internal(parent, tx, index, child)?;
+        }
+        Ok(Unmounted::new(Node::from(*parent.id())))
+    }
+}
+
+#[repr(transparent)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlTextPrelim(TextPrelim);
+
+impl XmlTextPrelim {
+    pub fn new<S: AsRef<str>>(text: S) -> Self {
+        XmlTextPrelim(TextPrelim::from(text.as_ref()))
+    }
+}
+
+impl Prelim for XmlTextPrelim {
+    type Return = Unmounted<XmlText>;
+
+    #[inline]
+    fn clock_len(&self) -> Clock {
+        Clock::new(1) // the text object itself is 1 element
+    }
+
+    fn prepare(&self) -> crate::Result<Prepare> {
+        Ok(Prepare::Node(NodeType::XmlText))
+    }
+
+    fn integrate<'tx>(
+        self,
+        parent: &mut BlockMut,
+        tx: &mut TxMutScope<'tx>,
+    ) -> crate::Result<Self::Return> {
+        let text = self.0.integrate(parent, tx)?;
+        Ok(Unmounted::nested(text.node_id()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lib0::Value;
+    use crate::test_util::{multi_doc, sync};
+    use crate::types::dynamic::Dyn;
+    use crate::{
+        List, Map, Unmounted, XmlElement, XmlElementPrelim, XmlEvent, XmlFragment, XmlNode,
+        XmlTextPrelim,
+    };
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn concurrent_attributes_converge() {
+        let root: Unmounted<XmlElement> = Unmounted::root("xml");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        let mut e1 = root.mount_mut(&mut t1).unwrap();
+        e1.set_attribute("class", "a").unwrap();
+        e1.set_attribute("id", "x").unwrap();
+        e1.push_back(XmlElementPrelim::new("p").with_attribute("lang", "en"))
+            .unwrap();
+        let mut e2 = root.mount_mut(&mut t2).unwrap();
+        e2.set_attribute("class", "b").unwrap();
+        e2.set_attribute("title", "y").unwrap();
+        e2.push_back(XmlTextPrelim::new("hello")).unwrap();
+
+        sync([&mut t1, &mut t2]);
+
+        let attrs = root.mount(&t1).unwrap().attributes().unwrap();
+        assert_eq!(root.mount(&t2).unwrap().attributes().unwrap(), attrs);
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(attrs["id"], "x");
+        assert_eq!(attrs["title"], "y");
+        // the same last-writer-wins rule as in maps: higher client ID wins
+        assert_eq!(attrs["class"], "b");
+
+        // attributes are not part of the children sequence
+        let e2 = root.mount(&t2).unwrap();
+        assert_eq!(e2.len(), 2);
+        let children = e2.children().unwrap();
+        assert_eq!(root.mount(&t1).unwrap().children().unwrap(), children);
+        let p = match &children[0] {
+            XmlNode::Element(p) => p.mount(&t2).unwrap(),
+            other => panic!("expected element, got {:?}", other),
+        };
+        assert_eq!(p.tag().unwrap(), "p");
+        assert_eq!(p.get_attribute("lang").unwrap().as_deref(), Some("en"));
+        assert!(p.is_empty());
+        match &children[1] {
+            XmlNode::Text(text) => assert_eq!(text.mount(&t2).unwrap().to_string(), "hello"),
+            other => panic!("expected text, got {:?}", other),
+        }
+
+        // concurrent removal and update of the same attribute
+        assert!(
+            root.mount_mut(&mut t1)
+                .unwrap()
+                .remove_attribute("class")
+                .unwrap()
+        );
+        root.mount_mut(&mut t2)
+            .unwrap()
+            .set_attribute("id", "z")
+            .unwrap();
+        sync([&mut t1, &mut t2]);
+
+        let expected = BTreeMap::from([
+            ("id".to_string(), "z".to_string()),
+            ("title".to_string(), "y".to_string()),
+        ]);
+        assert_eq!(root.mount(&t1).unwrap().attributes().unwrap(), expected);
+        assert_eq!(root.mount(&t2).unwrap().attributes().unwrap(), expected);
+        assert_eq!(
+            root.mount(&t1).unwrap().get_attribute("class").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn serialize_fragment() {
+        let root: Unmounted<XmlFragment> = Unmounted::root("xml");
+
+        let (d1, _dir1) = multi_doc(1);
+        let (d2, _dir2) = multi_doc(2);
+        let mut t1 = d1.transact_mut("test").unwrap();
+        let mut t2 = d2.transact_mut("test").unwrap();
+
+        let mut xml = root.mount_mut(&mut t1).unwrap();
+        xml.push_back(
+            XmlElementPrelim::new("p")
+                .with_attribute("title", "\"quoted\" & <b>")
+                .with_child(XmlTextPrelim::new("a < b & c"))
+                .with_child(
+                    XmlElementPrelim::new("b")
+                        .with_child(XmlTextPrelim::new("bold"))
+                        .with_child(XmlElementPrelim::new("br")),
+                ),
+        )
+        .unwrap();
+        let text = match xml.push_back(XmlTextPrelim::new("tail")).unwrap() {
+            XmlNode::Text(text) => text,
+            other => panic!("expected text, got {:?}", other),
+        };
+        text.mount_mut(&mut t1).unwrap().insert(0, "> ").unwrap();
+
+        let expected = concat!(
+            "<p title=\"&quot;quoted&quot; &amp; &lt;b&gt;\">",
+            "a &lt; b &amp; c<b>bold<br></br></b>",
+            "</p>&gt; tail"
+        );
+        assert_eq!(root.mount(&t1).unwrap().to_string(), expected);
+
+        sync([&mut t1, &mut t2]);
+        let xml = root.mount(&t2).unwrap();
+        assert_eq!(xml.to_string(), expected);
+
+        let events: Vec<_> = xml
+            .walk()
+            .map(|e| match e.unwrap() {
+                XmlEvent::Enter { tag, .. } => format!("+{}", tag),
+                XmlEvent::Leave { tag, .. } => format!("-{}", tag),
+                XmlEvent::Text { text, .. } => text,
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "+p",
+                "a < b & c",
+                "+b",
+                "bold",
+                "+br",
+                "-br",
+                "-b",
+                "-p",
+                "> tail"
+            ]
+        );
+
+        // serializing a single element includes its own tags
+        let p = match xml.get(0).unwrap() {
+            XmlNode::Element(p) => p.mount(&t2).unwrap(),
+            other => panic!("expected element, got {:?}", other),
+        };
+        assert!(p.to_string().starts_with("<p title="));
+        assert!(p.to_string().ends_with("</b></p>"));
+    }
+
+    #[test]
+    fn root_element_serialization() {
+        let root: Unmounted<XmlElement> = Unmounted::root("xml");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+
+        let xml = root.mount(&tx).unwrap();
+        assert_eq!(xml.tag().unwrap(), XmlElement::ROOT_TAG);
+        assert_eq!(xml.to_string(), "<UNDEFINED></UNDEFINED>");
+
+        let mut xml = root.mount_mut(&mut tx).unwrap();
+        xml.set_attribute("id", "a").unwrap();
+        xml.push_back(XmlElementPrelim::new("p")).unwrap();
+        let xml = root.mount(&tx).unwrap();
+        assert_eq!(
+            xml.to_xml().unwrap(),
+            "<UNDEFINED id=\"a\"><p></p></UNDEFINED>"
+        );
+        assert_eq!(xml.to_string(), xml.to_xml().unwrap());
+
+        let node: Unmounted<Dyn> = Unmounted::root("xml");
+        assert_eq!(
+            node.mount(&tx).unwrap().to_value().unwrap(),
+            Value::String(xml.to_xml().unwrap())
+        );
+    }
+
+    #[test]
+    fn nested_in_map_and_list_to_value() {
+        let map: Unmounted<Map> = Unmounted::root("map");
+        let list: Unmounted<List> = Unmounted::root("list");
+        let (doc, _dir) = multi_doc(1);
+        let mut tx = doc.transact_mut("test").unwrap();
+
+        let prelim = || {
+            XmlElementPrelim::new("p")
+                .with_attribute("lang", "en")
+                .with_child(XmlTextPrelim::new("a < b"))
+                .with_child(XmlElementPrelim::new("br"))
+        };
+        let element = map
+            .mount_mut(&mut tx)
+            .unwrap()
+            .insert("p", prelim())
+            .unwrap();
+        list.mount_mut(&mut tx)
+            .unwrap()
+            .push_back(prelim())
+            .unwrap();
+
+        let expected = "<p lang=\"en\">a &lt; b<br></br></p>";
+        assert_eq!(element.mount(&tx).unwrap().to_xml().unwrap(), expected);
+        let mut entries = HashMap::new();
+        entries.insert("p".to_owned(), Value::String(expected.into()));
+        assert_eq!(
+            map.mount(&tx).unwrap().to_value().unwrap(),
+            Value::Object(entries)
+        );
+        assert_eq!(
+            list.mount(&tx).unwrap().to_value().unwrap(),
+            Value::Array(vec![Value::String(expected.into())])
+        );
+    }
+}